@@ -0,0 +1,38 @@
+/* C declarations of the functions exported by the `ffi` feature of the pointer-value-pair crate.
+ *
+ * A packed pointer is the address of a pointer aligned to `align` (a power of two), bitwise-or'ed with a value less
+ * than `align`. This is the layout of `PointerValuePair<T>` in Rust, where `align` is the alignment of `T` (including
+ * zero-sized `T`, whose address is kept), so packed pointers can be passed between Rust and C as `const void *`.
+ *
+ * This only covers sized pointees. For slices, the address is packed the same way with the alignment of the
+ * elements, and the length is stored unchanged next to it. `PointerValuePair<str>` (and `CStr`, `OsStr`, `Path` and
+ * `ByteSlice`) stores the value in the most significant bit of the length instead, and leaves the address
+ * unchanged. */
+
+#ifndef POINTER_VALUE_PAIR_H
+#define POINTER_VALUE_PAIR_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Returns whether `ptr` is aligned to `align` and `value` is less than `align`. */
+bool pvp_can_pack(const void *ptr, size_t value, size_t align);
+
+/* Packs `value` in the low bits of `ptr`. Returns NULL if `pvp_can_pack` returns false. */
+const void *pvp_pack(const void *ptr, size_t value, size_t align);
+
+/* Returns the pointer of a packed pointer. */
+const void *pvp_ptr(const void *packed, size_t align);
+
+/* Returns the value of a packed pointer. */
+size_t pvp_value(const void *packed, size_t align);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
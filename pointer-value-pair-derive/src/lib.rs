@@ -0,0 +1,256 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{spanned::Spanned, Data, DeriveInput, Fields, Type};
+
+/// Derives `TagValue` for a fieldless enum.
+///
+/// The number of bits of the tag (`TagValue::BITS`) is computed from the largest discriminant. Whether it fits in
+/// the spare bits of a pointer is checked at compile time when the enum is used as the tag of a `TaggedPtr`.
+/// The enum must also implement `Copy`.
+#[proc_macro_derive(TagValue)]
+pub fn derive_tag_value(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match tag_value(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn tag_value(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.span(),
+            "`TagValue` can only be derived for fieldless enums",
+        ));
+    };
+
+    let mut variants = Vec::new();
+    for variant in data.variants.iter() {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new(
+                variant.span(),
+                "`TagValue` can only be derived for fieldless enums",
+            ));
+        }
+        variants.push(&variant.ident);
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::pointer_value_pair::TagValue for #name #ty_generics #where_clause {
+            const BITS: u32 = {
+                let mut max = 0usize;
+                #(
+                    if #name::#variants as usize > max {
+                        max = #name::#variants as usize;
+                    }
+                )*
+                usize::BITS - max.leading_zeros()
+            };
+
+            fn into_bits(self) -> usize {
+                self as usize
+            }
+
+            fn from_bits(bits: usize) -> Self {
+                #(
+                    if bits == #name::#variants as usize {
+                        return #name::#variants;
+                    }
+                )*
+                ::core::panic!("invalid tag bits ({:#x})", bits)
+            }
+        }
+    })
+}
+
+/// Derives a one-word packed representation for a struct with a pointer field (`&'a T`, `*const T` or `*mut T`)
+/// and small fields implementing `TagValue`.
+///
+/// For a struct `Edge`, this generates a `PackedEdge` struct with the same generics and visibility, holding a
+/// `PointerValuePair` with the small fields in the spare bits of the pointer (in declaration order, starting from
+/// the lowest bit). `PackedEdge` has a getter and a setter (`set_<field>`) for each field, and converts from and
+/// into `Edge` with `From`. Whether the small fields fit in the spare bits is checked at compile time when packing,
+/// and `From` and the setters panic if `into_bits` returns more than `BITS` bits, like `TaggedPtr::new`.
+#[proc_macro_derive(PackPointer)]
+pub fn derive_pack_pointer(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match pack_pointer(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn pack_pointer(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "`PackPointer` can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "`PackPointer` can only be derived for structs with named fields",
+            ))
+        }
+    };
+
+    // the pointer field, and the other fields with their offsets
+    let mut pointer = None;
+    let mut tags = Vec::new();
+    for field in fields.iter() {
+        let pointee = match &field.ty {
+            Type::Reference(r) if r.mutability.is_none() => Some(&*r.elem),
+            Type::Ptr(p) => Some(&*p.elem),
+            Type::Reference(r) => {
+                return Err(syn::Error::new(
+                    r.span(),
+                    "`PackPointer` doesn't support exclusive references, use `*mut T` instead",
+                ))
+            }
+            _ => None,
+        };
+        match pointee {
+            Some(_) if pointer.is_some() => {
+                return Err(syn::Error::new(
+                    field.span(),
+                    "`PackPointer` structs must have exactly one pointer field",
+                ))
+            }
+            Some(pointee) => pointer = Some((field, pointee)),
+            None => tags.push(field),
+        }
+    }
+    let Some((pointer, pointee)) = pointer else {
+        return Err(syn::Error::new(
+            input.span(),
+            "`PackPointer` structs must have exactly one pointer field",
+        ));
+    };
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let packed = format_ident!("Packed{}", name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let generics = &input.generics.params;
+
+    let ptr_name = pointer.ident.as_ref().unwrap();
+    let ptr_ty = &pointer.ty;
+    let set_ptr = format_ident!("set_{}", ptr_name);
+    let get_ptr = match ptr_ty {
+        // SAFETY: the pointer has been created from a reference with the same lifetime
+        Type::Reference(_) => quote! { unsafe { &*self.inner.ptr() } },
+        _ => quote! { self.inner.ptr() as #ptr_ty },
+    };
+
+    let tag_names: Vec<_> = tags.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let tag_tys: Vec<_> = tags.iter().map(|f| &f.ty).collect();
+    let set_tags: Vec<_> = tag_names.iter().map(|n| format_ident!("set_{}", n)).collect();
+    // the offset of each field is the sum of the widths of the previous ones
+    let shifts: Vec<_> = (0..tags.len())
+        .map(|i| {
+            let prev = &tag_tys[..i];
+            quote! { (0 #(+ <#prev as ::pointer_value_pair::TagValue>::BITS)*) }
+        })
+        .collect();
+    let doc = format!("The packed representation of `{name}`, generated by `#[derive(PackPointer)]`.");
+    let overflow = format!("the fields of `{name}` don't fit in the spare bits of the pointer");
+
+    Ok(quote! {
+        #[doc = #doc]
+        #vis struct #packed <#generics> #where_clause {
+            inner: ::pointer_value_pair::PointerValuePair<#pointee>,
+            _phantom: ::core::marker::PhantomData<#name #ty_generics>,
+        }
+
+        impl #impl_generics ::core::marker::Copy for #packed #ty_generics #where_clause {}
+
+        impl #impl_generics ::core::clone::Clone for #packed #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl #impl_generics #packed #ty_generics #where_clause {
+            /// Returns the pointer field.
+            #vis fn #ptr_name(&self) -> #ptr_ty {
+                #get_ptr
+            }
+
+            /// Replaces the pointer field, keeping the other fields.
+            #vis fn #set_ptr(&mut self, #ptr_name: #ptr_ty) {
+                self.inner = ::pointer_value_pair::PointerValuePair::new(#ptr_name, self.inner.value());
+            }
+
+            #(
+                /// Returns the field.
+                #vis fn #tag_names(&self) -> #tag_tys {
+                    let mask = (1usize << <#tag_tys as ::pointer_value_pair::TagValue>::BITS) - 1;
+                    <#tag_tys as ::pointer_value_pair::TagValue>::from_bits((self.inner.value() >> #shifts) & mask)
+                }
+
+                /// Replaces the field, keeping the other fields.
+                #vis fn #set_tags(&mut self, #tag_names: #tag_tys) {
+                    let mask = (1usize << <#tag_tys as ::pointer_value_pair::TagValue>::BITS) - 1;
+                    let bits = ::pointer_value_pair::TagValue::into_bits(#tag_names);
+                    ::core::assert!(
+                        bits >> <#tag_tys as ::pointer_value_pair::TagValue>::BITS == 0,
+                        "tag bits ({:#x}) do not fit in {} bits",
+                        bits,
+                        <#tag_tys as ::pointer_value_pair::TagValue>::BITS
+                    );
+                    let value = self.inner.value() & !(mask << #shifts) | bits << #shifts;
+                    self.inner.replace_value(value);
+                }
+            )*
+
+            /// Unpacks the fields.
+            #vis fn unpack(self) -> #name #ty_generics {
+                #name {
+                    #ptr_name: self.#ptr_name(),
+                    #(#tag_names: self.#tag_names(),)*
+                }
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<#name #ty_generics> for #packed #ty_generics #where_clause {
+            fn from(v: #name #ty_generics) -> Self {
+                const {
+                    ::core::assert!(
+                        0 #(+ <#tag_tys as ::pointer_value_pair::TagValue>::BITS)*
+                            <= ::pointer_value_pair::PointerValuePair::<#pointee>::available_bits(),
+                        #overflow
+                    )
+                };
+                let mut value = 0usize;
+                #(
+                    let bits = ::pointer_value_pair::TagValue::into_bits(v.#tag_names);
+                    ::core::assert!(
+                        bits >> <#tag_tys as ::pointer_value_pair::TagValue>::BITS == 0,
+                        "tag bits ({:#x}) do not fit in {} bits",
+                        bits,
+                        <#tag_tys as ::pointer_value_pair::TagValue>::BITS
+                    );
+                    value |= bits << #shifts;
+                )*
+                #packed {
+                    inner: ::pointer_value_pair::PointerValuePair::new(v.#ptr_name, value),
+                    _phantom: ::core::marker::PhantomData,
+                }
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<#packed #ty_generics> for #name #ty_generics #where_clause {
+            fn from(v: #packed #ty_generics) -> Self {
+                v.unpack()
+            }
+        }
+    })
+}
@@ -0,0 +1,239 @@
+use crate::{PackError, PointeeAlign, PointerValuePair};
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    ptr,
+};
+
+/// A pair of a raw pointer and an integer value, for pointers that are known to be aligned to at least `ALIGN`
+/// bytes, which may be stronger than the alignment of `T`.
+///
+/// This is useful for pointers coming from over-aligned allocations (e.g. arenas with 64-byte aligned slots), where
+/// more low bits are available to store the value than `mem::align_of::<T>()` implies.
+/// The number of bits available to store the value is `log2(max(ALIGN, align_of::<T>()))`.
+///
+/// # Notes
+///
+/// `ALIGN` must be a power of two. In debug builds, `new` checks that the pointers satisfy the alignment. For pointee
+/// types whose alignment is guaranteed by the way they are allocated, implementing `ExtraAlignment<ALIGN>` allows
+/// creating pairs from references with `from_ref` and `from_mut`.
+#[repr(transparent)]
+pub struct AlignedPointerValuePair<T: ?Sized, const ALIGN: usize> {
+    pv: *const T,
+}
+
+impl<T: ?Sized, const ALIGN: usize> Copy for AlignedPointerValuePair<T, ALIGN> {}
+
+impl<T: ?Sized, const ALIGN: usize> Clone for AlignedPointerValuePair<T, ALIGN> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized, const ALIGN: usize> PartialEq for AlignedPointerValuePair<T, ALIGN> {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.pv, other.pv)
+    }
+}
+
+impl<T: ?Sized, const ALIGN: usize> Eq for AlignedPointerValuePair<T, ALIGN> {}
+
+impl<T: ?Sized, const ALIGN: usize> Hash for AlignedPointerValuePair<T, ALIGN> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.pv.hash(state)
+    }
+}
+
+impl<T: ?Sized + PointeeAlign, const ALIGN: usize> fmt::Debug for AlignedPointerValuePair<T, ALIGN> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlignedPointerValuePair")
+            .field("ptr", &self.ptr().cast::<()>())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<T: ?Sized + PointeeAlign, const ALIGN: usize> AlignedPointerValuePair<T, ALIGN> {
+    /// Fails to compile if `ALIGN` is not a power of two.
+    const CHECK_ALIGN: () = assert!(ALIGN.is_power_of_two(), "the alignment must be a power of two");
+
+    /// Returns a bitmask of the zero low bits of the pointers.
+    const fn align_bits() -> usize {
+        if ALIGN > T::ALIGN {
+            ALIGN - 1
+        } else {
+            T::ALIGN - 1
+        }
+    }
+
+    /// Creates a new `AlignedPointerValuePair` from the given raw pointer and extra bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    /// In debug builds, also panics if the pointer is not aligned to `ALIGN`.
+    pub fn new(ptr: *const T, value: usize) -> AlignedPointerValuePair<T, ALIGN> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CHECK_ALIGN;
+        let m = Self::align_bits();
+        debug_assert!(
+            ptr.addr() & m == 0,
+            "pointer {:p} is not aligned to {} bytes",
+            ptr.cast::<()>(),
+            m + 1
+        );
+        assert!(
+            value <= m,
+            "not enough alignment bits ({}) to store the value ({})",
+            Self::available_bits(),
+            value
+        );
+
+        AlignedPointerValuePair {
+            pv: ptr.map_addr(|addr| addr | value),
+        }
+    }
+
+    /// Returns the pointer.
+    pub fn ptr(self) -> *const T {
+        self.pv.map_addr(|addr| addr & !Self::align_bits())
+    }
+
+    /// Returns the value stored alongside the pointer.
+    pub fn value(self) -> usize {
+        self.pv.addr() & Self::align_bits()
+    }
+
+    /// Returns the number of bits available to store the value.
+    pub const fn available_bits() -> u32 {
+        Self::align_bits().count_ones()
+    }
+
+    /// Returns the maximum (inclusive) integer value that can be stored in the pointer.
+    pub const fn max_value() -> usize {
+        Self::align_bits()
+    }
+}
+
+/// Marker trait for types whose instances are always aligned to `ALIGN` bytes, even though `mem::align_of::<Self>()`
+/// may be smaller (e.g. nodes that are only ever allocated in 32-byte slots of an arena, or in pages returned by
+/// `mmap`).
+///
+/// `AlignedPointerValuePair<T, ALIGN>` can then be created from any `&T`, with `log2(ALIGN)` bits available to store
+/// the value:
+///
+/// ```ignore
+/// // SAFETY: `Node`s are only created by `NodeArena`, in 32-byte aligned slots
+/// unsafe impl ExtraAlignment<32> for Node {}
+/// ```
+///
+/// # Safety
+///
+/// `ALIGN` must be a power of two, and every instance of `Self` that can be referenced must be located at an address
+/// that is a multiple of `ALIGN`. In particular, `Self` must not be constructible by safe code outside of the
+/// aligned storage (e.g. on the stack, or in a `Vec`), which usually means that it has private fields.
+pub unsafe trait ExtraAlignment<const ALIGN: usize> {}
+
+impl<T: ExtraAlignment<ALIGN>, const ALIGN: usize> AlignedPointerValuePair<T, ALIGN> {
+    /// Creates a new `AlignedPointerValuePair` from a reference to an instance of `T`, which is known to be aligned
+    /// to `ALIGN` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn from_ref(r: &T, value: usize) -> AlignedPointerValuePair<T, ALIGN> {
+        AlignedPointerValuePair::new(r, value)
+    }
+
+    /// Creates a new `AlignedPointerValuePair` from a mutable reference, keeping the write permissions of the
+    /// pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn from_mut(r: &mut T, value: usize) -> AlignedPointerValuePair<T, ALIGN> {
+        AlignedPointerValuePair::new(r as *mut T, value)
+    }
+}
+
+impl<T: ?Sized + PointeeAlign, const ALIGN: usize> TryFrom<PointerValuePair<T>> for AlignedPointerValuePair<T, ALIGN> {
+    type Error = PackError;
+
+    /// Converts a `PointerValuePair` to an `AlignedPointerValuePair` with the same pointer and value, or returns
+    /// `PackError::MisalignedPointer` if the pointer is not aligned to `ALIGN`.
+    fn try_from(pv: PointerValuePair<T>) -> Result<Self, PackError> {
+        if pv.ptr().addr() & Self::align_bits() != 0 {
+            return Err(PackError::MisalignedPointer);
+        }
+        // the value fits, since there are at least as many bits as in `pv`
+        Ok(AlignedPointerValuePair::new(pv.ptr(), pv.value()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AlignedPointerValuePair, ExtraAlignment, PackError, PointerValuePair};
+    use std::mem;
+
+    #[repr(C, align(64))]
+    struct Slot([u8; 64]);
+
+    /// Only ever stored in `Nodes`.
+    struct Node(u64, #[allow(dead_code)] [u64; 3]);
+
+    #[repr(C, align(32))]
+    struct Nodes([Node; 2]);
+
+    // SAFETY: `Node` is 32 bytes large, and all the nodes of the tests are in an array aligned to 32 bytes
+    unsafe impl ExtraAlignment<32> for Node {}
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(
+            mem::size_of::<*const i32>(),
+            mem::size_of::<AlignedPointerValuePair<i32, 64>>()
+        );
+    }
+
+    #[test]
+    fn over_aligned() {
+        let slot = Slot([0; 64]);
+        let ptr = &slot as *const Slot as *const u64;
+        assert_eq!(AlignedPointerValuePair::<u64, 64>::available_bits(), 6);
+        assert_eq!(AlignedPointerValuePair::<u64, 64>::max_value(), 63);
+        // weaker than the natural alignment
+        assert_eq!(AlignedPointerValuePair::<u64, 2>::available_bits(), 3);
+
+        let pv = AlignedPointerValuePair::<u64, 64>::new(ptr, 42);
+        assert_eq!(pv.ptr(), ptr);
+        assert_eq!(pv.value(), 42);
+
+        let pv: AlignedPointerValuePair<u64, 64> = PointerValuePair::new(ptr, 5).try_into().unwrap();
+        assert_eq!(pv.ptr(), ptr);
+        assert_eq!(pv.value(), 5);
+        assert_eq!(
+            AlignedPointerValuePair::<u64, 64>::try_from(PointerValuePair::new(ptr.wrapping_add(1), 5)),
+            Err(PackError::MisalignedPointer)
+        );
+    }
+
+    #[test]
+    fn extra_alignment() {
+        let mut nodes = Nodes([Node(1, [0; 3]), Node(2, [0; 3])]);
+        assert_eq!(AlignedPointerValuePair::<Node, 32>::available_bits(), 5);
+        let pv = AlignedPointerValuePair::<Node, 32>::from_ref(&nodes.0[1], 31);
+        assert_eq!((unsafe { (*pv.ptr()).0 }, pv.value()), (2, 31));
+        let pv = AlignedPointerValuePair::<Node, 32>::from_mut(&mut nodes.0[0], 17);
+        unsafe { (*(pv.ptr() as *mut Node)).0 = 3 };
+        assert_eq!((nodes.0[0].0, pv.value()), (3, 17));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic]
+    fn misaligned() {
+        let slot = Slot([0; 64]);
+        let ptr = (&slot as *const Slot as *const u64).wrapping_add(1);
+        AlignedPointerValuePair::<u64, 64>::new(ptr, 0);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::AlignedPointerValuePair;
+use alloc::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr,
+};
+
+/// An owned heap allocation of a `T`, aligned to at least `ALIGN` bytes, with an integer value packed in the
+/// alignment bits of its pointer.
+///
+/// Raising the alignment of the allocation above the alignment of `T` makes more bits available to store the value:
+/// `log2(max(ALIGN, align_of::<T>()))` (e.g. 6 bits with `ALIGN = 64`). The alignment is part of the type, so the
+/// constructors accept values up to `max_value()` without `unsafe`.
+///
+/// # Notes
+///
+/// `ALIGN` must be a power of two, which is checked at compile time.
+pub struct AlignedBox<T, const ALIGN: usize> {
+    inner: AlignedPointerValuePair<T, ALIGN>,
+    _phantom: PhantomData<T>,
+}
+
+// SAFETY: `AlignedBox` behaves like `Box<T>`.
+unsafe impl<T: Send, const ALIGN: usize> Send for AlignedBox<T, ALIGN> {}
+unsafe impl<T: Sync, const ALIGN: usize> Sync for AlignedBox<T, ALIGN> {}
+
+impl<T, const ALIGN: usize> AlignedBox<T, ALIGN> {
+    /// Returns the layout of the allocation.
+    fn layout() -> Layout {
+        const { assert!(ALIGN.is_power_of_two(), "the alignment must be a power of two") };
+        Layout::from_size_align(mem::size_of::<T>(), ALIGN.max(mem::align_of::<T>())).expect("invalid layout")
+    }
+
+    /// Allocates memory with an alignment of at least `ALIGN`, moves `v` into it, and returns a new `AlignedBox`
+    /// with the given value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn new(v: T, value: usize) -> AlignedBox<T, ALIGN> {
+        // checked before allocating, so that the allocation is not leaked on panic
+        assert!(
+            value <= Self::max_value(),
+            "not enough alignment bits ({}) to store the value ({})",
+            Self::available_bits(),
+            value
+        );
+        let layout = Self::layout();
+        let ptr = if layout.size() == 0 {
+            ptr::without_provenance_mut::<T>(layout.align())
+        } else {
+            // SAFETY: the layout has a non-zero size
+            let ptr = unsafe { alloc(layout) }.cast::<T>();
+            if ptr.is_null() {
+                handle_alloc_error(layout)
+            }
+            ptr
+        };
+        // SAFETY: the pointer is valid for writes and aligned
+        unsafe { ptr.write(v) };
+        AlignedBox {
+            inner: AlignedPointerValuePair::new(ptr, value),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of bits available to store the value.
+    pub const fn available_bits() -> u32 {
+        AlignedPointerValuePair::<T, ALIGN>::available_bits()
+    }
+
+    /// Returns the maximum (inclusive) integer value that can be stored in the pointer.
+    pub const fn max_value() -> usize {
+        AlignedPointerValuePair::<T, ALIGN>::max_value()
+    }
+
+    /// Returns the value stored alongside the pointer.
+    pub fn value(&self) -> usize {
+        self.inner.value()
+    }
+
+    /// Sets the value stored alongside the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn set_value(&mut self, value: usize) {
+        self.inner = AlignedPointerValuePair::new(self.inner.ptr(), value);
+    }
+
+    /// Moves the boxed object out of the allocation, and frees it.
+    pub fn into_inner(self) -> T {
+        let ptr = self.inner.ptr() as *mut T;
+        mem::forget(self);
+        unsafe {
+            // SAFETY: the object is moved out, and the allocation is freed without dropping it again
+            let v = ptr.read();
+            Self::free(ptr);
+            v
+        }
+    }
+
+    /// Returns the underlying `AlignedPointerValuePair`.
+    ///
+    /// The `AlignedBox` still owns the allocation: the returned pointer must not outlive it.
+    pub fn as_pair(&self) -> AlignedPointerValuePair<T, ALIGN> {
+        self.inner
+    }
+
+    /// Frees the allocation, without dropping the object.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated by `new`.
+    unsafe fn free(ptr: *mut T) {
+        let layout = Self::layout();
+        if layout.size() != 0 {
+            dealloc(ptr.cast(), layout)
+        }
+    }
+}
+
+impl<T, const ALIGN: usize> Drop for AlignedBox<T, ALIGN> {
+    fn drop(&mut self) {
+        let ptr = self.inner.ptr() as *mut T;
+        unsafe {
+            // SAFETY: the pointer has been allocated by `new`, and the object is dropped only once
+            ptr::drop_in_place(ptr);
+            Self::free(ptr);
+        }
+    }
+}
+
+impl<T, const ALIGN: usize> Deref for AlignedBox<T, ALIGN> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the pointer points to a live allocation owned by `self`.
+        unsafe { &*self.inner.ptr() }
+    }
+}
+
+impl<T, const ALIGN: usize> DerefMut for AlignedBox<T, ALIGN> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the pointer points to a live allocation owned by `self`, which we borrow mutably.
+        unsafe { &mut *(self.inner.ptr() as *mut T) }
+    }
+}
+
+impl<T: Clone, const ALIGN: usize> Clone for AlignedBox<T, ALIGN> {
+    /// Clones the boxed object into a new allocation, and keeps the value stored alongside the pointer.
+    fn clone(&self) -> Self {
+        AlignedBox::new(T::clone(self), self.value())
+    }
+}
+
+impl<T: fmt::Debug, const ALIGN: usize> fmt::Debug for AlignedBox<T, ALIGN> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlignedBox")
+            .field("box", &&**self)
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AlignedBox;
+    use std::{format, mem, rc::Rc};
+
+    #[test]
+    fn over_aligned() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<AlignedBox<u8, 64>>());
+        assert_eq!(AlignedBox::<u8, 64>::max_value(), 63);
+        // weaker than the natural alignment
+        assert_eq!(AlignedBox::<u64, 2>::available_bits(), 3);
+
+        let mut b = AlignedBox::<u8, 64>::new(7, 63);
+        assert_eq!(b.as_pair().ptr().addr() % 64, 0);
+        assert_eq!((*b, b.value()), (7, 63));
+        *b += 1;
+        b.set_value(42);
+        assert_eq!(format!("{:?}", b.clone()), "AlignedBox { box: 8, value: 42 }");
+        assert_eq!(b.into_inner(), 8);
+
+        let z = AlignedBox::<(), 16>::new((), 15);
+        assert_eq!((*z, z.value()), ((), 15));
+    }
+
+    #[test]
+    fn drops() {
+        let rc = Rc::new(());
+        let b = AlignedBox::<_, 32>::new(rc.clone(), 31);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(b);
+        assert_eq!(Rc::strong_count(&rc), 1);
+        let inner = AlignedBox::<_, 32>::new(rc.clone(), 0).into_inner();
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(inner);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    #[should_panic = "not enough alignment bits"]
+    fn too_large() {
+        AlignedBox::<u8, 8>::new(0, 8);
+    }
+}
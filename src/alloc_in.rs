@@ -0,0 +1,286 @@
+use crate::{PointeeAlign, PointerValuePair};
+use alloc::{alloc::Allocator, boxed::Box};
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem::{ManuallyDrop, MaybeUninit},
+    ops::{Deref, DerefMut},
+};
+
+const BORROWED: usize = 0usize;
+const OWNED: usize = 1usize;
+
+/// Like `Cow`, but the owned variant is a `Box<T, A>` allocated with a custom allocator.
+///
+/// The allocator is stored next to the pointer, and only initialized if the value is owned: for zero-sized allocators
+/// (like `Global`), this is the size of a pointer.
+pub struct CowIn<'a, T, A: Allocator> {
+    inner: PointerValuePair<T>,
+    alloc: MaybeUninit<A>,
+    _phantom: PhantomData<&'a T>,
+}
+
+// SAFETY: a `CowIn` is either a `&'a T` or a `Box<T, A>`.
+unsafe impl<'a, T: Send + Sync, A: Allocator + Send> Send for CowIn<'a, T, A> {}
+// SAFETY: a `&CowIn` only gives access to a `&T`.
+unsafe impl<'a, T: Sync, A: Allocator + Sync> Sync for CowIn<'a, T, A> {}
+
+impl<'a, T, A: Allocator> CowIn<'a, T, A> {
+    /// Creates a new `CowIn` representing a borrowed value.
+    pub fn borrowed(v: &'a T) -> CowIn<'a, T, A> {
+        CowIn {
+            inner: PointerValuePair::new(v, BORROWED),
+            alloc: MaybeUninit::uninit(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new `CowIn` holding a boxed value, and the allocator of the box.
+    pub fn owned(v: Box<T, A>) -> CowIn<'a, T, A> {
+        const {
+            assert!(
+                PointerValuePair::<T>::max_value() >= OWNED,
+                "not enough alignment bits to store the discriminant"
+            )
+        };
+        let (ptr, alloc) = Box::into_raw_with_allocator(v);
+        CowIn {
+            inner: PointerValuePair::new(ptr, OWNED),
+            alloc: MaybeUninit::new(alloc),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns whether this `CowIn` holds a borrow.
+    pub fn is_borrowed(&self) -> bool {
+        self.inner.value() == BORROWED
+    }
+
+    /// Returns whether this `CowIn` holds a boxed value.
+    pub fn is_owned(&self) -> bool {
+        self.inner.value() == OWNED
+    }
+
+    /// Returns the allocator of the boxed value, or `None` if this `CowIn` is borrowed.
+    pub fn allocator(&self) -> Option<&A> {
+        // SAFETY: the allocator is initialized if the value is owned
+        self.is_owned().then(|| unsafe { self.alloc.assume_init_ref() })
+    }
+
+    /// Converts this `CowIn` into a `Box<T, A>`. If this `CowIn` is a borrow, clones the value into a box
+    /// allocated with `alloc`; otherwise, `alloc` is dropped and the box is returned with its own allocator.
+    pub fn into_owned_in(self, alloc: A) -> Box<T, A>
+    where
+        T: Clone,
+    {
+        if self.is_owned() {
+            let this = ManuallyDrop::new(self);
+            unsafe {
+                // SAFETY: the pointer and the allocator come from `Box::into_raw_with_allocator` in `CowIn::owned`,
+                // and `this` is not dropped.
+                Box::from_raw_in(this.inner.ptr() as *mut T, this.alloc.assume_init_read())
+            }
+        } else {
+            Box::new_in(T::clone(&self), alloc)
+        }
+    }
+}
+
+impl<'a, T, A: Allocator> Drop for CowIn<'a, T, A> {
+    fn drop(&mut self) {
+        validate!(self.inner.value() <= OWNED, "corrupted CowIn discriminant");
+        if self.is_owned() {
+            unsafe {
+                // SAFETY: see `into_owned_in`
+                drop(Box::from_raw_in(
+                    self.inner.ptr() as *mut T,
+                    self.alloc.assume_init_read(),
+                ))
+            }
+        }
+    }
+}
+
+impl<'a, T, A: Allocator> Deref for CowIn<'a, T, A> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: same as `Cow`
+        unsafe { &*self.inner.ptr() }
+    }
+}
+
+impl<'a, T: fmt::Debug, A: Allocator> fmt::Debug for CowIn<'a, T, A> {
+    /// Formats the value as `Borrowed(..)` or `Owned(..)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.is_owned() { "Owned" } else { "Borrowed" };
+        f.debug_tuple(state).field(&&**self).finish()
+    }
+}
+
+/// Like `TaggedBox`, but the allocation is made with a custom allocator, which is stored next to the pointer.
+pub struct TaggedBoxIn<T: ?Sized + PointeeAlign, A: Allocator> {
+    inner: PointerValuePair<T>,
+    alloc: ManuallyDrop<A>,
+    _phantom: PhantomData<T>,
+}
+
+// SAFETY: `TaggedBoxIn` behaves like `Box<T, A>`.
+unsafe impl<T: ?Sized + PointeeAlign + Send, A: Allocator + Send> Send for TaggedBoxIn<T, A> {}
+unsafe impl<T: ?Sized + PointeeAlign + Sync, A: Allocator + Sync> Sync for TaggedBoxIn<T, A> {}
+
+impl<T: ?Sized + PointeeAlign, A: Allocator> TaggedBoxIn<T, A> {
+    /// Creates a new `TaggedBoxIn` from the given box and value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn new(b: Box<T, A>, value: usize) -> TaggedBoxIn<T, A> {
+        // checked before `Box::into_raw_with_allocator` so that the box is not leaked on panic
+        assert!(
+            value <= PointerValuePair::<T>::max_value(),
+            "not enough alignment bits ({}) to store the value ({})",
+            PointerValuePair::<T>::available_bits(),
+            value
+        );
+        let (ptr, alloc) = Box::into_raw_with_allocator(b);
+        TaggedBoxIn {
+            inner: PointerValuePair::new(ptr, value),
+            alloc: ManuallyDrop::new(alloc),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Converts this back into a `Box<T, A>`, discarding the value.
+    pub fn into_box(self) -> Box<T, A> {
+        let mut this = ManuallyDrop::new(self);
+        unsafe {
+            // SAFETY: the pointer and the allocator come from `Box::into_raw_with_allocator` in `TaggedBoxIn::new`,
+            // and `this` is not dropped.
+            Box::from_raw_in(this.inner.ptr() as *mut T, ManuallyDrop::take(&mut this.alloc))
+        }
+    }
+
+    /// Returns the value stored alongside the pointer.
+    pub fn value(&self) -> usize {
+        self.inner.value()
+    }
+
+    /// Sets the value stored alongside the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn set_value(&mut self, value: usize) {
+        self.inner.replace_value(value);
+    }
+
+    /// Returns the allocator of the box.
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+}
+
+impl<T: ?Sized + PointeeAlign, A: Allocator> Drop for TaggedBoxIn<T, A> {
+    fn drop(&mut self) {
+        unsafe {
+            // SAFETY: see `into_box`
+            let alloc = ManuallyDrop::take(&mut self.alloc);
+            drop(Box::from_raw_in(self.inner.ptr() as *mut T, alloc));
+        }
+    }
+}
+
+impl<T: ?Sized + PointeeAlign, A: Allocator> Deref for TaggedBoxIn<T, A> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the pointer points to a live allocation owned by `self`.
+        unsafe { &*self.inner.ptr() }
+    }
+}
+
+impl<T: ?Sized + PointeeAlign, A: Allocator> DerefMut for TaggedBoxIn<T, A> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the pointer points to a live allocation owned by `self`, which we borrow mutably.
+        unsafe { &mut *(self.inner.ptr() as *mut T) }
+    }
+}
+
+impl<T: ?Sized + PointeeAlign + fmt::Debug, A: Allocator> fmt::Debug for TaggedBoxIn<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaggedBoxIn")
+            .field("box", &&**self)
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CowIn, TaggedBoxIn};
+    use std::{
+        alloc::{AllocError, Allocator, Global, Layout},
+        boxed::Box,
+        cell::Cell,
+        format, mem,
+        ptr::NonNull,
+    };
+
+    /// Counts the live allocations.
+    #[derive(Copy, Clone)]
+    struct Counting<'a>(&'a Cell<usize>);
+
+    unsafe impl Allocator for Counting<'_> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.set(self.0.get() + 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.0.set(self.0.get() - 1);
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(mem::size_of::<*const u32>(), mem::size_of::<CowIn<u32, Global>>());
+        assert_eq!(mem::size_of::<*const u32>(), mem::size_of::<TaggedBoxIn<u32, Global>>());
+    }
+
+    #[test]
+    fn cow_in() {
+        let live = Cell::new(0);
+        let alloc = Counting(&live);
+        let cow = CowIn::owned(Box::new_in(5u32, alloc));
+        assert_eq!(live.get(), 1);
+        assert!(cow.is_owned() && cow.allocator().is_some());
+        assert_eq!(format!("{:?}", cow), "Owned(5)");
+        drop(cow);
+        assert_eq!(live.get(), 0);
+
+        let x = 6u32;
+        let cow = CowIn::<_, Counting>::borrowed(&x);
+        assert!(cow.is_borrowed() && cow.allocator().is_none());
+        let b = cow.into_owned_in(alloc);
+        assert_eq!(*b, 6);
+        assert_eq!(live.get(), 1);
+        drop(b);
+        assert_eq!(live.get(), 0);
+    }
+
+    #[test]
+    fn tagged_box_in() {
+        let live = Cell::new(0);
+        let mut b = TaggedBoxIn::new(Box::new_in(1u64, Counting(&live)), 3);
+        assert_eq!(live.get(), 1);
+        *b += 1;
+        b.set_value(7);
+        assert_eq!((*b, b.value()), (2, 7));
+        let b = b.into_box();
+        assert_eq!(live.get(), 1);
+        drop(TaggedBoxIn::new(b, 0));
+        assert_eq!(live.get(), 0);
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{GenPtr, Generational};
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
+    mem, slice,
+};
+
+/// Number of slots of the first chunk. Each chunk is twice as large as the previous one.
+const FIRST_CHUNK_LEN: usize = 16;
+
+/// A slot of an `Arena`, over-aligned to leave at least 4 bits for the generation in the handles.
+#[repr(align(16))]
+struct Slot<T> {
+    /// Incremented each time the value of the slot is removed.
+    generation: usize,
+    value: Option<T>,
+}
+
+impl<T> Generational for Slot<T> {
+    fn generation(&self) -> usize {
+        self.generation
+    }
+}
+
+/// A handle to a value stored in an `Arena`: a pointer to its slot, with the generation of the slot packed in the
+/// alignment bits.
+///
+/// Handles are a single pointer wide and `Copy`. They can only be dereferenced through the arena, which checks that
+/// they point to one of its slots, and that the value they were created for hasn't been removed.
+pub struct ArenaHandle<T> {
+    ptr: GenPtr<Slot<T>>,
+}
+
+// SAFETY: handles can't be dereferenced without the arena.
+unsafe impl<T> Send for ArenaHandle<T> {}
+unsafe impl<T> Sync for ArenaHandle<T> {}
+
+impl<T> Copy for ArenaHandle<T> {}
+
+impl<T> Clone for ArenaHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for ArenaHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr
+    }
+}
+
+impl<T> Eq for ArenaHandle<T> {}
+
+impl<T> Hash for ArenaHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ptr.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for ArenaHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArenaHandle")
+            .field("slot", &self.ptr.ptr())
+            .field("generation", &self.ptr.generation())
+            .finish()
+    }
+}
+
+/// An arena (or "slot map") that stores values in stable slots and hands out one-word `ArenaHandle`s to them.
+///
+/// Values can be accessed, mutated and removed through their handle, safely: the arena checks that the handle
+/// points to one of its slots (with a search over its chunks, whose number is logarithmic in the capacity), and that
+/// the slot hasn't been reused since the handle was created, by comparing the generation stored in the handle with
+/// the generation of the slot.
+///
+/// # Notes
+///
+/// - The slots are allocated in chunks that never move, so inserting values doesn't invalidate handles. The memory
+///   is only released when the arena is dropped.
+/// - The generation stored in the handles has 4 bits (more if `T` is aligned to more than 16 bytes), so a stale
+///   handle can be mistaken for a valid one if its slot has been reused a multiple of 16 times. This is memory-safe
+///   (the handle then refers to the new value of the slot), but is a logic error.
+/// - Likewise, a handle to a slot of a dropped arena may refer to a slot of a new arena allocated at the same
+///   address.
+pub struct Arena<T> {
+    chunks: Vec<Box<[Slot<T>]>>,
+    /// The (chunk, index) pairs of the empty slots.
+    free: Vec<(usize, usize)>,
+    len: usize,
+}
+
+impl<T> Arena<T> {
+    /// Creates a new empty arena, which doesn't allocate.
+    pub fn new() -> Arena<T> {
+        Arena {
+            chunks: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Returns the number of values in the arena.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the arena is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Inserts a value in an empty slot, allocating a new chunk of slots if there are none, and returns a handle to
+    /// it.
+    pub fn insert(&mut self, value: T) -> ArenaHandle<T> {
+        let (chunk, index) = match self.free.pop() {
+            Some(free) => free,
+            None => {
+                let len = FIRST_CHUNK_LEN << self.chunks.len();
+                let chunk = self.chunks.len();
+                self.chunks.push(
+                    (0..len)
+                        .map(|_| Slot {
+                            generation: 0,
+                            value: None,
+                        })
+                        .collect(),
+                );
+                self.free.extend((1..len).rev().map(|index| (chunk, index)));
+                (chunk, 0)
+            }
+        };
+        let slot = &mut self.chunks[chunk][index];
+        slot.value = Some(value);
+        self.len += 1;
+        ArenaHandle { ptr: GenPtr::to(slot) }
+    }
+
+    /// Returns the (chunk, index) of the slot of the handle, if it belongs to this arena and its value hasn't been
+    /// removed.
+    fn find(&self, handle: ArenaHandle<T>) -> Option<(usize, usize)> {
+        let addr = handle.ptr.ptr().addr();
+        let size = mem::size_of::<Slot<T>>();
+        let (chunk, offset) = self.chunks.iter().enumerate().find_map(|(i, chunk)| {
+            let offset = addr.wrapping_sub(chunk.as_ptr().addr());
+            (offset < chunk.len() * size).then_some((i, offset))
+        })?;
+        let index = offset / size;
+        let slot = &self.chunks[chunk][index];
+        (offset % size == 0 && slot.value.is_some() && handle.ptr.same_generation(slot.generation))
+            .then_some((chunk, index))
+    }
+
+    /// Returns whether the handle refers to a value of this arena.
+    pub fn contains(&self, handle: ArenaHandle<T>) -> bool {
+        self.find(handle).is_some()
+    }
+
+    /// Returns a reference to the value of the handle, or `None` if the handle doesn't belong to this arena or its
+    /// value has been removed.
+    pub fn get(&self, handle: ArenaHandle<T>) -> Option<&T> {
+        let (chunk, index) = self.find(handle)?;
+        self.chunks[chunk][index].value.as_ref()
+    }
+
+    /// Returns a mutable reference to the value of the handle, or `None` if the handle doesn't belong to this arena
+    /// or its value has been removed.
+    pub fn get_mut(&mut self, handle: ArenaHandle<T>) -> Option<&mut T> {
+        let (chunk, index) = self.find(handle)?;
+        self.chunks[chunk][index].value.as_mut()
+    }
+
+    /// Removes the value of the handle and returns it, or returns `None` if the handle doesn't belong to this arena
+    /// or its value has already been removed. The handles to the value become invalid.
+    pub fn remove(&mut self, handle: ArenaHandle<T>) -> Option<T> {
+        let (chunk, index) = self.find(handle)?;
+        let slot = &mut self.chunks[chunk][index];
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push((chunk, index));
+        self.len -= 1;
+        slot.value.take()
+    }
+
+    /// Returns an iterator over the handles and values of the arena, in no particular order.
+    pub fn iter(&self) -> ArenaIter<'_, T> {
+        ArenaIter {
+            chunks: self.chunks.iter(),
+            slots: [].iter(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Arena::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Arena<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Arena<T> {
+    type Item = (ArenaHandle<T>, &'a T);
+    type IntoIter = ArenaIter<'a, T>;
+
+    fn into_iter(self) -> ArenaIter<'a, T> {
+        self.iter()
+    }
+}
+
+/// An iterator over the handles and values of an `Arena`.
+pub struct ArenaIter<'a, T> {
+    chunks: slice::Iter<'a, Box<[Slot<T>]>>,
+    slots: slice::Iter<'a, Slot<T>>,
+    /// The number of values left.
+    len: usize,
+}
+
+impl<'a, T> Iterator for ArenaIter<'a, T> {
+    type Item = (ArenaHandle<T>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.len > 0 {
+            match self.slots.next() {
+                Some(slot) => {
+                    if let Some(value) = &slot.value {
+                        self.len -= 1;
+                        return Some((ArenaHandle { ptr: GenPtr::to(slot) }, value));
+                    }
+                }
+                None => self.slots = self.chunks.next()?.iter(),
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for ArenaIter<'_, T> {}
+
+impl<T> FusedIterator for ArenaIter<'_, T> {}
+
+impl<T> fmt::Debug for ArenaIter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArenaIter").field("len", &self.len).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Arena, ArenaHandle};
+    use std::{mem, vec::Vec};
+
+    #[test]
+    fn insert_remove() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<ArenaHandle<u8>>());
+        let mut arena = Arena::new();
+        let handles: Vec<_> = (0..40).map(|i| arena.insert(i)).collect();
+        assert_eq!(arena.len(), 40);
+        assert_eq!(arena.get(handles[20]), Some(&20));
+        *arena.get_mut(handles[39]).unwrap() += 1;
+        assert_eq!(arena.get(handles[39]), Some(&40));
+
+        assert_eq!(arena.remove(handles[3]), Some(3));
+        assert!(arena.get(handles[3]).is_none() && arena.remove(handles[3]).is_none());
+        // the slot is reused with a new generation
+        let h = arena.insert(100);
+        assert_ne!(h, handles[3]);
+        assert!(arena.get(handles[3]).is_none() && !arena.contains(handles[3]));
+        assert_eq!((arena.get(h), arena.len()), (Some(&100), 40));
+
+        let mut values: Vec<_> = arena.iter().map(|(h, &v)| (arena.get(h) == Some(&v), v)).collect();
+        values.sort();
+        assert_eq!(values.len(), 40);
+        assert!(values.iter().all(|&(ok, _)| ok));
+    }
+
+    #[test]
+    fn other_arena() {
+        let mut a = Arena::new();
+        let mut b = Arena::new();
+        let ha = a.insert(1u32);
+        let hb = b.insert(2u32);
+        assert_eq!((a.get(hb), b.get(ha)), (None, None));
+        assert_eq!((a.get(ha), b.get(hb)), (Some(&1), Some(&2)));
+        assert!(b.remove(ha).is_none() && a.iter().len() == 1);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::PointerValuePair;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// A `PointerValuePair<T>` that can be safely shared between threads.
+///
+/// The pointer and the value are stored in a single `AtomicPtr<T>`, so both are always loaded and
+/// updated together. In addition to the usual `load`/`store`/`swap` operations, it provides
+/// read-modify-write operations that only touch the value bits (`fetch_or_value`, `fetch_and_value`),
+/// which is useful to set or clear flags without a compare-and-swap loop.
+#[repr(transparent)]
+pub struct AtomicPointerValuePair<T> {
+    pv: AtomicPtr<T>,
+}
+
+impl<T> AtomicPointerValuePair<T> {
+    /// Creates a new `AtomicPointerValuePair` from the given pointer-value pair.
+    pub const fn new(pv: PointerValuePair<T>) -> AtomicPointerValuePair<T> {
+        AtomicPointerValuePair {
+            pv: AtomicPtr::new(pv.into_raw() as *mut T),
+        }
+    }
+
+    /// Consumes the atomic and returns the contained pointer-value pair.
+    pub fn into_inner(self) -> PointerValuePair<T> {
+        PointerValuePair::from_raw(self.pv.into_inner())
+    }
+
+    /// Loads the pointer-value pair.
+    pub fn load(&self, order: Ordering) -> PointerValuePair<T> {
+        PointerValuePair::from_raw(self.pv.load(order))
+    }
+
+    /// Stores a pointer-value pair.
+    pub fn store(&self, pv: PointerValuePair<T>, order: Ordering) {
+        self.pv.store(pv.into_raw() as *mut T, order)
+    }
+
+    /// Stores a pointer-value pair, returning the previous one.
+    pub fn swap(&self, pv: PointerValuePair<T>, order: Ordering) -> PointerValuePair<T> {
+        PointerValuePair::from_raw(self.pv.swap(pv.into_raw() as *mut T, order))
+    }
+
+    /// Stores `new` if the current pointer-value pair is `current` (both the pointer and the value must match).
+    ///
+    /// Returns the previous pointer-value pair, in `Ok` if it was replaced, in `Err` otherwise.
+    pub fn compare_exchange(
+        &self,
+        current: PointerValuePair<T>,
+        new: PointerValuePair<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<PointerValuePair<T>, PointerValuePair<T>> {
+        self.pv
+            .compare_exchange(current.into_raw() as *mut T, new.into_raw() as *mut T, success, failure)
+            .map(|pv| PointerValuePair::from_raw(pv))
+            .map_err(|pv| PointerValuePair::from_raw(pv))
+    }
+
+    /// Like `compare_exchange`, but may spuriously fail even if the pointer-value pair is `current`, which can be
+    /// more efficient in a loop.
+    pub fn compare_exchange_weak(
+        &self,
+        current: PointerValuePair<T>,
+        new: PointerValuePair<T>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<PointerValuePair<T>, PointerValuePair<T>> {
+        self.pv
+            .compare_exchange_weak(current.into_raw() as *mut T, new.into_raw() as *mut T, success, failure)
+            .map(|pv| PointerValuePair::from_raw(pv))
+            .map_err(|pv| PointerValuePair::from_raw(pv))
+    }
+
+    /// Bitwise "or" of the stored value with `value`, leaving the pointer untouched.
+    ///
+    /// Returns the previous pointer-value pair.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` doesn't fit in the available bits.
+    pub fn fetch_or_value(&self, value: usize, order: Ordering) -> PointerValuePair<T> {
+        assert!(
+            value <= PointerValuePair::<T>::max_value(),
+            "not enough alignment bits ({}) to store the value ({})",
+            PointerValuePair::<T>::available_bits(),
+            value
+        );
+        PointerValuePair::from_raw(self.pv.fetch_or(value, order))
+    }
+
+    /// Bitwise "and" of the stored value with `value`, leaving the pointer untouched.
+    ///
+    /// Returns the previous pointer-value pair.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` doesn't fit in the available bits.
+    pub fn fetch_and_value(&self, value: usize, order: Ordering) -> PointerValuePair<T> {
+        let m = PointerValuePair::<T>::max_value();
+        assert!(
+            value <= m,
+            "not enough alignment bits ({}) to store the value ({})",
+            PointerValuePair::<T>::available_bits(),
+            value
+        );
+        PointerValuePair::from_raw(self.pv.fetch_and(value | !m, order))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AtomicPointerValuePair, PointerValuePair};
+    use std::{mem, sync::atomic::Ordering};
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(
+            mem::size_of::<*const i32>(),
+            mem::size_of::<AtomicPointerValuePair<i32>>()
+        );
+    }
+
+    #[test]
+    fn fetch_value_ops() {
+        let pointee = 42u64;
+        let apv = AtomicPointerValuePair::new(PointerValuePair::new(&pointee, 0b001));
+
+        let prev = apv.fetch_or_value(0b110, Ordering::Relaxed);
+        assert_eq!(prev.value(), 0b001);
+        let pv = apv.load(Ordering::Relaxed);
+        assert_eq!(pv.ptr(), &pointee as *const _);
+        assert_eq!(pv.value(), 0b111);
+
+        let prev = apv.fetch_and_value(0b010, Ordering::Relaxed);
+        assert_eq!(prev.value(), 0b111);
+        let pv = apv.into_inner();
+        assert_eq!(pv.ptr(), &pointee as *const _);
+        assert_eq!(pv.value(), 0b010);
+    }
+
+    #[test]
+    fn compare_exchange() {
+        let pointee = 42u64;
+        let apv = AtomicPointerValuePair::new(PointerValuePair::new(&pointee, 1));
+        let stale = PointerValuePair::new(&pointee, 2);
+        let new = PointerValuePair::new(&pointee, 3);
+        assert_eq!(
+            apv.compare_exchange(stale, new, Ordering::Relaxed, Ordering::Relaxed),
+            Err(PointerValuePair::new(&pointee, 1))
+        );
+        assert!(apv
+            .compare_exchange(
+                PointerValuePair::new(&pointee, 1),
+                new,
+                Ordering::Relaxed,
+                Ordering::Relaxed
+            )
+            .is_ok());
+        assert_eq!(apv.load(Ordering::Relaxed).value(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fetch_or_value_overflow() {
+        let pointee = 42u32;
+        let apv = AtomicPointerValuePair::new(PointerValuePair::new(&pointee, 0));
+        apv.fetch_or_value(4, Ordering::Relaxed);
+    }
+}
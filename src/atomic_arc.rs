@@ -0,0 +1,248 @@
+use crate::{AtomicPointerValuePair, PointerValuePair};
+use alloc::sync::Arc;
+use core::{
+    fmt, hint,
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// Number of slots in `READERS`, i.e. of `load`s that can run at the same time without waiting for a free slot.
+const READER_SLOTS: usize = 64;
+
+/// The pointers whose reference count is being incremented by a `load`, shared by all the `AtomicTaggedArc`s. A
+/// `swap` waits until the `Arc` it has replaced is in none of the slots before releasing it.
+static READERS: [AtomicPtr<()>; READER_SLOTS] = [const { AtomicPtr::new(ptr::null_mut()) }; READER_SLOTS];
+
+/// Publishes `ptr` in a free slot of `READERS`, and returns the slot.
+fn acquire_slot(ptr: *mut ()) -> &'static AtomicPtr<()> {
+    let start = (ptr.addr() >> 4) % READER_SLOTS;
+    loop {
+        for i in 0..READER_SLOTS {
+            let slot = &READERS[(start + i) % READER_SLOTS];
+            if slot
+                .compare_exchange(ptr::null_mut(), ptr, Ordering::SeqCst, Ordering::Relaxed)
+                .is_ok()
+            {
+                return slot;
+            }
+        }
+        // more `load`s than slots are in progress
+        hint::spin_loop();
+    }
+}
+
+/// Waits until `ptr` is in none of the slots of `READERS`, i.e. until the `load`s that may be incrementing its
+/// reference count are done.
+fn wait_for_readers(ptr: *mut ()) {
+    for slot in &READERS {
+        while slot.load(Ordering::SeqCst) == ptr {
+            hint::spin_loop();
+        }
+    }
+}
+
+/// An `Arc<T>` and a tag, stored in a single atomic word, that can be loaded and replaced from multiple threads (e.g.
+/// the current configuration of a program and an epoch counter).
+///
+/// The tag is stored in the alignment bits of the pointer returned by `Arc::into_raw`. `load` never writes to the
+/// word: while it increments the reference count, the pointer is published in one of a fixed set of reader slots
+/// shared by all the `AtomicTaggedArc`s, and `swap` waits until the `Arc` it has replaced is in none of the slots
+/// before releasing it. This is similar to hazard pointers, and to the debts of the `arc-swap` crate.
+///
+/// # Notes
+///
+/// The tag can use the alignment bits of `T` (see `max_tag`). `store` and `swap` scan all the reader slots, and may
+/// briefly wait for the `load`s of the same `Arc` in progress, so they are more expensive than `load`.
+pub struct AtomicTaggedArc<T> {
+    pv: AtomicPointerValuePair<T>,
+    _phantom: PhantomData<Arc<T>>,
+}
+
+// SAFETY: `AtomicTaggedArc` shares an `Arc<T>` between threads.
+unsafe impl<T: Send + Sync> Send for AtomicTaggedArc<T> {}
+unsafe impl<T: Send + Sync> Sync for AtomicTaggedArc<T> {}
+
+impl<T> AtomicTaggedArc<T> {
+    /// Returns the maximum (inclusive) tag.
+    pub const fn max_tag() -> usize {
+        PointerValuePair::<T>::max_value()
+    }
+
+    /// Packs an `Arc` and a tag.
+    fn pack(arc: Arc<T>, tag: usize) -> PointerValuePair<T> {
+        // checked before `into_raw` so that the reference is not leaked on panic
+        assert!(tag <= Self::max_tag(), "tag ({tag}) doesn't fit in the available bits");
+        PointerValuePair::new(Arc::into_raw(arc), tag)
+    }
+
+    /// Unpacks a pair created by `pack`.
+    ///
+    /// # Safety
+    ///
+    /// The pair must have been created by `pack`, and its reference must not be released elsewhere.
+    unsafe fn unpack(pv: PointerValuePair<T>) -> (Arc<T>, usize) {
+        // SAFETY: the pointer has been created by `Arc::into_raw`, and the caller transfers the reference
+        (unsafe { Arc::from_raw(pv.ptr()) }, pv.value())
+    }
+
+    /// Creates a new `AtomicTaggedArc` holding the given `Arc` and tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag is greater than `max_tag()`.
+    pub fn new(arc: Arc<T>, tag: usize) -> AtomicTaggedArc<T> {
+        AtomicTaggedArc {
+            pv: AtomicPointerValuePair::new(Self::pack(arc, tag)),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns a new reference to the current `Arc`, and the current tag.
+    ///
+    /// This doesn't write to the atomic word, and only retries if the `Arc` is replaced while its reference count is
+    /// being incremented.
+    pub fn load(&self) -> (Arc<T>, usize) {
+        loop {
+            let ptr = self.pv.load(Ordering::Relaxed).ptr();
+            let slot = acquire_slot(ptr as *mut ());
+            let pv = self.pv.load(Ordering::SeqCst);
+            if ptr::eq(pv.ptr(), ptr) {
+                // SAFETY: the pointer has been created by `Arc::into_raw`. It was still stored in `self` after being
+                // published in the slot, so a `swap` replacing it waits for the slot to be cleared before releasing it
+                unsafe { Arc::increment_strong_count(ptr) };
+                slot.store(ptr::null_mut(), Ordering::Release);
+                // SAFETY: we have just incremented the reference count
+                return (unsafe { Arc::from_raw(ptr) }, pv.value());
+            }
+            // replaced in the meantime: the `Arc` may have been released already
+            slot.store(ptr::null_mut(), Ordering::Release);
+        }
+    }
+
+    /// Returns the current tag, without touching the reference count.
+    pub fn load_tag(&self) -> usize {
+        self.pv.load(Ordering::Acquire).value()
+    }
+
+    /// Replaces the `Arc` and the tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag is greater than `max_tag()`.
+    pub fn store(&self, arc: Arc<T>, tag: usize) {
+        drop(self.swap(arc, tag))
+    }
+
+    /// Replaces the `Arc` and the tag, and returns the previous ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag is greater than `max_tag()`.
+    pub fn swap(&self, arc: Arc<T>, tag: usize) -> (Arc<T>, usize) {
+        let old = self.pv.swap(Self::pack(arc, tag), Ordering::SeqCst);
+        wait_for_readers(old.ptr() as *mut ());
+        // SAFETY: the reference held by `self` is transferred to us, and no `load` can still be incrementing it
+        unsafe { Self::unpack(old) }
+    }
+
+    /// Replaces the tag with `new` if it is `current`, keeping the `Arc`.
+    ///
+    /// Returns the previous tag, in `Ok` if it was replaced, in `Err` otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new tag is greater than `max_tag()`.
+    pub fn compare_and_swap_tag(&self, current: usize, new: usize) -> Result<usize, usize> {
+        assert!(new <= Self::max_tag(), "tag ({new}) doesn't fit in the available bits");
+        let mut pv = self.pv.load(Ordering::Acquire);
+        loop {
+            if pv.value() != current {
+                return Err(pv.value());
+            }
+            let replaced = PointerValuePair::new(pv.ptr(), new);
+            match self
+                .pv
+                .compare_exchange_weak(pv, replaced, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => return Ok(current),
+                Err(actual) => pv = actual,
+            }
+        }
+    }
+
+    /// Returns the `Arc` and the tag.
+    pub fn into_inner(self) -> (Arc<T>, usize) {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `self` is consumed, so its reference is transferred to us
+        unsafe { Self::unpack(this.pv.load(Ordering::Relaxed)) }
+    }
+}
+
+impl<T> Drop for AtomicTaggedArc<T> {
+    fn drop(&mut self) {
+        // SAFETY: we have exclusive access, so no `load` is in progress, and the reference is released here
+        drop(unsafe { Self::unpack(self.pv.load(Ordering::Relaxed)) })
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for AtomicTaggedArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (arc, tag) = self.load();
+        f.debug_struct("AtomicTaggedArc")
+            .field("arc", &arc)
+            .field("tag", &tag)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AtomicTaggedArc;
+    use std::{format, sync::Arc, thread, vec::Vec};
+
+    #[test]
+    fn swap_and_tag() {
+        assert_eq!(AtomicTaggedArc::<u64>::max_tag(), 7);
+        let config = AtomicTaggedArc::new(Arc::new(1u64), 0);
+        let (old, tag) = config.load();
+        assert_eq!((*old, tag), (1, 0));
+        assert_eq!(format!("{:?}", config), "AtomicTaggedArc { arc: 1, tag: 0 }");
+
+        let (prev, _) = config.swap(Arc::new(2), 1);
+        assert!(Arc::ptr_eq(&prev, &old));
+        assert_eq!(Arc::strong_count(&old), 2);
+        drop(prev);
+
+        assert_eq!(config.compare_and_swap_tag(0, 2), Err(1));
+        assert_eq!(config.compare_and_swap_tag(1, 2), Ok(1));
+        assert_eq!(config.load_tag(), 2);
+        let (arc, tag) = config.into_inner();
+        assert_eq!((*arc, tag, Arc::strong_count(&arc)), (2, 2, 1));
+    }
+
+    #[test]
+    fn concurrent_reload() {
+        let config = Arc::new(AtomicTaggedArc::new(Arc::new(0u64), 0));
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let config = config.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        let (arc, tag) = config.load();
+                        assert_eq!(*arc as usize % 4, tag);
+                    }
+                })
+            })
+            .collect();
+        for i in 1..1000u64 {
+            config.store(Arc::new(i), i as usize % 4);
+        }
+        for r in readers {
+            r.join().unwrap();
+        }
+        let (arc, _) = config.load();
+        assert_eq!((*arc, Arc::strong_count(&arc)), (999, 2));
+    }
+}
@@ -0,0 +1,116 @@
+use crate::PointerValuePair;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+// Bulk operations over slices of pairs. The checks are hoisted out of the loops, which only do mask operations on the
+// addresses, so that the compiler can vectorize them.
+impl<T> PointerValuePair<T> {
+    /// Sets the given bits of the value of all the pairs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mask doesn't fit in the available bits.
+    pub fn set_bits_all(pairs: &mut [PointerValuePair<T>], mask: usize) {
+        assert!(
+            mask <= Self::max_value(),
+            "mask ({mask:#x}) doesn't fit in the available bits"
+        );
+        for pv in pairs {
+            *pv = PointerValuePair::from_raw(pv.into_raw().map_addr(|addr| addr | mask));
+        }
+    }
+
+    /// Clears the given bits of the value of all the pairs. Bits outside of the available bits are ignored.
+    pub fn clear_bits_all(pairs: &mut [PointerValuePair<T>], mask: usize) {
+        let mask = mask & Self::max_value();
+        for pv in pairs {
+            *pv = PointerValuePair::from_raw(pv.into_raw().map_addr(|addr| addr & !mask));
+        }
+    }
+
+    /// Packs all the pointers with their values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any value doesn't fit in the available bits. In debug builds, also panics if any pointer is
+    /// misaligned.
+    #[cfg(feature = "alloc")]
+    pub fn pack_all(items: &[(*const T, usize)]) -> Vec<PointerValuePair<T>> {
+        let (addrs, values) = items
+            .iter()
+            .fold((0, 0), |(a, v), &(ptr, value)| (a | ptr.addr(), v | value));
+        debug_assert!(addrs & Self::max_value() == 0, "misaligned pointer");
+        assert!(
+            values <= Self::max_value(),
+            "not enough alignment bits ({}) to store the values",
+            Self::available_bits()
+        );
+        items
+            .iter()
+            .map(|&(ptr, value)| PointerValuePair::from_raw(ptr.map_addr(|addr| addr | value)))
+            .collect()
+    }
+
+    /// Returns the values of all the pairs.
+    #[cfg(feature = "alloc")]
+    pub fn values_all(pairs: &[PointerValuePair<T>]) -> Vec<usize> {
+        pairs.iter().map(|pv| pv.value()).collect()
+    }
+
+    /// Returns a bitset with one bit per pair, in the order of the slice, set if any of the given bits of the value
+    /// of the pair is set. Bit `i` is bit `i % 64` of word `i / 64`.
+    #[cfg(feature = "alloc")]
+    pub fn bitset_all(pairs: &[PointerValuePair<T>], mask: usize) -> Vec<u64> {
+        pairs
+            .chunks(64)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |word, (i, pv)| word | ((pv.value() & mask != 0) as u64) << i)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PointerValuePair;
+
+    #[test]
+    fn set_clear() {
+        let nodes = [1u64, 2, 3];
+        let mut pairs: std::vec::Vec<_> = nodes.iter().map(|n| PointerValuePair::new(n, 1)).collect();
+        PointerValuePair::set_bits_all(&mut pairs, 0b110);
+        assert!(pairs.iter().all(|pv| pv.value() == 0b111));
+        PointerValuePair::clear_bits_all(&mut pairs, 0b1001);
+        assert!(pairs.iter().all(|pv| pv.value() == 0b110));
+        assert!(pairs.iter().zip(&nodes).all(|(pv, n)| core::ptr::eq(pv.ptr(), n)));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn pack_extract() {
+        let nodes = [0u64; 70];
+        let items: std::vec::Vec<_> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n as *const u64, (i % 3 == 0) as usize))
+            .collect();
+        let pairs = PointerValuePair::pack_all(&items);
+        assert_eq!(PointerValuePair::values_all(&pairs[..4]), [1, 0, 0, 1]);
+
+        let bitset = PointerValuePair::bitset_all(&pairs, 1);
+        assert_eq!(bitset.len(), 2);
+        assert_eq!(bitset[0] & 0b1111, 0b1001);
+        assert_eq!(bitset[1], 0b100100);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[should_panic = "not enough alignment bits"]
+    fn pack_too_large() {
+        let n = 0u32;
+        PointerValuePair::pack_all(&[(&n as *const u32, 1), (&n as *const u32, 4)]);
+    }
+}
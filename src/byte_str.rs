@@ -0,0 +1,324 @@
+use crate::{Cow, CowPointee, PointerValuePair, PointerValuePairAccess, ToBoxed};
+use alloc::{boxed::Box, ffi::CString, vec::Vec};
+use core::{
+    ffi::CStr,
+    fmt,
+    ops::{Deref, DerefMut},
+};
+#[cfg(feature = "std")]
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+};
+
+/// Implements `Cow` support for the unsized types that have the layout of a byte slice (`Path`, `OsStr`, `CStr`,
+/// `ByteSlice`).
+///
+/// Like `str`, these types have an alignment of 1, so the value is stored in the most significant bit of the length
+/// (see `PointerValuePair::new_str`).
+macro_rules! impl_byte_str_pointee {
+    ($(#[$attr:meta])* $ty:ty) => {
+        $(#[$attr])*
+        impl PointerValuePairAccess for PointerValuePair<$ty> {
+            type Target = $ty;
+
+            fn ptr(self) -> *const $ty {
+                PointerValuePair::from_raw(self.into_raw() as *const str).ptr() as *const $ty
+            }
+
+            fn mut_ptr(self) -> *mut $ty {
+                PointerValuePairAccess::ptr(self) as *mut $ty
+            }
+
+            fn value(self) -> usize {
+                PointerValuePair::from_raw(self.into_raw() as *const str).value()
+            }
+
+            fn available_bits() -> u32 {
+                PointerValuePair::<str>::available_bits()
+            }
+
+            fn max_value() -> usize {
+                PointerValuePair::<str>::max_value()
+            }
+        }
+
+        // SAFETY: the pointer is packed in the same way as a `*const str` with the same length
+        $(#[$attr])*
+        unsafe impl CowPointee for $ty {
+            const MAX_VALUE: usize = PointerValuePair::<str>::max_value();
+
+            fn pack(ptr: *const $ty, value: usize) -> PointerValuePair<$ty> {
+                let pv = PointerValuePair::new_str(ptr as *const str, value);
+                PointerValuePair::from_raw(pv.into_raw() as *const $ty)
+            }
+        }
+
+        $(#[$attr])*
+        impl ToBoxed for $ty {
+            fn to_boxed(&self) -> Box<$ty> {
+                Box::from(self)
+            }
+        }
+    };
+}
+
+/// Implements `Cow` support and the conversions from and into the owned type for the string types that have the
+/// layout of a byte slice.
+macro_rules! impl_byte_str {
+    ($($(#[$attr:meta])* $ty:ty => $owned:ty, $into_boxed:ident, $into_owned:ident;)*) => {
+        $(
+            impl_byte_str_pointee!($(#[$attr])* $ty);
+
+            $(#[$attr])*
+            impl From<$owned> for Cow<'_, $ty> {
+                #[doc = concat!("Creates an owned `Cow<", stringify!($ty), ">`, shrinking the allocation to fit.")]
+                fn from(v: $owned) -> Self {
+                    Cow::owned(v.$into_boxed())
+                }
+            }
+
+            $(#[$attr])*
+            impl Cow<'_, $ty> {
+                #[doc = concat!(
+                    "Converts this `Cow` into an owned `", stringify!($owned), "`, cloning the data if it is borrowed."
+                )]
+                pub fn $into_owned(self) -> $owned {
+                    self.into_owned().into()
+                }
+            }
+        )*
+    };
+}
+
+impl_byte_str! {
+    CStr => CString, into_boxed_c_str, into_c_string;
+    #[cfg(feature = "std")]
+    OsStr => OsString, into_boxed_os_str, into_os_string;
+    #[cfg(feature = "std")]
+    Path => PathBuf, into_boxed_path, into_path_buf;
+}
+
+/// A byte slice that can be held in an owned `Cow`.
+///
+/// A `Cow<[u8]>` can only be borrowed, since `u8` has no alignment bits to store the discriminant. `ByteSlice` has
+/// the same layout as `[u8]`, but stores the discriminant in the length, like `str`, so that `Cow<'a, ByteSlice>`
+/// can hold either a `&'a [u8]` or a `Box<[u8]>` in a single pointer.
+#[repr(transparent)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSlice([u8]);
+
+impl ByteSlice {
+    /// Wraps a byte slice.
+    pub fn new(bytes: &[u8]) -> &ByteSlice {
+        // SAFETY: `ByteSlice` is a transparent wrapper around `[u8]`
+        unsafe { &*(bytes as *const [u8] as *const ByteSlice) }
+    }
+
+    /// Wraps a boxed byte slice.
+    pub fn from_boxed(bytes: Box<[u8]>) -> Box<ByteSlice> {
+        // SAFETY: `ByteSlice` is a transparent wrapper around `[u8]`
+        unsafe { Box::from_raw(Box::into_raw(bytes) as *mut ByteSlice) }
+    }
+
+    /// Returns the wrapped byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Unwraps a boxed byte slice.
+    pub fn into_boxed_bytes(self: Box<ByteSlice>) -> Box<[u8]> {
+        // SAFETY: `ByteSlice` is a transparent wrapper around `[u8]`
+        unsafe { Box::from_raw(Box::into_raw(self) as *mut [u8]) }
+    }
+}
+
+impl Deref for ByteSlice {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl DerefMut for ByteSlice {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+impl AsRef<[u8]> for ByteSlice {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for ByteSlice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<&ByteSlice> for Box<ByteSlice> {
+    fn from(v: &ByteSlice) -> Self {
+        ByteSlice::from_boxed(Box::from(&v.0))
+    }
+}
+
+impl_byte_str_pointee!(ByteSlice);
+
+impl<'a> Cow<'a, ByteSlice> {
+    /// Creates a borrowed `Cow` from a byte slice.
+    pub fn from_slice(bytes: &'a [u8]) -> Self {
+        Cow::borrowed(ByteSlice::new(bytes))
+    }
+
+    /// Creates an owned `Cow` from a vector, shrinking the allocation to fit.
+    pub fn from_vec(bytes: Vec<u8>) -> Self {
+        Cow::owned(ByteSlice::from_boxed(bytes.into_boxed_slice()))
+    }
+
+    /// Returns the bytes.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Copies the bytes into a new vector.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    /// Converts this `Cow` into a vector, copying the bytes only if they are borrowed.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.into_owned().into_boxed_bytes().into_vec()
+    }
+
+    /// Appends `extra` to the bytes, and returns the result as an owned `Cow`.
+    ///
+    /// If this `Cow` is owned, its allocation is reused (if there is enough room after the bytes in the allocator's
+    /// block).
+    pub fn extend_into_owned(self, extra: &[u8]) -> Cow<'static, ByteSlice> {
+        let mut v = self.into_vec();
+        v.extend_from_slice(extra);
+        Cow::from_vec(v)
+    }
+}
+
+impl<'a> From<&'a [u8]> for Cow<'a, ByteSlice> {
+    fn from(v: &'a [u8]) -> Self {
+        Cow::from_slice(v)
+    }
+}
+
+impl From<Vec<u8>> for Cow<'_, ByteSlice> {
+    fn from(v: Vec<u8>) -> Self {
+        Cow::from_vec(v)
+    }
+}
+
+impl From<Box<[u8]>> for Cow<'_, ByteSlice> {
+    fn from(v: Box<[u8]>) -> Self {
+        Cow::owned(ByteSlice::from_boxed(v))
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl From<Cow<'_, ByteSlice>> for bytes::Bytes {
+    /// Converts a `Cow` into `Bytes` (which implements `bytes::Buf`), copying the bytes only if they are borrowed.
+    fn from(v: Cow<'_, ByteSlice>) -> Self {
+        v.into_vec().into()
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl From<bytes::Bytes> for Cow<'_, ByteSlice> {
+    /// Converts `Bytes` into an owned `Cow`, without copying if the `Bytes` is the unique owner of its buffer.
+    fn from(v: bytes::Bytes) -> Self {
+        Cow::from_vec(v.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ByteSlice, Cow};
+    use std::{
+        ffi::{CStr, CString},
+        format, mem, vec,
+    };
+    #[cfg(feature = "std")]
+    use std::{
+        ffi::{OsStr, OsString},
+        path::{Path, PathBuf},
+        string::String,
+    };
+
+    #[test]
+    fn c_str() {
+        assert_eq!(mem::size_of::<*const CStr>(), mem::size_of::<Cow<CStr>>());
+        let cow = Cow::borrowed(c"hello");
+        assert!(cow.is_borrowed());
+        assert_eq!(cow.to_bytes(), b"hello");
+        assert_eq!(format!("{:?}", cow), "Borrowed(\"hello\")");
+
+        let cow = Cow::<CStr>::from(CString::new("world").unwrap());
+        assert!(cow.is_owned());
+        assert_eq!(&*cow, c"world");
+        assert_eq!(cow.into_c_string().as_bytes_with_nul(), b"world\0");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn paths() {
+        fn file_name<'a>(p: impl Into<Cow<'a, Path>>) -> Option<String> {
+            p.into().file_name().map(|n| n.to_string_lossy().into_owned())
+        }
+
+        assert_eq!(file_name(Path::new("/tmp/a.txt")).as_deref(), Some("a.txt"));
+        assert_eq!(file_name(PathBuf::from("b/c.rs")).as_deref(), Some("c.rs"));
+
+        let cow = Cow::<Path>::from(PathBuf::from("/usr"));
+        assert!(cow.is_owned());
+        let buf = cow.into_path_buf();
+        assert_eq!(buf, Path::new("/usr"));
+
+        let cow = Cow::borrowed(OsStr::new("abc"));
+        assert_eq!(cow.clone().into_os_string(), OsString::from("abc"));
+        assert_eq!(cow, *OsStr::new("abc"));
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(mem::size_of::<*const [u8]>(), mem::size_of::<Cow<ByteSlice>>());
+        let data = [1u8, 2, 3];
+        let cow = Cow::<ByteSlice>::from(&data[..]);
+        assert!(cow.is_borrowed());
+        assert_eq!(cow.as_slice(), &[1, 2, 3]);
+        assert_eq!(format!("{:?}", cow), "Borrowed([1, 2, 3])");
+
+        let cow = cow.extend_into_owned(&[4]);
+        assert!(cow.is_owned());
+        assert_eq!(cow.to_vec(), vec![1, 2, 3, 4]);
+        let addr = cow.as_ptr();
+        let v = cow.into_vec();
+        assert_eq!(v.as_ptr(), addr);
+
+        let cow = Cow::<ByteSlice>::from_vec(v);
+        assert!(cow.is_owned());
+        assert_eq!(cow.as_slice(), &[1, 2, 3, 4]);
+        assert_eq!(cow.into_owned().into_boxed_bytes().len(), 4);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn bytes_buf() {
+        use bytes::{Buf, Bytes};
+
+        let mut buf = Bytes::from(Cow::<ByteSlice>::from(&b"\x01\x02rest"[..]));
+        assert_eq!(buf.get_u16(), 0x0102);
+        assert_eq!(buf.chunk(), b"rest");
+
+        let cow = Cow::<ByteSlice>::from(Bytes::from(vec![5u8, 6]));
+        assert!(cow.is_owned());
+        assert_eq!(cow.as_slice(), &[5, 6]);
+    }
+}
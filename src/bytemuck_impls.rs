@@ -0,0 +1,43 @@
+use crate::PointerValuePair;
+use bytemuck::{TransparentWrapper, Zeroable};
+
+// SAFETY: `PointerValuePair<T>` is `repr(transparent)` over `*const T`, and any `*const T` is a valid (if
+// meaningless) packed representation.
+unsafe impl<T: ?Sized> TransparentWrapper<*const T> for PointerValuePair<T> {}
+
+// SAFETY: the all-zeroes representation is a null pointer with a zero value.
+unsafe impl<T> Zeroable for PointerValuePair<T> {}
+
+// SAFETY: the all-zeroes representation is an empty slice with a null data pointer and a zero value.
+unsafe impl<T> Zeroable for PointerValuePair<[T]> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::PointerValuePair;
+    use bytemuck::{TransparentWrapper, Zeroable};
+
+    #[test]
+    fn transparent_wrapper() {
+        let pointee = 42u64;
+        let pv = PointerValuePair::new(&pointee, 3);
+        let raw: *const u64 = PointerValuePair::peel(pv);
+        let pv2 = PointerValuePair::wrap(raw);
+        assert_eq!(pv, pv2);
+
+        let pairs = [pv, PointerValuePair::new(&pointee, 1)];
+        let raws: &[*const u64] = PointerValuePair::peel_slice(&pairs);
+        let pairs2: &[PointerValuePair<u64>] = PointerValuePair::wrap_slice(raws);
+        assert_eq!(pairs2, &pairs);
+    }
+
+    #[test]
+    fn zeroed() {
+        let pv = PointerValuePair::<u64>::zeroed();
+        assert!(pv.is_null());
+        assert_eq!(pv.value(), 0);
+
+        let pv = PointerValuePair::<[u32]>::zeroed();
+        assert!(pv.is_null());
+        assert_eq!(pv.ptr().len(), 0);
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{PointerValuePair, TagValue};
+use core::{fmt, ops::Not, ptr};
+
+/// The color of a node of a red-black tree, stored in the alignment bit of the parent pointer of a `ColoredLink`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    Red,
+    Black,
+}
+
+impl TagValue for Color {
+    const BITS: u32 = 1;
+
+    fn into_bits(self) -> usize {
+        self as usize
+    }
+
+    fn from_bits(bits: usize) -> Self {
+        if bits == 0 {
+            Color::Red
+        } else {
+            Color::Black
+        }
+    }
+}
+
+/// A side of a node of a binary tree.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Not for Side {
+    type Output = Side;
+
+    /// Returns the opposite side.
+    fn not(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+/// The links of a node of a binary tree (e.g. a red-black tree or an AVL tree): a parent pointer with the color of
+/// the node packed in its alignment bit, and two child pointers with flags packed in their alignment bits (e.g. the
+/// balance factor of an AVL tree, or the "thread" bits of a threaded tree).
+///
+/// All accessors preserve the bits that they don't modify, so the masking is never done by hand. The links only
+/// hold raw pointers: reading and writing them is safe, dereferencing them is up to the tree implementation (see
+/// `ColoredLink::rotate`).
+///
+/// # Notes
+///
+/// `T` must be aligned to at least 2 bytes, which is checked at compile time when setting the color.
+pub struct ColoredLink<T> {
+    parent: PointerValuePair<T>,
+    children: [PointerValuePair<T>; 2],
+}
+
+impl<T> Copy for ColoredLink<T> {}
+
+impl<T> Clone for ColoredLink<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> ColoredLink<T> {
+    /// Creates new links for a red node without a parent or children.
+    pub const fn new() -> ColoredLink<T> {
+        ColoredLink {
+            parent: PointerValuePair::from_raw(ptr::null()),
+            children: [PointerValuePair::from_raw(ptr::null()); 2],
+        }
+    }
+
+    /// Returns the parent pointer.
+    pub fn parent(&self) -> *const T {
+        self.parent.ptr()
+    }
+
+    /// Sets the parent pointer, keeping the color.
+    pub fn set_parent(&mut self, parent: *const T) {
+        self.parent = PointerValuePair::new(parent, self.parent.value());
+    }
+
+    /// Returns the color of the node.
+    pub fn color(&self) -> Color {
+        Color::from_bits(self.parent.value())
+    }
+
+    /// Sets the color of the node, keeping the parent pointer.
+    pub fn set_color(&mut self, color: Color) {
+        const {
+            assert!(
+                PointerValuePair::<T>::available_bits() >= Color::BITS,
+                "not enough alignment bits to store the color"
+            )
+        };
+        self.parent.replace_value(color.into_bits());
+    }
+
+    /// Returns whether the node is red.
+    pub fn is_red(&self) -> bool {
+        self.color() == Color::Red
+    }
+
+    /// Returns the child pointer on the given side.
+    pub fn child(&self, side: Side) -> *const T {
+        self.children[side as usize].ptr()
+    }
+
+    /// Returns the left child pointer.
+    pub fn left(&self) -> *const T {
+        self.child(Side::Left)
+    }
+
+    /// Returns the right child pointer.
+    pub fn right(&self) -> *const T {
+        self.child(Side::Right)
+    }
+
+    /// Sets the child pointer on the given side, keeping its flags, and returns the previous pointer.
+    pub fn replace_child(&mut self, side: Side, child: *const T) -> *const T {
+        let old = self.children[side as usize];
+        self.children[side as usize] = PointerValuePair::new(child, old.value());
+        old.ptr()
+    }
+
+    /// Sets the child pointer on the given side, keeping its flags.
+    pub fn set_child(&mut self, side: Side, child: *const T) {
+        self.replace_child(side, child);
+    }
+
+    /// Returns the flags stored in the child pointer on the given side.
+    pub fn child_flags(&self, side: Side) -> usize {
+        self.children[side as usize].value()
+    }
+
+    /// Sets the flags stored in the child pointer on the given side, keeping the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the flags are greater than `max_child_flags()`.
+    pub fn set_child_flags(&mut self, side: Side, flags: usize) {
+        self.children[side as usize].replace_value(flags);
+    }
+
+    /// Returns the maximum (inclusive) value of the flags of the child pointers.
+    pub const fn max_child_flags() -> usize {
+        PointerValuePair::<T>::max_value()
+    }
+
+    /// Returns the side of the given child, or `None` if it is not a child of this node.
+    pub fn side_of(&self, child: *const T) -> Option<Side> {
+        if self.left() == child {
+            Some(Side::Left)
+        } else if self.right() == child {
+            Some(Side::Right)
+        } else {
+            None
+        }
+    }
+
+    /// Swaps the left and right children, with their flags.
+    pub fn swap_children(&mut self) {
+        self.children.swap(0, 1);
+    }
+
+    /// Swaps the colors of two nodes, keeping their parent pointers.
+    pub fn swap_colors(&mut self, other: &mut ColoredLink<T>) {
+        let color = self.color();
+        self.set_color(other.color());
+        other.set_color(color);
+    }
+
+    /// Rotates the subtree rooted at `node` towards `side`: the child of `node` on the opposite side takes its place,
+    /// and `node` becomes its child on `side`. Returns the new root of the subtree.
+    ///
+    /// The parent of `node` (if any) is updated to point to the new root, and so are the parent pointers of the
+    /// nodes that change parents. Colors and flags are not modified. If `node` was the root of the tree, the caller
+    /// must update the root pointer.
+    ///
+    /// # Safety
+    ///
+    /// `link` must return a pointer to the `ColoredLink` of the given node. `node`, its child on the opposite side
+    /// of `side` (which must not be null), and its parent and the nodes linked to them must be valid for writes, and
+    /// not accessed through other references during the call.
+    pub unsafe fn rotate(node: *const T, side: Side, link: impl Fn(*const T) -> *mut ColoredLink<T>) -> *const T {
+        let x = &mut *link(node);
+        let pivot = x.child(!side);
+        debug_assert!(
+            !pivot.is_null(),
+            "cannot rotate a node without a child on the opposite side"
+        );
+        let y = &mut *link(pivot);
+
+        // the inner child of the pivot moves to `node`
+        let inner = y.child(side);
+        x.set_child(!side, inner);
+        if !inner.is_null() {
+            (*link(inner)).set_parent(node);
+        }
+
+        // the pivot takes the place of `node` in its parent
+        let parent = x.parent();
+        y.set_parent(parent);
+        if !parent.is_null() {
+            let p = &mut *link(parent);
+            if let Some(parent_side) = p.side_of(node) {
+                p.set_child(parent_side, pivot);
+            }
+        }
+
+        y.set_child(side, node);
+        x.set_parent(pivot);
+        pivot
+    }
+}
+
+impl<T> Default for ColoredLink<T> {
+    fn default() -> Self {
+        ColoredLink::new()
+    }
+}
+
+impl<T> fmt::Debug for ColoredLink<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ColoredLink")
+            .field("parent", &self.parent())
+            .field("color", &Color::from_bits(self.parent.value()))
+            .field("left", &self.left())
+            .field("right", &self.right())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, ColoredLink, Side};
+    use std::{mem, ptr};
+
+    struct Node {
+        link: ColoredLink<Node>,
+        key: u32,
+    }
+
+    fn link(node: *const Node) -> *mut ColoredLink<Node> {
+        unsafe { ptr::addr_of_mut!((*(node as *mut Node)).link) }
+    }
+
+    #[test]
+    fn accessors() {
+        assert_eq!(mem::size_of::<[*const Node; 3]>(), mem::size_of::<ColoredLink<Node>>());
+        let a = Node {
+            link: ColoredLink::new(),
+            key: 1,
+        };
+        let mut l = ColoredLink::<Node>::new();
+        assert!(l.is_red() && l.parent().is_null());
+        l.set_color(Color::Black);
+        l.set_parent(&a);
+        assert_eq!((l.parent(), l.color()), (&a as *const Node, Color::Black));
+
+        l.set_child_flags(Side::Right, ColoredLink::<Node>::max_child_flags());
+        l.set_child(Side::Right, &a);
+        assert_eq!(l.side_of(&a), Some(Side::Right));
+        l.swap_children();
+        assert_eq!(
+            (l.left(), l.child_flags(Side::Left)),
+            (&a as *const Node, ColoredLink::<Node>::max_child_flags())
+        );
+        assert!(l.right().is_null() && l.child_flags(Side::Right) == 0);
+
+        let mut m = ColoredLink::<Node>::new();
+        l.swap_colors(&mut m);
+        assert_eq!((l.color(), m.color()), (Color::Red, Color::Black));
+        assert_eq!(l.parent(), &a as *const Node);
+        assert_eq!(a.key, 1);
+    }
+
+    #[test]
+    fn rotate() {
+        // p -> x -> (a, y -> (b, c)), rotated left: p -> y -> (x -> (a, b), c)
+        let nodes: [Node; 6] = core::array::from_fn(|i| Node {
+            link: ColoredLink::new(),
+            key: i as u32,
+        });
+        let [p, x, a, y, b, c] = nodes.each_ref().map(|n| n as *const Node);
+        unsafe {
+            let edges = [
+                (p, Side::Left, x),
+                (x, Side::Left, a),
+                (x, Side::Right, y),
+                (y, Side::Left, b),
+                (y, Side::Right, c),
+            ];
+            for (parent, side, child) in edges {
+                (*link(parent)).set_child(side, child);
+                (*link(child)).set_parent(parent);
+            }
+            (*link(y)).set_color(Color::Black);
+
+            assert_eq!(ColoredLink::rotate(x, Side::Left, link), y);
+            let keys = |n: *const Node| {
+                let l = &*link(n);
+                let key = |n: *const Node| n.as_ref().map(|n| n.key);
+                (key(l.parent()), key(l.left()), key(l.right()))
+            };
+            assert_eq!(keys(p), (None, Some(3), None));
+            assert_eq!(keys(y), (Some(0), Some(1), Some(5)));
+            assert_eq!(keys(x), (Some(3), Some(2), Some(4)));
+            assert_eq!(keys(b), (Some(1), None, None));
+            assert!(!(*link(y)).is_red());
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{TagValue, ThinBox};
+use alloc::boxed::Box;
+use core::{error::Error, fmt, marker::PhantomData, mem::ManuallyDrop};
+
+/// Set in the representation of inline codes. The pointers to boxed errors are aligned, so this bit is always clear.
+const CODE: usize = 1;
+
+type DynError = dyn Error + Send + Sync + 'static;
+
+union Repr {
+    boxed: ManuallyDrop<ThinBox<DynError>>,
+    /// The bits of the code shifted left by one, with `CODE` set.
+    code: usize,
+}
+
+/// Wraps an already boxed error, so that it can be moved into a `ThinBox`.
+struct BoxedError(Box<DynError>);
+
+impl fmt::Debug for BoxedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for BoxedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Error for BoxedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// An error that is a single pointer wide: either a boxed `dyn Error + Send + Sync`, or an inline error code of type
+/// `C` (typically a fieldless enum implementing `TagValue`).
+///
+/// Boxed errors are stored in a `ThinBox`, whose pointer is aligned, and inline codes are stored shifted left by one
+/// with the low bit set, so frequent errors can be reported without allocating, and rare ones can carry rich
+/// information, while keeping `Result<T, CompactError<C>>` small.
+///
+/// Like `anyhow::Error`, `CompactError` can be created from any error with `?`, but doesn't implement `Error`
+/// itself.
+pub struct CompactError<C> {
+    repr: Repr,
+    _phantom: PhantomData<C>,
+}
+
+// SAFETY: the boxed errors are `Send + Sync`, and the codes are stored as bits.
+unsafe impl<C: Send> Send for CompactError<C> {}
+unsafe impl<C: Sync> Sync for CompactError<C> {}
+
+impl<C: TagValue> CompactError<C> {
+    /// Boxes the given error.
+    pub fn new<E: Error + Send + Sync + 'static>(e: E) -> CompactError<C> {
+        CompactError {
+            repr: Repr {
+                boxed: ManuallyDrop::new(ThinBox::new_unsize(e, 0, |e| e as &mut DynError)),
+            },
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a `CompactError` from an already boxed error. The box is moved into a new allocation.
+    pub fn from_boxed(e: Box<DynError>) -> CompactError<C> {
+        CompactError::new(BoxedError(e))
+    }
+
+    /// Creates a `CompactError` holding an inline code, which doesn't allocate.
+    pub fn from_code(code: C) -> CompactError<C> {
+        const {
+            assert!(
+                C::BITS < usize::BITS,
+                "the code must leave one bit for the discriminant"
+            )
+        };
+        CompactError {
+            repr: Repr {
+                code: (code.into_bits() << 1) | CODE,
+            },
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns whether this holds an inline code.
+    pub fn is_code(&self) -> bool {
+        // SAFETY: all bit patterns are valid `usize`s, and the low bit of boxed errors is clear
+        unsafe { self.repr.code & CODE != 0 }
+    }
+
+    /// Returns the inline code, or `None` if this holds a boxed error.
+    pub fn code(&self) -> Option<C> {
+        // SAFETY: the union holds a code
+        self.is_code().then(|| C::from_bits(unsafe { self.repr.code } >> 1))
+    }
+
+    /// Returns the boxed error, or `None` if this holds an inline code.
+    pub fn boxed(&self) -> Option<&DynError> {
+        // SAFETY: the low bit is clear, so the union holds a `ThinBox`
+        (!self.is_code()).then(|| unsafe { &**self.repr.boxed })
+    }
+
+    /// Returns a reference to the boxed error if it is of type `E`. Errors created with `from_boxed` are downcast
+    /// from the box they were created with.
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&E> {
+        let e = self.boxed()?;
+        match e.downcast_ref::<BoxedError>() {
+            Some(b) => b.0.downcast_ref(),
+            None => e.downcast_ref(),
+        }
+    }
+}
+
+impl<C> Drop for CompactError<C> {
+    fn drop(&mut self) {
+        // SAFETY: all bit patterns are valid `usize`s, and if the low bit is clear, the union holds a `ThinBox`
+        unsafe {
+            if self.repr.code & CODE == 0 {
+                ManuallyDrop::drop(&mut self.repr.boxed)
+            }
+        }
+    }
+}
+
+impl<C: TagValue, E: Error + Send + Sync + 'static> From<E> for CompactError<C> {
+    fn from(e: E) -> Self {
+        CompactError::new(e)
+    }
+}
+
+impl<C: TagValue + fmt::Debug> fmt::Debug for CompactError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code(), self.boxed()) {
+            (Some(code), _) => fmt::Debug::fmt(&code, f),
+            (_, Some(e)) => fmt::Debug::fmt(e, f),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<C: TagValue + fmt::Display> fmt::Display for CompactError<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code(), self.boxed()) {
+            (Some(code), _) => fmt::Display::fmt(&code, f),
+            (_, Some(e)) => fmt::Display::fmt(e, f),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompactError, TagValue};
+    use std::{boxed::Box, fmt, format, io, mem, sync::Arc};
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Code {
+        NotFound,
+        Busy,
+    }
+
+    impl TagValue for Code {
+        const BITS: u32 = 1;
+
+        fn into_bits(self) -> usize {
+            self as usize
+        }
+
+        fn from_bits(bits: usize) -> Self {
+            [Code::NotFound, Code::Busy][bits]
+        }
+    }
+
+    impl fmt::Display for Code {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(if *self == Code::NotFound { "not found" } else { "busy" })
+        }
+    }
+
+    fn parse(s: &str) -> Result<u32, CompactError<Code>> {
+        if s.is_empty() {
+            return Err(CompactError::from_code(Code::NotFound));
+        }
+        Ok(s.parse()?)
+    }
+
+    #[test]
+    fn codes_and_boxes() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<CompactError<Code>>());
+        assert_eq!(parse("12").ok(), Some(12));
+
+        let e = parse("").unwrap_err();
+        assert_eq!((e.code(), e.boxed().is_none()), (Some(Code::NotFound), true));
+        assert_eq!(format!("{} {:?}", e, e), "not found NotFound");
+        assert_eq!(format!("{}", CompactError::from_code(Code::Busy)), "busy");
+
+        let e = parse("x").unwrap_err();
+        assert!(!e.is_code() && e.code().is_none());
+        assert!(e.downcast_ref::<core::num::ParseIntError>().is_some());
+        assert_eq!(format!("{}", e), "invalid digit found in string");
+
+        let e = CompactError::<Code>::from_boxed(Box::new(io::Error::other("disk")));
+        assert_eq!(format!("{}", e), "disk");
+        assert_eq!(
+            e.downcast_ref::<io::Error>().map(|e| e.kind()),
+            Some(io::ErrorKind::Other)
+        );
+    }
+
+    #[test]
+    fn drops() {
+        #[derive(Debug)]
+        struct Tracked(#[allow(dead_code)] Arc<()>);
+
+        impl fmt::Display for Tracked {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("tracked")
+            }
+        }
+
+        impl std::error::Error for Tracked {}
+
+        let arc = Arc::new(());
+        let e = CompactError::<Code>::new(Tracked(arc.clone()));
+        assert_eq!(Arc::strong_count(&arc), 2);
+        drop(e);
+        assert_eq!(Arc::strong_count(&arc), 1);
+        drop(CompactError::<Code>::from_code(Code::Busy));
+    }
+}
@@ -0,0 +1,228 @@
+use crate::PointerValuePair;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+/// A region of memory of at most 4 GiB, whose pointers can be compressed to 32-bit `CompressedPtr`s (offsets from
+/// the start of the region).
+///
+/// The base is the source of the provenance of the decompressed pointers, so it must be derived from a pointer to
+/// the whole region (e.g. `slice.as_ptr()`).
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CompressedBase {
+    base: *const u8,
+    len: usize,
+}
+
+impl CompressedBase {
+    /// Creates a new `CompressedBase` for the `len` bytes starting at `base`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is greater than 4 GiB.
+    pub fn new(base: *const u8, len: usize) -> CompressedBase {
+        assert!(
+            len as u64 <= 1 << 32,
+            "the region is too large ({len} bytes) to be addressed with 32-bit offsets"
+        );
+        CompressedBase { base, len }
+    }
+
+    /// Creates a new `CompressedBase` for the elements of a slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice is larger than 4 GiB.
+    pub fn for_slice<T>(s: &[T]) -> CompressedBase {
+        CompressedBase::new(s.as_ptr().cast(), size_of_val(s))
+    }
+
+    /// Returns whether the pointer is in the region.
+    pub fn contains<T>(&self, ptr: *const T) -> bool {
+        ptr.addr().wrapping_sub(self.base.addr()) < self.len
+    }
+
+    /// Compresses a pointer into the region, with the given value stored in its alignment bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pointer is not in the region, if its offset from the base is not aligned to `T`, or if the value
+    /// doesn't fit in the available bits.
+    pub fn compress<T>(&self, ptr: *const T, value: usize) -> CompressedPtr<T> {
+        assert!(self.contains(ptr), "the pointer {ptr:p} is not in the region");
+        let offset = ptr.addr() - self.base.addr();
+        assert!(
+            offset & CompressedPtr::<T>::max_value() == 0,
+            "the offset of the pointer from the base ({offset}) is not aligned"
+        );
+        assert!(
+            value <= CompressedPtr::<T>::max_value(),
+            "not enough alignment bits ({}) to store the value ({})",
+            CompressedPtr::<T>::available_bits(),
+            value
+        );
+        CompressedPtr::from_raw((offset | value) as u32)
+    }
+
+    /// Returns the pointer represented by a `CompressedPtr` of this region.
+    ///
+    /// Decompressing a `CompressedPtr` created by another `CompressedBase` returns a pointer with the same offset
+    /// in this region, which may not be in bounds.
+    pub fn decompress<T>(&self, ptr: CompressedPtr<T>) -> *const T {
+        self.base.wrapping_add(ptr.offset()).cast()
+    }
+}
+
+/// A pointer compressed to a 32-bit offset from the start of a region (see `CompressedBase`), with an integer value
+/// packed in the low bits of the offset that are known to be zero because of the alignment of `T`.
+///
+/// This halves the size of the pointers on 64-bit targets, at the cost of an addition when decompressing them, for
+/// data structures whose nodes all live in a single allocation of at most 4 GiB (e.g. the nodes of a graph in a
+/// `Vec`).
+///
+/// # Notes
+///
+/// The offsets are only aligned if the base of the region is aligned to `T`, which is the case for `for_slice`.
+#[repr(transparent)]
+pub struct CompressedPtr<T> {
+    repr: u32,
+    _phantom: PhantomData<*const T>,
+}
+
+impl<T> Copy for CompressedPtr<T> {}
+
+impl<T> Clone for CompressedPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for CompressedPtr<T> {
+    /// Compares the offsets and values of both pointers.
+    fn eq(&self, other: &Self) -> bool {
+        self.repr == other.repr
+    }
+}
+
+impl<T> Eq for CompressedPtr<T> {}
+
+impl<T> Hash for CompressedPtr<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.repr.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for CompressedPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompressedPtr")
+            .field("offset", &self.offset())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<T> CompressedPtr<T> {
+    /// Creates a `CompressedPtr` from its packed representation, returned by `into_raw`.
+    pub const fn from_raw(repr: u32) -> CompressedPtr<T> {
+        CompressedPtr {
+            repr,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the packed representation of the pointer.
+    pub const fn into_raw(self) -> u32 {
+        self.repr
+    }
+
+    /// Returns the number of bits available to store the value.
+    pub const fn available_bits() -> u32 {
+        PointerValuePair::<T>::available_bits()
+    }
+
+    /// Returns the maximum (inclusive) integer value that can be stored in the pointer.
+    pub const fn max_value() -> usize {
+        PointerValuePair::<T>::max_value()
+    }
+
+    /// Returns the offset of the pointer from the base of its region, in bytes.
+    pub fn offset(self) -> usize {
+        self.repr as usize & !Self::max_value()
+    }
+
+    /// Returns the value stored alongside the offset.
+    pub fn value(self) -> usize {
+        self.repr as usize & Self::max_value()
+    }
+
+    /// Replaces the value stored alongside the offset, and returns the previous one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn replace_value(&mut self, value: usize) -> usize {
+        assert!(
+            value <= Self::max_value(),
+            "not enough alignment bits ({}) to store the value ({})",
+            Self::available_bits(),
+            value
+        );
+        let old = self.value();
+        self.repr = (self.offset() | value) as u32;
+        old
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompressedBase, CompressedPtr};
+    use std::{format, mem, ptr, vec::Vec};
+
+    struct Node {
+        weight: u32,
+        edges: [CompressedPtr<Node>; 2],
+    }
+
+    #[test]
+    fn graph() {
+        assert_eq!(mem::size_of::<u32>(), mem::size_of::<CompressedPtr<Node>>());
+        assert_eq!(CompressedPtr::<Node>::max_value(), 3);
+
+        let mut nodes: Vec<Node> = (0..4)
+            .map(|i| Node {
+                weight: i * 10,
+                edges: [CompressedPtr::from_raw(0); 2],
+            })
+            .collect();
+        let base = CompressedBase::for_slice(&nodes);
+        let ptrs: Vec<_> = nodes.iter().map(|n| base.compress(n, 0)).collect();
+        for (i, node) in nodes.iter_mut().enumerate() {
+            node.edges = [ptrs[(i + 1) % 4], ptrs[(i + 2) % 4]];
+            node.edges[1].replace_value(i % 4);
+        }
+
+        let base = CompressedBase::for_slice(&nodes);
+        let edge = nodes[3].edges[1];
+        assert_eq!((edge.offset(), edge.value()), (mem::size_of::<Node>(), 3));
+        assert!(ptr::eq(base.decompress(edge), &nodes[1]));
+        let weight = |p: CompressedPtr<Node>| unsafe { (*base.decompress(p)).weight };
+        assert_eq!(
+            nodes.iter().map(|n| weight(n.edges[0])).collect::<Vec<_>>(),
+            [10, 20, 30, 0]
+        );
+        assert_eq!(
+            format!("{:?}", edge),
+            format!("CompressedPtr {{ offset: {}, value: 3 }}", mem::size_of::<Node>())
+        );
+    }
+
+    #[test]
+    #[should_panic = "not in the region"]
+    fn out_of_region() {
+        let data = [0u32; 4];
+        let other = 0u32;
+        CompressedBase::for_slice(&data).compress(&other, 0);
+    }
+}
@@ -1,13 +1,46 @@
-use crate::{PointerValuePair, PointerValuePairAccess};
-use std::{marker::PhantomData, mem, ops::Deref};
+use crate::{
+    try_alloc::{try_box, try_box_slice},
+    AllocError, PointeeAlign, PointerValuePair, PointerValuePairAccess,
+};
+use alloc::{
+    borrow::{Borrow, Cow as StdCow, ToOwned},
+    boxed::Box,
+    string::String,
+    vec::{self, Vec},
+};
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem,
+    ops::Deref,
+    pin::Pin,
+    ptr, slice,
+};
 
 /// A pointer-sized object that holds either a borrow (`&'a T`) or a boxed value (`Box<T>`).
 ///
 /// TODO doc: implements deref, construction, ToOwned, etc.
 ///
+/// # Trait objects
+///
+/// `Cow<'a, dyn Trait>` holds either a `&'a dyn Trait` or a `Box<dyn Trait>`, once the minimum alignment of the
+/// implementors of the trait is declared with `PointeeAlign` (see `CowPointee`). With the `unsize` feature, a
+/// `Cow<'a, T>` can also be coerced into a `Cow<'a, dyn Trait>` if `T: Trait`.
+///
+/// # Variance
+///
+/// Like `std::borrow::Cow`, `Cow<'a, T>` is covariant in `'a` and `T`: the borrowed value is only read, and the
+/// boxed value is owned.
+///
 /// # Notes
 ///
-/// Because it uses `PointerValuePair` internally, `T` cannot not be a zero-sized type.
+/// Because it uses `PointerValuePair` internally, `T` (or the element type, for slices) must be aligned to at least
+/// 2 bytes, which is checked at compile time when creating owned `Cow`s. Zero-sized types are always supported: the
+/// discriminant is stored in the address of a dangling pointer instead, which the `Cow` creates itself.
+/// `str`, `CStr` and `ByteSlice` (for byte slices) store the discriminant in the length instead.
 #[repr(transparent)]
 pub struct Cow<'a, T>
 where
@@ -18,50 +51,230 @@ where
     _phantom: PhantomData<&'a T>,
 }
 
+/// Allows coercing `Cow<'a, T>` into `Cow<'a, U>` when `T: Unsize<U>`, like `Box` and references.
+#[cfg(feature = "unsize")]
+impl<'a, T, U> core::ops::CoerceUnsized<Cow<'a, U>> for Cow<'a, T>
+where
+    T: ?Sized + core::marker::Unsize<U>,
+    U: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess,
+    PointerValuePair<U>: PointerValuePairAccess,
+{
+}
+
+// SAFETY: a `Cow` is either a `&'a T`, which is `Send` if `T: Sync`, or a `Box<T>`, which is `Send` if `T: Send`.
+unsafe impl<'a, T> Send for Cow<'a, T>
+where
+    T: ?Sized + Send + Sync,
+    PointerValuePair<T>: PointerValuePairAccess,
+{
+}
+
+// SAFETY: a `&Cow` only gives access to a `&T`.
+unsafe impl<'a, T> Sync for Cow<'a, T>
+where
+    T: ?Sized + Sync,
+    PointerValuePair<T>: PointerValuePairAccess,
+{
+}
+
 const BORROWED: usize = 0usize;
 const OWNED: usize = 1usize;
 
-impl<'a, T> Cow<'a, T> {
+/// Returns the address of the dangling pointers held by `Cow`s of zero-sized types, which store the discriminant in
+/// the address rather than in the low bits: any non-null address aligned to `align` is valid for them.
+const fn zst_addr(align: usize, discriminant: usize) -> usize {
+    align << discriminant
+}
+
+/// Pointee types that can be held by a `Cow`: sized types, slices, `str`, and other dynamically-sized types that
+/// implement `PointeeAlign` (e.g. trait objects).
+///
+/// # Safety
+///
+/// `pack` must return a pair from which `PointerValuePairAccess::ptr` and `PointerValuePairAccess::value` return the
+/// original pointer and value, and `MAX_VALUE` must be the maximum value that can be packed.
+pub unsafe trait CowPointee {
+    /// The maximum (inclusive) value that can be packed with a pointer to `Self`.
+    const MAX_VALUE: usize;
+
+    /// Packs a pointer to `Self` with the given value.
+    fn pack(ptr: *const Self, value: usize) -> PointerValuePair<Self>;
+}
+
+unsafe impl<T: ?Sized + PointeeAlign> CowPointee for T {
+    const MAX_VALUE: usize = T::ALIGN - 1;
+
+    fn pack(ptr: *const T, value: usize) -> PointerValuePair<T> {
+        PointerValuePair::new(ptr, value)
+    }
+}
+
+unsafe impl CowPointee for str {
+    const MAX_VALUE: usize = PointerValuePair::<str>::max_value();
+
+    fn pack(ptr: *const str, value: usize) -> PointerValuePair<str> {
+        PointerValuePair::new_str(ptr, value)
+    }
+}
+
+/// Pointee types that can be cloned into a box, used to turn a borrowed `Cow` into an owned one.
+///
+/// This is the equivalent of `std::borrow::ToOwned` for `Cow`, whose owned form is always a `Box<Self>`.
+pub trait ToBoxed {
+    /// Clones the value into a box.
+    fn to_boxed(&self) -> Box<Self>;
+}
+
+impl<T: Clone> ToBoxed for T {
+    fn to_boxed(&self) -> Box<T> {
+        Box::new(self.clone())
+    }
+}
+
+impl<T: Clone> ToBoxed for [T] {
+    fn to_boxed(&self) -> Box<[T]> {
+        self.into()
+    }
+}
+
+impl ToBoxed for str {
+    fn to_boxed(&self) -> Box<str> {
+        self.into()
+    }
+}
+
+impl<'a, T> Cow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
     /// Creates a new `Cow` representing a borrowed value.
-    pub fn borrowed(v: &'a T) -> Cow<'a, T> {
+    ///
+    /// This is a `const fn`, so that `Cow`s borrowing `static` items can be created in constant expressions.
+    pub const fn borrowed(v: &'a T) -> Cow<'a, T> {
+        let mut ptr: *const T = v;
+        if <PointerValuePair<T> as PointerValuePairAccess>::ZERO_SIZED {
+            // `with_addr` can't be called in a `const fn`: build the dangling pointer from its address
+            let dangling = ptr::without_provenance::<u8>(zst_addr(T::MAX_VALUE + 1, BORROWED));
+            // SAFETY: zero-sized types are sized, so `*const T` is a thin pointer, with the same layout as `*const u8`
+            ptr = unsafe { ptr::read((&dangling as *const *const u8).cast::<*const T>()) };
+        }
+        // the borrowed discriminant is zero, so the pointer is already packed
         Cow {
-            inner: PointerValuePair::new(v, BORROWED),
+            inner: PointerValuePair::from_raw(ptr),
             _phantom: PhantomData,
         }
     }
 
     /// Creates a new `Cow` holding a boxed value.
     pub fn owned(v: Box<T>) -> Cow<'a, T> {
+        const {
+            assert!(
+                <PointerValuePair<T> as PointerValuePairAccess>::ZERO_SIZED || T::MAX_VALUE >= OWNED,
+                "not enough alignment bits to store the discriminant"
+            )
+        };
+        let ptr = Box::into_raw(v);
+        let inner = if <PointerValuePair<T> as PointerValuePairAccess>::ZERO_SIZED {
+            // boxes of zero-sized types don't allocate, so the address can be replaced
+            PointerValuePair::from_raw(ptr.with_addr(zst_addr(T::MAX_VALUE + 1, OWNED)))
+        } else {
+            T::pack(ptr, OWNED)
+        };
         Cow {
-            inner: PointerValuePair::new(Box::into_raw(v), OWNED),
+            inner,
             _phantom: PhantomData,
         }
     }
-}
 
-impl<'a, T> Cow<'a, T>
-where
-    T: Clone,
-{
+    /// Creates a new pinned `Cow` representing a borrow of a pinned value.
+    pub fn borrowed_pinned(v: Pin<&'a T>) -> Pin<Cow<'a, T>> {
+        // SAFETY: the value is pinned for `'a`, and a `Cow` only gives shared access to it
+        unsafe { Pin::new_unchecked(Cow::borrowed(Pin::get_ref(v))) }
+    }
+
+    /// Creates a new pinned `Cow` holding a pinned boxed value.
+    ///
+    /// Since a pinned `Cow` only gives shared access to the value, it never moves out of the box.
+    pub fn owned_pinned(v: Pin<Box<T>>) -> Pin<Cow<'a, T>> {
+        unsafe {
+            // SAFETY: the value stays in the same allocation, which is dropped in place by the `Cow`
+            Pin::new_unchecked(Cow::owned(Pin::into_inner_unchecked(v)))
+        }
+    }
+
+    /// Returns a shared reference to the `Cow` of a pinned `Cow`, e.g. to check whether it is owned.
+    pub fn unpin_ref<'b>(this: &'b Pin<Cow<'a, T>>) -> &'b Cow<'a, T> {
+        // SAFETY: `Pin` is `repr(transparent)`, and `&Cow` only gives shared access to the value
+        unsafe { &*(this as *const Pin<Cow<'a, T>> as *const Cow<'a, T>) }
+    }
+
     /// Converts this `Cow` into a `Box<T>`. If this `Cow` is a borrow, clones the value and boxes it.
-    pub fn into_owned(self) -> Box<T> {
-        if self.inner.value() == OWNED {
+    pub fn into_owned(self) -> Box<T>
+    where
+        T: ToBoxed,
+    {
+        if self.is_owned() {
             let boxed = unsafe {
                 // SAFETY: the pointer has been created with `Box::into_raw` by `Cow::owned`.
                 // We inhibit drop by calling mem::forget below.
-                Box::from_raw(self.inner.ptr() as *mut T)
+                Box::from_raw(self.inner.mut_ptr())
             };
             // we extracted the boxed value already, don't double-drop
             mem::forget(self);
             boxed
         } else {
-            Box::new(self.deref().clone())
+            T::to_boxed(&self)
+        }
+    }
+
+    /// Returns the box if this `Cow` is owned, or gives `self` back if it is borrowed.
+    ///
+    /// Unlike `into_owned`, this doesn't require `T: ToBoxed`, so it can be used with values that can't be cloned.
+    pub fn try_unwrap_owned(self) -> Result<Box<T>, Self> {
+        if !self.is_owned() {
+            return Err(self);
+        }
+        match self.into_ref_or_box() {
+            Err(boxed) => Ok(boxed),
+            Ok(_) => unreachable!(),
+        }
+    }
+
+    /// Returns a reference to the value that lives for `'a`: the original reference if this `Cow` is borrowed, or
+    /// the leaked box (see `Box::leak`) if it is owned.
+    ///
+    /// This is useful for values that must live for the rest of the program (e.g. interned names), and avoids
+    /// allocating again when the `Cow` is already owned. The memory of owned values is never freed.
+    pub fn leak(self) -> &'a T {
+        match self.into_ref_or_box() {
+            Ok(r) => r,
+            Err(boxed) => Box::leak(boxed),
+        }
+    }
+
+    /// Returns a mutable reference to the owned value. If this `Cow` is a borrow, clones the value and boxes it
+    /// first.
+    pub fn to_mut(&mut self) -> &mut T
+    where
+        T: ToBoxed,
+    {
+        if !self.is_owned() {
+            *self = Cow::owned(T::to_boxed(self));
+        }
+        unsafe {
+            // SAFETY: we own the boxed value, and borrow `self` mutably.
+            &mut *self.inner.mut_ptr()
         }
     }
 
     /// Converts this `Cow` into an owned `Cow` by cloning the value and boxing it, if it is borrowed.
-    pub fn into_owned_cow<'b>(self) -> Cow<'b, T> {
-        if self.inner.value() == OWNED {
+    pub fn into_owned_cow<'b>(self) -> Cow<'b, T>
+    where
+        T: ToBoxed,
+    {
+        if self.is_owned() {
             // We own the value, so it's OK to just transfer it
             let result = Cow {
                 inner: self.inner,
@@ -71,63 +284,271 @@ where
             mem::forget(self);
             result
         } else {
-            Cow::owned(Box::new(self.deref().clone()))
+            Cow::owned(T::to_boxed(&self))
+        }
+    }
+
+    /// Returns the original reference if this `Cow` is borrowed, or the box if it is owned.
+    fn into_ref_or_box(self) -> Result<&'a T, Box<T>> {
+        let ptr = self.inner.mut_ptr();
+        let owned = self.is_owned();
+        // ownership of the box (if any) is transferred to the result
+        mem::forget(self);
+        unsafe {
+            if owned {
+                // SAFETY: the pointer has been created with `Box::into_raw` by `Cow::owned`.
+                Err(Box::from_raw(ptr))
+            } else {
+                // SAFETY: the pointer comes from a `&'a T` passed to `Cow::borrowed`.
+                Ok(&*ptr)
+            }
+        }
+    }
+
+    /// Maps the value of this `Cow`, with `f` if it is borrowed or with `g` if it is owned.
+    ///
+    /// This can be used to narrow a `Cow` to a part of its value without cloning, e.g.
+    /// `cow.map(|v| &v.field, |b| Box::new(b.field))`.
+    pub fn map<U>(self, f: impl FnOnce(&T) -> &U, g: impl FnOnce(Box<T>) -> Box<U>) -> Cow<'a, U>
+    where
+        U: ?Sized + CowPointee,
+        PointerValuePair<U>: PointerValuePairAccess<Target = U>,
+    {
+        match self.into_ref_or_box() {
+            Ok(v) => Cow::borrowed(f(v)),
+            Err(b) => Cow::owned(g(b)),
+        }
+    }
+
+    /// Fallible version of `map`: maps the value of this `Cow` with `f` if it is borrowed or with `g` if it is owned,
+    /// and returns the error of the closure if it fails.
+    pub fn try_map<U, E>(
+        self,
+        f: impl FnOnce(&T) -> Result<&U, E>,
+        g: impl FnOnce(Box<T>) -> Result<Box<U>, E>,
+    ) -> Result<Cow<'a, U>, E>
+    where
+        U: ?Sized + CowPointee,
+        PointerValuePair<U>: PointerValuePairAccess<Target = U>,
+    {
+        match self.into_ref_or_box() {
+            Ok(v) => f(v).map(Cow::borrowed),
+            Err(b) => g(b).map(Cow::owned),
+        }
+    }
+
+    /// Projects this `Cow` to a part of its value. If it is borrowed, the result borrows the same data; if it is owned,
+    /// only the part returned by `f` is cloned.
+    pub fn project<U>(self, f: impl FnOnce(&T) -> &U) -> Cow<'a, U>
+    where
+        U: ?Sized + CowPointee + ToBoxed,
+        PointerValuePair<U>: PointerValuePairAccess<Target = U>,
+    {
+        match self.into_ref_or_box() {
+            Ok(v) => Cow::borrowed(f(v)),
+            Err(b) => Cow::owned(f(&b).to_boxed()),
+        }
+    }
+}
+
+impl<'a, T> Cow<'a, T> {
+    /// Creates a new `Cow` holding the given value, after boxing it.
+    pub fn owned_value(v: T) -> Cow<'a, T> {
+        Cow::owned(Box::new(v))
+    }
+
+    /// Creates a new `Cow` holding the given value, after boxing it, or returns an error if the allocation fails.
+    pub fn try_owned_value(v: T) -> Result<Cow<'a, T>, AllocError> {
+        try_box(v).map(Cow::owned)
+    }
+
+    /// Returns the value, moved out of the box if this `Cow` is owned, or cloned if it is borrowed.
+    pub fn into_inner(self) -> T
+    where
+        T: Clone,
+    {
+        match self.try_into_inner() {
+            Ok(v) => v,
+            Err(this) => T::clone(&this),
+        }
+    }
+
+    /// Returns the value moved out of the box if this `Cow` is owned, or gives `self` back if it is borrowed.
+    ///
+    /// Unlike `into_inner`, this doesn't require `T: Clone`.
+    pub fn try_into_inner(self) -> Result<T, Self> {
+        self.try_unwrap_owned().map(|boxed| *boxed)
+    }
+
+    /// Converts this `Cow` into a `std::borrow::Cow`, unboxing the value if it is owned.
+    pub fn into_std_cow(self) -> StdCow<'a, T>
+    where
+        T: Clone,
+    {
+        match self.borrowed_ref() {
+            Some(v) => StdCow::Borrowed(v),
+            None => StdCow::Owned(*self.into_owned()),
         }
     }
 }
 
 impl<'a, T> Cow<'a, [T]> {
     /// Creates a new `Cow` representing a borrowed value.
-    pub fn borrowed_slice(v: &'a [T]) -> Cow<'a, [T]> {
-        Cow {
-            inner: PointerValuePair::new_slice(v, BORROWED),
-            _phantom: PhantomData,
-        }
+    ///
+    /// This is the same as `Cow::borrowed`.
+    pub const fn borrowed_slice(v: &'a [T]) -> Cow<'a, [T]> {
+        Cow::borrowed(v)
     }
 
-    /// Creates a new `Cow` holding a boxed value.
+    /// Creates a new `Cow` holding a boxed slice.
+    ///
+    /// This is the same as `Cow::owned`.
     pub fn owned_slice(v: Box<[T]>) -> Cow<'a, [T]> {
-        Cow {
-            inner: PointerValuePair::new_slice(Box::into_raw(v), OWNED),
-            _phantom: PhantomData,
-        }
+        Cow::owned(v)
+    }
+
+    /// Creates a new `Cow` holding a boxed copy of the given slice, or returns an error if the allocation fails.
+    pub fn try_owned_slice_from(v: &[T]) -> Result<Cow<'a, [T]>, AllocError>
+    where
+        T: Clone,
+    {
+        try_box_slice(v).map(Cow::owned)
     }
 }
 
-// impl Cow<[T]>
 impl<'a, T> Cow<'a, [T]>
 where
-    T: Copy,
+    T: Clone,
 {
     /// Converts this `Cow` into a boxed slice. If this `Cow` is a borrow, clones the slice and boxes it.
+    ///
+    /// This is the same as `into_owned`.
     pub fn into_owned_slice(self) -> Box<[T]> {
-        if self.inner.value() == OWNED {
-            let boxed = unsafe {
-                // SAFETY: the pointer has been created with `Box::into_raw` by `Cow::owned`.
-                // We inhibit drop by calling mem::forget below.
-                Box::from_raw(self.inner.ptr() as *mut [T])
-            };
-            // we extracted the boxed value already, don't double-drop
-            mem::forget(self);
-            boxed
-        } else {
-            self.deref().into()
-        }
+        self.into_owned()
     }
 
     /// Converts this `Cow` into an owned `Cow` by cloning the value and boxing it, if it is borrowed.
+    ///
+    /// This is the same as `into_owned_cow`.
     pub fn into_owned_cow_slice<'b>(self) -> Cow<'b, [T]> {
-        if self.inner.value() == OWNED {
-            // We own the value, so it's OK to just transfer it
-            let result = Cow {
-                inner: self.inner,
-                _phantom: Default::default(),
-            };
-            // we transferred ownership of the box, don't double-drop
-            mem::forget(self);
-            result
+        self.into_owned_cow()
+    }
+
+    /// Converts this `Cow` into a `std::borrow::Cow`, converting the boxed slice to a `Vec` if it is owned.
+    pub fn into_std_cow(self) -> StdCow<'a, [T]> {
+        match self.borrowed_ref() {
+            Some(v) => StdCow::Borrowed(v),
+            None => StdCow::Owned(self.into_owned().into_vec()),
+        }
+    }
+}
+
+impl<'a> Cow<'a, str> {
+    /// Creates a new `Cow` representing a borrowed string slice.
+    ///
+    /// This is the same as `Cow::borrowed`.
+    pub const fn borrowed_str(v: &'a str) -> Cow<'a, str> {
+        Cow::borrowed(v)
+    }
+
+    /// Creates a new `Cow` holding a boxed string slice.
+    ///
+    /// This is the same as `Cow::owned`.
+    pub fn owned_str(v: Box<str>) -> Cow<'a, str> {
+        Cow::owned(v)
+    }
+
+    /// Creates a new `Cow` holding a boxed copy of the given string, or returns an error if the allocation fails.
+    pub fn try_owned_str_from(v: &str) -> Result<Cow<'a, str>, AllocError> {
+        let bytes = try_box_slice(v.as_bytes())?;
+        // SAFETY: the bytes have been copied from a `str`
+        Ok(Cow::owned(unsafe { alloc::str::from_boxed_utf8_unchecked(bytes) }))
+    }
+
+    /// Converts this `Cow` into a `String`. If this `Cow` is a borrow, copies the string.
+    pub fn into_string(self) -> String {
+        self.into_owned().into_string()
+    }
+
+    /// Converts this `Cow` into a `std::borrow::Cow`, converting the boxed string to a `String` if it is owned.
+    pub fn into_std_cow(self) -> StdCow<'a, str> {
+        match self.borrowed_ref() {
+            Some(v) => StdCow::Borrowed(v),
+            None => StdCow::Owned(self.into_string()),
+        }
+    }
+}
+
+impl<'a, T> Cow<'a, T>
+where
+    T: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess,
+{
+    /// Swaps the contents of two `Cow`s, without cloning or moving the pointees.
+    pub fn swap(&mut self, other: &mut Self) {
+        mem::swap(self, other)
+    }
+
+    /// Returns whether this `Cow` holds a borrow.
+    pub fn is_borrowed(&self) -> bool {
+        self.discriminant() == BORROWED
+    }
+
+    /// Returns whether this `Cow` holds a boxed value.
+    pub fn is_owned(&self) -> bool {
+        self.discriminant() == OWNED
+    }
+
+    /// Returns the discriminant, which is stored in the address of the pointer for zero-sized types (see `zst_addr`).
+    fn discriminant(&self) -> usize {
+        if <PointerValuePair<T> as PointerValuePairAccess>::ZERO_SIZED {
+            let align = <PointerValuePair<T> as PointerValuePairAccess>::max_value() + 1;
+            (self.inner.into_raw().addr() / align).trailing_zeros() as usize
+        } else {
+            self.inner.value()
+        }
+    }
+}
+
+impl<'a, T> Cow<'a, T>
+where
+    T: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Returns whether the two `Cow`s point to the same value, like `Rc::ptr_eq`, regardless of whether they are
+    /// borrowed or owned.
+    ///
+    /// Unlike `==`, which compares the pointees, this only compares the pointers (and the lengths, for slices).
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.inner.ptr_eq(other.inner)
+    }
+
+    /// Returns a reference to the value.
+    ///
+    /// This is the same as dereferencing the `Cow`, but can be used in generic code where `Deref` isn't available.
+    #[allow(clippy::should_implement_trait)]
+    pub fn as_ref(&self) -> &T {
+        self
+    }
+
+    /// Returns a mutable reference to the value if this `Cow` owns it, or `None` if it is borrowed.
+    ///
+    /// Unlike `to_mut`, this never clones the value.
+    #[allow(clippy::should_implement_trait)]
+    pub fn as_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: we own the boxed value, and borrow `self` mutably.
+        self.is_owned().then(|| unsafe { &mut *self.inner.mut_ptr() })
+    }
+
+    /// Returns the original reference if this `Cow` is borrowed, or `None` if it is owned.
+    ///
+    /// Unlike `as_ref`, the returned reference is not tied to the lifetime of the `Cow`.
+    pub fn borrowed_ref(&self) -> Option<&'a T> {
+        if self.is_borrowed() {
+            // SAFETY: the pointer comes from a `&'a T` passed to one of the `borrowed` constructors.
+            Some(unsafe { &*PointerValuePairAccess::ptr(self.inner) })
         } else {
-            Cow::owned_slice(self.deref().into())
+            None
         }
     }
 }
@@ -138,51 +559,508 @@ where
     PointerValuePair<T>: PointerValuePairAccess,
 {
     fn drop(&mut self) {
+        validate!(self.discriminant() <= OWNED, "corrupted Cow discriminant");
         unsafe {
-            if self.inner.value() == OWNED {
+            if self.is_owned() {
                 drop(Box::from_raw(self.inner.mut_ptr()))
             }
         }
     }
 }
 
-impl<'a, T> Deref for Cow<'a, T> {
+impl<'a, T> Deref for Cow<'a, T>
+where
+    T: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
     type Target = T;
 
-    fn deref(&self) -> &Self::Target {
+    fn deref(&self) -> &T {
+        validate!(
+            self.discriminant() <= OWNED && !PointerValuePairAccess::ptr(self.inner).is_null(),
+            "corrupted Cow pointer"
+        );
         // SAFETY: ptr is either a pointer to a boxed value for which we are the owner (and are responsible for the deletion),
         // or a pointer to a borrowed value, whose validity is ensured by the lifetime bound.
-        unsafe { &*self.inner.ptr() }
+        unsafe { &*PointerValuePairAccess::ptr(self.inner) }
     }
 }
 
-impl<'a, T> Deref for Cow<'a, [T]> {
-    type Target = [T];
+impl<'a, T> AsRef<T> for Cow<'a, T>
+where
+    T: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
 
-    fn deref(&self) -> &Self::Target {
-        // SAFETY: ptr is either a pointer to a boxed value for which we are the owner (and are responsible for the deletion),
-        // or a pointer to a borrowed value, whose validity is ensured by the lifetime bound.
-        unsafe { &*self.inner.ptr() }
+impl<'a, T> Borrow<T> for Cow<'a, T>
+where
+    T: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<'a, T> Clone for Cow<'a, T>
+where
+    T: ?Sized + CowPointee + ToBoxed,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Clones the borrow if this `Cow` is borrowed, or clones the boxed value if it is owned.
+    fn clone(&self) -> Self {
+        if self.is_owned() {
+            Cow::owned(T::to_boxed(self))
+        } else {
+            Cow {
+                inner: self.inner,
+                _phantom: PhantomData,
+            }
+        }
+    }
+}
+
+impl<'a, T> fmt::Debug for Cow<'a, T>
+where
+    T: ?Sized + fmt::Debug,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    /// Formats the value as `Borrowed(..)` or `Owned(..)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.is_owned() { "Owned" } else { "Borrowed" };
+        f.debug_tuple(state).field(&&**self).finish()
+    }
+}
+
+impl<'a, T> fmt::Display for Cow<'a, T>
+where
+    T: ?Sized + fmt::Display,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// Forwards to the error, borrowed or owned, so that a `Cow` of an error can be used as an error. This also applies to
+/// `Cow<'a, dyn Error>`, once the alignment of the errors is declared with `PointeeAlign`.
+impl<'a, T> core::error::Error for Cow<'a, T>
+where
+    T: ?Sized + core::error::Error,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        (**self).source()
+    }
+
+    #[allow(deprecated)]
+    fn description(&self) -> &str {
+        (**self).description()
+    }
+}
+
+impl<'a, T> PartialEq for Cow<'a, T>
+where
+    T: ?Sized + PartialEq,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    /// Compares the values, regardless of whether they are borrowed or owned.
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<'a, 'b, T> PartialEq<&'b T> for Cow<'a, T>
+where
+    T: ?Sized + PartialEq,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    /// Compares the value with the referenced value.
+    fn eq(&self, other: &&'b T) -> bool {
+        **self == **other
+    }
+}
+
+impl<'a, T> PartialEq<Box<T>> for Cow<'a, T>
+where
+    T: ?Sized + PartialEq,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    /// Compares the value with the boxed value.
+    fn eq(&self, other: &Box<T>) -> bool {
+        **self == **other
+    }
+}
+
+impl<'a, 'b, T> PartialEq<StdCow<'b, T>> for Cow<'a, T>
+where
+    T: ?Sized + PartialEq + ToOwned,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    /// Compares the value with the value of a standard `Cow`, regardless of whether they are borrowed or owned.
+    fn eq(&self, other: &StdCow<'b, T>) -> bool {
+        **self == **other
+    }
+}
+
+impl<'a, T> PartialEq<T> for Cow<'a, T>
+where
+    T: ?Sized + PartialEq,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    /// Compares the value with `other`.
+    fn eq(&self, other: &T) -> bool {
+        **self == *other
+    }
+}
+
+impl<'a> PartialEq<String> for Cow<'a, str> {
+    fn eq(&self, other: &String) -> bool {
+        **self == **other
+    }
+}
+
+impl<'a> PartialEq<Cow<'a, str>> for String {
+    fn eq(&self, other: &Cow<'a, str>) -> bool {
+        **self == **other
     }
 }
 
-impl<'a, T> From<&'a [T]> for Cow<'a, [T]> {
-    /// Creates a borrowed `Cow<[T]>` from the given slice.
-    fn from(slice: &'a [T]) -> Self {
-        Cow::borrowed_slice(slice)
+impl<'a> PartialEq<Cow<'a, str>> for str {
+    fn eq(&self, other: &Cow<'a, str>) -> bool {
+        *self == **other
+    }
+}
+
+impl<'a> PartialEq<Cow<'a, str>> for &str {
+    fn eq(&self, other: &Cow<'a, str>) -> bool {
+        **self == **other
+    }
+}
+
+impl<'a, T> Eq for Cow<'a, T>
+where
+    T: ?Sized + Eq,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+}
+
+impl<'a, T> PartialOrd for Cow<'a, T>
+where
+    T: ?Sized + PartialOrd,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<'a, T> Ord for Cow<'a, T>
+where
+    T: ?Sized + Ord,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<'a, T> Hash for Cow<'a, T>
+where
+    T: ?Sized + Hash,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    /// Hashes the value, so that borrowed and owned `Cow`s holding equal values have the same hash.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl<'a, T> Default for Cow<'a, T>
+where
+    T: Default,
+{
+    /// Creates an owned `Cow` holding the default value of `T`.
+    fn default() -> Self {
+        Cow::owned(Box::default())
+    }
+}
+
+impl<'a, T> From<&'a T> for Cow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Creates a borrowed `Cow` from the given reference.
+    fn from(v: &'a T) -> Self {
+        Cow::borrowed(v)
+    }
+}
+
+impl<T> From<Box<T>> for Cow<'_, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Creates an owned `Cow` from the given box.
+    fn from(v: Box<T>) -> Self {
+        Cow::owned(v)
+    }
+}
+
+impl<T> From<T> for Cow<'_, T> {
+    /// Boxes the given value and creates an owned `Cow` holding it. See `Cow::owned_value`.
+    ///
+    /// Because of this impl, `Cow::from(x)` may need an annotation of the target type, e.g. `Cow::<str>::from(x)`.
+    fn from(v: T) -> Self {
+        Cow::owned_value(v)
+    }
+}
+
+impl<T> From<Vec<T>> for Cow<'_, [T]> {
+    /// Creates an owned `Cow<[T]>` from the given vector, shrinking its allocation to fit.
+    fn from(v: Vec<T>) -> Self {
+        Cow::owned_slice(v.into_boxed_slice())
+    }
+}
+
+impl<'a, T: Clone> From<StdCow<'a, T>> for Cow<'a, T> {
+    /// Converts a `std::borrow::Cow` into a `Cow`, boxing the value if it is owned.
+    fn from(cow: StdCow<'a, T>) -> Self {
+        match cow {
+            StdCow::Borrowed(v) => Cow::borrowed(v),
+            StdCow::Owned(v) => Cow::owned(Box::new(v)),
+        }
+    }
+}
+
+impl<'a, T: Clone> From<StdCow<'a, [T]>> for Cow<'a, [T]> {
+    /// Converts a `std::borrow::Cow` into a `Cow`, boxing the vector if it is owned.
+    fn from(cow: StdCow<'a, [T]>) -> Self {
+        match cow {
+            StdCow::Borrowed(v) => Cow::borrowed_slice(v),
+            StdCow::Owned(v) => Cow::owned_slice(v.into_boxed_slice()),
+        }
+    }
+}
+
+impl<'a> From<StdCow<'a, str>> for Cow<'a, str> {
+    /// Converts a `std::borrow::Cow` into a `Cow`, boxing the string if it is owned.
+    fn from(cow: StdCow<'a, str>) -> Self {
+        match cow {
+            StdCow::Borrowed(v) => Cow::borrowed_str(v),
+            StdCow::Owned(v) => Cow::owned_str(v.into_boxed_str()),
+        }
+    }
+}
+
+impl<T> FromIterator<T> for Cow<'_, [T]> {
+    /// Collects the elements into an owned `Cow<[T]>`.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Cow::owned_slice(iter.into_iter().collect())
+    }
+}
+
+impl FromIterator<char> for Cow<'_, str> {
+    /// Collects the characters into an owned `Cow<str>`.
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        Cow::owned_str(iter.into_iter().collect())
+    }
+}
+
+impl<'a, 'b, T> IntoIterator for &'b Cow<'a, [T]> {
+    type Item = &'b T;
+    type IntoIter = slice::Iter<'b, T>;
+
+    fn into_iter(self) -> slice::Iter<'b, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T: Clone> IntoIterator for Cow<'a, [T]> {
+    type Item = T;
+    type IntoIter = CowIntoIter<'a, T>;
+
+    /// Returns an iterator that clones the elements if the slice is borrowed, or moves them out of the box if it is
+    /// owned.
+    fn into_iter(self) -> CowIntoIter<'a, T> {
+        match self.into_ref_or_box() {
+            Ok(slice) => CowIntoIter::Borrowed(slice.iter()),
+            Err(boxed) => CowIntoIter::Owned(boxed.into_vec().into_iter()),
+        }
+    }
+}
+
+/// An iterator that moves out of a `Cow<[T]>`, cloning the elements if it is borrowed.
+///
+/// This is returned by `Cow::into_iter`.
+pub enum CowIntoIter<'a, T> {
+    /// Iterates over a borrowed slice, cloning the elements.
+    Borrowed(slice::Iter<'a, T>),
+    /// Moves the elements out of an owned slice.
+    Owned(vec::IntoIter<T>),
+}
+
+impl<T: fmt::Debug> fmt::Debug for CowIntoIter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CowIntoIter::Borrowed(iter) => f.debug_tuple("Borrowed").field(&iter.as_slice()).finish(),
+            CowIntoIter::Owned(iter) => f.debug_tuple("Owned").field(&iter.as_slice()).finish(),
+        }
+    }
+}
+
+impl<T: Clone> Iterator for CowIntoIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        match self {
+            CowIntoIter::Borrowed(iter) => iter.next().cloned(),
+            CowIntoIter::Owned(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            CowIntoIter::Borrowed(iter) => iter.size_hint(),
+            CowIntoIter::Owned(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<T: Clone> DoubleEndedIterator for CowIntoIter<'_, T> {
+    fn next_back(&mut self) -> Option<T> {
+        match self {
+            CowIntoIter::Borrowed(iter) => iter.next_back().cloned(),
+            CowIntoIter::Owned(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<T: Clone> ExactSizeIterator for CowIntoIter<'_, T> {}
+
+impl<T: Clone> FusedIterator for CowIntoIter<'_, T> {}
+
+impl From<String> for Cow<'_, str> {
+    /// Creates an owned `Cow<str>` from the given string, shrinking its allocation to fit.
+    fn from(s: String) -> Self {
+        Cow::owned_str(s.into_boxed_str())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::Cow;
-    use std::{cell::Cell, mem};
+    use crate::{Cow, PointeeAlign};
+    use std::{
+        borrow::Cow as StdCow,
+        boxed::Box,
+        cell::Cell,
+        collections::{HashMap, HashSet},
+        error::Error,
+        fmt, format,
+        marker::PhantomPinned,
+        mem,
+        pin::pin,
+        ptr,
+        string::String,
+        vec,
+        vec::Vec,
+    };
 
     #[test]
     fn pointer_sized() {
         assert_eq!(mem::size_of::<*const i32>(), mem::size_of::<Cow<'static, i32>>());
     }
 
+    #[test]
+    fn ptr_eq() {
+        let s = [1u32, 2, 3];
+        let a = Cow::borrowed(&s[..]);
+        let b = Cow::borrowed(&s[..]);
+        let c = Cow::owned_slice(Box::new([1u32, 2, 3]));
+        assert!(Cow::ptr_eq(&a, &b) && !Cow::ptr_eq(&a, &c));
+        assert_eq!(a, c);
+        assert!(!Cow::ptr_eq(&a, &Cow::borrowed(&s[..2])));
+    }
+
+    #[test]
+    fn try_unwrap_owned() {
+        // not `Clone`
+        #[derive(Debug, PartialEq)]
+        struct Handle(u32);
+
+        let h = Handle(1);
+        let cow = Cow::borrowed(&h);
+        let cow = cow.try_unwrap_owned().unwrap_err();
+        assert!(cow.is_borrowed());
+        let cow = Cow::owned(Box::new(Handle(2)));
+        assert_eq!(cow.try_unwrap_owned().ok(), Some(Box::new(Handle(2))));
+    }
+
+    #[test]
+    fn leak() {
+        let name: &'static str = Cow::owned_str("interned".into()).leak();
+        assert_eq!(name, "interned");
+        let s = [1u32, 2];
+        assert!(std::ptr::eq(Cow::borrowed(&s[..]).leak(), &s[..]));
+    }
+
+    #[test]
+    fn into_inner() {
+        let v = vec![1, 2];
+        assert_eq!(Cow::borrowed(&v).into_inner(), v);
+        assert_eq!(Cow::owned_value(vec![3]).into_inner(), [3]);
+        assert!(Cow::borrowed(&v).try_into_inner().is_err());
+        assert_eq!(Cow::<u32>::owned_value(4).try_into_inner().ok(), Some(4));
+    }
+
+    #[test]
+    fn error() {
+        #[derive(Debug)]
+        #[repr(align(4))]
+        struct ParseError(std::num::ParseIntError);
+
+        impl fmt::Display for ParseError {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("invalid config")
+            }
+        }
+
+        impl Error for ParseError {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        fn check(e: &dyn Error) -> String {
+            format!("{}: {}", e, e.source().unwrap())
+        }
+
+        let e = ParseError("x".parse::<u32>().unwrap_err());
+        assert_eq!(
+            check(&Cow::borrowed(&e)),
+            "invalid config: invalid digit found in string"
+        );
+        let owned: Box<dyn Error> = Box::new(Cow::<ParseError>::owned_value(e));
+        assert!(owned.source().is_some());
+    }
+
     #[test]
     fn owned_cow_drop() {
         let drop_flag = Cell::new(false);
@@ -229,6 +1107,24 @@ mod tests {
         assert!(drop_flag.get());
     }
 
+    #[test]
+    fn swap() {
+        let x = 1;
+        let mut a = Cow::borrowed(&x);
+        let mut b = Cow::owned(Box::new(2));
+        a.swap(&mut b);
+        assert_eq!(*a, 2);
+        assert_eq!(*b, 1);
+    }
+
+    #[test]
+    fn default() {
+        let cow = Cow::<'static, u32>::default();
+        assert_eq!(*cow, 0);
+        let boxed = cow.into_owned();
+        assert_eq!(*boxed, 0);
+    }
+
     #[test]
     fn dst_cow_drop() {
         let drop_count = Cell::new(0usize);
@@ -262,14 +1158,443 @@ mod tests {
         drop(slice);
         assert_eq!(drop_count.get(), 6);
 
-        /*//----------------------------------------------------------------
+        //----------------------------------------------------------------
         drop_count.set(0);
-        let borrowed_cow =
-            Cow::borrowed_slice(&[dt.clone(), dt.clone(), dt.clone(), dt.clone(), dt.clone(), dt.clone()]);
+        let slice = [dt.clone(), dt.clone(), dt.clone(), dt.clone(), dt.clone(), dt.clone()];
+        let borrowed_cow = Cow::borrowed_slice(&slice);
         let owned_cow = borrowed_cow.into_owned_cow_slice();
-        drop(borrowed_cow);
         assert_eq!(drop_count.get(), 0);
+        drop(slice);
+        assert_eq!(drop_count.get(), 6);
         drop(owned_cow);
-        assert_eq!(drop_count.get(), 6);*/
+        assert_eq!(drop_count.get(), 12);
+    }
+
+    #[test]
+    fn slice_into_owned() {
+        let v = [1u32, 2, 3];
+        let cow = Cow::borrowed_slice(&v[..]);
+        assert_eq!(&*cow, &[1, 2, 3]);
+        let boxed = cow.into_owned();
+        assert_eq!(&*boxed, &[1, 2, 3]);
+
+        let cow = Cow::owned_slice(boxed);
+        let ptr = cow.as_ptr();
+        let boxed = cow.into_owned();
+        assert_eq!(boxed.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn strs() {
+        assert_eq!(mem::size_of::<&str>(), mem::size_of::<Cow<'static, str>>());
+
+        let s = String::from("hello");
+        let cow = Cow::<str>::from(&s[1..]);
+        assert_eq!(&*cow, "ello");
+        assert_eq!(cow.into_string(), "ello");
+
+        let cow = Cow::<str>::from(s);
+        let ptr = cow.as_ptr();
+        assert_eq!(&*cow, "hello");
+        let s = cow.into_string();
+        assert_eq!(s.as_ptr(), ptr);
+        assert_eq!(s, "hello");
+    }
+
+    #[test]
+    fn to_mut() {
+        let x = 1;
+        let mut cow = Cow::borrowed(&x);
+        *cow.to_mut() += 1;
+        assert_eq!((*cow, x), (2, 1));
+        let ptr = cow.to_mut() as *mut i32;
+        // already owned: no copy
+        assert_eq!(cow.to_mut() as *mut i32, ptr);
+
+        let v = [1, 2, 3];
+        let mut cow = Cow::borrowed_slice(&v[..]);
+        cow.to_mut()[0] = 0;
+        assert_eq!((&*cow, v), (&[0, 2, 3][..], [1, 2, 3]));
+
+        let mut cow = Cow::<str>::from("hello");
+        cow.to_mut().make_ascii_uppercase();
+        assert_eq!(&*cow, "HELLO");
+    }
+
+    #[test]
+    fn clone() {
+        let x = 1;
+        let a = Cow::borrowed(&x);
+        let b = a.clone();
+        assert!(ptr::eq(&*a, &*b));
+
+        let a = Cow::owned(Box::new(1));
+        let b = a.clone();
+        assert_eq!(*a, *b);
+        assert!(!ptr::eq(&*a, &*b));
+
+        let a = Cow::owned_slice(Box::from(&[1u32, 2][..]));
+        assert_eq!(&*a.clone(), &[1, 2]);
+        let a = Cow::<str>::from("hello");
+        assert!(ptr::eq(&*a.clone(), &*a));
+    }
+
+    #[test]
+    fn fmt() {
+        let x = 42;
+        assert_eq!(format!("{:?}", Cow::borrowed(&x)), "Borrowed(42)");
+        assert_eq!(format!("{:?}", Cow::owned(Box::new(42))), "Owned(42)");
+        assert_eq!(format!("{:?}", Cow::<[u16]>::from(&[1u16, 2][..])), "Borrowed([1, 2])");
+        assert_eq!(format!("{:?}", Cow::<str>::from(String::from("hi"))), "Owned(\"hi\")");
+        assert_eq!(format!("{}", Cow::<str>::from("hello")), "hello");
+        assert_eq!(format!("{:>4}", Cow::borrowed(&x)), "  42");
+    }
+
+    #[test]
+    fn cmp_hash() {
+        let x = 1;
+        assert_eq!(Cow::borrowed(&x), Cow::owned(Box::new(1)));
+        assert!(Cow::borrowed(&x) < Cow::owned(Box::new(2)));
+        let (abc, abd) = (Cow::<str>::from("abc"), Cow::<str>::from(String::from("abd")));
+        assert!(abc < abd);
+
+        let mut set = HashSet::new();
+        set.insert(Cow::<str>::from(String::from("hello")));
+        assert!(set.contains(&Cow::<str>::from("hello")));
+        assert!(!set.contains(&Cow::<str>::from("world")));
+    }
+
+    #[test]
+    fn cross_type_eq() {
+        let x = 1;
+        let cow = Cow::borrowed(&x);
+        assert_eq!(cow, 1);
+        assert_eq!(cow, &1);
+        assert_eq!(cow, Box::new(1));
+        assert_ne!(cow, StdCow::<u32>::Owned(2));
+
+        let s = Cow::<str>::from(String::from("abc"));
+        assert_eq!(s, "abc");
+        assert_eq!(s, *"abc");
+        assert_eq!(s, String::from("abc"));
+        assert_eq!(s, StdCow::Borrowed("abc"));
+        assert_eq!("abc", s);
+        assert_eq!(String::from("abc"), s);
+        assert_eq!(Cow::borrowed_slice(&[1u16, 2][..]), &[1, 2][..]);
+    }
+
+    #[test]
+    fn from() {
+        fn take<'a>(v: impl Into<Cow<'a, u32>>) -> Cow<'a, u32> {
+            v.into()
+        }
+        let x = &1;
+        assert_eq!(format!("{:?}", take(x)), "Borrowed(1)");
+        assert_eq!(format!("{:?}", take(Box::new(2))), "Owned(2)");
+        assert_eq!(format!("{:?}", take(3)), "Owned(3)");
+
+        let v: Cow<[u16]> = vec![1, 2].into();
+        assert_eq!(format!("{:?}", v), "Owned([1, 2])");
+        let v: Cow<[u16]> = Box::<[u16]>::from(&[3][..]).into();
+        assert_eq!(&*v, &[3]);
+        let s: Cow<str> = Box::<str>::from("abc").into();
+        assert_eq!(format!("{:?}", s), "Owned(\"abc\")");
+    }
+
+    #[test]
+    fn std_cow() {
+        let x = 5;
+        let cow = Cow::<u32>::from(StdCow::Borrowed(&x));
+        assert!(ptr::eq(&*cow, &x));
+        assert!(matches!(cow.into_std_cow(), StdCow::Borrowed(r) if ptr::eq(r, &x)));
+        let cow = Cow::<u32>::from(StdCow::Owned(6));
+        assert!(matches!(cow.into_std_cow(), StdCow::Owned(6)));
+
+        let cow = Cow::<[u16]>::from(StdCow::Owned(vec![1, 2]));
+        assert_eq!(format!("{:?}", cow), "Owned([1, 2])");
+        assert_eq!(cow.into_std_cow(), StdCow::<[u16]>::Owned(vec![1, 2]));
+
+        let cow = Cow::<str>::from(StdCow::Borrowed("abc"));
+        assert_eq!(format!("{:?}", cow), "Borrowed(\"abc\")");
+        assert!(matches!(cow.into_std_cow(), StdCow::Borrowed("abc")));
+    }
+
+    #[test]
+    fn inspect() {
+        let x = 5;
+        let borrowed = Cow::borrowed(&x);
+        assert!(borrowed.is_borrowed() && !borrowed.is_owned());
+        let r = borrowed.borrowed_ref().unwrap();
+        drop(borrowed);
+        assert!(ptr::eq(r, &x));
+
+        let owned = Cow::owned(Box::new(6));
+        assert!(owned.is_owned() && !owned.is_borrowed());
+        assert_eq!(owned.borrowed_ref(), None);
+        assert_eq!(*owned.as_ref(), 6);
+
+        let s = Cow::borrowed_str("abc");
+        assert_eq!(s.borrowed_ref(), Some("abc"));
+        assert_eq!(Cow::borrowed_slice(&[1u16, 2]).as_ref(), &[1, 2]);
+    }
+
+    #[test]
+    fn owned_value_collect() {
+        let cow = Cow::owned_value(String::from("a"));
+        assert!(cow.is_owned());
+        assert_eq!(*cow, "a");
+
+        let cow: Cow<[u32]> = (1..4).collect();
+        assert!(cow.is_owned());
+        assert_eq!(&*cow, &[1, 2, 3]);
+        let cow: Cow<str> = "abc".chars().rev().collect();
+        assert_eq!(&*cow, "cba");
+    }
+
+    #[test]
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Cow<u32>>();
+        assert_send_sync::<Cow<[String]>>();
+        assert_send_sync::<Cow<str>>();
+    }
+
+    #[test]
+    fn generic_pointees() {
+        let cow = Cow::borrowed(&[1u32, 2][..]);
+        assert_eq!(&*cow.into_owned(), &[1, 2]);
+        let mut cow = Cow::borrowed("abc");
+        cow.to_mut().make_ascii_uppercase();
+        assert_eq!(&*cow, "ABC");
+
+        trait Shape {
+            fn area(&self) -> u32;
+        }
+        unsafe impl PointeeAlign for dyn Shape {
+            const ALIGN: usize = 4;
+        }
+        struct Square(u32);
+        impl Shape for Square {
+            fn area(&self) -> u32 {
+                self.0 * self.0
+            }
+        }
+        let sq = Square(3);
+        let borrowed: Cow<dyn Shape> = Cow::borrowed(&sq);
+        let owned: Cow<dyn Shape> = Cow::owned(Box::new(Square(4)));
+        assert_eq!((borrowed.area(), owned.area()), (9, 16));
+        assert!(borrowed.is_borrowed() && owned.is_owned());
+    }
+
+    #[test]
+    fn map() {
+        struct Entry {
+            key: String,
+            id: u32,
+        }
+        let e = Entry {
+            key: String::from("a"),
+            id: 1,
+        };
+        let key = Cow::borrowed(&e).map(|e| &*e.key, |e| e.key.into_boxed_str());
+        assert!(ptr::eq(&*key, &*e.key));
+        let key = Cow::owned_value(Entry {
+            key: String::from("b"),
+            id: 2,
+        })
+        .map(|e| &*e.key, |e| e.key.into_boxed_str());
+        assert_eq!(format!("{:?}", key), "Owned(\"b\")");
+
+        let id: Result<Cow<u32>, ()> = Cow::borrowed(&e).try_map(|e| Ok(&e.id), |e| Ok(Box::new(e.id)));
+        assert_eq!(*id.unwrap(), 1);
+        let id: Result<Cow<u32>, &str> = Cow::owned_value(Entry {
+            key: String::new(),
+            id: 0,
+        })
+        .try_map(|_| Err("borrowed"), |_| Err("owned"));
+        assert_eq!(id.unwrap_err(), "owned");
+
+        let v = Cow::owned_value((String::from("c"), 3u16)).project(|v| &v.1);
+        assert!(v.is_owned());
+        assert_eq!(*v, 3);
+    }
+
+    #[test]
+    fn as_ref_borrow() {
+        fn len(s: impl AsRef<str>) -> usize {
+            s.as_ref().len()
+        }
+        assert_eq!(len(Cow::borrowed("abc")), 3);
+
+        let mut map = HashMap::new();
+        map.insert(Cow::<str>::from(String::from("a")), 1);
+        map.insert(Cow::borrowed("b"), 2);
+        assert_eq!(map.get("a"), Some(&1));
+        assert_eq!(map.get("b"), Some(&2));
+
+        let v: Cow<[u32]> = vec![1, 2].into();
+        assert_eq!(AsRef::<[u32]>::as_ref(&v), &[1, 2]);
+    }
+
+    #[test]
+    fn trait_objects() {
+        trait Strategy {
+            fn apply(&self, x: u32) -> u32;
+        }
+        unsafe impl<'a> PointeeAlign for dyn Strategy + 'a {
+            const ALIGN: usize = 2;
+        }
+        struct Add(u32);
+        impl Strategy for Add {
+            fn apply(&self, x: u32) -> u32 {
+                x + self.0
+            }
+        }
+        struct Counted<'a>(&'a Cell<u32>, u16);
+        impl Strategy for Counted<'_> {
+            fn apply(&self, x: u32) -> u32 {
+                x * self.1 as u32
+            }
+        }
+        impl Drop for Counted<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let add = Add(1);
+        let strategies: [Cow<dyn Strategy + '_>; 2] = [Cow::borrowed(&add), Cow::owned(Box::new(Counted(&drops, 3)))];
+        assert_eq!(strategies.iter().fold(1, |x, s| s.apply(x)), 6);
+        assert!(strategies[0].is_borrowed() && strategies[1].is_owned());
+        drop(strategies);
+        assert_eq!(drops.get(), 1);
+    }
+
+    #[cfg(feature = "unsize")]
+    #[test]
+    fn unsize() {
+        use std::fmt::Debug;
+        trait Named: Debug {}
+        unsafe impl PointeeAlign for dyn Named {
+            const ALIGN: usize = 4;
+        }
+        #[derive(Debug)]
+        struct Name(#[allow(dead_code)] u32);
+        impl Named for Name {}
+
+        let cow: Cow<dyn Named> = Cow::owned_value(Name(5));
+        assert_eq!(format!("{:?}", cow), "Owned(Name(5))");
+        let slice: Cow<[u32]> = Cow::owned(Box::new([1, 2]));
+        assert_eq!(&*slice, &[1, 2]);
+    }
+
+    #[test]
+    fn try_owned() {
+        let cow = Cow::try_owned_value(5u32).unwrap();
+        assert!(cow.is_owned());
+        assert_eq!(*cow, 5);
+        let cow = Cow::try_owned_slice_from(&[1u32, 2]).unwrap();
+        assert_eq!(format!("{:?}", cow), "Owned([1, 2])");
+        let cow = Cow::try_owned_str_from("abc").unwrap();
+        assert_eq!(format!("{:?}", cow), "Owned(\"abc\")");
+    }
+
+    #[test]
+    fn as_mut() {
+        let x = 1;
+        let mut cow = Cow::borrowed(&x);
+        assert_eq!(cow.as_mut(), None);
+        let mut cow = Cow::owned_value(1);
+        *cow.as_mut().unwrap() += 1;
+        assert_eq!(*cow, 2);
+        let mut cow = Cow::<str>::from(String::from("abc"));
+        cow.as_mut().unwrap().make_ascii_uppercase();
+        assert_eq!(&*cow, "ABC");
+    }
+
+    #[test]
+    fn zero_sized() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Unit;
+
+        assert_eq!(mem::size_of::<*const Unit>(), mem::size_of::<Cow<Unit>>());
+        let cow = Cow::borrowed(&Unit);
+        assert!(cow.is_borrowed());
+        assert_eq!(*cow, Unit);
+        let cow = Cow::owned_value(());
+        assert!(cow.is_owned());
+        assert_eq!(format!("{:?}", cow.clone()), "Owned(())");
+        assert!(!cow.into_owned_cow().is_borrowed());
+        #[repr(align(4096))]
+        #[derive(Clone)]
+        struct Page;
+        let cow = Cow::owned_value(Page);
+        assert!(cow.is_owned() && (&*cow as *const Page).is_aligned());
+        let mut cow = Cow::borrowed(&[(); 2]).into_owned_cow();
+        assert!(cow.to_mut().len() == 2);
+    }
+
+    #[test]
+    fn const_borrowed() {
+        static NAME: &str = "default";
+        static LIMITS: [u32; 2] = [16, 64];
+        static UNIT: () = ();
+        static DEFAULT_NAME: Cow<'static, str> = Cow::borrowed_str(NAME);
+        const LIMIT: Cow<'static, u32> = Cow::borrowed(&LIMITS[1]);
+        const ALL_LIMITS: Cow<'static, [u32]> = Cow::borrowed_slice(&LIMITS);
+        const NOTHING: Cow<'static, ()> = Cow::borrowed(&UNIT);
+
+        assert!(DEFAULT_NAME.is_borrowed());
+        assert_eq!(DEFAULT_NAME, "default");
+        assert_eq!(LIMIT, 64);
+        assert_eq!(ALL_LIMITS, &[16, 64][..]);
+        assert!(NOTHING.is_borrowed());
+
+        let config = [LIMIT, Cow::owned_value(128)];
+        assert!(config[0].is_borrowed() && config[1].is_owned());
+    }
+
+    #[test]
+    fn iter() {
+        let v = [1u32, 2, 3];
+        let cow = Cow::borrowed_slice(&v[..]);
+        let mut sum = 0;
+        for x in &cow {
+            sum += x;
+        }
+        assert_eq!(sum, 6);
+
+        let items = vec![String::from("a"), String::from("b")];
+        let borrowed = Cow::borrowed_slice(&items[..]);
+        let iter = borrowed.into_iter();
+        assert_eq!(format!("{:?}", iter), "Borrowed([\"a\", \"b\"])");
+        assert_eq!(iter.rev().collect::<Vec<_>>(), ["b", "a"]);
+
+        let ptr = items[1].as_ptr();
+        let owned = Cow::<[String]>::from(items);
+        let mut iter = owned.into_iter();
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.next().unwrap(), "a");
+        // moved, not cloned
+        let b = iter.next().unwrap();
+        assert_eq!(b.as_ptr(), ptr);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn pinned() {
+        let pinned = Box::pin((PhantomPinned, 1u32));
+        let addr: *const (PhantomPinned, u32) = &*pinned;
+        let cow = Cow::owned_pinned(pinned);
+        assert!(ptr::eq(&*cow, addr));
+        assert!(Cow::unpin_ref(&cow).is_owned());
+        assert_eq!(cow.1, 1);
+
+        let value = (PhantomPinned, 2u32);
+        let value = pin!(value);
+        let cow = Cow::borrowed_pinned(value.as_ref());
+        assert!(Cow::unpin_ref(&cow).is_borrowed());
+        assert_eq!(cow.as_ref().get_ref().1, 2);
     }
 }
@@ -0,0 +1,266 @@
+use crate::{PointerValuePair, PointerValuePairAccess};
+use alloc::boxed::Box;
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+};
+
+/// A pointer-sized object that holds either an exclusive borrow (`&'a mut T`) or a boxed value (`Box<T>`).
+///
+/// This is the mutable counterpart of `Cow`: it can be used to pass either a caller-provided buffer or a freshly
+/// allocated one, and gives mutable access to the value in both cases.
+///
+/// # Notes
+///
+/// Like `Cow`, `T` (or the element type, for slices) must be aligned to at least 2 bytes, which is checked at compile
+/// time when creating owned `CowMut`s.
+#[repr(transparent)]
+pub struct CowMut<'a, T>
+where
+    T: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess,
+{
+    inner: PointerValuePair<T>,
+    _phantom: PhantomData<&'a mut T>,
+}
+
+// SAFETY: a `CowMut` is either a `&'a mut T` or a `Box<T>`, which are both `Send` if `T: Send`.
+unsafe impl<'a, T> Send for CowMut<'a, T>
+where
+    T: ?Sized + Send,
+    PointerValuePair<T>: PointerValuePairAccess,
+{
+}
+
+// SAFETY: a `&CowMut` only gives access to a `&T`.
+unsafe impl<'a, T> Sync for CowMut<'a, T>
+where
+    T: ?Sized + Sync,
+    PointerValuePair<T>: PointerValuePairAccess,
+{
+}
+
+const BORROWED: usize = 0usize;
+const OWNED: usize = 1usize;
+
+impl<'a, T> CowMut<'a, T> {
+    /// Creates a new `CowMut` representing a mutable borrow.
+    pub fn borrowed(v: &'a mut T) -> CowMut<'a, T> {
+        CowMut {
+            inner: PointerValuePair::new(v as *mut T, BORROWED),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new `CowMut` holding a boxed value.
+    pub fn owned(v: Box<T>) -> CowMut<'a, T> {
+        const {
+            assert!(
+                PointerValuePair::<T>::max_value() >= OWNED,
+                "not enough alignment bits to store the discriminant"
+            )
+        };
+        CowMut {
+            inner: PointerValuePair::new(Box::into_raw(v), OWNED),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Converts this `CowMut` into a `Box<T>`. If this `CowMut` is a borrow, clones the value and boxes it.
+    pub fn into_owned(self) -> Box<T>
+    where
+        T: Clone,
+    {
+        if self.is_owned() {
+            let boxed = unsafe {
+                // SAFETY: the pointer has been created with `Box::into_raw` by `CowMut::owned`.
+                Box::from_raw(self.inner.ptr() as *mut T)
+            };
+            // we extracted the boxed value already, don't double-drop
+            mem::forget(self);
+            boxed
+        } else {
+            Box::new(T::clone(&self))
+        }
+    }
+}
+
+impl<'a, T> CowMut<'a, [T]> {
+    /// Creates a new `CowMut` representing a mutable borrow of a slice.
+    pub fn borrowed_slice(v: &'a mut [T]) -> CowMut<'a, [T]> {
+        CowMut {
+            inner: PointerValuePair::new_slice(v as *mut [T], BORROWED),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new `CowMut` holding a boxed slice.
+    pub fn owned_slice(v: Box<[T]>) -> CowMut<'a, [T]> {
+        const {
+            assert!(
+                PointerValuePair::<[T]>::max_value() >= OWNED,
+                "not enough alignment bits to store the discriminant"
+            )
+        };
+        CowMut {
+            inner: PointerValuePair::new_slice(Box::into_raw(v), OWNED),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Converts this `CowMut` into a boxed slice. If this `CowMut` is a borrow, clones the slice and boxes it.
+    pub fn into_owned(self) -> Box<[T]>
+    where
+        T: Clone,
+    {
+        if self.is_owned() {
+            let boxed = unsafe {
+                // SAFETY: the pointer has been created with `Box::into_raw` by `CowMut::owned_slice`.
+                Box::from_raw(self.inner.ptr() as *mut [T])
+            };
+            // we extracted the boxed slice already, don't double-drop
+            mem::forget(self);
+            boxed
+        } else {
+            Box::from(&*self)
+        }
+    }
+}
+
+impl<'a, T> CowMut<'a, T>
+where
+    T: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess,
+{
+    /// Returns whether this `CowMut` holds a borrow.
+    pub fn is_borrowed(&self) -> bool {
+        self.inner.value() == BORROWED
+    }
+
+    /// Returns whether this `CowMut` holds a boxed value.
+    pub fn is_owned(&self) -> bool {
+        self.inner.value() == OWNED
+    }
+}
+
+impl<'a, T> Drop for CowMut<'a, T>
+where
+    T: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess,
+{
+    fn drop(&mut self) {
+        validate!(self.inner.value() <= OWNED, "corrupted CowMut discriminant");
+        unsafe {
+            if self.inner.value() == OWNED {
+                drop(Box::from_raw(self.inner.mut_ptr()))
+            }
+        }
+    }
+}
+
+impl<'a, T> Deref for CowMut<'a, T>
+where
+    T: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        validate!(
+            self.inner.value() <= OWNED && !PointerValuePairAccess::ptr(self.inner).is_null(),
+            "corrupted CowMut pointer"
+        );
+        // SAFETY: ptr is either a pointer to a boxed value that we own, or comes from a `&'a mut T`, which is
+        // exclusively borrowed for the lifetime of `self`.
+        unsafe { &*PointerValuePairAccess::ptr(self.inner) }
+    }
+}
+
+impl<'a, T> DerefMut for CowMut<'a, T>
+where
+    T: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: same as `Deref`, and `self` is borrowed mutably.
+        unsafe { &mut *self.inner.mut_ptr() }
+    }
+}
+
+impl<'a, T> fmt::Debug for CowMut<'a, T>
+where
+    T: ?Sized + fmt::Debug,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Formats the value as `Borrowed(..)` or `Owned(..)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.is_owned() { "Owned" } else { "Borrowed" };
+        f.debug_tuple(state).field(&&**self).finish()
+    }
+}
+
+impl<'a, T> From<&'a mut T> for CowMut<'a, T> {
+    /// Creates a borrowed `CowMut` from the given reference.
+    fn from(v: &'a mut T) -> Self {
+        CowMut::borrowed(v)
+    }
+}
+
+impl<'a, T> From<&'a mut [T]> for CowMut<'a, [T]> {
+    /// Creates a borrowed `CowMut<[T]>` from the given slice.
+    fn from(v: &'a mut [T]) -> Self {
+        CowMut::borrowed_slice(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CowMut;
+    use std::{boxed::Box, format, mem, rc::Rc, vec};
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(mem::size_of::<*const u64>(), mem::size_of::<CowMut<u64>>());
+        assert_eq!(mem::size_of::<*const [u64]>(), mem::size_of::<CowMut<[u64]>>());
+    }
+
+    #[test]
+    fn borrowed_and_owned() {
+        fn fill(mut buf: CowMut<[u32]>) -> CowMut<[u32]> {
+            for (i, x) in buf.iter_mut().enumerate() {
+                *x = i as u32;
+            }
+            buf
+        }
+
+        let mut scratch = [0u32; 3];
+        let cow = fill(CowMut::from(&mut scratch[..]));
+        assert!(cow.is_borrowed());
+        assert_eq!(format!("{:?}", cow), "Borrowed([0, 1, 2])");
+        assert_eq!(&*cow.into_owned(), &[0, 1, 2]);
+        assert_eq!(scratch, [0, 1, 2]);
+
+        let cow = fill(CowMut::owned_slice(vec![9; 2].into_boxed_slice()));
+        assert!(cow.is_owned());
+        assert_eq!(&*cow.into_owned(), &[0, 1]);
+
+        let mut x = 1u32;
+        *CowMut::from(&mut x) += 1;
+        assert_eq!(x, 2);
+    }
+
+    #[test]
+    fn drop_owned() {
+        let rc = Rc::new(());
+        let cow = CowMut::owned(Box::new(rc.clone()));
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(cow);
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        let mut other = rc.clone();
+        drop(CowMut::borrowed(&mut other));
+        assert_eq!(Rc::strong_count(&rc), 2);
+    }
+}
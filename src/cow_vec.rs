@@ -0,0 +1,480 @@
+use crate::SliceLenPointerValuePair;
+use alloc::{
+    alloc::{alloc, dealloc, handle_alloc_error, realloc, Layout},
+    vec::Vec,
+};
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::Deref,
+    ptr::{self, NonNull},
+};
+
+const BORROWED: usize = 0usize;
+const OWNED: usize = 1usize;
+
+/// A growable vector that holds either a borrowed slice (`&'a [T]`) or an owned buffer, and clones the borrowed
+/// elements into an owned buffer the first time it is modified (e.g. by `push`).
+///
+/// A `CowVec` has the size of a slice reference: the pointer to the elements and the length, with the discriminant
+/// stored in the most significant bit of the length (see `SliceLenPointerValuePair`), so any element type is
+/// supported. The capacity of owned buffers is stored in a header at the start of the allocation.
+///
+/// # Notes
+///
+/// Removing elements at the end (`pop`, `truncate`) or at the start (`remove(0)`) of a borrowed `CowVec` only
+/// shortens the borrowed slice, without allocating.
+pub struct CowVec<'a, T> {
+    repr: SliceLenPointerValuePair<T, 1>,
+    _phantom: PhantomData<(&'a [T], Vec<T>)>,
+}
+
+// SAFETY: a `CowVec` is either a `&'a [T]` or owns its elements, like a `Vec<T>`.
+unsafe impl<'a, T: Send + Sync> Send for CowVec<'a, T> {}
+// SAFETY: a `&CowVec` only gives access to a `&[T]`.
+unsafe impl<'a, T: Sync> Sync for CowVec<'a, T> {}
+
+impl<'a, T> CowVec<'a, T> {
+    /// Offset of the elements from the start of the allocation, after the header holding the capacity.
+    const DATA_OFFSET: usize = {
+        let align = mem::align_of::<T>();
+        (mem::size_of::<usize>() + align - 1) & !(align - 1)
+    };
+
+    /// Layout of an owned buffer with room for `capacity` elements.
+    fn layout(capacity: usize) -> Layout {
+        Layout::array::<T>(capacity)
+            .and_then(|array| Layout::new::<usize>().extend(array))
+            .map(|(layout, _)| layout)
+            .expect("capacity overflow")
+    }
+
+    /// Allocates a buffer with room for `capacity` elements, and returns the pointer to the first element.
+    fn allocate(capacity: usize) -> *mut T {
+        if mem::size_of::<T>() == 0 {
+            // doesn't allocate
+            return NonNull::dangling().as_ptr();
+        }
+        let layout = Self::layout(capacity);
+        unsafe {
+            // SAFETY: the layout has a non-zero size, since it contains the header
+            let base = alloc(layout);
+            if base.is_null() {
+                handle_alloc_error(layout);
+            }
+            base.cast::<usize>().write(capacity);
+            // SAFETY: the elements are at `DATA_OFFSET` in the allocation
+            base.add(Self::DATA_OFFSET).cast()
+        }
+    }
+
+    fn from_raw_parts(data: *const T, len: usize, state: usize) -> CowVec<'a, T> {
+        CowVec {
+            repr: SliceLenPointerValuePair::new(ptr::slice_from_raw_parts(data, len), state),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn data(&self) -> *mut T {
+        self.repr.ptr() as *mut T
+    }
+
+    /// Sets the length of an owned `CowVec`.
+    ///
+    /// # Safety
+    ///
+    /// The `CowVec` must be owned, and the first `len` elements of the buffer must be initialized.
+    unsafe fn set_len(&mut self, len: usize) {
+        self.repr = SliceLenPointerValuePair::new(ptr::slice_from_raw_parts(self.data(), len), OWNED);
+    }
+
+    /// Returns the pointer to the start of the allocation of an owned `CowVec` of non-zero-sized elements.
+    fn base(&self) -> *mut u8 {
+        // SAFETY: the elements are at `DATA_OFFSET` in the allocation
+        unsafe { self.data().cast::<u8>().sub(Self::DATA_OFFSET) }
+    }
+
+    /// Creates a new empty `CowVec`, which doesn't allocate.
+    pub fn new() -> CowVec<'a, T> {
+        CowVec::borrowed(&[])
+    }
+
+    /// Creates a new `CowVec` representing a borrowed slice.
+    pub fn borrowed(v: &'a [T]) -> CowVec<'a, T> {
+        CowVec::from_raw_parts(v.as_ptr(), v.len(), BORROWED)
+    }
+
+    /// Creates a new empty owned `CowVec` with room for `capacity` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size of the buffer exceeds `isize::MAX` bytes.
+    pub fn with_capacity(capacity: usize) -> CowVec<'a, T> {
+        CowVec::from_raw_parts(Self::allocate(capacity), 0, OWNED)
+    }
+
+    /// Returns whether this `CowVec` holds a borrowed slice.
+    pub fn is_borrowed(&self) -> bool {
+        self.repr.value() == BORROWED
+    }
+
+    /// Returns whether this `CowVec` holds an owned buffer.
+    pub fn is_owned(&self) -> bool {
+        self.repr.value() == OWNED
+    }
+
+    /// Returns the original slice if this `CowVec` is borrowed.
+    pub fn borrowed_ref(&self) -> Option<&'a [T]> {
+        // SAFETY: the pointer comes from a `&'a [T]` passed to `borrowed`.
+        self.is_borrowed().then(|| unsafe { &*self.repr.ptr() })
+    }
+
+    /// Returns the number of elements that the owned buffer can hold without reallocating, or 0 if this `CowVec` is
+    /// borrowed.
+    pub fn capacity(&self) -> usize {
+        if self.is_borrowed() {
+            0
+        } else if mem::size_of::<T>() == 0 {
+            usize::MAX
+        } else {
+            // SAFETY: the header of owned buffers holds the capacity
+            unsafe { self.base().cast::<usize>().read() }
+        }
+    }
+
+    /// Converts this `CowVec` into a `Vec<T>`, cloning the elements if they are borrowed.
+    pub fn into_vec(mut self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        if let Some(v) = self.borrowed_ref() {
+            return v.to_vec();
+        }
+        let len = self.len();
+        let mut vec = Vec::with_capacity(len);
+        unsafe {
+            // SAFETY: the elements are moved to the vector, and no longer owned by `self`, which only deallocates the
+            // buffer when dropped.
+            ptr::copy_nonoverlapping(self.data(), vec.as_mut_ptr(), len);
+            vec.set_len(len);
+            self.set_len(0);
+        }
+        vec
+    }
+
+    /// Converts this into a `CowVec` that doesn't borrow anything, by cloning the elements into an owned buffer only
+    /// if they are borrowed.
+    pub fn into_owned(self) -> CowVec<'static, T>
+    where
+        T: Clone,
+    {
+        if let Some(v) = self.borrowed_ref() {
+            let mut owned = CowVec::with_capacity(v.len());
+            owned.extend_from_slice(v);
+            owned
+        } else {
+            // ownership of the buffer is transferred to the result
+            let repr = self.repr;
+            mem::forget(self);
+            CowVec {
+                repr,
+                _phantom: PhantomData,
+            }
+        }
+    }
+}
+
+impl<'a, T: Clone> CowVec<'a, T> {
+    /// Reserves room for at least `additional` more elements, cloning the elements into an owned buffer if they are
+    /// borrowed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size of the buffer exceeds `isize::MAX` bytes.
+    pub fn reserve(&mut self, additional: usize) {
+        let len = self.len();
+        let required = len.checked_add(additional).expect("capacity overflow");
+        if let Some(v) = self.borrowed_ref() {
+            let mut owned = CowVec::with_capacity(required.max(4));
+            owned.extend_from_slice(v);
+            *self = owned;
+            return;
+        }
+        let capacity = self.capacity();
+        if required <= capacity {
+            return;
+        }
+        // `capacity * 2` doesn't overflow, since elements are not zero-sized and the size of the buffer fits in an
+        // `isize`.
+        let new_capacity = required.max(capacity * 2).max(4);
+        let new_layout = Self::layout(new_capacity);
+        unsafe {
+            // SAFETY: the buffer has been allocated with the layout for `capacity` elements
+            let base = realloc(self.base(), Self::layout(capacity), new_layout.size());
+            if base.is_null() {
+                handle_alloc_error(new_layout);
+            }
+            base.cast::<usize>().write(new_capacity);
+            // SAFETY: the elements have been moved to the new allocation, at `DATA_OFFSET`
+            let data = base.add(Self::DATA_OFFSET).cast::<T>();
+            self.repr = SliceLenPointerValuePair::new(ptr::slice_from_raw_parts(data, len), OWNED);
+        }
+    }
+
+    /// Returns a mutable reference to the elements, cloning them into an owned buffer if they are borrowed.
+    pub fn to_mut(&mut self) -> &mut [T] {
+        self.reserve(0);
+        // SAFETY: we own the buffer, and borrow `self` mutably.
+        unsafe { &mut *(self.repr.ptr() as *mut [T]) }
+    }
+
+    /// Appends an element, cloning the elements into an owned buffer first if they are borrowed.
+    pub fn push(&mut self, v: T) {
+        self.reserve(1);
+        let len = self.len();
+        unsafe {
+            // SAFETY: the buffer has room for one more element
+            self.data().add(len).write(v);
+            self.set_len(len + 1);
+        }
+    }
+
+    /// Clones and appends all the elements of a slice.
+    pub fn extend_from_slice(&mut self, other: &[T]) {
+        self.reserve(other.len());
+        for v in other {
+            let len = self.len();
+            unsafe {
+                // SAFETY: the buffer has room for the elements of `other`. The length is updated after each element
+                // so that the elements are dropped if `clone` panics.
+                self.data().add(len).write(v.clone());
+                self.set_len(len + 1);
+            }
+        }
+    }
+
+    /// Inserts an element at position `index`, shifting the following elements to the right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, v: T) {
+        let len = self.len();
+        assert!(index <= len, "insertion index ({index}) should be <= len ({len})");
+        self.reserve(1);
+        unsafe {
+            // SAFETY: the buffer has room for one more element
+            let p = self.data().add(index);
+            ptr::copy(p, p.add(1), len - index);
+            p.write(v);
+            self.set_len(len + 1);
+        }
+    }
+
+    /// Removes and returns the element at position `index`, shifting the following elements to the left.
+    ///
+    /// The first element of a borrowed `CowVec` is removed by shortening the slice and cloning the element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= len`.
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len();
+        assert!(index < len, "removal index ({index}) should be < len ({len})");
+        if let (Some(v), 0) = (self.borrowed_ref(), index) {
+            *self = CowVec::borrowed(&v[1..]);
+            return v[0].clone();
+        }
+        self.reserve(0);
+        unsafe {
+            // SAFETY: the element is initialized, and is no longer part of the vector after the shift
+            let p = self.data().add(index);
+            let v = p.read();
+            ptr::copy(p.add(1), p, len - index - 1);
+            self.set_len(len - 1);
+            v
+        }
+    }
+
+    /// Removes and returns the last element, or returns `None` if the vector is empty.
+    ///
+    /// The last element of a borrowed `CowVec` is removed by shortening the slice and cloning the element.
+    pub fn pop(&mut self) -> Option<T> {
+        let len = self.len().checked_sub(1)?;
+        if let Some(v) = self.borrowed_ref() {
+            *self = CowVec::borrowed(&v[..len]);
+            return Some(v[len].clone());
+        }
+        unsafe {
+            // SAFETY: the last element is initialized, and is no longer part of the vector
+            self.set_len(len);
+            Some(self.data().add(len).read())
+        }
+    }
+
+    /// Shortens the vector to `len` elements, dropping the others. Does nothing if `len` is greater than the current
+    /// length.
+    pub fn truncate(&mut self, len: usize) {
+        let old_len = self.len();
+        if len >= old_len {
+            return;
+        }
+        if let Some(v) = self.borrowed_ref() {
+            *self = CowVec::borrowed(&v[..len]);
+            return;
+        }
+        unsafe {
+            // SAFETY: the length is updated first, so that the other elements are not dropped twice if dropping one
+            // of them panics.
+            self.set_len(len);
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.data().add(len), old_len - len));
+        }
+    }
+
+    /// Removes all the elements, keeping the owned buffer (if any).
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+}
+
+impl<'a, T> Drop for CowVec<'a, T> {
+    fn drop(&mut self) {
+        if self.is_owned() {
+            let capacity = self.capacity();
+            unsafe {
+                // SAFETY: the first `len` elements of owned buffers are initialized
+                ptr::drop_in_place(self.repr.ptr() as *mut [T]);
+                if mem::size_of::<T>() != 0 {
+                    // SAFETY: the buffer has been allocated by `allocate` or `reserve` with this layout
+                    dealloc(self.base(), Self::layout(capacity));
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T> Deref for CowVec<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: the pointer is either borrowed for `'a`, or points to a buffer that we own.
+        unsafe { &*self.repr.ptr() }
+    }
+}
+
+impl<'a, T: Clone> Clone for CowVec<'a, T> {
+    /// Copies the borrow, or clones the elements into a new owned buffer.
+    fn clone(&self) -> Self {
+        if self.is_borrowed() {
+            CowVec::from_raw_parts(self.data(), self.len(), BORROWED)
+        } else {
+            let mut owned = CowVec::with_capacity(self.len());
+            owned.extend_from_slice(self);
+            owned
+        }
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for CowVec<'a, T> {
+    /// Formats the value as `Borrowed([..])` or `Owned([..])`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.is_borrowed() { "Borrowed" } else { "Owned" };
+        f.debug_tuple(state).field(&&**self).finish()
+    }
+}
+
+impl<'a, T> Default for CowVec<'a, T> {
+    fn default() -> Self {
+        CowVec::new()
+    }
+}
+
+impl<'a, T> From<&'a [T]> for CowVec<'a, T> {
+    fn from(v: &'a [T]) -> Self {
+        CowVec::borrowed(v)
+    }
+}
+
+impl<T> From<Vec<T>> for CowVec<'_, T> {
+    /// Moves the elements of the vector into a new owned buffer.
+    fn from(mut v: Vec<T>) -> Self {
+        let mut owned = CowVec::with_capacity(v.len());
+        unsafe {
+            // SAFETY: the elements are moved to the new buffer, and no longer owned by the vector.
+            ptr::copy_nonoverlapping(v.as_ptr(), owned.data(), v.len());
+            owned.set_len(v.len());
+            v.set_len(0);
+        }
+        owned
+    }
+}
+
+impl<'a, T: Clone> Extend<T> for CowVec<'a, T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for v in iter {
+            self.push(v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CowVec;
+    use std::{format, mem, ptr, rc::Rc, vec, vec::Vec};
+
+    #[test]
+    fn promote() {
+        assert_eq!(mem::size_of::<&[u8]>(), mem::size_of::<CowVec<u8>>());
+
+        let tokens = [1u8, 2, 3];
+        let mut v = CowVec::from(&tokens[..]);
+        assert!(v.is_borrowed() && v.capacity() == 0);
+        assert_eq!(format!("{:?}", v.clone()), "Borrowed([1, 2, 3])");
+
+        v.push(4);
+        assert!(v.is_owned() && v.capacity() >= 4);
+        assert_eq!(format!("{:?}", v), "Owned([1, 2, 3, 4])");
+        v.extend(5..=20);
+        assert_eq!(v.len(), 20);
+        v.insert(0, 0);
+        assert_eq!(v.remove(1), 1);
+        assert_eq!(&v[..4], &[0, 2, 3, 4]);
+        v.to_mut()[0] = 9;
+        assert_eq!(v.into_vec(), [9].into_iter().chain(2..=20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn shrink_borrowed() {
+        let tokens = [1u32, 2, 3, 4];
+        let mut v = CowVec::borrowed(&tokens[..]);
+        assert_eq!(v.remove(0), 1);
+        assert_eq!(v.pop(), Some(4));
+        v.truncate(1);
+        assert!(v.is_borrowed());
+        assert!(ptr::eq(&*v, &tokens[1..2]));
+        v.clear();
+        assert_eq!(v.pop(), None);
+    }
+
+    #[test]
+    fn drops() {
+        let rc = Rc::new(());
+        let mut v = CowVec::from(vec![rc.clone(), rc.clone()]);
+        assert!(v.is_owned());
+        let cloned = v.clone();
+        assert_eq!(Rc::strong_count(&rc), 5);
+        drop(v.pop());
+        v.truncate(0);
+        assert_eq!(Rc::strong_count(&rc), 3);
+        drop((v, cloned));
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        let mut v = CowVec::<()>::new();
+        v.extend([(); 3]);
+        assert!(v.is_owned() && v.capacity() == usize::MAX);
+        assert_eq!(v.into_owned().len(), 3);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::PointerUnion;
+use alloc::boxed::Box;
+use core::{fmt, marker::PhantomData, mem};
+
+/// An owned heap allocation of either an `A` or a `B` (`Box<A>` or `Box<B>`), with the discriminant stored in the
+/// lowest alignment bit of the pointer.
+///
+/// This is the owning counterpart of `PointerUnion`: the allocation is freed, with the destructor of the right type,
+/// when the `EitherBox` is dropped. Both `A` and `B` must be aligned to at least 2 bytes, which is checked at compile
+/// time.
+#[repr(transparent)]
+pub struct EitherBox<A, B> {
+    inner: PointerUnion<A, B>,
+    _phantom: PhantomData<(A, B)>,
+}
+
+// SAFETY: `EitherBox` behaves like `Box<A>` or `Box<B>`.
+unsafe impl<A: Send, B: Send> Send for EitherBox<A, B> {}
+unsafe impl<A: Sync, B: Sync> Sync for EitherBox<A, B> {}
+
+impl<A, B> EitherBox<A, B> {
+    /// Boxes an `A`.
+    pub fn new_a(a: A) -> EitherBox<A, B> {
+        Self::from_box_a(Box::new(a))
+    }
+
+    /// Boxes a `B`.
+    pub fn new_b(b: B) -> EitherBox<A, B> {
+        Self::from_box_b(Box::new(b))
+    }
+
+    /// Creates an `EitherBox` holding the given `Box<A>`.
+    pub fn from_box_a(a: Box<A>) -> EitherBox<A, B> {
+        EitherBox {
+            inner: PointerUnion::from_a(Box::into_raw(a)),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates an `EitherBox` holding the given `Box<B>`.
+    pub fn from_box_b(b: Box<B>) -> EitherBox<A, B> {
+        EitherBox {
+            inner: PointerUnion::from_b(Box::into_raw(b)),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns whether this holds an `A`.
+    pub fn is_a(&self) -> bool {
+        self.inner.is_a()
+    }
+
+    /// Returns whether this holds a `B`.
+    pub fn is_b(&self) -> bool {
+        self.inner.is_b()
+    }
+
+    /// Returns a reference to the `A`, or `None` if this holds a `B`.
+    pub fn as_a(&self) -> Option<&A> {
+        // SAFETY: the pointer has been created with `Box::into_raw`, and is owned by `self`
+        self.inner.as_a().map(|a| unsafe { &*a })
+    }
+
+    /// Returns a reference to the `B`, or `None` if this holds an `A`.
+    pub fn as_b(&self) -> Option<&B> {
+        // SAFETY: the pointer has been created with `Box::into_raw`, and is owned by `self`
+        self.inner.as_b().map(|b| unsafe { &*b })
+    }
+
+    /// Returns a mutable reference to the `A`, or `None` if this holds a `B`.
+    pub fn as_a_mut(&mut self) -> Option<&mut A> {
+        // SAFETY: the pointer has been created with `Box::into_raw`, and is owned by `self`, which we borrow mutably
+        self.inner.as_a().map(|a| unsafe { &mut *(a as *mut A) })
+    }
+
+    /// Returns a mutable reference to the `B`, or `None` if this holds an `A`.
+    pub fn as_b_mut(&mut self) -> Option<&mut B> {
+        // SAFETY: the pointer has been created with `Box::into_raw`, and is owned by `self`, which we borrow mutably
+        self.inner.as_b().map(|b| unsafe { &mut *(b as *mut B) })
+    }
+
+    /// Returns the `Box<A>`, or gives `self` back if this holds a `B`.
+    pub fn try_into_a(self) -> Result<Box<A>, Self> {
+        match self.inner.as_a() {
+            Some(a) => {
+                // ownership is transferred to the returned box
+                mem::forget(self);
+                // SAFETY: the pointer has been created with `Box::into_raw`
+                Ok(unsafe { Box::from_raw(a as *mut A) })
+            }
+            None => Err(self),
+        }
+    }
+
+    /// Returns the `Box<B>`, or gives `self` back if this holds an `A`.
+    pub fn try_into_b(self) -> Result<Box<B>, Self> {
+        match self.inner.as_b() {
+            Some(b) => {
+                // ownership is transferred to the returned box
+                mem::forget(self);
+                // SAFETY: the pointer has been created with `Box::into_raw`
+                Ok(unsafe { Box::from_raw(b as *mut B) })
+            }
+            None => Err(self),
+        }
+    }
+
+    /// Returns the `Box<A>`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this holds a `B`.
+    pub fn unwrap_a(self) -> Box<A> {
+        match self.try_into_a() {
+            Ok(a) => a,
+            Err(_) => panic!("called `EitherBox::unwrap_a` on a `B`"),
+        }
+    }
+
+    /// Returns the `Box<B>`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this holds an `A`.
+    pub fn unwrap_b(self) -> Box<B> {
+        match self.try_into_b() {
+            Ok(b) => b,
+            Err(_) => panic!("called `EitherBox::unwrap_b` on an `A`"),
+        }
+    }
+
+    /// Returns the underlying `PointerUnion`.
+    ///
+    /// The `EitherBox` still owns the allocation: the returned pointer must not outlive it.
+    pub fn as_union(&self) -> PointerUnion<A, B> {
+        self.inner
+    }
+}
+
+impl<A, B> Drop for EitherBox<A, B> {
+    fn drop(&mut self) {
+        unsafe {
+            // SAFETY: the pointer has been created with `Box::into_raw`, from a box of the type given by the tag
+            match (self.inner.as_a(), self.inner.as_b()) {
+                (Some(a), _) => drop(Box::from_raw(a as *mut A)),
+                (_, Some(b)) => drop(Box::from_raw(b as *mut B)),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+impl<A: Clone, B: Clone> Clone for EitherBox<A, B> {
+    fn clone(&self) -> Self {
+        match (self.as_a(), self.as_b()) {
+            (Some(a), _) => EitherBox::new_a(a.clone()),
+            (_, Some(b)) => EitherBox::new_b(b.clone()),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<A: fmt::Debug, B: fmt::Debug> fmt::Debug for EitherBox<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.as_a(), self.as_b()) {
+            (Some(a), _) => f.debug_tuple("A").field(a).finish(),
+            (_, Some(b)) => f.debug_tuple("B").field(b).finish(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::EitherBox;
+    use std::{boxed::Box, format, mem, rc::Rc, string::String};
+
+    #[test]
+    fn access() {
+        assert_eq!(mem::size_of::<Box<u32>>(), mem::size_of::<EitherBox<u32, String>>());
+        let mut e = EitherBox::<u32, String>::new_a(1);
+        assert!(e.is_a() && e.as_b().is_none());
+        *e.as_a_mut().unwrap() += 1;
+        assert_eq!(format!("{:?}", e.clone()), "A(2)");
+        let e = e.try_into_b().unwrap_err();
+        assert_eq!(*e.unwrap_a(), 2);
+
+        let mut e = EitherBox::<u32, String>::from_box_b(Box::new(String::from("b")));
+        e.as_b_mut().unwrap().push('!');
+        assert_eq!((e.as_a(), e.as_b().map(String::as_str)), (None, Some("b!")));
+        assert_eq!(*e.unwrap_b(), "b!");
+    }
+
+    #[test]
+    fn drops() {
+        let rc = Rc::new(());
+        let a = EitherBox::<Rc<()>, u64>::new_a(rc.clone());
+        let b = EitherBox::<u64, Rc<()>>::new_b(rc.clone());
+        assert_eq!(Rc::strong_count(&rc), 3);
+        drop((a, b));
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        let inner = EitherBox::<u64, Rc<()>>::new_b(rc.clone()).unwrap_b();
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(inner);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    #[should_panic = "on a `B`"]
+    fn unwrap_wrong() {
+        EitherBox::<u32, u32>::new_b(1).unwrap_a();
+    }
+}
@@ -0,0 +1,68 @@
+use core::ffi::c_void;
+
+/// Returns the mask of the low bits that store the value for the given alignment.
+fn value_mask(align: usize) -> usize {
+    align.wrapping_sub(1)
+}
+
+/// Returns whether `ptr` and `value` can be packed together: `align` is a power of two, `ptr` is aligned to `align`,
+/// and `value` is less than `align`.
+#[no_mangle]
+pub extern "C" fn pvp_can_pack(ptr: *const c_void, value: usize, align: usize) -> bool {
+    align.is_power_of_two() && ptr.addr() & value_mask(align) == 0 && value <= value_mask(align)
+}
+
+/// Packs `value` in the low bits of `ptr`, with the same layout as `PointerValuePair`.
+///
+/// Returns null if the arguments can't be packed together (see `pvp_can_pack`).
+#[no_mangle]
+pub extern "C" fn pvp_pack(ptr: *const c_void, value: usize, align: usize) -> *const c_void {
+    if !pvp_can_pack(ptr, value, align) {
+        return core::ptr::null();
+    }
+    ptr.map_addr(|addr| addr | value)
+}
+
+/// Returns the pointer of a packed pointer created with `pvp_pack` or `PointerValuePair::into_packed_ptr`.
+#[no_mangle]
+pub extern "C" fn pvp_ptr(packed: *const c_void, align: usize) -> *const c_void {
+    packed.map_addr(|addr| addr & !value_mask(align))
+}
+
+/// Returns the value of a packed pointer created with `pvp_pack` or `PointerValuePair::into_packed_ptr`.
+#[no_mangle]
+pub extern "C" fn pvp_value(packed: *const c_void, align: usize) -> usize {
+    packed.addr() & value_mask(align)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pvp_can_pack, pvp_pack, pvp_ptr, pvp_value};
+    use crate::PointerValuePair;
+    use core::ffi::c_void;
+    use std::{mem, ptr};
+
+    #[test]
+    fn same_layout() {
+        let pointee = 42u64;
+        let p = &pointee as *const u64 as *const c_void;
+        let align = mem::align_of::<u64>();
+        let packed = pvp_pack(p, 5, align);
+        assert_eq!((pvp_ptr(packed, align), pvp_value(packed, align)), (p, 5));
+
+        let pv = unsafe { PointerValuePair::from_packed_ptr(packed as *const u64) };
+        assert_eq!((pv.ptr(), pv.value()), (&pointee as *const u64, 5));
+        assert_eq!(pv.into_packed_ptr() as *const c_void, packed);
+        assert_eq!(unsafe { *(pvp_ptr(packed, align) as *const u64) }, 42);
+    }
+
+    #[test]
+    fn invalid() {
+        let p = ptr::without_provenance::<c_void>(0x1004);
+        assert!(pvp_can_pack(p, 3, 4));
+        assert!(!pvp_can_pack(p, 4, 4));
+        assert!(!pvp_can_pack(p, 0, 8));
+        assert!(!pvp_can_pack(p, 0, 3));
+        assert!(pvp_pack(p, 1, 8).is_null());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::PointerValuePair;
+use alloc::boxed::Box;
+use core::{fmt, marker::PhantomData, mem, ptr};
+
+/// An optional owned heap allocation (`Option<Box<T>>`) and an independent boolean flag, packed in a single word.
+///
+/// `None` is represented by a null pointer, and the flag is stored in the lowest alignment bit of the pointer, so it
+/// can be set and read whether there is a box or not (e.g. a cache entry with an optional payload and a dirty bit).
+///
+/// # Notes
+///
+/// `T` must be aligned to at least 2 bytes, which is checked at compile time.
+#[repr(transparent)]
+pub struct FlaggedOptionBox<T> {
+    inner: PointerValuePair<T>,
+    _phantom: PhantomData<Option<Box<T>>>,
+}
+
+// SAFETY: `FlaggedOptionBox` behaves like `(Option<Box<T>>, bool)`.
+unsafe impl<T: Send> Send for FlaggedOptionBox<T> {}
+unsafe impl<T: Sync> Sync for FlaggedOptionBox<T> {}
+
+impl<T> FlaggedOptionBox<T> {
+    /// Creates a new `FlaggedOptionBox` from an optional box and a flag.
+    pub fn new(b: Option<Box<T>>, flag: bool) -> FlaggedOptionBox<T> {
+        const {
+            assert!(
+                PointerValuePair::<T>::max_value() >= 1,
+                "not enough alignment bits to store the flag"
+            )
+        };
+        let ptr = b.map_or(ptr::null_mut(), Box::into_raw);
+        FlaggedOptionBox {
+            inner: PointerValuePair::new(ptr, flag as usize),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns whether there is a box.
+    pub fn is_some(&self) -> bool {
+        !self.inner.is_null()
+    }
+
+    /// Returns whether there is no box.
+    pub fn is_none(&self) -> bool {
+        self.inner.is_null()
+    }
+
+    /// Returns a reference to the boxed value, if any.
+    pub fn as_ref(&self) -> Option<&T> {
+        // SAFETY: the pointer is null or has been created with `Box::into_raw`, and is owned by `self`
+        unsafe { self.inner.ptr().as_ref() }
+    }
+
+    /// Returns a mutable reference to the boxed value, if any.
+    pub fn as_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: the pointer is null or has been created with `Box::into_raw`, and is owned by `self`, which we
+        // borrow mutably
+        unsafe { (self.inner.ptr() as *mut T).as_mut() }
+    }
+
+    /// Returns the flag.
+    pub fn flag(&self) -> bool {
+        self.inner.value() != 0
+    }
+
+    /// Sets the flag, without touching the box.
+    pub fn set_flag(&mut self, flag: bool) {
+        self.inner.replace_value(flag as usize);
+    }
+
+    /// Replaces the box (if any) with the given one, keeping the flag, and returns the previous box.
+    pub fn replace(&mut self, b: Option<Box<T>>) -> Option<Box<T>> {
+        let flag = self.flag();
+        mem::replace(self, FlaggedOptionBox::new(b, flag)).into_parts().0
+    }
+
+    /// Takes the box out, leaving `None` in its place and keeping the flag.
+    pub fn take(&mut self) -> Option<Box<T>> {
+        self.replace(None)
+    }
+
+    /// Returns the box and the flag.
+    pub fn into_parts(self) -> (Option<Box<T>>, bool) {
+        let (ptr, flag) = (self.inner.ptr() as *mut T, self.flag());
+        // ownership is transferred to the returned box
+        mem::forget(self);
+        // SAFETY: the pointer is null or has been created with `Box::into_raw`
+        (unsafe { (!ptr.is_null()).then(|| Box::from_raw(ptr)) }, flag)
+    }
+}
+
+impl<T> Drop for FlaggedOptionBox<T> {
+    fn drop(&mut self) {
+        if self.is_some() {
+            // SAFETY: the pointer has been created with `Box::into_raw`
+            unsafe { drop(Box::from_raw(self.inner.ptr() as *mut T)) }
+        }
+    }
+}
+
+impl<T> Default for FlaggedOptionBox<T> {
+    /// Returns a `FlaggedOptionBox` without a box, and with the flag cleared.
+    fn default() -> Self {
+        FlaggedOptionBox::new(None, false)
+    }
+}
+
+impl<T: Clone> Clone for FlaggedOptionBox<T> {
+    /// Clones the boxed value (if any) into a new allocation, and keeps the flag.
+    fn clone(&self) -> Self {
+        FlaggedOptionBox::new(self.as_ref().map(|v| Box::new(v.clone())), self.flag())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for FlaggedOptionBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlaggedOptionBox")
+            .field("box", &self.as_ref())
+            .field("flag", &self.flag())
+            .finish()
+    }
+}
+
+impl<T> From<Option<Box<T>>> for FlaggedOptionBox<T> {
+    /// Creates a `FlaggedOptionBox` with the flag cleared.
+    fn from(b: Option<Box<T>>) -> Self {
+        FlaggedOptionBox::new(b, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FlaggedOptionBox;
+    use std::{boxed::Box, format, mem, rc::Rc};
+
+    #[test]
+    fn cache_entry() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<FlaggedOptionBox<u32>>());
+        let mut e = FlaggedOptionBox::<u32>::default();
+        assert!(e.is_none() && !e.flag());
+        e.set_flag(true);
+        assert_eq!(e.replace(Some(Box::new(1))), None);
+        *e.as_mut().unwrap() += 1;
+        assert!(e.is_some() && e.flag());
+        assert_eq!(
+            format!("{:?}", e.clone()),
+            "FlaggedOptionBox { box: Some(2), flag: true }"
+        );
+
+        assert_eq!(e.take().as_deref(), Some(&2));
+        assert!(e.is_none() && e.flag());
+        let (b, flag) = FlaggedOptionBox::from(Some(Box::new(3))).into_parts();
+        assert_eq!((b.as_deref(), flag), (Some(&3), false));
+    }
+
+    #[test]
+    fn drops() {
+        let rc = Rc::new(());
+        let mut e = FlaggedOptionBox::new(Some(Box::new(rc.clone())), true);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        e.set_flag(false);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(e);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{AlignedPointerValuePair, PackError};
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem,
+};
+
+/// Function pointer types (`fn(A, B) -> R`, `unsafe fn`, `extern "C" fn`...), which can be stored in a
+/// `TaggedFnPtr`.
+///
+/// This is implemented for function pointers with up to 6 arguments. Function pointers that are generic over
+/// lifetimes (e.g. `for<'a> fn(&'a T)`) are not supported.
+///
+/// # Safety
+///
+/// `Self` must be a function pointer type, with the same size as `*const ()`.
+pub unsafe trait FnPtr: Copy {
+    /// Converts the function pointer to a raw pointer.
+    fn to_ptr(self) -> *const ();
+
+    /// Converts a raw pointer returned by `to_ptr` back to a function pointer.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `Self::to_ptr`.
+    unsafe fn from_ptr(ptr: *const ()) -> Self;
+}
+
+macro_rules! impl_fn_ptr {
+    ($($arg:ident),*) => {
+        impl_fn_ptr!(@each [$($arg),*] fn, unsafe fn, extern "C" fn, unsafe extern "C" fn);
+    };
+    (@each [$($arg:ident),*] $(,)?) => {};
+    (@each [$($arg:ident),*] fn $(, $($rest:tt)*)?) => {
+        impl_fn_ptr!(@impl [$($arg),*] fn);
+        impl_fn_ptr!(@each [$($arg),*] $($($rest)*)?);
+    };
+    (@each [$($arg:ident),*] unsafe fn $(, $($rest:tt)*)?) => {
+        impl_fn_ptr!(@impl [$($arg),*] unsafe fn);
+        impl_fn_ptr!(@each [$($arg),*] $($($rest)*)?);
+    };
+    (@each [$($arg:ident),*] extern "C" fn $(, $($rest:tt)*)?) => {
+        impl_fn_ptr!(@impl [$($arg),*] extern "C" fn);
+        impl_fn_ptr!(@each [$($arg),*] $($($rest)*)?);
+    };
+    (@each [$($arg:ident),*] unsafe extern "C" fn $(, $($rest:tt)*)?) => {
+        impl_fn_ptr!(@impl [$($arg),*] unsafe extern "C" fn);
+        impl_fn_ptr!(@each [$($arg),*] $($($rest)*)?);
+    };
+    (@impl [$($arg:ident),*] $($fn:tt)*) => {
+        unsafe impl<R, $($arg),*> FnPtr for $($fn)*($($arg),*) -> R {
+            fn to_ptr(self) -> *const () {
+                self as *const ()
+            }
+
+            unsafe fn from_ptr(ptr: *const ()) -> Self {
+                mem::transmute::<*const (), Self>(ptr)
+            }
+        }
+    };
+}
+
+impl_fn_ptr!();
+impl_fn_ptr!(A);
+impl_fn_ptr!(A, B);
+impl_fn_ptr!(A, B, C);
+impl_fn_ptr!(A, B, C, D);
+impl_fn_ptr!(A, B, C, D, E);
+impl_fn_ptr!(A, B, C, D, E, F);
+
+/// The alignment of the functions that can be stored in a `TaggedFnPtr`.
+const FN_ALIGN: usize = 4;
+
+/// A function pointer with a 2-bit integer value packed in its low bits.
+///
+/// Unlike data pointers, the alignment of functions is not known from their type: it is usually at least 4 bytes,
+/// but depends on the platform and the compiler options (on ARM, the lowest bit of the pointers to Thumb functions
+/// is set). Therefore the alignment is checked when creating a `TaggedFnPtr`.
+pub struct TaggedFnPtr<F: FnPtr> {
+    inner: AlignedPointerValuePair<u8, FN_ALIGN>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F: FnPtr> Copy for TaggedFnPtr<F> {}
+
+impl<F: FnPtr> Clone for TaggedFnPtr<F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F: FnPtr> PartialEq for TaggedFnPtr<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<F: FnPtr> Eq for TaggedFnPtr<F> {}
+
+impl<F: FnPtr> Hash for TaggedFnPtr<F> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
+    }
+}
+
+impl<F: FnPtr> fmt::Debug for TaggedFnPtr<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaggedFnPtr")
+            .field("ptr", &self.inner.ptr().cast::<()>())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<F: FnPtr> TaggedFnPtr<F> {
+    /// Creates a new `TaggedFnPtr` from the given function pointer and value, checking that the function is
+    /// sufficiently aligned and that the value fits in the available bits.
+    pub fn try_new(f: F, value: usize) -> Result<TaggedFnPtr<F>, PackError> {
+        let ptr = f.to_ptr();
+        if ptr.addr() & (FN_ALIGN - 1) != 0 {
+            return Err(PackError::MisalignedPointer);
+        }
+        if value > Self::max_value() {
+            return Err(PackError::ValueTooLarge);
+        }
+        Ok(TaggedFnPtr {
+            inner: AlignedPointerValuePair::new(ptr.cast(), value),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Creates a new `TaggedFnPtr` from the given function pointer and value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function is not aligned to 4 bytes, or if the value doesn't fit in the available bits.
+    pub fn new(f: F, value: usize) -> TaggedFnPtr<F> {
+        Self::try_new(f, value).unwrap_or_else(|err| panic!("cannot pack the function pointer: {}", err))
+    }
+
+    /// Returns the function pointer.
+    pub fn get(self) -> F {
+        unsafe {
+            // SAFETY: the pointer was returned by `F::to_ptr` in `try_new`.
+            F::from_ptr(self.inner.ptr().cast())
+        }
+    }
+
+    /// Returns the value stored alongside the function pointer.
+    pub fn value(self) -> usize {
+        self.inner.value()
+    }
+
+    /// Returns the maximum (inclusive) integer value that can be stored alongside a function pointer.
+    pub const fn max_value() -> usize {
+        FN_ALIGN - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PackError, TaggedFnPtr};
+    use std::mem;
+
+    fn add(a: u32, b: u32) -> u32 {
+        a + b
+    }
+
+    extern "C" fn neg(a: i32) -> i32 {
+        -a
+    }
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(mem::size_of::<fn()>(), mem::size_of::<TaggedFnPtr<fn()>>());
+    }
+
+    #[test]
+    fn call() {
+        // the alignment of functions isn't guaranteed, so accept both outcomes
+        match TaggedFnPtr::try_new(add as fn(u32, u32) -> u32, 2) {
+            Ok(f) => {
+                assert_eq!(f.value(), 2);
+                assert_eq!((f.get())(1, 2), 3);
+            }
+            Err(err) => assert_eq!(err, PackError::MisalignedPointer),
+        }
+        if let Ok(f) = TaggedFnPtr::try_new(neg as extern "C" fn(i32) -> i32, 3) {
+            assert_eq!(f.value(), 3);
+            assert_eq!((f.get())(5), -5);
+        }
+    }
+
+    #[test]
+    fn value_too_large() {
+        let f = add as fn(u32, u32) -> u32;
+        if TaggedFnPtr::try_new(f, 0).is_ok() {
+            assert_eq!(TaggedFnPtr::try_new(f, 4), Err(PackError::ValueTooLarge));
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use crate::PointerValuePair;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+/// Objects that keep track of their generation, i.e. the number of times their memory has been reused (typically
+/// the slots of an object pool), so that `GenPtr::get` can detect stale pointers.
+pub trait Generational {
+    /// Returns the current generation of the object. Only the low bits (up to `GenPtr::<Self>::max_generation()`)
+    /// are compared with the generation of the pointers.
+    fn generation(&self) -> usize;
+}
+
+/// A raw pointer with a generation counter packed in its alignment bits.
+///
+/// The generation identifies the "incarnation" of the pointee that the pointer was created for: when the memory of
+/// an object is reused for another object (e.g. in an object pool), its generation is bumped, and dereferencing a
+/// pointer with an older generation can be detected with `get` instead of silently accessing the new object.
+///
+/// # Notes
+///
+/// The counter only has as many bits as the alignment of `T` allows (e.g. 3 bits for 8-byte aligned pointees), and
+/// wraps around, so a stale pointer goes undetected if the memory has been reused a multiple of
+/// `max_generation() + 1` times. This makes it a debugging aid, not a safety mechanism.
+#[repr(transparent)]
+pub struct GenPtr<T> {
+    inner: PointerValuePair<T>,
+}
+
+impl<T> Copy for GenPtr<T> {}
+
+impl<T> Clone for GenPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for GenPtr<T> {
+    /// Compares the addresses and generations of both pointers.
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T> Eq for GenPtr<T> {}
+
+impl<T> Hash for GenPtr<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for GenPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GenPtr")
+            .field("ptr", &self.ptr())
+            .field("generation", &self.generation())
+            .finish()
+    }
+}
+
+impl<T> GenPtr<T> {
+    /// Creates a new `GenPtr` from a pointer and a generation. The generation is truncated to the available bits.
+    pub fn new(ptr: *const T, generation: usize) -> GenPtr<T> {
+        GenPtr {
+            inner: PointerValuePair::new(ptr, generation & Self::max_generation()),
+        }
+    }
+
+    /// Returns the maximum (inclusive) generation, after which the counter wraps around to 0.
+    pub const fn max_generation() -> usize {
+        PointerValuePair::<T>::max_value()
+    }
+
+    /// Returns the pointer.
+    pub fn ptr(self) -> *const T {
+        self.inner.ptr()
+    }
+
+    /// Returns the generation.
+    pub fn generation(self) -> usize {
+        self.inner.value()
+    }
+
+    /// Increments the generation, wrapping around after `max_generation()`, and returns the new generation.
+    pub fn bump_generation(&mut self) -> usize {
+        let generation = (self.generation() + 1) & Self::max_generation();
+        self.inner.replace_value(generation);
+        generation
+    }
+
+    /// Returns whether the pointer was created for the given generation of the pointee, comparing only the bits
+    /// stored in the pointer.
+    pub fn same_generation(self, generation: usize) -> bool {
+        self.generation() == generation & Self::max_generation()
+    }
+
+    /// Returns a reference to the pointee if the pointer was created for the given generation, or `None` otherwise.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be null or valid for reads for `'a`, whatever the generation of the pointee (e.g. because
+    /// the pointee is a slot of a pool that is never deallocated).
+    pub unsafe fn get_if<'a>(self, generation: usize) -> Option<&'a T> {
+        if self.same_generation(generation) {
+            self.ptr().as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: Generational> GenPtr<T> {
+    /// Creates a `GenPtr` to the given object, with its current generation.
+    pub fn to(object: &T) -> GenPtr<T> {
+        GenPtr::new(object, object.generation())
+    }
+
+    /// Returns a reference to the pointee if it is still of the generation of the pointer, or `None` otherwise (or
+    /// if the pointer is null).
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be null or valid for reads for `'a`, whatever the generation of the pointee (e.g. because
+    /// the pointee is a slot of a pool that is never deallocated).
+    pub unsafe fn get<'a>(self) -> Option<&'a T> {
+        let object = self.ptr().as_ref()?;
+        self.same_generation(object.generation()).then_some(object)
+    }
+
+    /// Returns whether the pointee is of a newer generation than the pointer (or the pointer is null).
+    ///
+    /// # Safety
+    ///
+    /// Same as `get`.
+    pub unsafe fn is_stale(self) -> bool {
+        self.get().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GenPtr, Generational};
+    use std::{cell::Cell, format, mem, ptr};
+
+    struct Slot {
+        generation: Cell<usize>,
+        value: Cell<u32>,
+    }
+
+    impl Generational for Slot {
+        fn generation(&self) -> usize {
+            self.generation.get()
+        }
+    }
+
+    #[test]
+    fn generations() {
+        assert_eq!(mem::size_of::<*const u64>(), mem::size_of::<GenPtr<u64>>());
+        assert_eq!(GenPtr::<u64>::max_generation(), 7);
+        let x = 0u64;
+        let mut p = GenPtr::new(&x, 6);
+        assert_eq!(p.bump_generation(), 7);
+        assert_eq!(p.bump_generation(), 0);
+        assert!(p.same_generation(8) && !p.same_generation(7));
+        assert_eq!(p.ptr(), &x as *const u64);
+        assert_eq!(unsafe { (p.get_if(0), p.get_if(1)) }, (Some(&0), None));
+        assert_eq!(GenPtr::new(&x, 9), GenPtr::new(&x, 1));
+        assert_eq!(
+            format!("{:?}", GenPtr::<u64>::new(ptr::null(), 2)),
+            "GenPtr { ptr: 0x0, generation: 2 }"
+        );
+    }
+
+    #[test]
+    fn stale() {
+        let slot = Slot {
+            generation: Cell::new(0),
+            value: Cell::new(1),
+        };
+        let p = GenPtr::to(&slot);
+        unsafe {
+            assert_eq!(p.get().map(|s| s.value.get()), Some(1));
+            // reuse the slot for another object
+            slot.generation.set(slot.generation.get() + 1);
+            slot.value.set(2);
+            assert!(p.is_stale() && p.get().is_none());
+            assert_eq!(GenPtr::to(&slot).get().map(|s| s.value.get()), Some(2));
+            assert!(GenPtr::<Slot>::new(ptr::null(), 0).is_stale());
+        }
+    }
+}
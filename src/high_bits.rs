@@ -0,0 +1,235 @@
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    ptr,
+};
+
+/// Number of high bits of the address used to store the value.
+const HIGH_BITS: u32 = 16;
+
+#[cfg(all(
+    target_pointer_width = "64",
+    any(target_arch = "x86_64", target_arch = "aarch64"),
+    not(target_os = "android")
+))]
+mod repr {
+    use super::HIGH_BITS;
+
+    /// Shift of the value in the packed pointer.
+    const VALUE_SHIFT: u32 = usize::BITS - HIGH_BITS;
+
+    /// Mask of the address bits of the packed pointer.
+    const ADDR_MASK: usize = usize::MAX >> HIGH_BITS;
+
+    /// The pointer, with the value in the high bits of the address.
+    #[repr(transparent)]
+    pub(super) struct Repr<T: ?Sized>(*const T);
+
+    impl<T: ?Sized> Repr<T> {
+        pub(super) fn new(ptr: *const T, value: usize) -> Repr<T> {
+            assert!(
+                ptr.addr() & !ADDR_MASK == 0,
+                "the high bits of the pointer {:p} are not zero",
+                ptr.cast::<()>()
+            );
+            Repr(ptr.map_addr(|addr| addr | (value << VALUE_SHIFT)))
+        }
+
+        pub(super) fn ptr(&self) -> *const T {
+            self.0.map_addr(|addr| addr & ADDR_MASK)
+        }
+
+        pub(super) fn value(&self) -> usize {
+            self.0.addr() >> VALUE_SHIFT
+        }
+    }
+}
+
+#[cfg(not(all(
+    target_pointer_width = "64",
+    any(target_arch = "x86_64", target_arch = "aarch64"),
+    not(target_os = "android")
+)))]
+mod repr {
+    /// The pointer and the value, side by side: the high bits of the addresses may be in use on this target.
+    pub(super) struct Repr<T: ?Sized> {
+        ptr: *const T,
+        value: u16,
+    }
+
+    impl<T: ?Sized> Repr<T> {
+        pub(super) fn new(ptr: *const T, value: usize) -> Repr<T> {
+            Repr {
+                ptr,
+                value: value as u16,
+            }
+        }
+
+        pub(super) fn ptr(&self) -> *const T {
+            self.ptr
+        }
+
+        pub(super) fn value(&self) -> usize {
+            self.value as usize
+        }
+    }
+}
+
+/// A pair of a raw pointer and a 16-bit integer value, packed in the unused high bits of the address.
+///
+/// On x86-64 and AArch64, user-space virtual addresses are at most 48 bits wide, so the upper 16 bits of a
+/// pointer are always zero. This type stores the value there instead of in the alignment bits, which gives a much
+/// larger value range than `PointerValuePair` for types with a small alignment (e.g. `u8`), and works regardless
+/// of the alignment of the pointers.
+///
+/// This is only available with the `high-bits` feature. On other targets (including 32-bit targets such as wasm32,
+/// the x32 ABI of x86-64, and Android), the high bits of the addresses may be in use, so the value is stored next to
+/// the pointer instead, and `HighBitsPointerValuePair` is two words wide. It still holds 16-bit values, and behaves
+/// the same. `is_packed` tells whether the pair is packed in a single word on the current target.
+///
+/// # Notes
+///
+/// On x86-64 and AArch64, this relies on architectural assumptions that may not hold on all systems:
+/// - on x86-64 with 5-level paging (LA57), the kernel may hand out addresses wider than 48 bits if
+///   requested to (Linux only does so if the `mmap` hint address is above the 47-bit boundary);
+/// - on AArch64, the virtual address space may be configured to be 52 bits wide;
+/// - on AArch64, Top Byte Ignore (TBI) lets the top byte of the addresses hold a tag: the Memory Tagging Extension
+///   (MTE) stores allocation tags there when it is enabled, and Android's allocator tags heap pointers even without
+///   it. Android is therefore treated like the other targets, with a two-word pair.
+///
+/// `new` asserts that the high bits of the pointer are zero, so these cases are detected at construction.
+#[repr(transparent)]
+pub struct HighBitsPointerValuePair<T: ?Sized> {
+    repr: repr::Repr<T>,
+}
+
+impl<T: ?Sized> Copy for HighBitsPointerValuePair<T> {}
+
+impl<T: ?Sized> Clone for HighBitsPointerValuePair<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for repr::Repr<T> {}
+
+impl<T: ?Sized> Clone for repr::Repr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> PartialEq for HighBitsPointerValuePair<T> {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.ptr(), other.ptr()) && self.value() == other.value()
+    }
+}
+
+impl<T: ?Sized> Eq for HighBitsPointerValuePair<T> {}
+
+impl<T: ?Sized> Hash for HighBitsPointerValuePair<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ptr().hash(state);
+        self.value().hash(state)
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for HighBitsPointerValuePair<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HighBitsPointerValuePair")
+            .field("ptr", &self.ptr().cast::<()>())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<T: ?Sized> HighBitsPointerValuePair<T> {
+    /// Creates a new `HighBitsPointerValuePair` from the given raw pointer and value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in 16 bits, or if the high bits of the pointer are not zero (on x86-64 and
+    /// AArch64).
+    pub fn new(ptr: *const T, value: usize) -> HighBitsPointerValuePair<T> {
+        assert!(
+            value <= Self::max_value(),
+            "not enough high bits ({}) to store the value ({})",
+            Self::available_bits(),
+            value
+        );
+
+        HighBitsPointerValuePair {
+            repr: repr::Repr::new(ptr, value),
+        }
+    }
+
+    /// Returns the pointer.
+    pub fn ptr(self) -> *const T {
+        self.repr.ptr()
+    }
+
+    /// Returns the value stored alongside the pointer.
+    pub fn value(self) -> usize {
+        self.repr.value()
+    }
+
+    /// Returns whether the value is stored in the high bits of the pointer on the current target, in which case
+    /// `HighBitsPointerValuePair` is the same size as `*const T`.
+    pub const fn is_packed() -> bool {
+        cfg!(all(
+            target_pointer_width = "64",
+            any(target_arch = "x86_64", target_arch = "aarch64"),
+            not(target_os = "android")
+        ))
+    }
+
+    /// Returns the number of bits available to store the value.
+    pub const fn available_bits() -> u32 {
+        HIGH_BITS
+    }
+
+    /// Returns the maximum (inclusive) integer value that can be stored in the pointer.
+    pub const fn max_value() -> usize {
+        (1 << HIGH_BITS) - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::HighBitsPointerValuePair;
+    use std::mem;
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(
+            mem::size_of::<*const u8>() == mem::size_of::<HighBitsPointerValuePair<u8>>(),
+            HighBitsPointerValuePair::<u8>::is_packed()
+        );
+    }
+
+    #[test]
+    fn basic_get_set() {
+        let bytes = [0u8, 1, 2, 3];
+        let pv = HighBitsPointerValuePair::new(&bytes[1], 0xBEEF);
+        assert_eq!(pv.ptr(), &bytes[1] as *const u8);
+        assert_eq!(unsafe { *pv.ptr() }, 1);
+        assert_eq!(pv.value(), 0xBEEF);
+        assert_eq!(HighBitsPointerValuePair::<u8>::max_value(), 0xFFFF);
+    }
+
+    #[test]
+    fn slices() {
+        let s = "hello";
+        let pv = HighBitsPointerValuePair::new(s, 1234);
+        assert_eq!(pv.ptr(), s as *const str);
+        assert_eq!(unsafe { &*pv.ptr() }, "hello");
+        assert_eq!(pv.value(), 1234);
+    }
+
+    #[test]
+    #[should_panic]
+    fn value_overflow() {
+        let pointee = 0u8;
+        HighBitsPointerValuePair::new(&pointee, 0x10000);
+    }
+}
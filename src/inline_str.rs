@@ -0,0 +1,49 @@
+use core::{mem, str};
+
+const WORD: usize = mem::size_of::<usize>();
+/// Index of the byte that holds the least significant bits of the pointer, and the tag of inline strings.
+const TAG: usize = if cfg!(target_endian = "little") { 0 } else { WORD - 1 };
+/// Index of the first byte of inline strings.
+const START: usize = if cfg!(target_endian = "little") { 1 } else { 0 };
+/// Set in the tag of inline strings. The pointers stored in place of inline strings are aligned, so this bit is
+/// always clear in them.
+const INLINE: u8 = 1;
+
+/// A short string stored in a word, shared by the types that hold either an inline string or an aligned pointer in a
+/// union (`SmallStr` and `Symbol`).
+///
+/// The tag byte holds `INLINE` and the length shifted left by one, and the other bytes hold the string.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct InlineStr([u8; WORD]);
+
+impl InlineStr {
+    /// The maximum length (in bytes) of the strings stored inline.
+    pub(crate) const CAPACITY: usize = WORD - 1;
+
+    /// Stores the string inline, or returns `None` if it's longer than `CAPACITY`.
+    pub(crate) fn new(s: &str) -> Option<InlineStr> {
+        if s.len() > Self::CAPACITY {
+            return None;
+        }
+        let mut bytes = [0u8; WORD];
+        bytes[TAG] = ((s.len() as u8) << 1) | INLINE;
+        bytes[START..START + s.len()].copy_from_slice(s.as_bytes());
+        Some(InlineStr(bytes))
+    }
+
+    /// Returns whether the word holds an inline string, rather than the bytes of an aligned pointer.
+    pub(crate) fn is_inline(&self) -> bool {
+        self.0[TAG] & INLINE != 0
+    }
+
+    /// Returns the string.
+    ///
+    /// # Safety
+    ///
+    /// The word must hold an inline string (see `is_inline`).
+    pub(crate) unsafe fn as_str(&self) -> &str {
+        let len = (self.0[TAG] >> 1) as usize;
+        // SAFETY: the bytes have been copied from a `str` by `new`
+        unsafe { str::from_utf8_unchecked(&self.0[START..START + len]) }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{PointeeAlign, PointerValuePair};
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+/// A `PointerValuePair` whose value is exactly `BITS` bits wide.
+///
+/// Unlike `PointerValuePair<T>`, where the number of bits available to store the value depends on the alignment of
+/// `T` and is only checked when values are stored, the bit budget is part of the type, and it is checked at
+/// compile time that `*const T` has at least `BITS` spare low bits.
+///
+/// This is similar to `llvm::PointerIntPair<T*, BITS>`.
+#[repr(transparent)]
+pub struct PointerIntPair<T: ?Sized + PointeeAlign, const BITS: u32> {
+    inner: PointerValuePair<T>,
+}
+
+impl<T: ?Sized + PointeeAlign, const BITS: u32> Copy for PointerIntPair<T, BITS> {}
+
+impl<T: ?Sized + PointeeAlign, const BITS: u32> Clone for PointerIntPair<T, BITS> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized + PointeeAlign, const BITS: u32> PartialEq for PointerIntPair<T, BITS> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T: ?Sized + PointeeAlign, const BITS: u32> Eq for PointerIntPair<T, BITS> {}
+
+impl<T: ?Sized + PointeeAlign, const BITS: u32> Hash for PointerIntPair<T, BITS> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
+    }
+}
+
+impl<T: PointeeAlign, const BITS: u32> fmt::Debug for PointerIntPair<T, BITS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PointerIntPair")
+            .field("ptr", &self.ptr())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<T: ?Sized + PointeeAlign, const BITS: u32> PointerIntPair<T, BITS> {
+    /// Fails to compile if `*const T` doesn't have `BITS` spare bits.
+    const CHECK_BITS: () = assert!(
+        BITS <= PointerValuePair::<T>::available_bits(),
+        "not enough alignment bits to store a value of the requested width"
+    );
+
+    /// The number of bits of the value.
+    pub const BITS: u32 = BITS;
+
+    /// Creates a new `PointerIntPair` from the given raw pointer and value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is not representable in `BITS` bits.
+    pub fn new(ptr: *const T, value: usize) -> PointerIntPair<T, BITS> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CHECK_BITS;
+        assert!(
+            value <= Self::max_value(),
+            "value ({}) does not fit in {} bits",
+            value,
+            BITS
+        );
+        PointerIntPair {
+            inner: PointerValuePair::new(ptr, value),
+        }
+    }
+
+    /// Returns the pointer.
+    pub fn ptr(self) -> *const T {
+        self.inner.ptr()
+    }
+
+    /// Returns the value stored alongside the pointer.
+    pub fn value(self) -> usize {
+        self.inner.value()
+    }
+
+    /// Returns the maximum (inclusive) integer value that can be stored in the pointer.
+    pub const fn max_value() -> usize {
+        if BITS == 0 {
+            0
+        } else {
+            usize::MAX >> (usize::BITS - BITS)
+        }
+    }
+
+    /// Returns the underlying `PointerValuePair`.
+    pub fn into_pair(self) -> PointerValuePair<T> {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PointerIntPair;
+    use std::mem;
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(mem::size_of::<*const i32>(), mem::size_of::<PointerIntPair<i32, 2>>());
+    }
+
+    #[test]
+    fn basic_get_set() {
+        let pointee = 42u64;
+        let pv = PointerIntPair::<_, 2>::new(&pointee, 3);
+        assert_eq!(pv.ptr(), &pointee as *const _);
+        assert_eq!(pv.value(), 3);
+        assert_eq!(PointerIntPair::<u64, 2>::max_value(), 3);
+        assert_eq!(PointerIntPair::<u64, 0>::max_value(), 0);
+        assert_eq!(pv.into_pair().value(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn value_too_wide() {
+        let pointee = 42u64;
+        PointerIntPair::<_, 2>::new(&pointee, 4);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::PointerValuePair;
+use core::{cell::Cell, fmt, iter::FusedIterator, marker::PhantomData, ptr};
+
+/// Set in the value of the links of the nodes that are in a list.
+const LINKED: usize = 1usize;
+
+/// The link embedded in the nodes of an `IntrusiveList`: a pointer to the next node, with flags packed in its
+/// alignment bits.
+///
+/// One bit is used to track whether the node is in a list, so `T` must be aligned to at least 2 bytes, and the flags
+/// get the remaining alignment bits (e.g. 2 bits for 8-byte aligned nodes on 64-bit targets).
+pub struct ListLink<T> {
+    next: Cell<PointerValuePair<T>>,
+}
+
+impl<T> ListLink<T> {
+    /// Creates a new link, for a node that is not in a list.
+    pub const fn new() -> ListLink<T> {
+        ListLink {
+            next: Cell::new(PointerValuePair::from_raw(ptr::null())),
+        }
+    }
+
+    /// Returns the maximum (inclusive) value of the flags.
+    pub const fn max_flags() -> usize {
+        PointerValuePair::<T>::max_value() >> 1
+    }
+
+    /// Returns whether the node is in a list.
+    pub fn is_linked(&self) -> bool {
+        self.next.get().value() & LINKED != 0
+    }
+
+    /// Returns the flags stored in the link.
+    pub fn flags(&self) -> usize {
+        self.next.get().value() >> 1
+    }
+
+    /// Sets the flags stored in the link.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the flags are greater than `max_flags()`.
+    pub fn set_flags(&self, flags: usize) {
+        assert!(
+            flags <= Self::max_flags(),
+            "not enough alignment bits to store the flags ({flags})"
+        );
+        let mut next = self.next.get();
+        next.replace_value((flags << 1) | (next.value() & LINKED));
+        self.next.set(next);
+    }
+
+    fn next(&self) -> *const T {
+        self.next.get().ptr()
+    }
+
+    fn link(&self, next: *const T) {
+        self.next.set(PointerValuePair::new(next, (self.flags() << 1) | LINKED));
+    }
+
+    fn unlink(&self) {
+        self.next.set(PointerValuePair::new(ptr::null(), self.flags() << 1));
+    }
+}
+
+impl<T> Default for ListLink<T> {
+    fn default() -> Self {
+        ListLink::new()
+    }
+}
+
+impl<T> fmt::Debug for ListLink<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ListLink")
+            .field("linked", &self.is_linked())
+            .field("flags", &self.flags())
+            .finish()
+    }
+}
+
+/// Types that can be stored in an `IntrusiveList`.
+///
+/// # Safety
+///
+/// `link` must always return the same `ListLink`, which must be owned by `self` (typically a field).
+pub unsafe trait Linked: Sized {
+    /// Returns the link embedded in the node.
+    fn link(&self) -> &ListLink<Self>;
+}
+
+/// A singly-linked list of borrowed nodes, whose links (`ListLink`) are embedded in the nodes.
+///
+/// The list doesn't allocate: nodes are borrowed for `'a`, and linked through their `ListLink`, which also holds
+/// per-node flags in the alignment bits of the pointer to the next node.
+///
+/// ```ignore
+/// struct Task {
+///     link: ListLink<Task>,
+///     id: u32,
+/// }
+///
+/// unsafe impl Linked for Task {
+///     fn link(&self) -> &ListLink<Task> {
+///         &self.link
+///     }
+/// }
+/// ```
+pub struct IntrusiveList<'a, T: Linked> {
+    head: *const T,
+    _phantom: PhantomData<&'a T>,
+}
+
+impl<'a, T: Linked> IntrusiveList<'a, T> {
+    /// Creates a new empty list.
+    pub const fn new() -> IntrusiveList<'a, T> {
+        const {
+            assert!(
+                PointerValuePair::<T>::max_value() >= LINKED,
+                "not enough alignment bits to store the link state"
+            )
+        };
+        IntrusiveList {
+            head: ptr::null(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns whether the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.head.is_null()
+    }
+
+    /// Returns the first node.
+    pub fn front(&self) -> Option<&'a T> {
+        // SAFETY: the nodes of the list are borrowed for `'a`
+        unsafe { self.head.as_ref() }
+    }
+
+    /// Inserts a node at the front of the list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node is already in a list.
+    pub fn push_front(&mut self, node: &'a T) {
+        let link = node.link();
+        assert!(!link.is_linked(), "the node is already in a list");
+        link.link(self.head);
+        self.head = node;
+    }
+
+    /// Removes the first node of the list, and returns it.
+    pub fn pop_front(&mut self) -> Option<&'a T> {
+        let node = self.front()?;
+        self.head = node.link().next();
+        node.link().unlink();
+        Some(node)
+    }
+
+    /// Returns an iterator over the nodes, from front to back.
+    pub fn iter(&self) -> IntrusiveListIter<'a, T> {
+        IntrusiveListIter {
+            next: self.head,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Removes all the nodes from the list.
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+}
+
+impl<T: Linked> Drop for IntrusiveList<'_, T> {
+    /// Unlinks the nodes, so that they can be inserted in another list.
+    fn drop(&mut self) {
+        self.clear()
+    }
+}
+
+impl<T: Linked> Default for IntrusiveList<'_, T> {
+    fn default() -> Self {
+        IntrusiveList::new()
+    }
+}
+
+impl<T: Linked + fmt::Debug> fmt::Debug for IntrusiveList<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T: Linked> IntoIterator for &IntrusiveList<'a, T> {
+    type Item = &'a T;
+    type IntoIter = IntrusiveListIter<'a, T>;
+
+    fn into_iter(self) -> IntrusiveListIter<'a, T> {
+        self.iter()
+    }
+}
+
+/// An iterator over the nodes of an `IntrusiveList`.
+pub struct IntrusiveListIter<'a, T: Linked> {
+    next: *const T,
+    _phantom: PhantomData<&'a T>,
+}
+
+impl<'a, T: Linked> Iterator for IntrusiveListIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        // SAFETY: the nodes of the list are borrowed for `'a`, and `Linked::link` returns the link written by the
+        // list.
+        let node = unsafe { self.next.as_ref()? };
+        self.next = node.link().next();
+        Some(node)
+    }
+}
+
+impl<T: Linked> FusedIterator for IntrusiveListIter<'_, T> {}
+
+impl<T: Linked> fmt::Debug for IntrusiveListIter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntrusiveListIter").field("next", &self.next).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{IntrusiveList, Linked, ListLink};
+    use std::{format, vec::Vec};
+
+    #[derive(Debug)]
+    struct Task {
+        link: ListLink<Task>,
+        id: u32,
+    }
+
+    impl Task {
+        fn new(id: u32) -> Task {
+            Task {
+                link: ListLink::new(),
+                id,
+            }
+        }
+    }
+
+    unsafe impl Linked for Task {
+        fn link(&self) -> &ListLink<Task> {
+            &self.link
+        }
+    }
+
+    #[test]
+    fn push_pop() {
+        let tasks = [Task::new(1), Task::new(2), Task::new(3)];
+        let mut list = IntrusiveList::new();
+        assert!(list.is_empty() && list.front().is_none());
+        for t in &tasks {
+            list.push_front(t);
+        }
+        assert_eq!(list.iter().map(|t| t.id).collect::<Vec<_>>(), [3, 2, 1]);
+        assert!(tasks.iter().all(|t| t.link.is_linked()));
+
+        assert_eq!(list.pop_front().map(|t| t.id), Some(3));
+        assert!(!tasks[2].link.is_linked());
+        let mut other = IntrusiveList::new();
+        other.push_front(&tasks[2]);
+        drop(list);
+        assert!(!tasks[0].link.is_linked() && tasks[2].link.is_linked());
+        assert_eq!(other.front().map(|t| t.id), Some(3));
+    }
+
+    #[test]
+    fn flags() {
+        assert_eq!(ListLink::<u32>::max_flags(), 1);
+        assert_eq!(ListLink::<u64>::max_flags(), 3);
+
+        let tasks = [Task::new(1), Task::new(2)];
+        tasks[0].link.set_flags(3);
+        let mut list = IntrusiveList::new();
+        list.push_front(&tasks[0]);
+        list.push_front(&tasks[1]);
+        tasks[1].link.set_flags(1);
+        tasks[0].link.set_flags(0);
+        assert_eq!(
+            list.iter().map(|t| (t.id, t.link.flags())).collect::<Vec<_>>(),
+            [(2, 1), (1, 0)]
+        );
+        assert_eq!(format!("{:?}", tasks[1].link), "ListLink { linked: true, flags: 1 }");
+        list.clear();
+        assert_eq!(tasks[1].link.flags(), 1);
+    }
+
+    #[test]
+    #[should_panic = "already in a list"]
+    fn double_push() {
+        let task = Task::new(1);
+        let mut list = IntrusiveList::new();
+        list.push_front(&task);
+        list.push_front(&task);
+    }
+}
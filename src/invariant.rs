@@ -0,0 +1,110 @@
+use crate::PointerValuePair;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+/// A `PointerValuePair<T>` that is invariant in `T`, like `*mut T`.
+///
+/// `PointerValuePair<T>` is covariant in `T`, like `*const T`: a `PointerValuePair<&'static str>` can be used as a
+/// `PointerValuePair<&'a str>`. This is correct for pointers that are only read through, but unsound for wrappers
+/// that write through the pointer, such as `TaggedMut`, which must be invariant like `&'a mut T`. Such wrappers can
+/// hold an `InvariantPointerValuePair<T>` instead, which derefs to the pair and has the same layout.
+#[repr(transparent)]
+pub struct InvariantPointerValuePair<T: ?Sized> {
+    inner: PointerValuePair<T>,
+    _phantom: PhantomData<*mut T>,
+}
+
+impl<T: ?Sized> InvariantPointerValuePair<T> {
+    /// Wraps the given pair.
+    pub const fn from_pair(pv: PointerValuePair<T>) -> InvariantPointerValuePair<T> {
+        InvariantPointerValuePair {
+            inner: pv,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the wrapped pair, which is covariant in `T`.
+    pub const fn into_pair(self) -> PointerValuePair<T> {
+        self.inner
+    }
+}
+
+impl<T: ?Sized> Copy for InvariantPointerValuePair<T> {}
+
+impl<T: ?Sized> Clone for InvariantPointerValuePair<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> PartialEq for InvariantPointerValuePair<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T: ?Sized> Eq for InvariantPointerValuePair<T> {}
+
+impl<T: ?Sized> Hash for InvariantPointerValuePair<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
+    }
+}
+
+impl<T: ?Sized> Deref for InvariantPointerValuePair<T> {
+    type Target = PointerValuePair<T>;
+
+    fn deref(&self) -> &PointerValuePair<T> {
+        &self.inner
+    }
+}
+
+impl<T: ?Sized> DerefMut for InvariantPointerValuePair<T> {
+    fn deref_mut(&mut self) -> &mut PointerValuePair<T> {
+        &mut self.inner
+    }
+}
+
+impl<T: ?Sized> From<PointerValuePair<T>> for InvariantPointerValuePair<T> {
+    fn from(pv: PointerValuePair<T>) -> Self {
+        InvariantPointerValuePair::from_pair(pv)
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for InvariantPointerValuePair<T>
+where
+    PointerValuePair<T>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{InvariantPointerValuePair, PointerValuePair};
+    use std::mem;
+
+    // compiles only if `PointerValuePair` is covariant
+    fn shorten<'a>(pv: PointerValuePair<&'static str>) -> PointerValuePair<&'a str> {
+        pv
+    }
+
+    #[test]
+    fn access() {
+        assert_eq!(
+            mem::size_of::<usize>(),
+            mem::size_of::<InvariantPointerValuePair<u64>>()
+        );
+        let s: &'static str = "hello";
+        let mut pv = InvariantPointerValuePair::from_pair(shorten(PointerValuePair::new(&s, 1)));
+        assert_eq!(pv.replace_value(2), 1);
+        assert_eq!(unsafe { *pv.ptr() }, "hello");
+        assert_eq!(pv.into_pair().value(), 2);
+        assert_eq!(pv, InvariantPointerValuePair::from(pv.into_pair()));
+    }
+}
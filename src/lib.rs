@@ -1,5 +1,209 @@
+#![no_std]
+#![cfg_attr(feature = "ptr_metadata", feature(ptr_metadata))]
+#![cfg_attr(feature = "unsize", feature(coerce_unsized, unsize))]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(any(test, feature = "std"))]
+extern crate std;
+
+// allows the code generated by the derive macros to refer to `::pointer_value_pair` in tests
+#[cfg(test)]
+extern crate self as pointer_value_pair;
+
+/// Asserts an invariant of a packed representation, only with the `debug-validate` feature.
+macro_rules! validate {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "debug-validate")]
+        {
+            assert!($($arg)*);
+        }
+    };
+}
+
+mod aligned;
+#[cfg(feature = "alloc")]
+mod aligned_box;
+#[cfg(feature = "allocator_api")]
+mod alloc_in;
+#[cfg(feature = "alloc")]
+mod arena;
+#[cfg(target_has_atomic = "ptr")]
+mod atomic;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+mod atomic_arc;
+mod batch;
+#[cfg(feature = "alloc")]
+mod byte_str;
+#[cfg(feature = "bytemuck")]
+mod bytemuck_impls;
+mod colored_link;
+#[cfg(feature = "alloc")]
+mod compact_error;
+mod compressed;
+#[cfg(feature = "alloc")]
 mod cow;
+#[cfg(feature = "alloc")]
+mod cow_mut;
+#[cfg(feature = "alloc")]
+mod cow_vec;
+#[cfg(feature = "alloc")]
+mod either_box;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "alloc")]
+mod flagged_box;
+mod fn_ptr;
+mod gen_ptr;
+#[cfg(feature = "high-bits")]
+mod high_bits;
+mod inline_str;
+mod int_pair;
+mod intrusive;
+mod invariant;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+mod once_cell;
+mod opaque;
+mod packed_option;
+mod packed_result;
+mod packed_value;
 mod pair;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+mod parent_ptr;
+#[cfg(feature = "proptest")]
+mod proptest_impls;
+mod ptr_ext;
+#[cfg(target_has_atomic = "ptr")]
+mod ptr_lock;
+#[cfg(feature = "alloc")]
+mod rc_cow;
+#[cfg(feature = "serde")]
+mod serde_impls;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+mod shared_cow;
+mod slice_len;
+#[cfg(feature = "alloc")]
+mod small_cow;
+#[cfg(feature = "alloc")]
+mod small_ptr_vec;
+#[cfg(feature = "alloc")]
+mod small_str;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+mod stack;
+#[cfg(feature = "alloc")]
+mod static_cow;
+mod sum;
+mod symbol;
+mod tag;
+mod tag_field;
+#[cfg(feature = "alloc")]
+mod tagged_box;
+mod tagged_cell;
+mod tagged_index;
+#[cfg(feature = "alloc")]
+mod tagged_rc;
+mod tagged_ref;
+#[cfg(feature = "alloc")]
+mod thin_box;
+mod thin_dyn;
+mod thin_slice;
+#[cfg(feature = "alloc")]
+mod try_alloc;
+mod union;
+
+pub use aligned::{AlignedPointerValuePair, ExtraAlignment};
+#[cfg(feature = "alloc")]
+pub use aligned_box::AlignedBox;
+#[cfg(feature = "allocator_api")]
+pub use alloc_in::{CowIn, TaggedBoxIn};
+#[cfg(feature = "alloc")]
+pub use arena::{Arena, ArenaHandle, ArenaIter};
+#[cfg(target_has_atomic = "ptr")]
+pub use atomic::AtomicPointerValuePair;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use atomic_arc::AtomicTaggedArc;
+#[cfg(feature = "alloc")]
+pub use byte_str::ByteSlice;
+pub use colored_link::{Color, ColoredLink, Side};
+#[cfg(feature = "alloc")]
+pub use compact_error::CompactError;
+pub use compressed::{CompressedBase, CompressedPtr};
+#[cfg(feature = "alloc")]
+pub use cow::{Cow, CowIntoIter, CowPointee, ToBoxed};
+#[cfg(feature = "alloc")]
+pub use cow_mut::CowMut;
+#[cfg(feature = "alloc")]
+pub use cow_vec::CowVec;
+#[cfg(feature = "alloc")]
+pub use either_box::EitherBox;
+#[cfg(feature = "alloc")]
+pub use flagged_box::FlaggedOptionBox;
+pub use fn_ptr::{FnPtr, TaggedFnPtr};
+pub use gen_ptr::{GenPtr, Generational};
+#[cfg(feature = "high-bits")]
+pub use high_bits::HighBitsPointerValuePair;
+pub use int_pair::PointerIntPair;
+pub use intrusive::{IntrusiveList, IntrusiveListIter, Linked, ListLink};
+pub use invariant::InvariantPointerValuePair;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use once_cell::TaggedOnceCell;
+pub use opaque::OpaquePointerValuePair;
+pub use packed_option::PackedOption;
+pub use packed_result::PackedResult;
+pub use packed_value::{PackedKind, PackedValue};
+pub use pair::{available_bits_for_align, PackError, PointeeAlign, PointerValuePair, PointerValuePairAccess};
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use parent_ptr::TaggedParentPtr;
+#[cfg(feature = "derive")]
+pub use pointer_value_pair_derive::{PackPointer, TagValue};
+pub use ptr_ext::TaggedPtrExt;
+#[cfg(target_has_atomic = "ptr")]
+pub use ptr_lock::{PtrLock, PtrLockGuard};
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use rc_cow::ArcCow;
+#[cfg(feature = "alloc")]
+pub use rc_cow::RcCow;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use shared_cow::SharedCow;
+pub use slice_len::SliceLenPointerValuePair;
+#[cfg(feature = "alloc")]
+pub use small_cow::SmallCow;
+#[cfg(feature = "alloc")]
+pub use small_ptr_vec::{SmallPtrVec, SmallPtrVecIter};
+#[cfg(feature = "alloc")]
+pub use small_str::SmallStr;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use stack::TreiberStack;
+#[cfg(feature = "alloc")]
+pub use static_cow::StaticCow;
+pub use sum::SumPointer;
+#[cfg(feature = "std")]
+pub use symbol::GlobalInterner;
+pub use symbol::{Interner, Symbol};
+pub use tag::{FlaggedPtr, TagValue, TaggedPtr};
+pub use tag_field::TagField;
+#[cfg(feature = "alloc")]
+pub use tagged_box::TaggedBox;
+pub use tagged_cell::TaggedCell;
+pub use tagged_index::TaggedIndex;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use tagged_rc::TaggedArc;
+#[cfg(feature = "alloc")]
+pub use tagged_rc::TaggedRc;
+pub use tagged_ref::{TaggedMut, TaggedRef};
+#[cfg(feature = "alloc")]
+pub use thin_box::ThinBox;
+pub use thin_dyn::{ThinDyn, ThinDynRef};
+pub use thin_slice::ThinSlicePtr;
+#[cfg(feature = "alloc")]
+pub use try_alloc::AllocError;
+pub use union::PointerUnion;
 
-pub use cow::Cow;
-pub use pair::{PointerValuePair, PointerValuePairAccess};
+// used by the exported macros
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::sum::min_align;
+    #[cfg(feature = "bitflags")]
+    pub use bitflags;
+}
@@ -0,0 +1,224 @@
+use crate::{AtomicPointerValuePair, PointerValuePair};
+use alloc::boxed::Box;
+use core::{fmt, hint, marker::PhantomData, mem, ptr, sync::atomic::Ordering};
+
+/// Set in the value of the pair, with a null pointer, while `get_or_init` runs the initializer.
+const INITIALIZING: usize = 1;
+
+/// A cell holding a lazily initialized `Box<T>` and a tag, in a single atomic word.
+///
+/// This is a one-word alternative to `OnceLock<(Box<T>, usize)>` (e.g. for metadata computed on demand for each node
+/// of a graph). The cell is uninitialized while the pointer is null. If several threads call `get_or_init` at the
+/// same time, only one of them runs its initializer, and the others spin until it has stored its box and tag.
+///
+/// # Notes
+///
+/// The lowest alignment bit of the pointer marks the cell as being initialized, and the remaining alignment bits hold
+/// the tag (see `max_tag`), so `T` must be aligned to at least 2 bytes, which is checked at compile time.
+pub struct TaggedOnceCell<T> {
+    pv: AtomicPointerValuePair<T>,
+    _phantom: PhantomData<Box<T>>,
+}
+
+// SAFETY: `TaggedOnceCell` behaves like `OnceLock<Box<T>>`.
+unsafe impl<T: Send> Send for TaggedOnceCell<T> {}
+unsafe impl<T: Send + Sync> Sync for TaggedOnceCell<T> {}
+
+impl<T> TaggedOnceCell<T> {
+    /// Creates a new uninitialized cell.
+    pub const fn new() -> TaggedOnceCell<T> {
+        TaggedOnceCell {
+            pv: AtomicPointerValuePair::new(PointerValuePair::from_raw(ptr::null())),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the maximum (inclusive) tag.
+    pub const fn max_tag() -> usize {
+        const {
+            assert!(
+                PointerValuePair::<T>::max_value() >= INITIALIZING,
+                "not enough alignment bits to store the initialization state"
+            )
+        };
+        PointerValuePair::<T>::max_value() >> 1
+    }
+
+    /// Packs a box and a tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag is greater than `max_tag()`. The tag is checked before `into_raw`, so that the box is not
+    /// leaked on panic.
+    fn pack(b: Box<T>, tag: usize) -> PointerValuePair<T> {
+        assert!(tag <= Self::max_tag(), "tag ({tag}) doesn't fit in the available bits");
+        PointerValuePair::new(Box::into_raw(b), tag << 1)
+    }
+
+    /// Spins while another thread is running the initializer of `get_or_init`.
+    fn wait(&self) {
+        while self.pv.load(Ordering::Acquire) == PointerValuePair::null(INITIALIZING) {
+            hint::spin_loop();
+        }
+    }
+
+    /// Returns a reference to the value and the tag, or `None` if the cell is uninitialized.
+    pub fn get(&self) -> Option<(&T, usize)> {
+        let pv = self.pv.load(Ordering::Acquire);
+        // SAFETY: the pointer is null or has been created with `Box::into_raw`, and is owned by `self` until it is
+        // dropped, which requires exclusive access
+        unsafe { pv.as_ref() }.map(|v| (v, pv.value() >> 1))
+    }
+
+    /// Initializes the cell with the given box and tag if it is uninitialized, or gives them back otherwise. If
+    /// another thread is running the initializer of `get_or_init`, waits for it to finish.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag is greater than `max_tag()`.
+    pub fn set(&self, b: Box<T>, tag: usize) -> Result<(), (Box<T>, usize)> {
+        let new = Self::pack(b, tag);
+        loop {
+            match self
+                .pv
+                .compare_exchange(PointerValuePair::null(0), new, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => return Ok(()),
+                Err(current) if current.is_null() => self.wait(),
+                // SAFETY: the pointer has just been created with `Box::into_raw`, and wasn't stored
+                Err(_) => return Err((unsafe { Box::from_raw(new.ptr() as *mut T) }, tag)),
+            }
+        }
+    }
+
+    /// Returns a reference to the value and the tag, initializing the cell with `f` if it is uninitialized.
+    ///
+    /// `f` is only called once, even if several threads call `get_or_init` at the same time. If it panics, the cell
+    /// is left uninitialized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag returned by `f` is greater than `max_tag()`.
+    pub fn get_or_init(&self, f: impl FnOnce() -> (Box<T>, usize)) -> (&T, usize) {
+        loop {
+            if let Some(v) = self.get() {
+                return v;
+            }
+            match self.pv.compare_exchange(
+                PointerValuePair::null(0),
+                PointerValuePair::null(INITIALIZING),
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(_) => self.wait(),
+            }
+        }
+
+        /// Resets the cell to the uninitialized state if the initializer panics.
+        struct Reset<'a, T>(&'a TaggedOnceCell<T>);
+
+        impl<T> Drop for Reset<'_, T> {
+            fn drop(&mut self) {
+                self.0.pv.store(PointerValuePair::null(0), Ordering::Release);
+            }
+        }
+
+        let reset = Reset(self);
+        let (b, tag) = f();
+        let new = Self::pack(b, tag);
+        mem::forget(reset);
+        self.pv.store(new, Ordering::Release);
+        self.get().unwrap()
+    }
+
+    /// Takes the box and the tag out of the cell, leaving it uninitialized.
+    pub fn take(&mut self) -> Option<(Box<T>, usize)> {
+        let pv = self.pv.swap(PointerValuePair::null(0), Ordering::Relaxed);
+        // SAFETY: the pointer is null or has been created with `Box::into_raw`, and we have exclusive access
+        (!pv.is_null()).then(|| (unsafe { Box::from_raw(pv.ptr() as *mut T) }, pv.value() >> 1))
+    }
+
+    /// Returns the box and the tag, or `None` if the cell is uninitialized.
+    pub fn into_inner(mut self) -> Option<(Box<T>, usize)> {
+        self.take()
+    }
+}
+
+impl<T> Drop for TaggedOnceCell<T> {
+    fn drop(&mut self) {
+        drop(self.take())
+    }
+}
+
+impl<T> Default for TaggedOnceCell<T> {
+    fn default() -> Self {
+        TaggedOnceCell::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TaggedOnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some((v, tag)) => f.debug_tuple("TaggedOnceCell").field(v).field(&tag).finish(),
+            None => f.write_str("TaggedOnceCell(<uninit>)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TaggedOnceCell;
+    use std::{
+        boxed::Box,
+        format, mem,
+        panic::{self, AssertUnwindSafe},
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn init_once() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<TaggedOnceCell<u64>>());
+        let mut cell = TaggedOnceCell::<u64>::new();
+        assert!(cell.get().is_none());
+        assert_eq!(format!("{:?}", cell), "TaggedOnceCell(<uninit>)");
+        assert_eq!(cell.get_or_init(|| (Box::new(1), 2)), (&1, 2));
+        assert_eq!(cell.get_or_init(|| unreachable!()), (&1, 2));
+        assert_eq!(cell.set(Box::new(3), 0), Err((Box::new(3), 0)));
+        assert_eq!(format!("{:?}", cell), "TaggedOnceCell(1, 2)");
+        assert_eq!(cell.take(), Some((Box::new(1), 2)));
+        assert_eq!(TaggedOnceCell::<u64>::max_tag(), 3);
+        assert!(cell.set(Box::new(4), 3).is_ok());
+        assert_eq!(cell.into_inner(), Some((Box::new(4), 3)));
+
+        // a panicking initializer leaves the cell uninitialized
+        let cell = TaggedOnceCell::<u64>::new();
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| cell.get_or_init(|| panic!("init")))).is_err());
+        assert_eq!(cell.get_or_init(|| (Box::new(5), 1)), (&5, 1));
+    }
+
+    #[test]
+    fn race() {
+        let cell = TaggedOnceCell::<u64>::new();
+        let calls = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for i in 0..4 {
+                let (cell, calls) = (&cell, &calls);
+                s.spawn(move || {
+                    let (v, tag) = cell.get_or_init(|| {
+                        calls.fetch_add(1, Ordering::Relaxed);
+                        // give the other threads time to find the cell being initialized
+                        thread::sleep(Duration::from_millis(10));
+                        (Box::new(i), i as usize)
+                    });
+                    assert_eq!(*v as usize, tag);
+                });
+            }
+        });
+        let (v, tag) = cell.get().unwrap();
+        assert!(*v < 4 && tag == *v as usize);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+}
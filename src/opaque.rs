@@ -0,0 +1,206 @@
+use crate::PackError;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    ptr,
+};
+
+/// A pair of a raw pointer to an opaque type and an integer value, for pointers whose alignment is stated by the
+/// caller (`ALIGN`) rather than derived from the pointee type.
+///
+/// The alignment of opaque types is meaningless: handles of C libraries are usually declared as `c_void` or as
+/// zero-sized structs (`struct Handle { _private: [u8; 0] }`), which have an alignment of 1, and extern types have no
+/// alignment at all, so `PointerValuePair` would store no value in pointers to them. Unlike `PointerValuePair` and
+/// `AlignedPointerValuePair`, this type ignores the pointee entirely: the pointers are stored as is, and the
+/// `log2(ALIGN)` low bits hold the value.
+///
+/// # Notes
+///
+/// `ALIGN` must be a power of two, which is checked at compile time. Since the pointers usually come from foreign
+/// code, their alignment is checked at construction in all builds.
+#[repr(transparent)]
+pub struct OpaquePointerValuePair<T: ?Sized, const ALIGN: usize> {
+    pv: *const T,
+}
+
+impl<T: ?Sized, const ALIGN: usize> Copy for OpaquePointerValuePair<T, ALIGN> {}
+
+impl<T: ?Sized, const ALIGN: usize> Clone for OpaquePointerValuePair<T, ALIGN> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized, const ALIGN: usize> PartialEq for OpaquePointerValuePair<T, ALIGN> {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.pv, other.pv)
+    }
+}
+
+impl<T: ?Sized, const ALIGN: usize> Eq for OpaquePointerValuePair<T, ALIGN> {}
+
+impl<T: ?Sized, const ALIGN: usize> Hash for OpaquePointerValuePair<T, ALIGN> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.pv.hash(state)
+    }
+}
+
+impl<T: ?Sized, const ALIGN: usize> fmt::Debug for OpaquePointerValuePair<T, ALIGN> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpaquePointerValuePair")
+            .field("ptr", &self.ptr().cast::<()>())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<T: ?Sized, const ALIGN: usize> OpaquePointerValuePair<T, ALIGN> {
+    /// Returns a bitmask of the zero low bits of the pointers.
+    const fn align_bits() -> usize {
+        const { assert!(ALIGN.is_power_of_two(), "the alignment must be a power of two") };
+        ALIGN - 1
+    }
+
+    /// Creates a new `OpaquePointerValuePair` from the given raw pointer and value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pointer is not aligned to `ALIGN`, or if the value doesn't fit in the available bits.
+    pub fn new(ptr: *const T, value: usize) -> OpaquePointerValuePair<T, ALIGN> {
+        match Self::try_new(ptr, value) {
+            Ok(pv) => pv,
+            Err(PackError::MisalignedPointer) => {
+                panic!("pointer {:p} is not aligned to {} bytes", ptr.cast::<()>(), ALIGN)
+            }
+            Err(_) => panic!(
+                "not enough alignment bits ({}) to store the value ({})",
+                Self::available_bits(),
+                value
+            ),
+        }
+    }
+
+    /// Creates a new `OpaquePointerValuePair` from the given raw pointer and value, checking that the pointer is
+    /// aligned to `ALIGN` and that the value fits in the available bits.
+    pub fn try_new(ptr: *const T, value: usize) -> Result<OpaquePointerValuePair<T, ALIGN>, PackError> {
+        let m = Self::align_bits();
+        if ptr.addr() & m != 0 {
+            return Err(PackError::MisalignedPointer);
+        }
+        if value > m {
+            return Err(PackError::ValueTooLarge);
+        }
+        Ok(OpaquePointerValuePair {
+            pv: ptr.map_addr(|addr| addr | value),
+        })
+    }
+
+    /// Creates a new `OpaquePointerValuePair` from the given raw pointer and value, without checking them.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be aligned to `ALIGN`, and the value must be at most `max_value()`.
+    pub unsafe fn new_unchecked(ptr: *const T, value: usize) -> OpaquePointerValuePair<T, ALIGN> {
+        OpaquePointerValuePair {
+            pv: ptr.map_addr(|addr| addr | value),
+        }
+    }
+
+    /// Returns the pointer.
+    pub fn ptr(self) -> *const T {
+        self.pv.map_addr(|addr| addr & !Self::align_bits())
+    }
+
+    /// Returns the value stored alongside the pointer.
+    pub fn value(self) -> usize {
+        self.pv.addr() & Self::align_bits()
+    }
+
+    /// Replaces the value stored alongside the pointer, and returns the previous one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn replace_value(&mut self, value: usize) -> usize {
+        let old = self.value();
+        *self = OpaquePointerValuePair::new(self.ptr(), value);
+        old
+    }
+
+    /// Returns the number of bits available to store the value.
+    pub const fn available_bits() -> u32 {
+        Self::align_bits().count_ones()
+    }
+
+    /// Returns the maximum (inclusive) integer value that can be stored in the pointer.
+    pub const fn max_value() -> usize {
+        Self::align_bits()
+    }
+
+    /// Returns the packed pointer, with the value in its low bits, e.g. to pass it to foreign code.
+    pub const fn into_packed_ptr(self) -> *const T {
+        self.pv
+    }
+
+    /// Creates an `OpaquePointerValuePair` from a packed pointer returned by `into_packed_ptr`.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must have been returned by `into_packed_ptr` on an `OpaquePointerValuePair<T, ALIGN>`, or have
+    /// the same layout.
+    pub const unsafe fn from_packed_ptr(ptr: *const T) -> OpaquePointerValuePair<T, ALIGN> {
+        OpaquePointerValuePair { pv: ptr }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OpaquePointerValuePair, PackError};
+    use std::{ffi::c_void, mem, ptr};
+
+    /// An opaque C handle.
+    #[repr(C)]
+    struct Handle {
+        _private: [u8; 0],
+    }
+
+    #[repr(C, align(16))]
+    struct Storage([u8; 32]);
+
+    #[test]
+    fn opaque_handles() {
+        assert_eq!(
+            mem::size_of::<*const c_void>(),
+            mem::size_of::<OpaquePointerValuePair<c_void, 16>>()
+        );
+        let storage = Storage([0; 32]);
+        let handle = (&storage as *const Storage).cast::<Handle>().wrapping_byte_add(16);
+        assert_eq!(OpaquePointerValuePair::<Handle, 16>::max_value(), 15);
+
+        let mut pv = OpaquePointerValuePair::<Handle, 16>::new(handle, 9);
+        // the address of the zero-sized handle is kept
+        assert!(ptr::eq(pv.ptr(), handle));
+        assert_eq!(pv.replace_value(15), 9);
+        let packed = pv.into_packed_ptr();
+        assert_eq!(packed.addr(), handle.addr() | 15);
+        // SAFETY: returned by `into_packed_ptr`
+        assert_eq!(unsafe { OpaquePointerValuePair::from_packed_ptr(packed) }, pv);
+
+        let p = (&storage as *const Storage).cast::<c_void>();
+        assert_eq!(OpaquePointerValuePair::<c_void, 8>::new(p, 7).value(), 7);
+    }
+
+    #[test]
+    fn checked() {
+        let storage = Storage([0; 32]);
+        let p = (&storage as *const Storage).cast::<c_void>();
+        assert_eq!(
+            OpaquePointerValuePair::<c_void, 16>::try_new(p.wrapping_byte_add(4), 0),
+            Err(PackError::MisalignedPointer)
+        );
+        assert_eq!(
+            OpaquePointerValuePair::<c_void, 4>::try_new(p.wrapping_byte_add(4), 4),
+            Err(PackError::ValueTooLarge)
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::PointerValuePair;
+use core::{fmt, mem};
+
+/// An optional `PointerValuePair<T>`, where `None` is represented by a null pointer.
+///
+/// Unlike `Option<PointerValuePair<T>>`, which is two words wide, this is the size of a pointer. Pairs holding
+/// a null pointer cannot be stored in a `PackedOption`, since they would be confused with `None`.
+#[repr(transparent)]
+pub struct PackedOption<T> {
+    inner: PointerValuePair<T>,
+}
+
+impl<T> Copy for PackedOption<T> {}
+
+impl<T> Clone for PackedOption<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for PackedOption<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T> Eq for PackedOption<T> {}
+
+impl<T> Default for PackedOption<T> {
+    /// Returns `PackedOption::none()`.
+    fn default() -> Self {
+        PackedOption::none()
+    }
+}
+
+impl<T> fmt::Debug for PackedOption<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.expand().fmt(f)
+    }
+}
+
+impl<T> PackedOption<T> {
+    /// Returns a `PackedOption` holding no pair.
+    pub fn none() -> PackedOption<T> {
+        PackedOption {
+            inner: PointerValuePair::null(0),
+        }
+    }
+
+    /// Returns a `PackedOption` holding the given pair.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pointer of the pair is null.
+    pub fn some(pv: PointerValuePair<T>) -> PackedOption<T> {
+        assert!(!pv.is_null(), "null pointers cannot be stored in a PackedOption");
+        PackedOption { inner: pv }
+    }
+
+    /// Returns whether this holds no pair.
+    pub fn is_none(self) -> bool {
+        self.inner.is_null()
+    }
+
+    /// Returns whether this holds a pair.
+    pub fn is_some(self) -> bool {
+        !self.is_none()
+    }
+
+    /// Converts this into an `Option`.
+    pub fn expand(self) -> Option<PointerValuePair<T>> {
+        if self.is_none() {
+            None
+        } else {
+            Some(self.inner)
+        }
+    }
+
+    /// Takes the pair out of this `PackedOption`, leaving `None` in its place.
+    pub fn take(&mut self) -> Option<PointerValuePair<T>> {
+        mem::take(self).expand()
+    }
+
+    /// Replaces the pair held by this `PackedOption`, and returns the previous one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pointer of the pair is null.
+    pub fn replace(&mut self, pv: PointerValuePair<T>) -> Option<PointerValuePair<T>> {
+        mem::replace(self, PackedOption::some(pv)).expand()
+    }
+}
+
+impl<T> From<PointerValuePair<T>> for PackedOption<T> {
+    /// Converts a pair into a `PackedOption` holding it. See `PackedOption::some`.
+    fn from(pv: PointerValuePair<T>) -> Self {
+        PackedOption::some(pv)
+    }
+}
+
+impl<T> From<Option<PointerValuePair<T>>> for PackedOption<T> {
+    /// Converts an `Option` into a `PackedOption`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the option holds a pair with a null pointer.
+    fn from(pv: Option<PointerValuePair<T>>) -> Self {
+        pv.map_or_else(PackedOption::none, PackedOption::some)
+    }
+}
+
+impl<T> From<PackedOption<T>> for Option<PointerValuePair<T>> {
+    fn from(opt: PackedOption<T>) -> Self {
+        opt.expand()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PackedOption, PointerValuePair};
+    use std::{format, mem};
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(mem::size_of::<*const u64>(), mem::size_of::<PackedOption<u64>>());
+    }
+
+    #[test]
+    fn some_none() {
+        let pointee = 42u64;
+        let pv = PointerValuePair::new(&pointee, 3);
+        let mut opt = PackedOption::none();
+        assert!(opt.is_none());
+        assert_eq!(opt.expand(), None);
+        assert_eq!(format!("{:?}", opt), "None");
+
+        assert_eq!(opt.replace(pv), None);
+        assert!(opt.is_some());
+        assert_eq!(opt.expand(), Some(pv));
+        assert_eq!(opt.take(), Some(pv));
+        assert!(opt.is_none());
+
+        let opt: PackedOption<u64> = Some(pv).into();
+        assert_eq!(Option::from(opt), Some(pv));
+    }
+
+    #[test]
+    #[should_panic]
+    fn null_pointer() {
+        PackedOption::some(PointerValuePair::<u64>::null(1));
+    }
+}
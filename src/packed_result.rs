@@ -0,0 +1,166 @@
+use crate::TagValue;
+use core::{fmt, marker::PhantomData, mem, ptr};
+
+/// Set in the representation of errors. The low bit of references is always clear.
+const ERR: usize = 1;
+
+/// A `Result<&'a T, E>` packed in a single word, where the error is a small `TagValue` (typically a fieldless enum).
+///
+/// `Ok` is represented by the reference itself, whose low bit is always clear since `T` is aligned to at least 2
+/// bytes. `Err` is represented by the bits of the error, shifted left by one, with the low bit set. Unlike
+/// `Result<&'a T, E>`, which is two words wide, it can be returned in a single register.
+///
+/// # Notes
+///
+/// `T` must be aligned to at least 2 bytes, which is checked at compile time.
+pub struct PackedResult<'a, T, E> {
+    repr: *const T,
+    _phantom: PhantomData<(&'a T, E)>,
+}
+
+// SAFETY: `PackedResult` behaves like `Result<&'a T, E>`.
+unsafe impl<T: Sync, E: Send> Send for PackedResult<'_, T, E> {}
+unsafe impl<T: Sync, E: Sync> Sync for PackedResult<'_, T, E> {}
+
+impl<T, E: TagValue> Copy for PackedResult<'_, T, E> {}
+
+impl<T, E: TagValue> Clone for PackedResult<'_, T, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: fmt::Debug, E: TagValue + fmt::Debug> fmt::Debug for PackedResult<'_, T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.expand().fmt(f)
+    }
+}
+
+impl<'a, T, E: TagValue> PackedResult<'a, T, E> {
+    /// Fails to compile if the low bit of `*const T` can't be used to tell `Ok` and `Err` apart.
+    const CHECK_LAYOUT: () = {
+        assert!(
+            mem::align_of::<T>() >= 2,
+            "not enough alignment bits to store the discriminant"
+        );
+        assert!(
+            E::BITS < usize::BITS,
+            "the error must leave one bit for the discriminant"
+        );
+    };
+
+    /// Returns a `PackedResult` holding the given reference.
+    pub fn ok(r: &'a T) -> PackedResult<'a, T, E> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CHECK_LAYOUT;
+        PackedResult {
+            repr: r,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns a `PackedResult` holding the given error.
+    pub fn err(e: E) -> PackedResult<'a, T, E> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CHECK_LAYOUT;
+        PackedResult {
+            repr: ptr::without_provenance((e.into_bits() << 1) | ERR),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns whether this holds a reference.
+    pub fn is_ok(self) -> bool {
+        self.repr.addr() & ERR == 0
+    }
+
+    /// Returns whether this holds an error.
+    pub fn is_err(self) -> bool {
+        !self.is_ok()
+    }
+
+    /// Returns the reference, or `None` if this holds an error.
+    pub fn get_ok(self) -> Option<&'a T> {
+        // SAFETY: the pointer has been created from a `&'a T` by `ok`
+        self.is_ok().then(|| unsafe { &*self.repr })
+    }
+
+    /// Returns the error, or `None` if this holds a reference.
+    pub fn get_err(self) -> Option<E> {
+        self.is_err().then(|| E::from_bits(self.repr.addr() >> 1))
+    }
+
+    /// Converts this into a `Result`.
+    pub fn expand(self) -> Result<&'a T, E> {
+        match self.get_ok() {
+            Some(r) => Ok(r),
+            None => Err(E::from_bits(self.repr.addr() >> 1)),
+        }
+    }
+}
+
+impl<'a, T, E: TagValue> From<Result<&'a T, E>> for PackedResult<'a, T, E> {
+    fn from(r: Result<&'a T, E>) -> Self {
+        match r {
+            Ok(r) => PackedResult::ok(r),
+            Err(e) => PackedResult::err(e),
+        }
+    }
+}
+
+impl<'a, T, E: TagValue> From<PackedResult<'a, T, E>> for Result<&'a T, E> {
+    fn from(r: PackedResult<'a, T, E>) -> Self {
+        r.expand()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PackedResult, TagValue};
+    use std::{format, mem};
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum LookupError {
+        NotFound,
+        Removed,
+    }
+
+    impl TagValue for LookupError {
+        const BITS: u32 = 1;
+
+        fn into_bits(self) -> usize {
+            self as usize
+        }
+
+        fn from_bits(bits: usize) -> Self {
+            [LookupError::NotFound, LookupError::Removed][bits]
+        }
+    }
+
+    fn lookup(nodes: &[u32], i: usize) -> PackedResult<'_, u32, LookupError> {
+        match nodes.get(i) {
+            Some(0) => PackedResult::err(LookupError::Removed),
+            Some(n) => PackedResult::ok(n),
+            None => PackedResult::err(LookupError::NotFound),
+        }
+    }
+
+    #[test]
+    fn lookups() {
+        assert_eq!(
+            mem::size_of::<usize>(),
+            mem::size_of::<PackedResult<u32, LookupError>>()
+        );
+        let nodes = [1, 0, 3];
+        assert_eq!(lookup(&nodes, 2).expand(), Ok(&3));
+        assert_eq!(lookup(&nodes, 1).expand(), Err(LookupError::Removed));
+        assert_eq!(lookup(&nodes, 3).get_err(), Some(LookupError::NotFound));
+        assert!(lookup(&nodes, 0).is_ok() && lookup(&nodes, 0).get_err().is_none());
+        assert_eq!(format!("{:?}", lookup(&nodes, 5)), "Err(NotFound)");
+
+        let r: PackedResult<u32, bool> = Ok(&nodes[0]).into();
+        assert_eq!(Result::from(r), Ok(&1));
+        let r: PackedResult<u32, bool> = Err(true).into();
+        assert_eq!((r.is_err(), r.get_ok()), (true, None));
+    }
+}
@@ -0,0 +1,252 @@
+use crate::PointerValuePair;
+use core::fmt;
+
+/// Mask of the bits used to distinguish pointers and integers from floating-point numbers.
+const TAG_MASK: u64 = 0xFFFF << 48;
+
+/// Tag of the pointers, in the space of the negative quiet NaNs.
+const PTR_TAG: u64 = 0xFFF9 << 48;
+
+/// Tag of the integers, in the space of the negative quiet NaNs.
+const INT_TAG: u64 = 0xFFFA << 48;
+
+/// Bits of the canonical NaN, which all NaNs are converted to.
+const CANONICAL_NAN: u64 = 0x7FF8 << 48;
+
+#[cfg(target_pointer_width = "64")]
+mod repr {
+    use super::{PTR_TAG, TAG_MASK};
+    use core::ptr;
+
+    /// The bits of the value, as a pointer, so that pointers keep their provenance.
+    #[repr(transparent)]
+    pub(super) struct Repr<T>(*const T);
+
+    impl<T> Repr<T> {
+        pub(super) fn from_bits(bits: u64) -> Repr<T> {
+            Repr(ptr::without_provenance(bits as usize))
+        }
+
+        pub(super) fn from_ptr(raw: *const T) -> Repr<T> {
+            Repr(raw.map_addr(|addr| addr | PTR_TAG as usize))
+        }
+
+        pub(super) fn bits(&self) -> u64 {
+            self.0.addr() as u64
+        }
+
+        pub(super) fn ptr(&self) -> *const T {
+            self.0.map_addr(|addr| addr & !TAG_MASK as usize)
+        }
+    }
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+mod repr {
+    use super::PTR_TAG;
+    use core::ptr;
+
+    /// The bits of the value, and the pointer if it holds one, so that it keeps its provenance: pointers are
+    /// narrower than the bits on this target.
+    pub(super) struct Repr<T> {
+        bits: u64,
+        ptr: *const T,
+    }
+
+    impl<T> Repr<T> {
+        pub(super) fn from_bits(bits: u64) -> Repr<T> {
+            Repr { bits, ptr: ptr::null() }
+        }
+
+        pub(super) fn from_ptr(raw: *const T) -> Repr<T> {
+            Repr {
+                bits: raw.addr() as u64 | PTR_TAG,
+                ptr: raw,
+            }
+        }
+
+        pub(super) fn bits(&self) -> u64 {
+            self.bits
+        }
+
+        pub(super) fn ptr(&self) -> *const T {
+            self.ptr
+        }
+    }
+}
+
+impl<T> Copy for repr::Repr<T> {}
+
+impl<T> Clone for repr::Repr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// The kind of value held by a `PackedValue`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PackedKind {
+    /// A `PointerValuePair`.
+    Ptr,
+    /// A 48-bit signed integer.
+    Int,
+    /// A 64-bit floating-point number.
+    Float,
+}
+
+/// A one-word dynamic value that holds either a `PointerValuePair<T>`, a 48-bit signed integer, or an `f64`.
+///
+/// This uses NaN-boxing: floating-point numbers are stored as is (with all NaNs converted to a single canonical NaN),
+/// and pointers and integers are stored in the payload of negative quiet NaNs, which are never produced otherwise.
+///
+/// On 64-bit targets, `PackedValue` is the same size as a pointer. On narrower targets (e.g. wasm32), it holds the 64
+/// bits of the value and a pointer side by side, and behaves the same, with the same range of integers.
+///
+/// # Notes
+///
+/// Like `HighBitsPointerValuePair`, this assumes that the high 16 bits of the pointers are zero, which is asserted
+/// on construction (and always holds on targets with narrower pointers).
+#[repr(transparent)]
+pub struct PackedValue<T> {
+    repr: repr::Repr<T>,
+}
+
+impl<T> Copy for PackedValue<T> {}
+
+impl<T> Clone for PackedValue<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> fmt::Debug for PackedValue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind() {
+            PackedKind::Ptr => self.as_ptr().unwrap().fmt(f),
+            PackedKind::Int => f.debug_tuple("Int").field(&self.as_int().unwrap()).finish(),
+            PackedKind::Float => f.debug_tuple("Float").field(&self.as_f64().unwrap()).finish(),
+        }
+    }
+}
+
+impl<T> PackedValue<T> {
+    /// The smallest integer that can be stored.
+    pub const MIN_INT: i64 = -(1 << 47);
+
+    /// The largest integer that can be stored.
+    pub const MAX_INT: i64 = (1 << 47) - 1;
+
+    /// Creates a `PackedValue` holding a pair.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the high 16 bits of the pointer are not zero.
+    pub fn from_ptr(pv: PointerValuePair<T>) -> PackedValue<T> {
+        let raw = pv.into_raw();
+        assert!(
+            raw.addr() as u64 & TAG_MASK == 0,
+            "the high bits of the pointer {:p} are not zero",
+            raw
+        );
+        PackedValue {
+            repr: repr::Repr::from_ptr(raw),
+        }
+    }
+
+    /// Creates a `PackedValue` holding an integer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the integer is not between `MIN_INT` and `MAX_INT`.
+    pub fn from_int(i: i64) -> PackedValue<T> {
+        assert!(
+            (Self::MIN_INT..=Self::MAX_INT).contains(&i),
+            "integer ({}) out of range of a PackedValue",
+            i
+        );
+        PackedValue {
+            repr: repr::Repr::from_bits(INT_TAG | (i as u64 & !TAG_MASK)),
+        }
+    }
+
+    /// Creates a `PackedValue` holding a floating-point number.
+    ///
+    /// NaNs are converted to a canonical NaN.
+    pub fn from_f64(f: f64) -> PackedValue<T> {
+        let bits = if f.is_nan() { CANONICAL_NAN } else { f.to_bits() };
+        PackedValue {
+            repr: repr::Repr::from_bits(bits),
+        }
+    }
+
+    /// Returns the kind of value held.
+    pub fn kind(self) -> PackedKind {
+        match self.repr.bits() & TAG_MASK {
+            PTR_TAG => PackedKind::Ptr,
+            INT_TAG => PackedKind::Int,
+            _ => PackedKind::Float,
+        }
+    }
+
+    /// Returns the integer held, if any.
+    pub fn as_int(self) -> Option<i64> {
+        // shift left then right to sign-extend the 48-bit integer
+        (self.kind() == PackedKind::Int).then(|| ((self.repr.bits() << 16) as i64) >> 16)
+    }
+
+    /// Returns the floating-point number held, if any.
+    pub fn as_f64(self) -> Option<f64> {
+        (self.kind() == PackedKind::Float).then(|| f64::from_bits(self.repr.bits()))
+    }
+
+    /// Returns the pair held, if any.
+    pub fn as_ptr(self) -> Option<PointerValuePair<T>> {
+        (self.kind() == PackedKind::Ptr).then(|| PointerValuePair::from_raw(self.repr.ptr()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PackedKind, PackedValue, PointerValuePair};
+    use std::{format, mem};
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(
+            mem::size_of::<*const u64>() == mem::size_of::<PackedValue<u64>>(),
+            cfg!(target_pointer_width = "64")
+        );
+    }
+
+    #[test]
+    fn kinds() {
+        let pointee = 42u64;
+        let pv = PointerValuePair::new(&pointee, 5);
+        let v = PackedValue::from_ptr(pv);
+        assert_eq!(v.kind(), PackedKind::Ptr);
+        assert_eq!(v.as_ptr(), Some(pv));
+        assert_eq!(unsafe { *v.as_ptr().unwrap().ptr() }, 42);
+        assert_eq!(v.as_int(), None);
+
+        for i in [0, 1, -1, PackedValue::<u64>::MIN_INT, PackedValue::<u64>::MAX_INT] {
+            let v = PackedValue::<u64>::from_int(i);
+            assert_eq!(v.kind(), PackedKind::Int);
+            assert_eq!(v.as_int(), Some(i));
+        }
+
+        for f in [0.0, -0.0, 1.5, f64::INFINITY, f64::NEG_INFINITY, f64::MIN_POSITIVE] {
+            let v = PackedValue::<u64>::from_f64(f);
+            assert_eq!(v.kind(), PackedKind::Float);
+            assert_eq!(v.as_f64().unwrap().to_bits(), f.to_bits());
+        }
+        let nan = f64::from_bits(0xFFF9_0000_0000_0001);
+        assert!(PackedValue::<u64>::from_f64(nan).as_f64().unwrap().is_nan());
+        assert_eq!(format!("{:?}", PackedValue::<u64>::from_int(-3)), "Int(-3)");
+    }
+
+    #[test]
+    #[should_panic]
+    fn int_overflow() {
+        PackedValue::<u64>::from_int(PackedValue::<u64>::MAX_INT + 1);
+    }
+}
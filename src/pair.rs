@@ -1,4 +1,11 @@
-use std::{mem, ptr};
+#[cfg(feature = "ptr_metadata")]
+use core::ptr::Pointee;
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    mem, ptr,
+};
 
 /// A pair consisting of a raw pointer (`*const T`) and an integer value, packed so that it takes the size of a pointer.
 ///
@@ -8,10 +15,39 @@ use std::{mem, ptr};
 /// The size of the value that can be stored alongside the pointer is 3 bits for most types, but ultimately depends on the minimum alignment of `T`:
 /// for example, if `mem::align_of::<T>() == 16` then 4 bits are available to store the value.
 ///
-/// # Notes
-/// Pointers to zero-sized types do not have enough space to store any value, so it must be zero.
+/// # Zero-sized types
+/// Pointers to zero-sized types are packed like any other pointer: their address is kept, and the number of bits
+/// available to store the value is given by `mem::align_of::<T>()` (no bits for `()`). The address matters even though
+/// the pointee occupies no memory, since pointers returned by `Rc::into_raw` or `Arc::into_raw` must be passed back
+/// unchanged. `Cow` supports zero-sized types of any alignment by storing its discriminant in the address instead.
+///
+/// # Layout
+/// The layout is stable and part of the public API, so that other languages can produce and consume the same packed
+/// pointers: `PointerValuePair<T>` is `#[repr(transparent)]` over `*const T`, and the address of the packed pointer
+/// is the address of the pointer bitwise-or'ed with the value, which occupies the low `available_bits()` bits. The
+/// metadata of wide pointers (e.g. the length of a slice) is stored unchanged. `into_packed_ptr` and
+/// `from_packed_ptr` convert from and to the packed pointer, and the `ffi` feature exports C functions to pack and
+/// unpack it.
+///
+/// The alignment that gives the number of bits is `mem::align_of::<T>()` for sized types, including zero-sized ones,
+/// and the alignment of the element type for slices. `str` (and `CStr`, `OsStr`, `Path` and `ByteSlice`) are the
+/// exception: since their alignment is 1, the address is stored unchanged, and the value is stored in the most
+/// significant bit of the length instead (see `new_str`).
+///
+/// # Provenance
+/// The value is packed with `map_addr`, never by casting the pointer to an integer and back, so the provenance of
+/// the pointer is preserved: the pointer returned by `ptr()` can be used to access the same memory as the pointer
+/// passed to the constructor. This makes the type compatible with Miri's `-Zmiri-strict-provenance` mode.
+///
+/// # Variance
+/// Like `*const T`, `PointerValuePair<T>` is covariant in `T`. Wrappers that write through the pointer should use
+/// `InvariantPointerValuePair<T>` instead.
+///
+/// # Thread safety
+/// Like raw pointers, `PointerValuePair` is neither `Send` nor `Sync`, since it doesn't know whether it owns or borrows
+/// the pointee. The safe wrappers (`TaggedRef`, `TaggedMut`, `TaggedBox`, `TaggedArc`, `Cow`) are `Send` and `Sync`
+/// under the same conditions as the corresponding reference or smart pointer.
 #[repr(transparent)]
-#[derive(Debug)]
 pub struct PointerValuePair<T: ?Sized> {
     pv: *const T,
 }
@@ -20,24 +56,196 @@ impl<T: ?Sized> Copy for PointerValuePair<T> {}
 
 impl<T: ?Sized> Clone for PointerValuePair<T> {
     fn clone(&self) -> Self {
-        PointerValuePair { pv: self.pv }
+        *self
+    }
+}
+
+/// Compares the packed representation: two pairs are equal if both their pointers and their values are equal.
+///
+/// See also [`PointerValuePair::ptr_eq`] and [`PointerValuePair::value_eq`].
+impl<T: ?Sized> PartialEq for PointerValuePair<T> {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.pv, other.pv)
+    }
+}
+
+impl<T: ?Sized> Eq for PointerValuePair<T> {}
+
+impl<T: ?Sized> PartialOrd for PointerValuePair<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders pairs by their packed representation (i.e. by address first, then by value).
+impl<T: ?Sized> Ord for PointerValuePair<T> {
+    // comparing the metadata of wide pointers (e.g. slice lengths) is intended
+    #[allow(ambiguous_wide_pointer_comparisons)]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.pv.cmp(&other.pv)
+    }
+}
+
+/// Hashes the packed representation, consistently with `PartialEq`.
+impl<T: ?Sized> Hash for PointerValuePair<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.pv.hash(state)
+    }
+}
+
+/// Allows coercing `PointerValuePair<T>` into `PointerValuePair<U>` when `T: Unsize<U>` (e.g. arrays into slices,
+/// or concrete types into trait objects). The address and the value are unchanged.
+// `DispatchFromDyn` isn't implemented: the receiver of a method called through a vtable would get the packed
+// pointer, with the value bits set.
+#[cfg(feature = "unsize")]
+impl<T: ?Sized + core::marker::Unsize<U>, U: ?Sized> core::ops::CoerceUnsized<PointerValuePair<U>>
+    for PointerValuePair<T>
+{
+}
+
+/// Returns a pair with a null pointer and a zero value.
+impl<T> Default for PointerValuePair<T> {
+    fn default() -> Self {
+        Self::null(0)
+    }
+}
+
+/// Error returned by the checked constructors of `PointerValuePair`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PackError {
+    /// The low bits of the pointer are not zero.
+    MisalignedPointer,
+    /// The value doesn't fit in the available bits.
+    ValueTooLarge,
+    /// The index of a `TaggedIndex` doesn't fit in the bits that are not used by the value.
+    IndexTooLarge,
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackError::MisalignedPointer => write!(f, "the pointer is not sufficiently aligned"),
+            PackError::ValueTooLarge => write!(f, "not enough alignment bits to store the value"),
+            PackError::IndexTooLarge => write!(f, "not enough bits to store the index"),
+        }
+    }
+}
+
+impl core::error::Error for PackError {}
+
+/// Provides the minimum alignment of the pointers to `Self`, which determines how many low bits of a `*const Self`
+/// are known to be zero and can be used to store the value of a `PointerValuePair<Self>`.
+///
+/// This is implemented for all sized types (`mem::align_of::<T>()`) and for slices (the alignment of the element
+/// type). It can also be implemented for trait objects, whose alignment depends on the concrete type behind the
+/// pointer and thus cannot be known from the type alone:
+/// `unsafe impl<'a> PointeeAlign for dyn MyTrait + 'a { const ALIGN: usize = 8; }`. For opaque pointees (e.g.
+/// `c_void`, or handles declared as zero-sized structs), use `OpaquePointerValuePair` instead.
+///
+/// # Safety
+///
+/// `ALIGN` must be a power of two, and all pointers to `Self` used to create a `PointerValuePair` must be aligned
+/// to `ALIGN`. `ZERO_SIZED` must only be `true` if `Self` is a sized type of size zero (which the implementation for
+/// sized types takes care of).
+///
+/// With the `unsize` feature, pairs can be coerced from a type `T` to an unsized type `Self` (e.g. a trait object)
+/// without changing the address. The alignment of all such `T` must then be at most `ALIGN`, otherwise a value
+/// stored in a `PointerValuePair<T>` might not fit in the bits of a `PointerValuePair<Self>`.
+pub unsafe trait PointeeAlign {
+    /// The minimum alignment of the pointers to `Self`.
+    const ALIGN: usize;
+
+    /// Whether the pointees are zero-sized, in which case any non-null, aligned address is valid for them.
+    const ZERO_SIZED: bool = false;
+
+    /// Returns the length of the pointee if it is a slice, shown in the `Debug` output of the pairs.
+    #[doc(hidden)]
+    fn slice_len(_ptr: *const Self) -> Option<usize> {
+        None
     }
 }
 
+unsafe impl<T> PointeeAlign for T {
+    const ALIGN: usize = mem::align_of::<T>();
+    const ZERO_SIZED: bool = mem::size_of::<T>() == 0;
+}
+
+unsafe impl<T> PointeeAlign for [T] {
+    const ALIGN: usize = mem::align_of::<T>();
+
+    fn slice_len(ptr: *const Self) -> Option<usize> {
+        Some(ptr.len())
+    }
+}
+
+/// Returns the number of low bits that are known to be zero in pointers aligned to `align` bytes, i.e. the number of
+/// bits that would be available to store a value alongside them.
+///
+/// # Panics
+///
+/// Panics if `align` is not a power of two.
+pub const fn available_bits_for_align(align: usize) -> u32 {
+    assert!(align.is_power_of_two(), "the alignment must be a power of two");
+    align.trailing_zeros()
+}
+
 /// Returns a bitmask of the zero low bits of `*const T` pointers.
-const fn align_bits<T>() -> usize {
-    mem::align_of::<T>() - 1
+const fn align_bits<T: ?Sized + PointeeAlign>() -> usize {
+    T::ALIGN - 1
 }
 
-impl<T> PointerValuePair<T> {
+// implemented for all pointees (including `str`), without any knowledge of how the value is packed
+impl<T: ?Sized> PointerValuePair<T> {
+    /// Creates a pair from an already packed pointer.
+    pub(crate) const fn from_raw(pv: *const T) -> PointerValuePair<T> {
+        PointerValuePair { pv }
+    }
+
+    /// Returns the packed pointer.
+    pub(crate) const fn into_raw(self) -> *const T {
+        self.pv
+    }
+
+    /// Returns the packed pointer: the pointer with the value in its low bits (see the layout section of the type
+    /// documentation).
+    ///
+    /// Unlike `into_bits`, this keeps the provenance of the pointer, so it can be used to pass the pair as a
+    /// `void*` to C code that follows the same layout.
+    pub const fn into_packed_ptr(self) -> *const T {
+        self.pv
+    }
+
+    /// Creates a `PointerValuePair` from a packed pointer, as returned by `into_packed_ptr` or produced by C code
+    /// following the same layout.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `into_packed_ptr` for the same `T`, or follow the same layout. In particular,
+    /// the metadata of wide pointers must be valid for the unpacked pointer.
+    pub const unsafe fn from_packed_ptr(ptr: *const T) -> PointerValuePair<T> {
+        PointerValuePair { pv: ptr }
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> PointerValuePair<T> {
     /// Creates a new `PointerValuePair` from the given raw pointer and extra bits.
     ///
+    /// For pointers to dynamically-sized types (slices, trait objects), the pointer metadata is preserved.
+    ///
     /// # Panics
     ///
     /// Panics if the pointer type `*const T` does not have enough available low bits to store
     /// the value.
+    /// In debug builds, also panics if the pointer is misaligned (i.e. its low bits are not zero). Use
+    /// `PointerValuePair::new_aligned` to check the alignment in release builds as well.
     pub fn new(ptr: *const T, value: usize) -> PointerValuePair<T> {
         let m = align_bits::<T>();
+        debug_assert!(
+            ptr.addr() & m == 0,
+            "pointer {:p} is not aligned to {} bytes",
+            ptr.cast::<()>(),
+            T::ALIGN
+        );
         assert!(
             value <= m,
             "not enough alignment bits ({}) to store the value ({})",
@@ -45,20 +253,159 @@ impl<T> PointerValuePair<T> {
             value
         );
 
-        let mut repr = ptr as usize;
-        repr |= value;
+        let pv = PointerValuePair {
+            pv: ptr.map_addr(|addr| addr | value),
+        };
+        validate!(
+            ptr::eq(pv.ptr(), ptr) && pv.value() == value,
+            "packed pointer does not round-trip"
+        );
+        pv
+    }
+
+    /// Creates a new `PointerValuePair` from the given raw pointer and extra bits, checking that the
+    /// pointer is sufficiently aligned and that the value fits in the available bits.
+    pub fn new_aligned(ptr: *const T, value: usize) -> Result<PointerValuePair<T>, PackError> {
+        if !Self::can_pack(ptr) {
+            return Err(PackError::MisalignedPointer);
+        }
+        if value > align_bits::<T>() {
+            return Err(PackError::ValueTooLarge);
+        }
+        let pv = PointerValuePair {
+            pv: ptr.map_addr(|addr| addr | value),
+        };
+        validate!(
+            ptr::eq(pv.ptr(), ptr) && pv.value() == value,
+            "packed pointer does not round-trip"
+        );
+        Ok(pv)
+    }
+
+    /// Creates a new `PointerValuePair` from the given non-null pointer and extra bits. This works for slices too.
+    ///
+    /// # Panics
+    ///
+    /// Same as `PointerValuePair::new`.
+    pub fn new_nonnull(ptr: ptr::NonNull<T>, value: usize) -> PointerValuePair<T> {
+        Self::new(ptr.as_ptr(), value)
+    }
+
+    /// Returns the pointer as a `NonNull`, or `None` if it is null.
+    pub fn ptr_nonnull(self) -> Option<ptr::NonNull<T>> {
+        ptr::NonNull::new(self.ptr() as *mut T)
+    }
 
-        PointerValuePair { pv: repr as *const T }
+    /// Returns whether the given pointer can be used to create a `PointerValuePair<T>`, i.e. whether its low bits
+    /// are zero.
+    pub fn can_pack(ptr: *const T) -> bool {
+        ptr.addr() & align_bits::<T>() == 0
     }
 
     /// Returns the pointer.
     pub fn ptr(self) -> *const T {
-        (self.pv as usize & !align_bits::<T>()) as *const T
+        self.pv.map_addr(|addr| addr & !align_bits::<T>())
     }
 
     /// Returns the value stored alongside the pointer.
     pub fn value(self) -> usize {
-        self.pv as usize & align_bits::<T>()
+        self.pv.addr() & align_bits::<T>()
+    }
+
+    /// Returns the address of the pointer, without the value.
+    pub fn addr(self) -> usize {
+        self.ptr().addr()
+    }
+
+    /// Returns a pair with the pointer moved to the given address, keeping its provenance, its metadata and the
+    /// value (see `pointer::with_addr`).
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if the address is misaligned.
+    pub fn with_addr(self, addr: usize) -> PointerValuePair<T> {
+        Self::new(self.ptr().with_addr(addr), self.value())
+    }
+
+    /// Returns a pair with the address of the pointer replaced by the result of `f` applied to it, keeping the
+    /// provenance of the pointer, its metadata and the value (see `pointer::map_addr`).
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if the new address is misaligned.
+    pub fn map_addr(self, f: impl FnOnce(usize) -> usize) -> PointerValuePair<T> {
+        self.with_addr(f(self.addr()))
+    }
+
+    /// Replaces the value stored alongside the pointer, and returns the previous one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn replace_value(&mut self, value: usize) -> usize {
+        let old = self.value();
+        *self = Self::new(self.ptr(), value);
+        old
+    }
+
+    /// Replaces the pointer and the value with the result of `f` applied to the current ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new pointer is misaligned, or if the new value doesn't fit in the available bits.
+    pub fn update(&mut self, f: impl FnOnce(*const T, usize) -> (*const T, usize)) {
+        let (ptr, value) = f(self.ptr(), self.value());
+        *self = Self::new(ptr, value);
+    }
+
+    /// Replaces the value with the result of `f` applied to the current one, keeping the pointer, and returns the
+    /// new value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn update_value(&mut self, f: impl FnOnce(usize) -> usize) -> usize {
+        let value = f(self.value());
+        self.replace_value(value);
+        value
+    }
+
+    /// Returns whether the lowest bit of the value is set.
+    pub fn flag(self) -> bool {
+        self.value() & 1 != 0
+    }
+
+    /// Sets or clears the lowest bit of the value, leaving the other bits unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no bits available to store the value.
+    pub fn set_flag(&mut self, flag: bool) {
+        let value = self.value() & !1 | flag as usize;
+        self.replace_value(value);
+    }
+
+    /// Casts the pointer to a pointer to another type, preserving the value.
+    ///
+    /// It is checked at compile time that `U` is at least as aligned as `T`, so that no value bits are lost.
+    pub fn cast<U>(self) -> PointerValuePair<U> {
+        const {
+            assert!(
+                U::ALIGN >= T::ALIGN,
+                "the target type must be at least as aligned as the source type"
+            )
+        };
+        PointerValuePair { pv: self.pv.cast() }
+    }
+
+    /// Returns the pointer and the value.
+    pub fn into_parts(self) -> (*const T, usize) {
+        (self.ptr(), self.value())
+    }
+
+    /// Returns whether the pointer is null, regardless of the value stored alongside it.
+    pub fn is_null(self) -> bool {
+        self.ptr().is_null()
     }
 
     /// Returns the number of bits available to store the value.
@@ -72,64 +419,416 @@ impl<T> PointerValuePair<T> {
     }
 }
 
-// see https://github.com/rust-lang/rust/pull/94640
-/// Safety:
-/// The caller must ensure that the start of `ptr`:
-/// 1. does not point to memory that was previously allocated but is now deallocated;
-/// 2. must be within the bounds of a single allocated object.
-/// 3. ptr is not null?
-unsafe fn ptr_len<T>(ptr: *const [T]) -> usize {
-    (&*(ptr as *const [()])).len()
+impl<T> PointerValuePair<T> {
+    /// Creates a new `PointerValuePair` with a null pointer and the given value.
+    ///
+    /// This can be used to represent sentinel values ("no pointer, but flagged").
+    /// Note that the packed representation is not null if the value is not zero, but `is_null` still returns `true`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn null(value: usize) -> PointerValuePair<T> {
+        Self::new(ptr::null(), value)
+    }
+
+    /// Creates a new `PointerValuePair` with a dangling, but well-aligned pointer and the given value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn dangling(value: usize) -> PointerValuePair<T> {
+        Self::new(ptr::NonNull::dangling().as_ptr(), value)
+    }
+
+    /// Offsets the pointer by `count` elements of type `T`, keeping the value (see `pointer::offset`).
+    ///
+    /// # Safety
+    ///
+    /// The pointer must satisfy the requirements of `pointer::offset`.
+    pub unsafe fn offset(self, count: isize) -> PointerValuePair<T> {
+        // SAFETY: forwarded to the caller
+        Self::new(unsafe { self.ptr().offset(count) }, self.value())
+    }
+
+    /// Advances the pointer by `count` elements of type `T`, keeping the value (see `pointer::add`).
+    ///
+    /// # Safety
+    ///
+    /// The pointer must satisfy the requirements of `pointer::add`.
+    pub unsafe fn add(self, count: usize) -> PointerValuePair<T> {
+        // SAFETY: forwarded to the caller
+        Self::new(unsafe { self.ptr().add(count) }, self.value())
+    }
+
+    /// Advances the pointer by `count` bytes, keeping the value (see `pointer::byte_add`).
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if the resulting pointer is misaligned.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must satisfy the requirements of `pointer::byte_add`.
+    pub unsafe fn byte_add(self, count: usize) -> PointerValuePair<T> {
+        // SAFETY: forwarded to the caller
+        Self::new(unsafe { self.ptr().byte_add(count) }, self.value())
+    }
+
+    /// Offsets the pointer by `count` elements of type `T` using wrapping arithmetic, keeping the value (see
+    /// `pointer::wrapping_offset`).
+    pub fn wrapping_offset(self, count: isize) -> PointerValuePair<T> {
+        Self::new(self.ptr().wrapping_offset(count), self.value())
+    }
+
+    /// Returns the packed representation of the pair (the address of the pointer with the value in the low bits) as
+    /// an integer, and exposes the provenance of the pointer.
+    ///
+    /// This can be used to pass the pair across an FFI boundary as a plain integer. The pair can then be
+    /// reconstituted with `PointerValuePair::with_exposed_provenance`. See `pointer::expose_provenance` for the
+    /// details of the provenance semantics.
+    pub fn expose_provenance(self) -> usize {
+        self.pv.expose_provenance()
+    }
+
+    /// Creates a `PointerValuePair` from its packed representation, as returned by
+    /// `PointerValuePair::expose_provenance`.
+    ///
+    /// The pointer picks up a previously exposed provenance (see `ptr::with_exposed_provenance`). Accessing memory
+    /// through the pointer is only valid if the provenance of the original pointer has been exposed.
+    pub fn with_exposed_provenance(bits: usize) -> PointerValuePair<T> {
+        PointerValuePair {
+            pv: ptr::with_exposed_provenance(bits),
+        }
+    }
+
+    /// Returns the packed representation of the pair as an integer.
+    ///
+    /// This is the same as `PointerValuePair::expose_provenance`: the provenance of the pointer is exposed so that
+    /// the pair can be reconstituted with `PointerValuePair::from_bits`.
+    pub fn into_bits(self) -> usize {
+        self.expose_provenance()
+    }
+
+    /// Creates a `PointerValuePair` from its packed representation.
+    ///
+    /// # Safety
+    ///
+    /// `bits` must have been returned by `PointerValuePair::<T>::into_bits`.
+    pub unsafe fn from_bits(bits: usize) -> PointerValuePair<T> {
+        Self::with_exposed_provenance(bits)
+    }
+}
+
+#[cfg(feature = "ptr_metadata")]
+impl<T: ?Sized + PointeeAlign> PointerValuePair<T> {
+    /// Creates a new `PointerValuePair` from a data pointer, pointer metadata (e.g. the length of a slice or the
+    /// vtable of a trait object) and extra bits.
+    ///
+    /// This works uniformly for all dynamically-sized types, including custom DSTs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pointer type `*const T` does not have enough available low bits to store
+    /// the value.
+    pub fn from_raw_parts(data: *const (), metadata: <T as Pointee>::Metadata, value: usize) -> PointerValuePair<T> {
+        Self::new(ptr::from_raw_parts(data, metadata), value)
+    }
+
+    /// Returns the data pointer (without the value bits), the pointer metadata, and the value stored alongside the
+    /// pointer.
+    pub fn to_raw_parts(self) -> (*const (), <T as Pointee>::Metadata, usize) {
+        let (data, metadata) = self.ptr().to_raw_parts();
+        (data, metadata, self.value())
+    }
+
+    /// Returns the metadata of the pointer.
+    pub fn metadata(self) -> <T as Pointee>::Metadata {
+        ptr::metadata(self.pv)
+    }
 }
 
 // implementation for slices
 impl<T> PointerValuePair<[T]> {
     /// Creates a new `PointerValuePair` from the given raw pointer and extra bits.
     ///
+    /// This is the same as `PointerValuePair::new`.
+    ///
     /// # Panics
     ///
     /// Panics if the pointer type `*const T` does not have enough available low bits to store
     /// the value.
     pub fn new_slice(ptr: *const [T], value: usize) -> PointerValuePair<[T]> {
-        let m = align_bits::<T>();
+        Self::new(ptr, value)
+    }
+
+    /// Creates a new `PointerValuePair` to a slice from a pointer to an array and extra bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pointer type `*const T` does not have enough available low bits to store
+    /// the value.
+    pub fn from_array<const N: usize>(ptr: *const [T; N], value: usize) -> PointerValuePair<[T]> {
+        Self::new(ptr, value)
+    }
+
+    /// Returns the packed representation of the data pointer as an integer, and the length of the slice.
+    ///
+    /// The provenance of the pointer is exposed, see `PointerValuePair::expose_provenance`.
+    pub fn into_bits(self) -> (usize, usize) {
+        ((self.pv as *const T).expose_provenance(), self.pv.len())
+    }
+
+    /// Creates a `PointerValuePair` from the packed representation of the data pointer and the length of the
+    /// slice.
+    ///
+    /// # Safety
+    ///
+    /// `bits` and `len` must have been returned by `PointerValuePair::<[T]>::into_bits`.
+    pub unsafe fn from_bits(bits: usize, len: usize) -> PointerValuePair<[T]> {
+        PointerValuePair {
+            pv: ptr::slice_from_raw_parts(ptr::with_exposed_provenance(bits), len),
+        }
+    }
+
+    /// Returns the length of the slice.
+    pub fn len(self) -> usize {
+        // the length is stored in the metadata, which isn't affected by the value bits
+        self.pv.len()
+    }
+
+    /// Returns whether the slice is empty.
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a pointer to the element at index `i`.
+    ///
+    /// # Safety
+    ///
+    /// `i` must be less than the length of the slice, and the slice pointer must point to a single allocation
+    /// (see `pointer::add`).
+    pub unsafe fn get_unchecked(self, i: usize) -> *const T {
+        debug_assert!(i < self.len(), "index out of bounds");
+        (self.ptr() as *const T).add(i)
+    }
+
+    /// Splits the slice pointer in two at `mid`. Both halves keep the value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid > len`.
+    pub fn split_at(self, mid: usize) -> (PointerValuePair<[T]>, PointerValuePair<[T]>) {
+        let len = self.len();
+        assert!(mid <= len, "mid ({}) > len ({})", mid, len);
+        let data = self.pv as *const T;
+        (
+            PointerValuePair {
+                pv: ptr::slice_from_raw_parts(data, mid),
+            },
+            PointerValuePair {
+                // the value bits are kept: element pointers have the same alignment
+                pv: ptr::slice_from_raw_parts(data.wrapping_add(mid), len - mid),
+            },
+        )
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> From<(*const T, usize)> for PointerValuePair<T> {
+    /// Creates a pair from a pointer and a value. See `PointerValuePair::new`.
+    fn from((ptr, value): (*const T, usize)) -> Self {
+        PointerValuePair::new(ptr, value)
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> From<(ptr::NonNull<T>, usize)> for PointerValuePair<T> {
+    /// Creates a pair from a non-null pointer and a value. See `PointerValuePair::new_nonnull`.
+    fn from((ptr, value): (ptr::NonNull<T>, usize)) -> Self {
+        PointerValuePair::new_nonnull(ptr, value)
+    }
+}
+
+impl<T, const N: usize> From<PointerValuePair<[T; N]>> for PointerValuePair<[T]> {
+    /// Converts a pair to an array into a pair to a slice, keeping the value.
+    fn from(pv: PointerValuePair<[T; N]>) -> Self {
+        PointerValuePair {
+            // arrays have the same alignment as their elements, so the value bits are the same
+            pv: pv.pv,
+        }
+    }
+}
+
+impl<T> PointerValuePair<mem::MaybeUninit<T>> {
+    /// Writes an object to the uninitialized pointee, and returns a pair to the initialized pointee with the same
+    /// value.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be valid for writes and properly aligned. The previous contents of the pointee are
+    /// overwritten without being dropped.
+    pub unsafe fn write(self, v: T) -> PointerValuePair<T> {
+        // SAFETY: guaranteed by the caller
+        unsafe { (self.ptr() as *mut mem::MaybeUninit<T>).write(mem::MaybeUninit::new(v)) };
+        self.assume_init()
+    }
+
+    /// Converts this into a pair to `T`, preserving the value.
+    ///
+    /// This only changes the type of the pointer: the pointee must be initialized before it is read through the
+    /// returned pair.
+    pub fn assume_init(self) -> PointerValuePair<T> {
+        PointerValuePair {
+            // `MaybeUninit<T>` has the same alignment as `T`, so the value bits are the same
+            pv: self.pv.cast(),
+        }
+    }
+}
+
+/// Mask of the bits of the length of a `*const str` that are known to be zero, because the size of
+/// an allocation cannot exceed `isize::MAX` bytes.
+const STR_LEN_VALUE_BITS: usize = !(isize::MAX as usize);
+
+// implementation for string slices
+impl PointerValuePair<str> {
+    /// Creates a new `PointerValuePair` from the given raw string slice pointer and extra bits.
+    ///
+    /// Since `str` has an alignment of 1, there are no spare low bits in the pointer. Instead,
+    /// the value is stored in the most significant bit of the length, which is always zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits (i.e. if it's greater than 1).
+    pub fn new_str(ptr: *const str, value: usize) -> PointerValuePair<str> {
         assert!(
-            value <= m,
-            "not enough alignment bits ({}) to store the value ({})",
+            value <= Self::max_value(),
+            "not enough spare bits ({}) to store the value ({})",
             Self::available_bits(),
             value
         );
 
-        let pv = unsafe {
-            let len = ptr_len(ptr);
-            let mut repr = ptr as *const T as usize;
-            repr |= value;
-            ptr::slice_from_raw_parts(repr as *const T, len)
-        };
+        let len = (ptr as *const [u8]).len();
+        let repr = len | (value << STR_LEN_VALUE_BITS.trailing_zeros());
+        let pv = ptr::slice_from_raw_parts(ptr as *const u8, repr) as *const str;
 
         PointerValuePair { pv }
     }
 
     /// Returns the pointer.
-    pub fn ptr(self) -> *const [T] {
-        unsafe {
-            let len = ptr_len(self.pv);
-            ptr::slice_from_raw_parts((self.pv as *const T as usize & !align_bits::<T>()) as *const T, len)
-        }
+    pub fn ptr(self) -> *const str {
+        ptr::slice_from_raw_parts(self.pv as *const u8, self.len_bits() & !STR_LEN_VALUE_BITS) as *const str
     }
 
     /// Returns the value stored alongside the pointer.
     pub fn value(self) -> usize {
-        self.pv as *const T as usize & align_bits::<T>()
+        (self.len_bits() & STR_LEN_VALUE_BITS) >> STR_LEN_VALUE_BITS.trailing_zeros()
+    }
+
+    /// Replaces the value stored alongside the pointer, and returns the previous one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn replace_value(&mut self, value: usize) -> usize {
+        let old = self.value();
+        *self = Self::new_str(self.ptr(), value);
+        old
     }
 
     /// Returns the number of bits available to store the value.
     pub const fn available_bits() -> u32 {
-        align_bits::<T>().count_ones()
+        STR_LEN_VALUE_BITS.count_ones()
     }
 
     /// Returns the maximum (inclusive) integer value that can be stored in the pointer.
     pub const fn max_value() -> usize {
-        align_bits::<T>()
+        STR_LEN_VALUE_BITS >> STR_LEN_VALUE_BITS.trailing_zeros()
+    }
+
+    /// Returns the packed length, including the value bits.
+    fn len_bits(self) -> usize {
+        (self.pv as *const [u8]).len()
+    }
+}
+
+impl<T: ?Sized> PointerValuePair<T>
+where
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Returns whether the two pairs hold the same pointer, ignoring the values.
+    ///
+    /// For slices, the lengths must also be equal.
+    pub fn ptr_eq(self, other: Self) -> bool {
+        ptr::eq(PointerValuePairAccess::ptr(self), PointerValuePairAccess::ptr(other))
+    }
+
+    /// Returns whether the two pairs hold the same value, ignoring the pointers.
+    pub fn value_eq(self, other: Self) -> bool {
+        PointerValuePairAccess::value(self) == PointerValuePairAccess::value(other)
+    }
+
+    /// Returns a shared reference to the pointee, or `None` if the pointer is null (see `pointer::as_ref`).
+    ///
+    /// # Safety
+    ///
+    /// If the pointer is not null, it must be valid for reads of a `T` for `'a`, and the pointee must not be mutated
+    /// during `'a` (except through interior mutability).
+    pub unsafe fn as_ref<'a>(self) -> Option<&'a T> {
+        // SAFETY: forwarded to the caller
+        unsafe { PointerValuePairAccess::ptr(self).as_ref() }
+    }
+
+    /// Returns an exclusive reference to the pointee, or `None` if the pointer is null (see `pointer::as_mut`).
+    ///
+    /// # Safety
+    ///
+    /// If the pointer is not null, it must be valid for reads and writes of a `T` for `'a`, and the pointee must not
+    /// be accessed through any other pointer during `'a`.
+    pub unsafe fn as_mut<'a>(self) -> Option<&'a mut T> {
+        // SAFETY: forwarded to the caller
+        unsafe { (PointerValuePairAccess::ptr(self) as *mut T).as_mut() }
+    }
+
+    /// Returns whether the two pairs have the same packed representation, i.e. the same pointer and the same value.
+    ///
+    /// This is what `==` compares; use `ptr_eq` to ignore the values.
+    pub fn bits_eq(self, other: Self) -> bool {
+        self == other
+    }
+
+    /// Swaps the pointers and values of two pairs.
+    pub fn swap(&mut self, other: &mut Self) {
+        mem::swap(self, other)
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> fmt::Debug for PointerValuePair<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("PointerValuePair");
+        d.field("ptr", &self.ptr().cast::<()>());
+        if let Some(len) = T::slice_len(self.ptr()) {
+            d.field("len", &len);
+        }
+        d.field("value", &self.value()).finish()
+    }
+}
+
+impl fmt::Debug for PointerValuePair<str> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PointerValuePair")
+            .field("ptr", &(self.ptr() as *const u8))
+            .field("len", &(self.ptr() as *const [u8]).len())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+/// Formats the address of the pointer, without the value bits.
+impl<T: ?Sized> fmt::Pointer for PointerValuePair<T>
+where
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&PointerValuePairAccess::ptr(*self).cast::<()>(), f)
     }
 }
 
@@ -137,6 +836,8 @@ impl<T> PointerValuePair<[T]> {
 /// whether it points to a single element (`&T where T: Sized`) or a slice (`&[T]`).
 pub trait PointerValuePairAccess: Copy {
     type Target: ?Sized;
+    /// Whether the target is a sized type of size zero (see `PointeeAlign::ZERO_SIZED`).
+    const ZERO_SIZED: bool = false;
 
     /// Returns the stored pointer.
     fn ptr(self) -> *const Self::Target;
@@ -150,8 +851,9 @@ pub trait PointerValuePairAccess: Copy {
     fn max_value() -> usize;
 }
 
-impl<T> PointerValuePairAccess for PointerValuePair<T> {
+impl<T: ?Sized + PointeeAlign> PointerValuePairAccess for PointerValuePair<T> {
     type Target = T;
+    const ZERO_SIZED: bool = T::ZERO_SIZED;
 
     fn ptr(self) -> *const T {
         self.ptr()
@@ -174,15 +876,15 @@ impl<T> PointerValuePairAccess for PointerValuePair<T> {
     }
 }
 
-impl<T> PointerValuePairAccess for PointerValuePair<[T]> {
-    type Target = [T];
+impl PointerValuePairAccess for PointerValuePair<str> {
+    type Target = str;
 
-    fn ptr(self) -> *const [T] {
+    fn ptr(self) -> *const str {
         self.ptr()
     }
 
-    fn mut_ptr(self) -> *mut [T] {
-        self.ptr() as *mut [T]
+    fn mut_ptr(self) -> *mut str {
+        self.ptr() as *mut str
     }
 
     fn value(self) -> usize {
@@ -200,8 +902,8 @@ impl<T> PointerValuePairAccess for PointerValuePair<[T]> {
 
 #[cfg(test)]
 mod tests {
-    use super::PointerValuePair;
-    use std::mem;
+    use super::{available_bits_for_align, PackError, PointeeAlign, PointerValuePair};
+    use std::{collections::HashSet, format, mem, ptr};
 
     #[test]
     fn pointer_sized() {
@@ -236,6 +938,84 @@ mod tests {
         assert!(PointerValuePair::<Align32>::available_bits() >= 5);
     }
 
+    #[test]
+    fn provenance() {
+        // the packed pointers keep the provenance of the original ones, without exposing it (see the provenance
+        // section of the type documentation), so writing through them is accepted by `-Zmiri-strict-provenance`
+        let mut pointee = [42u64, 1];
+        let pv = PointerValuePair::new(&mut pointee[1] as *mut u64, 7);
+        unsafe {
+            *(pv.ptr() as *mut u64) = 43;
+        }
+        let pv = PointerValuePair::new(&mut pointee[..] as *mut [u64], 3);
+        unsafe {
+            (*(pv.ptr() as *mut [u64]))[0] += 1;
+        }
+        assert_eq!(pointee, [43, 43]);
+    }
+
+    #[test]
+    fn exposed_provenance() {
+        let mut pointee = 42u64;
+        let bits = PointerValuePair::new(&mut pointee as *mut u64, 5).expose_provenance();
+        let pv = PointerValuePair::<u64>::with_exposed_provenance(bits);
+        assert_eq!(pv.value(), 5);
+        unsafe {
+            *(pv.ptr() as *mut u64) = 43;
+        }
+        assert_eq!(pointee, 43);
+    }
+
+    #[test]
+    fn packed_ptr() {
+        let pointee = 42u64;
+        let packed = PointerValuePair::new(&pointee, 5).into_packed_ptr();
+        assert_eq!(packed.addr(), (&pointee as *const u64).addr() | 5);
+        let pv = unsafe { PointerValuePair::from_packed_ptr(packed) };
+        assert_eq!((pv.ptr(), pv.value()), (&pointee as *const u64, 5));
+    }
+
+    #[test]
+    fn new_aligned() {
+        let s = [0u32; 4];
+        let pv = PointerValuePair::new_aligned(&s[1], 3).unwrap();
+        assert_eq!(pv.ptr(), &s[1] as *const u32);
+        assert_eq!(pv.value(), 3);
+
+        let misaligned = (&s[1] as *const u32).cast::<u8>().wrapping_add(1).cast::<u32>();
+        assert_eq!(
+            PointerValuePair::new_aligned(misaligned, 0),
+            Err(PackError::MisalignedPointer)
+        );
+        assert_eq!(PointerValuePair::new_aligned(&s[1], 4), Err(PackError::ValueTooLarge));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic]
+    fn new_misaligned() {
+        let s = [0u32; 4];
+        let misaligned = (&s[1] as *const u32).cast::<u8>().wrapping_add(1).cast::<u32>();
+        PointerValuePair::new(misaligned, 0);
+    }
+
+    #[test]
+    fn bits_round_trip() {
+        let pointee = 42u64;
+        let pv = PointerValuePair::new(&pointee, 5);
+        let pv2 = unsafe { PointerValuePair::<u64>::from_bits(pv.into_bits()) };
+        assert_eq!(pv, pv2);
+        assert_eq!(unsafe { *pv2.ptr() }, 42);
+
+        let s = [0u32, 1, 2];
+        let pv = PointerValuePair::new_slice(&s[..], 3);
+        let (bits, len) = pv.into_bits();
+        assert_eq!(len, 3);
+        let pv2 = unsafe { PointerValuePair::<[u32]>::from_bits(bits, len) };
+        assert_eq!(pv, pv2);
+        assert_eq!(unsafe { &*pv2.ptr() }, &s);
+    }
+
     #[test]
     fn slices() {
         let s = &[0, 1, 2, 3, 4, 5];
@@ -244,4 +1024,393 @@ mod tests {
         assert_eq!(unsafe { &*pv.ptr() }, s);
         assert_eq!(pv.value(), 3);
     }
+
+    #[test]
+    fn eq_hash_ord() {
+        let s = [0u32, 1, 2, 3];
+        let a = PointerValuePair::new(&s[0], 1);
+        let b = PointerValuePair::new(&s[1], 1);
+        let c = PointerValuePair::new(&s[0], 2);
+
+        assert_eq!(a, PointerValuePair::new(&s[0], 1));
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+        assert!(a < c);
+        assert!(c < b);
+
+        assert!(a.ptr_eq(c));
+        assert!(!a.ptr_eq(b));
+        assert!(a.value_eq(b));
+        assert!(!a.value_eq(c));
+        assert!(a.bits_eq(PointerValuePair::new(&s[0], 1)) && !a.bits_eq(c));
+
+        let set: HashSet<_> = [a, b, c, a].into_iter().collect();
+        assert_eq!(set.len(), 3);
+
+        let sa = PointerValuePair::new_slice(&s[..], 1);
+        let sb = PointerValuePair::new_slice(&s[..2], 1);
+        assert!(!sa.ptr_eq(sb));
+        assert!(sa.value_eq(sb));
+        assert_ne!(sa, sb);
+    }
+
+    #[test]
+    fn swap() {
+        let (x, y) = (1u64, 2u64);
+        let mut a = PointerValuePair::new(&x, 1);
+        let mut b = PointerValuePair::new(&y, 2);
+        a.swap(&mut b);
+        assert_eq!(a, PointerValuePair::new(&y, 2));
+        assert_eq!(b, PointerValuePair::new(&x, 1));
+    }
+
+    #[test]
+    fn replace_value() {
+        let pointee = 42u64;
+        let mut pv = PointerValuePair::new(&pointee, 1);
+        assert_eq!(pv.replace_value(6), 1);
+        assert_eq!(pv.value(), 6);
+        assert_eq!(pv.ptr(), &pointee as *const u64);
+
+        let mut pv = PointerValuePair::new_str("hello", 1);
+        assert_eq!(pv.replace_value(0), 1);
+        assert_eq!(pv.value(), 0);
+        assert_eq!(unsafe { &*pv.ptr() }, "hello");
+    }
+
+    #[test]
+    #[should_panic]
+    fn replace_value_overflow() {
+        let pointee = 42u64;
+        let mut pv = PointerValuePair::new(&pointee, 1);
+        pv.replace_value(8);
+    }
+
+    #[test]
+    fn update() {
+        let s = [1u64, 2, 3];
+        let mut pv = PointerValuePair::new(&s[0], 1);
+        pv.update(|p, v| (p.wrapping_add(2), v + 1));
+        assert_eq!((pv.ptr(), pv.value()), (&s[2] as *const u64, 2));
+        assert_eq!(pv.update_value(|v| v * 3), 6);
+        assert_eq!((pv.ptr(), pv.value()), (&s[2] as *const u64, 6));
+    }
+
+    #[test]
+    #[should_panic]
+    fn update_overflow() {
+        let pointee = 42u64;
+        PointerValuePair::new(&pointee, 1).update_value(|v| v << 3);
+    }
+
+    #[test]
+    fn addr() {
+        let mut s = [1u64, 2, 3];
+        let pv = PointerValuePair::new(&mut s[0] as *mut u64, 3);
+        assert_eq!(pv.addr(), (&s[0] as *const u64).addr());
+        let next = pv.map_addr(|addr| addr + mem::size_of::<u64>());
+        assert_eq!((next.ptr(), next.value()), (&s[1] as *const u64, 3));
+        // the provenance of the original pointer is kept
+        unsafe { *(pv.with_addr(next.addr() + 8).ptr() as *mut u64) = 4 };
+        assert_eq!(s, [1, 2, 4]);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let s = [1u64, 2, 3, 4];
+        let pv = PointerValuePair::new(&s[0], 5);
+        let mut it = pv;
+        let mut sum = 0;
+        for _ in 0..s.len() {
+            sum += unsafe { *it.ptr() };
+            assert_eq!(it.value(), 5);
+            it = unsafe { it.add(1) };
+        }
+        assert_eq!(sum, 10);
+        assert_eq!(it.ptr(), s.as_ptr_range().end);
+
+        let last = unsafe { pv.byte_add(3 * mem::size_of::<u64>()) };
+        assert_eq!((unsafe { *last.ptr() }, last.value()), (4, 5));
+        assert_eq!(unsafe { last.offset(-2) }.ptr(), &s[1] as *const u64);
+        assert_eq!(last.wrapping_offset(-3), pv);
+    }
+
+    #[test]
+    fn as_ref_mut() {
+        let mut pointee = 42u64;
+        let pv = PointerValuePair::new(&mut pointee as *mut u64, 3);
+        assert_eq!(unsafe { pv.as_ref() }, Some(&42));
+        *unsafe { pv.as_mut() }.unwrap() += 1;
+        assert_eq!(pointee, 43);
+        assert_eq!(unsafe { PointerValuePair::<u64>::null(1).as_ref() }, None);
+
+        let s = [1u32, 2, 3];
+        let pv = PointerValuePair::new_slice(&s[1..], 1);
+        assert_eq!(unsafe { pv.as_ref() }, Some(&[2, 3][..]));
+        let pv = PointerValuePair::new_str("hi", 1);
+        assert_eq!(unsafe { pv.as_ref() }, Some("hi"));
+    }
+
+    #[test]
+    fn nonnull() {
+        let mut s = [1u64, 2];
+        let p = ptr::NonNull::from(&mut s[1]);
+        let pv = PointerValuePair::new_nonnull(p, 3);
+        assert_eq!((pv.ptr_nonnull(), pv.value()), (Some(p), 3));
+        assert_eq!(PointerValuePair::from((p, 1)).ptr_nonnull(), Some(p));
+        assert_eq!(PointerValuePair::<u64>::null(1).ptr_nonnull(), None);
+
+        let p = ptr::NonNull::from(&mut s[..]);
+        let pv = PointerValuePair::new_nonnull(p, 2);
+        assert_eq!((pv.ptr_nonnull(), pv.len()), (Some(p), 2));
+    }
+
+    #[test]
+    fn null_dangling() {
+        let pv = PointerValuePair::<u64>::null(3);
+        assert!(pv.is_null());
+        assert_eq!(pv.value(), 3);
+
+        let pv = PointerValuePair::<u64>::dangling(5);
+        assert!(!pv.is_null());
+        assert_eq!(pv.ptr(), std::ptr::NonNull::<u64>::dangling().as_ptr() as *const u64);
+        assert_eq!(pv.value(), 5);
+
+        let pointee = 42u64;
+        assert!(!PointerValuePair::new(&pointee, 0).is_null());
+    }
+
+    #[test]
+    fn default() {
+        let pv = PointerValuePair::<u64>::default();
+        assert!(pv.is_null());
+        assert_eq!(pv.value(), 0);
+    }
+
+    #[test]
+    fn can_pack() {
+        let s = [0u32; 4];
+        assert!(PointerValuePair::can_pack(&s[1]));
+        let misaligned = (&s[1] as *const u32).cast::<u8>().wrapping_add(2).cast::<u32>();
+        assert!(!PointerValuePair::can_pack(misaligned));
+
+        assert_eq!(available_bits_for_align(1), 0);
+        assert_eq!(available_bits_for_align(8), 3);
+        assert_eq!(available_bits_for_align(4096), 12);
+    }
+
+    #[test]
+    fn zero_sized() {
+        #[derive(Debug, PartialEq)]
+        struct Marker;
+        #[repr(align(32))]
+        struct Aligned;
+
+        let m = Marker;
+        let pv = PointerValuePair::new(&m, 0);
+        assert_eq!(PointerValuePair::<Marker>::available_bits(), 0);
+        // the address is kept
+        assert!(ptr::eq(pv.ptr(), &m));
+        assert_eq!(unsafe { &*pv.ptr() }, &Marker);
+        let odd = ptr::dangling::<u8>().wrapping_add(1).cast::<()>();
+        assert!(PointerValuePair::<()>::can_pack(odd));
+        assert_eq!(PointerValuePair::new_aligned(odd, 0).unwrap().ptr(), odd);
+
+        let a = Aligned;
+        let pv = PointerValuePair::new(&a, 31);
+        assert_eq!(pv.value(), 31);
+        assert!(ptr::eq(pv.ptr(), &a));
+    }
+
+    #[test]
+    fn cast() {
+        let pointee = 42u64;
+        let pv = PointerValuePair::new(&pointee, 3).cast::<[u64; 1]>();
+        assert_eq!(pv.ptr(), &pointee as *const u64 as *const [u64; 1]);
+        assert_eq!(pv.value(), 3);
+        let pv = pv.cast::<u64>();
+        assert_eq!(unsafe { *pv.ptr() }, 42);
+        assert_eq!(pv.value(), 3);
+    }
+
+    #[test]
+    fn debug() {
+        let pointee = 42u64;
+        let pv = PointerValuePair::new(&pointee, 5);
+        assert_eq!(
+            format!("{:?}", pv),
+            format!("PointerValuePair {{ ptr: {:?}, value: 5 }}", &pointee as *const u64)
+        );
+
+        let s = [0u32, 1, 2];
+        let pv = PointerValuePair::new_slice(&s[..], 2);
+        assert_eq!(
+            format!("{:?}", pv),
+            format!("PointerValuePair {{ ptr: {:?}, len: 3, value: 2 }}", s.as_ptr())
+        );
+    }
+
+    #[test]
+    fn pointer_fmt() {
+        let pointee = 42u64;
+        let pv = PointerValuePair::new(&pointee, 5);
+        assert_eq!(format!("{:p}", pv), format!("{:p}", &pointee));
+
+        let s = [0u32, 1, 2];
+        let pv = PointerValuePair::new_slice(&s[..], 2);
+        assert_eq!(format!("{:p}", pv), format!("{:p}", s.as_ptr()));
+    }
+
+    #[test]
+    fn strs() {
+        let s = "hello";
+        let pv = PointerValuePair::new_str(s, 1);
+        assert_eq!(pv.ptr(), s as *const str);
+        assert_eq!(unsafe { &*pv.ptr() }, "hello");
+        assert_eq!(pv.value(), 1);
+        assert_eq!(PointerValuePair::new_str(s, 0).value(), 0);
+        assert_eq!(PointerValuePair::<str>::available_bits(), 1);
+    }
+
+    #[test]
+    fn trait_objects() {
+        trait Node {
+            fn eval(&self) -> i32;
+        }
+
+        unsafe impl<'a> PointeeAlign for dyn Node + 'a {
+            const ALIGN: usize = 8;
+        }
+
+        #[repr(C, align(8))]
+        struct Lit(i32);
+        impl Node for Lit {
+            fn eval(&self) -> i32 {
+                self.0
+            }
+        }
+
+        let lit = Lit(42);
+        let pv = PointerValuePair::new(&lit as &dyn Node, 5);
+        assert_eq!(PointerValuePair::<dyn Node>::max_value(), 7);
+        assert_eq!(pv.ptr() as *const Lit, &lit as *const Lit);
+        assert_eq!(unsafe { (*pv.ptr()).eval() }, 42);
+        assert_eq!(pv.value(), 5);
+        assert_eq!(
+            format!("{:?}", pv),
+            format!("PointerValuePair {{ ptr: {:?}, value: 5 }}", &lit as *const Lit)
+        );
+    }
+
+    #[test]
+    fn slice_methods() {
+        let s = [1u64, 2, 3, 4, 5];
+        let pv = PointerValuePair::new(&s[..], 3);
+        assert_eq!(pv.len(), 5);
+        assert!(!pv.is_empty());
+        assert_eq!(unsafe { *pv.get_unchecked(2) }, 3);
+
+        let (a, b) = pv.split_at(2);
+        assert_eq!((a.len(), a.value()), (2, 3));
+        assert_eq!((b.len(), b.value()), (3, 3));
+        assert_eq!(unsafe { &*a.ptr() }, &[1, 2]);
+        assert_eq!(unsafe { &*b.ptr() }, &[3, 4, 5]);
+        assert!(pv.split_at(5).1.is_empty());
+    }
+
+    #[test]
+    fn flags() {
+        let pointee = 42u64;
+        let mut pv = PointerValuePair::new(&pointee, 6);
+        assert!(!pv.flag());
+        pv.set_flag(true);
+        assert!(pv.flag());
+        assert_eq!(pv.value(), 7);
+        pv.set_flag(false);
+        assert_eq!(pv.value(), 6);
+    }
+
+    #[test]
+    fn tuples() {
+        let pointee = 42u64;
+        let pv: PointerValuePair<u64> = (&pointee as *const u64, 5).into();
+        let (ptr, value) = pv.into_parts();
+        assert_eq!(ptr, &pointee as *const u64);
+        assert_eq!(value, 5);
+    }
+
+    #[test]
+    fn array_to_slice() {
+        static TABLE: [u32; 4] = [1, 2, 3, 4];
+        let pv = PointerValuePair::<[u32]>::from_array(&TABLE, 2);
+        assert_eq!((pv.len(), pv.value()), (4, 2));
+
+        let pv: PointerValuePair<[u32]> = PointerValuePair::new(&TABLE as *const [u32; 4], 3).into();
+        assert_eq!((pv.len(), pv.value()), (4, 3));
+        assert_eq!(unsafe { &*pv.ptr() }, &TABLE);
+    }
+
+    #[cfg(feature = "unsize")]
+    #[test]
+    fn unsize() {
+        trait Node {
+            fn eval(&self) -> u64;
+        }
+
+        unsafe impl<'a> PointeeAlign for dyn Node + 'a {
+            const ALIGN: usize = 8;
+        }
+
+        impl Node for u64 {
+            fn eval(&self) -> u64 {
+                *self
+            }
+        }
+
+        let a = [1u32, 2, 3];
+        let pv: PointerValuePair<[u32]> = PointerValuePair::new(&a, 3);
+        assert_eq!(pv.value(), 3);
+        assert_eq!(unsafe { &*pv.ptr() }, &[1, 2, 3]);
+
+        let b = 42u64;
+        let pv: PointerValuePair<dyn Node> = PointerValuePair::new(&b as *const u64, 6);
+        assert_eq!(pv.value(), 6);
+        assert_eq!(unsafe { (*pv.ptr()).eval() }, 42);
+    }
+
+    #[cfg(feature = "ptr_metadata")]
+    #[test]
+    fn custom_dst_raw_parts() {
+        #[repr(C)]
+        struct Node {
+            kind: u32,
+            children: [u64],
+        }
+
+        unsafe impl PointeeAlign for Node {
+            const ALIGN: usize = mem::align_of::<u64>();
+        }
+
+        let storage = [1u64, 10, 20, 30];
+        let pv = PointerValuePair::<Node>::from_raw_parts(storage.as_ptr() as *const (), 3, 6);
+        let (data, len, value) = pv.to_raw_parts();
+        assert_eq!(data, storage.as_ptr() as *const ());
+        assert_eq!(len, 3);
+        assert_eq!(pv.metadata(), 3);
+        assert_eq!(value, 6);
+        let node = unsafe { &*pv.ptr() };
+        assert_eq!(node.kind, 1);
+        assert_eq!(node.children, [10, 20, 30]);
+        assert_eq!(ptr::metadata(pv.ptr()), 3);
+    }
+
+    #[test]
+    fn uninit() {
+        let mut slot = mem::MaybeUninit::<u64>::uninit();
+        let pv = PointerValuePair::new(&mut slot as *mut mem::MaybeUninit<u64>, 5);
+        // SAFETY: the pointer is valid for writes
+        let pv = unsafe { pv.write(42) };
+        assert_eq!((unsafe { *pv.ptr() }, pv.value()), (42, 5));
+        assert_eq!(pv.cast::<mem::MaybeUninit<u64>>().assume_init(), pv);
+    }
 }
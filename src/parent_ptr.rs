@@ -0,0 +1,236 @@
+use crate::PointerValuePair;
+use alloc::sync::{Arc, Weak};
+use core::{fmt, marker::PhantomData, mem, ptr};
+
+const STRONG: usize = 0;
+const WEAK: usize = 1;
+
+/// Either an `Arc<T>` or a `Weak<T>`, packed in a single word, with the discriminant stored in the lowest alignment
+/// bit of the pointer.
+///
+/// This is meant for the links of trees with strong links to the children and weak links to the parents, which can
+/// share the same type and take one word each instead of an enum. The reference held by the link (strong or weak) is
+/// released when it is dropped.
+///
+/// # Notes
+///
+/// `T` must be aligned to at least 2 bytes, which is checked at compile time. This also applies to zero-sized types,
+/// since the address of the pointers must be kept for `Arc::from_raw` and `Weak::from_raw`.
+pub struct TaggedParentPtr<T> {
+    inner: PointerValuePair<T>,
+    _phantom: PhantomData<(Arc<T>, Weak<T>)>,
+}
+
+// SAFETY: `TaggedParentPtr` behaves like `Arc<T>` or `Weak<T>`, which are `Send` and `Sync` if `T: Send + Sync`.
+unsafe impl<T: Send + Sync> Send for TaggedParentPtr<T> {}
+unsafe impl<T: Send + Sync> Sync for TaggedParentPtr<T> {}
+
+impl<T> TaggedParentPtr<T> {
+    /// Creates a strong link.
+    pub fn new_strong(arc: Arc<T>) -> TaggedParentPtr<T> {
+        Self::from_raw(Arc::into_raw(arc), STRONG)
+    }
+
+    /// Creates a weak link.
+    pub fn new_weak(weak: Weak<T>) -> TaggedParentPtr<T> {
+        // `Weak::new` doesn't allocate, and returns a sentinel pointer that can't be packed. Store it as null instead,
+        // along with the weak references whose value has been dropped, which can't be upgraded either.
+        let ptr = if weak.strong_count() == 0 {
+            ptr::null()
+        } else {
+            Weak::into_raw(weak)
+        };
+        Self::from_raw(ptr, WEAK)
+    }
+
+    fn from_raw(ptr: *const T, tag: usize) -> TaggedParentPtr<T> {
+        const {
+            assert!(
+                PointerValuePair::<T>::max_value() >= 1,
+                "not enough alignment bits to store the discriminant"
+            )
+        };
+        TaggedParentPtr {
+            inner: PointerValuePair::new(ptr, tag),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns whether this is a strong link.
+    pub fn is_strong(&self) -> bool {
+        self.inner.value() == STRONG
+    }
+
+    /// Returns whether this is a weak link.
+    pub fn is_weak(&self) -> bool {
+        self.inner.value() == WEAK
+    }
+
+    /// Returns a reference to the value if this is a strong link.
+    pub fn get_strong(&self) -> Option<&T> {
+        // SAFETY: the pointer has been created with `Arc::into_raw`, and `self` holds a strong reference
+        self.is_strong().then(|| unsafe { &*self.inner.ptr() })
+    }
+
+    /// Returns a new strong reference to the value, or `None` if this is a weak link and the value has been dropped.
+    pub fn upgrade(&self) -> Option<Arc<T>> {
+        let (strong, weak) = self.new_refs();
+        strong.or_else(|| weak.and_then(|w| w.upgrade()))
+    }
+
+    /// Returns a new weak reference to the value.
+    pub fn to_weak(&self) -> Weak<T> {
+        match self.new_refs() {
+            (Some(arc), _) => Arc::downgrade(&arc),
+            (_, Some(weak)) => weak,
+            _ => Weak::new(),
+        }
+    }
+
+    /// Turns a strong link into a weak link, which doesn't keep the value alive.
+    pub fn downgrade(&mut self) {
+        if self.is_strong() {
+            *self = TaggedParentPtr::new_weak(self.to_weak());
+        }
+    }
+
+    /// Turns a weak link into a strong link if the value is still alive, and returns whether this is now a strong
+    /// link.
+    pub fn make_strong(&mut self) -> bool {
+        if self.is_weak() {
+            match self.upgrade() {
+                Some(arc) => *self = TaggedParentPtr::new_strong(arc),
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// Returns new references (strong or weak) to the value, without releasing the one held by `self`.
+    fn new_refs(&self) -> (Option<Arc<T>>, Option<Weak<T>>) {
+        let ptr = self.inner.ptr();
+        // SAFETY: the pointer has been created with `into_raw` from a reference of the type given by the tag, which
+        // is still held by `self`
+        unsafe {
+            match self.inner.value() {
+                STRONG => {
+                    Arc::increment_strong_count(ptr);
+                    (Some(Arc::from_raw(ptr)), None)
+                }
+                _ if ptr.is_null() => (None, None),
+                _ => {
+                    let weak = mem::ManuallyDrop::new(Weak::from_raw(ptr));
+                    (None, Some(Weak::clone(&weak)))
+                }
+            }
+        }
+    }
+}
+
+impl<T> Drop for TaggedParentPtr<T> {
+    fn drop(&mut self) {
+        let ptr = self.inner.ptr();
+        // SAFETY: the pointer has been created with `into_raw` from a reference of the type given by the tag
+        unsafe {
+            match self.inner.value() {
+                STRONG => drop(Arc::from_raw(ptr)),
+                _ if ptr.is_null() => {}
+                _ => drop(Weak::from_raw(ptr)),
+            }
+        }
+    }
+}
+
+impl<T> Clone for TaggedParentPtr<T> {
+    /// Clones the link, keeping it strong or weak.
+    fn clone(&self) -> Self {
+        match self.new_refs() {
+            (Some(arc), _) => TaggedParentPtr::new_strong(arc),
+            (_, weak) => TaggedParentPtr::new_weak(weak.unwrap_or_default()),
+        }
+    }
+}
+
+impl<T> From<Arc<T>> for TaggedParentPtr<T> {
+    fn from(arc: Arc<T>) -> Self {
+        TaggedParentPtr::new_strong(arc)
+    }
+}
+
+impl<T> From<Weak<T>> for TaggedParentPtr<T> {
+    fn from(weak: Weak<T>) -> Self {
+        TaggedParentPtr::new_weak(weak)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TaggedParentPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get_strong() {
+            Some(v) => f.debug_tuple("Strong").field(v).finish(),
+            None => f.write_str("Weak"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TaggedParentPtr;
+    use std::{
+        format, mem,
+        sync::{Arc, Weak},
+    };
+
+    #[test]
+    fn strong_and_weak() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<TaggedParentPtr<u64>>());
+        let parent = Arc::new(1u64);
+        let mut link = TaggedParentPtr::new_strong(parent.clone());
+        assert!(link.is_strong() && link.get_strong() == Some(&1));
+        assert_eq!(format!("{:?}", link), "Strong(1)");
+        assert_eq!(Arc::strong_count(&parent), 2);
+
+        link.downgrade();
+        assert!(link.is_weak() && link.get_strong().is_none());
+        assert_eq!((Arc::strong_count(&parent), Arc::weak_count(&parent)), (1, 1));
+        let clone = link.clone();
+        assert_eq!(Arc::weak_count(&parent), 2);
+        assert_eq!(clone.upgrade().as_deref(), Some(&1));
+        drop(clone);
+
+        assert!(link.make_strong());
+        assert_eq!((Arc::strong_count(&parent), Arc::weak_count(&parent)), (2, 0));
+        drop(link);
+        assert_eq!(Arc::strong_count(&parent), 1);
+    }
+
+    #[test]
+    fn dropped_parent() {
+        let parent = Arc::new(1u64);
+        let mut link = TaggedParentPtr::from(Arc::downgrade(&parent));
+        drop(parent);
+        assert!(link.upgrade().is_none() && !link.make_strong());
+        assert!(link.to_weak().upgrade().is_none());
+
+        let mut empty = TaggedParentPtr::<u64>::from(Weak::new());
+        assert!(empty.is_weak() && empty.upgrade().is_none() && !empty.make_strong());
+        assert_eq!(format!("{:?}", empty.clone()), "Weak");
+    }
+
+    #[test]
+    fn zero_sized() {
+        #[repr(align(2))]
+        #[derive(Debug)]
+        struct Unit;
+
+        let parent = Arc::new(Unit);
+        let mut link = TaggedParentPtr::new_strong(parent.clone());
+        link.downgrade();
+        assert_eq!((Arc::strong_count(&parent), Arc::weak_count(&parent)), (1, 1));
+        assert!(Arc::ptr_eq(&link.upgrade().unwrap(), &parent));
+        drop(link);
+        assert_eq!(Arc::weak_count(&parent), 0);
+
+        let empty = TaggedParentPtr::<Unit>::new_weak(Weak::new());
+        assert!(empty.upgrade().is_none() && empty.clone().to_weak().upgrade().is_none());
+    }
+}
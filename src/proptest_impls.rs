@@ -0,0 +1,128 @@
+use crate::{Cow, PointerValuePair, PointerValuePairAccess};
+use proptest::{
+    arbitrary::{any, any_with, Arbitrary},
+    collection::{vec, SizeRange},
+    option,
+    strategy::{BoxedStrategy, Strategy},
+};
+use std::{boxed::Box, fmt, ptr, string::String, vec::Vec};
+
+impl<T: Arbitrary + 'static> Arbitrary for PointerValuePair<T> {
+    type Parameters = T::Parameters;
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Generates pairs of a null pointer or a pointer to a leaked allocation holding an arbitrary `T`, and a value in
+    /// `0..=max_value()`.
+    ///
+    /// The allocations are never freed, so that the generated pointers stay valid for as long as the test needs them.
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        (option::of(any_with::<T>(args)), 0..=Self::max_value())
+            .prop_map(|(v, value)| {
+                let ptr = v.map_or(ptr::null(), |v| Box::leak(Box::new(v)) as *const T);
+                PointerValuePair::new(ptr, value)
+            })
+            .boxed()
+    }
+}
+
+/// Generates borrowed `Cow`s, pointing to leaked allocations, and owned `Cow`s, in equal proportion.
+fn borrowed_or_owned<T, U>(
+    values: impl Strategy<Value = U> + 'static,
+    borrowed: fn(&'static T) -> Cow<'static, T>,
+    owned: fn(U) -> Cow<'static, T>,
+    leak: fn(U) -> &'static T,
+) -> BoxedStrategy<Cow<'static, T>>
+where
+    T: ?Sized,
+    PointerValuePair<T>: PointerValuePairAccess,
+    U: fmt::Debug + 'static,
+    Cow<'static, T>: fmt::Debug,
+{
+    (any::<bool>(), values)
+        .prop_map(move |(is_owned, v)| if is_owned { owned(v) } else { borrowed(leak(v)) })
+        .boxed()
+}
+
+impl<T: Arbitrary + 'static> Arbitrary for Cow<'static, T> {
+    type Parameters = T::Parameters;
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Generates borrowed and owned `Cow`s holding an arbitrary `T`. Borrowed values are leaked.
+    fn arbitrary_with(args: Self::Parameters) -> Self::Strategy {
+        borrowed_or_owned(any_with::<T>(args), Cow::borrowed, Cow::owned_value, |v| {
+            Box::leak(Box::new(v))
+        })
+    }
+}
+
+impl<T: Arbitrary + 'static> Arbitrary for Cow<'static, [T]> {
+    type Parameters = (SizeRange, T::Parameters);
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Generates borrowed and owned `Cow`s holding slices of arbitrary length, like `Vec<T>`. Borrowed slices are
+    /// leaked.
+    fn arbitrary_with((size, args): Self::Parameters) -> Self::Strategy {
+        borrowed_or_owned(
+            vec(any_with::<T>(args), size),
+            Cow::borrowed_slice,
+            |v: Vec<T>| Cow::owned_slice(v.into()),
+            |v| Vec::leak(v),
+        )
+    }
+}
+
+impl Arbitrary for Cow<'static, str> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    /// Generates borrowed and owned `Cow`s holding arbitrary strings, like `String`. Borrowed strings are leaked.
+    fn arbitrary_with((): Self::Parameters) -> Self::Strategy {
+        borrowed_or_owned(any::<String>(), Cow::borrowed_str, Cow::from, |s| s.leak())
+    }
+}
+
+impl<T: 'static> PointerValuePair<T> {
+    /// Returns a strategy generating `PointerValuePair`s with one of the given pointers (e.g. to `static` items), and
+    /// a value in `0..=max_value()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ptrs` is empty.
+    pub fn strategy_from(ptrs: &'static [&'static T]) -> BoxedStrategy<PointerValuePair<T>> {
+        assert!(!ptrs.is_empty(), "no pointers to choose from");
+        (0..ptrs.len(), 0..=Self::max_value())
+            .prop_map(move |(i, value)| PointerValuePair::new(ptrs[i], value))
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cow, PointerValuePair};
+    use proptest::{arbitrary::any, proptest};
+    use std::{string::String, vec::Vec};
+
+    static STATICS: [&u64; 2] = [&1, &2];
+
+    proptest! {
+        #[test]
+        fn pairs(p in any::<PointerValuePair<u32>>()) {
+            assert!(p.value() <= PointerValuePair::<u32>::max_value());
+            // SAFETY: the pointer is null or points to a leaked `u32`
+            assert_eq!(unsafe { p.ptr().as_ref() }.is_some(), !p.is_null());
+        }
+
+        #[test]
+        fn statics(p in PointerValuePair::strategy_from(&STATICS)) {
+            assert!(STATICS.iter().any(|&s| std::ptr::eq(s, p.ptr())));
+            assert!(p.value() < 8);
+        }
+
+        #[test]
+        fn cows(a in any::<Cow<u32>>(), b in any::<Cow<[u16]>>(), c in any::<Cow<str>>()) {
+            assert_eq!(a.clone().into_owned_cow(), a);
+            assert_eq!(Vec::from(&*b), b.clone().into_owned_slice().into_vec());
+            assert_eq!(String::from(&*c), c.clone().into_string());
+        }
+    }
+}
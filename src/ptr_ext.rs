@@ -0,0 +1,121 @@
+use crate::{PointeeAlign, PointerValuePair, TaggedMut, TaggedRef};
+
+/// Extension methods to tag raw pointers and references in place, e.g. `ptr.with_tag(3)`.
+///
+/// On raw pointers, `with_tag` returns a `PointerValuePair`, and `tag` and `untagged` read a raw tagged pointer
+/// (a pointer with a value in its low bits, e.g. returned by `PointerValuePair::into_packed_ptr`), which eases
+/// adopting the crate in existing unsafe code that already manipulates tagged pointers. On references, `with_tag`
+/// returns a `TaggedRef` or a `TaggedMut`; references are never tagged, so `tag` returns zero and `untagged` returns
+/// the reference unchanged.
+pub trait TaggedPtrExt: Sized {
+    /// The type returned by `with_tag`.
+    type Tagged;
+
+    /// Packs the given value in the alignment bits of the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pointer is misaligned, or if the value doesn't fit in the available bits.
+    fn with_tag(self, value: usize) -> Self::Tagged;
+
+    /// Returns the value stored in the low bits of a tagged pointer.
+    fn tag(self) -> usize;
+
+    /// Returns the pointer with the value in its low bits cleared.
+    fn untagged(self) -> Self;
+}
+
+impl<T: ?Sized + PointeeAlign> TaggedPtrExt for *const T {
+    type Tagged = PointerValuePair<T>;
+
+    fn with_tag(self, value: usize) -> PointerValuePair<T> {
+        PointerValuePair::new(self, value)
+    }
+
+    fn tag(self) -> usize {
+        PointerValuePair::from_raw(self).value()
+    }
+
+    fn untagged(self) -> *const T {
+        PointerValuePair::from_raw(self).ptr()
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> TaggedPtrExt for *mut T {
+    type Tagged = PointerValuePair<T>;
+
+    fn with_tag(self, value: usize) -> PointerValuePair<T> {
+        PointerValuePair::new(self, value)
+    }
+
+    fn tag(self) -> usize {
+        (self as *const T).tag()
+    }
+
+    fn untagged(self) -> *mut T {
+        (self as *const T).untagged() as *mut T
+    }
+}
+
+impl<'a, T: ?Sized + PointeeAlign> TaggedPtrExt for &'a T {
+    type Tagged = TaggedRef<'a, T>;
+
+    fn with_tag(self, value: usize) -> TaggedRef<'a, T> {
+        TaggedRef::new(self, value)
+    }
+
+    fn tag(self) -> usize {
+        0
+    }
+
+    fn untagged(self) -> &'a T {
+        self
+    }
+}
+
+impl<'a, T: ?Sized + PointeeAlign> TaggedPtrExt for &'a mut T {
+    type Tagged = TaggedMut<'a, T>;
+
+    fn with_tag(self, value: usize) -> TaggedMut<'a, T> {
+        TaggedMut::new(self, value)
+    }
+
+    fn tag(self) -> usize {
+        0
+    }
+
+    fn untagged(self) -> &'a mut T {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TaggedPtrExt;
+
+    #[test]
+    fn raw_pointers() {
+        let mut pointee = 42u64;
+        let p = &mut pointee as *mut u64;
+        let pv = p.with_tag(3);
+        assert_eq!((pv.ptr(), pv.value()), (p as *const u64, 3));
+
+        let raw = pv.into_packed_ptr() as *mut u64;
+        assert_eq!((raw.tag(), raw.untagged()), (3, p));
+        unsafe { *raw.untagged() += 1 };
+        assert_eq!(pointee, 43);
+        assert_eq!((p.tag(), p.untagged()), (0, p));
+    }
+
+    #[test]
+    fn references() {
+        let mut pointee = 42u64;
+        let r = (&pointee).with_tag(5);
+        assert_eq!((*r.get(), r.value()), (42, 5));
+        assert_eq!(((&pointee).tag(), *(&pointee).untagged()), (0, 42));
+
+        let mut m = (&mut pointee).with_tag(1);
+        *m.get_mut() += 1;
+        assert_eq!(m.into_parts(), (&mut 43, 1));
+    }
+}
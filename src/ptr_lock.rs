@@ -0,0 +1,213 @@
+use crate::{AtomicPointerValuePair, PointerValuePair};
+use core::{fmt, hint, sync::atomic::Ordering};
+
+/// Set in the value of the pair while the lock is held.
+const LOCKED: usize = 1;
+
+/// A spinlock protecting a raw pointer and a tag, stored in a single atomic word.
+///
+/// The lowest alignment bit of the pointer is used as the lock flag, and the remaining alignment bits hold the tag,
+/// so that each node of a concurrent data structure can have its own lock without taking an extra word. `lock`
+/// returns a `PtrLockGuard`, through which the pointer and the tag can be read and replaced; the changes are
+/// published when the guard is dropped, which releases the lock.
+///
+/// Like `AtomicPointerValuePair`, this doesn't own the pointee: the lock only guarantees that the pointer and the tag
+/// are updated by one thread at a time.
+///
+/// # Notes
+///
+/// `T` must be aligned to at least 2 bytes, which is checked at compile time. The tag can use the remaining
+/// alignment bits (see `max_tag`). Waiting threads spin, so the lock should only be held for short periods.
+pub struct PtrLock<T> {
+    pv: AtomicPointerValuePair<T>,
+}
+
+impl<T> PtrLock<T> {
+    /// Returns the maximum (inclusive) tag.
+    pub const fn max_tag() -> usize {
+        const {
+            assert!(
+                PointerValuePair::<T>::max_value() >= LOCKED,
+                "not enough alignment bits to store the lock bit"
+            )
+        };
+        PointerValuePair::<T>::max_value() >> 1
+    }
+
+    /// Packs a pointer and a tag, with the lock bit cleared.
+    fn pack(ptr: *const T, tag: usize) -> PointerValuePair<T> {
+        assert!(tag <= Self::max_tag(), "tag ({tag}) doesn't fit in the available bits");
+        PointerValuePair::new(ptr, tag << 1)
+    }
+
+    /// Creates a new, unlocked `PtrLock` holding the given pointer and tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag is greater than `max_tag()`.
+    pub fn new(ptr: *const T, tag: usize) -> PtrLock<T> {
+        PtrLock {
+            pv: AtomicPointerValuePair::new(Self::pack(ptr, tag)),
+        }
+    }
+
+    /// Acquires the lock, spinning until it is available.
+    pub fn lock(&self) -> PtrLockGuard<'_, T> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+            // wait without writing to the word, so that the cache line isn't contended
+            while self.is_locked() {
+                hint::spin_loop();
+            }
+        }
+    }
+
+    /// Acquires the lock if it is available, or returns `None` if it is held by another guard.
+    pub fn try_lock(&self) -> Option<PtrLockGuard<'_, T>> {
+        let pv = self.pv.fetch_or_value(LOCKED, Ordering::Acquire);
+        (pv.value() & LOCKED == 0).then_some(PtrLockGuard { lock: self, pv })
+    }
+
+    /// Returns whether the lock is currently held.
+    pub fn is_locked(&self) -> bool {
+        self.pv.load(Ordering::Relaxed).value() & LOCKED != 0
+    }
+
+    /// Returns the pointer and the tag, without acquiring the lock. Since `self` is borrowed mutably, the lock is not
+    /// held.
+    pub fn get_unsynchronized(&mut self) -> (*const T, usize) {
+        let pv = self.pv.load(Ordering::Relaxed);
+        (pv.ptr(), pv.value() >> 1)
+    }
+
+    /// Returns the pointer and the tag.
+    pub fn into_inner(self) -> (*const T, usize) {
+        let pv = self.pv.into_inner();
+        (pv.ptr(), pv.value() >> 1)
+    }
+}
+
+impl<T> fmt::Debug for PtrLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => f
+                .debug_struct("PtrLock")
+                .field("ptr", &guard.ptr())
+                .field("tag", &guard.tag())
+                .finish(),
+            None => f
+                .debug_struct("PtrLock")
+                .field("ptr", &format_args!("<locked>"))
+                .finish(),
+        }
+    }
+}
+
+/// The lock of a `PtrLock`, giving access to the pointer and the tag. The lock is released when the guard is dropped.
+pub struct PtrLockGuard<'a, T> {
+    lock: &'a PtrLock<T>,
+    /// The pointer and the tag, shifted left by one, to be stored when the lock is released.
+    pv: PointerValuePair<T>,
+}
+
+impl<T> PtrLockGuard<'_, T> {
+    /// Returns the pointer.
+    pub fn ptr(&self) -> *const T {
+        self.pv.ptr()
+    }
+
+    /// Returns the tag.
+    pub fn tag(&self) -> usize {
+        self.pv.value() >> 1
+    }
+
+    /// Replaces the pointer, keeping the tag.
+    pub fn set_ptr(&mut self, ptr: *const T) {
+        self.pv = PointerValuePair::new(ptr, self.pv.value());
+    }
+
+    /// Replaces the tag, keeping the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag is greater than `max_tag()`.
+    pub fn set_tag(&mut self, tag: usize) {
+        self.pv = PtrLock::pack(self.ptr(), tag);
+    }
+
+    /// Replaces the pointer and the tag, and returns the previous ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag is greater than `max_tag()`.
+    pub fn replace(&mut self, ptr: *const T, tag: usize) -> (*const T, usize) {
+        let old = (self.ptr(), self.tag());
+        self.pv = PtrLock::pack(ptr, tag);
+        old
+    }
+}
+
+impl<T> Drop for PtrLockGuard<'_, T> {
+    fn drop(&mut self) {
+        // the lock bit of `self.pv` is clear, so this publishes the changes and releases the lock at once
+        self.lock.pv.store(self.pv, Ordering::Release);
+    }
+}
+
+impl<T> fmt::Debug for PtrLockGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PtrLockGuard")
+            .field("ptr", &self.ptr())
+            .field("tag", &self.tag())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PtrLock;
+    use std::{mem, ptr, thread};
+
+    #[test]
+    fn lock() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<PtrLock<u64>>());
+        assert_eq!(PtrLock::<u64>::max_tag(), 3);
+        let nodes = [1u64, 2];
+        let lock = PtrLock::new(&nodes[0], 1);
+
+        let mut guard = lock.lock();
+        assert!(lock.is_locked() && lock.try_lock().is_none());
+        assert!(ptr::eq(guard.ptr(), &nodes[0]) && guard.tag() == 1);
+        guard.set_tag(3);
+        assert_eq!(guard.replace(&nodes[1], 2), (&nodes[0] as *const u64, 3));
+        guard.set_ptr(&nodes[0]);
+        drop(guard);
+
+        assert!(!lock.is_locked());
+        assert_eq!(lock.try_lock().map(|g| g.tag()), Some(2));
+        assert_eq!(lock.into_inner(), (&nodes[0] as *const u64, 2));
+    }
+
+    #[test]
+    fn threads() {
+        let mut counter = 0u64;
+        let mut lock = PtrLock::new(&mut counter as *mut u64, 0);
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        let mut guard = lock.lock();
+                        // SAFETY: the counter is only accessed while the lock is held
+                        unsafe { *(guard.ptr() as *mut u64) += 1 };
+                        let tag = guard.tag();
+                        guard.set_tag((tag + 1) % 4);
+                    }
+                });
+            }
+        });
+        assert_eq!(lock.get_unsynchronized().1, 0);
+        assert_eq!(counter, 4000);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::{CowPointee, PointerValuePair, PointerValuePairAccess, ToBoxed};
+use alloc::rc::Rc;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use core::{fmt, marker::PhantomData, mem, ops::Deref};
+
+const BORROWED: usize = 0usize;
+const SHARED: usize = 1usize;
+
+macro_rules! rc_cow {
+    ($(#[$attr:meta])* $name:ident, $rc:ident) => {
+        $(#[$attr])*
+        #[repr(transparent)]
+        pub struct $name<'a, T>
+        where
+            T: ?Sized + CowPointee,
+            PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+        {
+            inner: PointerValuePair<T>,
+            _phantom: PhantomData<(&'a T, $rc<T>)>,
+        }
+
+        impl<'a, T> $name<'a, T>
+        where
+            T: ?Sized + CowPointee,
+            PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+        {
+            #[doc = concat!("Creates a new `", stringify!($name), "` representing a borrowed value.")]
+            pub fn borrowed(v: &'a T) -> $name<'a, T> {
+                $name {
+                    inner: T::pack(v, BORROWED),
+                    _phantom: PhantomData,
+                }
+            }
+
+            #[doc = concat!("Creates a new `", stringify!($name), "` holding a shared value.")]
+            pub fn owned(v: $rc<T>) -> $name<'a, T> {
+                const { assert!(T::MAX_VALUE >= SHARED, "not enough alignment bits to store the discriminant") };
+                $name {
+                    inner: T::pack($rc::into_raw(v), SHARED),
+                    _phantom: PhantomData,
+                }
+            }
+
+            /// Returns whether this holds a borrow.
+            pub fn is_borrowed(&self) -> bool {
+                self.inner.value() == BORROWED
+            }
+
+            #[doc = concat!("Returns whether this holds an `", stringify!($rc), "`.")]
+            pub fn is_owned(&self) -> bool {
+                self.inner.value() == SHARED
+            }
+
+            /// Returns the original reference if this is borrowed, or `None` if it is owned.
+            pub fn borrowed_ref(&self) -> Option<&'a T> {
+                // SAFETY: the pointer comes from a `&'a T` passed to `borrowed`.
+                self.is_borrowed().then(|| unsafe { &*PointerValuePairAccess::ptr(self.inner) })
+            }
+
+            #[doc = concat!(
+                "Converts this into an `", stringify!($rc), "<T>`. If this is a borrow, clones the value into a new ",
+                "allocation."
+            )]
+            pub fn into_owned(self) -> $rc<T>
+            where
+                T: ToBoxed,
+            {
+                if self.is_owned() {
+                    let ptr = PointerValuePairAccess::ptr(self.inner);
+                    // the reference count is transferred to the returned pointer
+                    mem::forget(self);
+                    unsafe {
+                        // SAFETY: the pointer has been created with `into_raw` by `owned`.
+                        $rc::from_raw(ptr)
+                    }
+                } else {
+                    $rc::from(T::to_boxed(&self))
+                }
+            }
+
+            /// Converts this into an owned value that doesn't borrow anything, by cloning the value into a new
+            /// allocation if it is borrowed.
+            pub fn into_owned_cow<'b>(self) -> $name<'b, T>
+            where
+                T: ToBoxed,
+            {
+                $name::owned(self.into_owned())
+            }
+        }
+
+        impl<'a, T> Clone for $name<'a, T>
+        where
+            T: ?Sized + CowPointee,
+            PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+        {
+            /// Copies the borrow, or increments the reference count if this is owned.
+            fn clone(&self) -> Self {
+                if self.is_owned() {
+                    unsafe {
+                        // SAFETY: the pointer has been created with `into_raw` by `owned`, and `self` holds a
+                        // reference.
+                        $rc::increment_strong_count(PointerValuePairAccess::ptr(self.inner));
+                    }
+                }
+                $name {
+                    inner: self.inner,
+                    _phantom: PhantomData,
+                }
+            }
+        }
+
+        impl<'a, T> Drop for $name<'a, T>
+        where
+            T: ?Sized + CowPointee,
+            PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+        {
+            fn drop(&mut self) {
+                validate!(self.inner.value() <= SHARED, concat!("corrupted ", stringify!($name), " discriminant"));
+                if self.is_owned() {
+                    unsafe {
+                        // SAFETY: the pointer has been created with `into_raw` by `owned`.
+                        drop($rc::from_raw(PointerValuePairAccess::ptr(self.inner)));
+                    }
+                }
+            }
+        }
+
+        impl<'a, T> Deref for $name<'a, T>
+        where
+            T: ?Sized + CowPointee,
+            PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+        {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                // SAFETY: the pointer is either borrowed for `'a`, or `self` holds a reference to the allocation.
+                unsafe { &*PointerValuePairAccess::ptr(self.inner) }
+            }
+        }
+
+        impl<'a, T> fmt::Debug for $name<'a, T>
+        where
+            T: ?Sized + CowPointee + fmt::Debug,
+            PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+        {
+            /// Formats the value as `Borrowed(..)` or `Owned(..)`.
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let state = if self.is_owned() { "Owned" } else { "Borrowed" };
+                f.debug_tuple(state).field(&&**self).finish()
+            }
+        }
+
+        impl<'a, T> From<&'a T> for $name<'a, T>
+        where
+            T: ?Sized + CowPointee,
+            PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+        {
+            fn from(v: &'a T) -> Self {
+                $name::borrowed(v)
+            }
+        }
+
+        impl<T> From<$rc<T>> for $name<'_, T>
+        where
+            T: ?Sized + CowPointee,
+            PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+        {
+            fn from(v: $rc<T>) -> Self {
+                $name::owned(v)
+            }
+        }
+    };
+}
+
+rc_cow! {
+    /// A pointer-sized object that holds either a borrow (`&'a T`) or a reference-counted value (`Rc<T>`).
+    ///
+    /// Unlike `Cow`, cloning an owned `RcCow` only increments the reference count. This is the single-threaded
+    /// equivalent of `ArcCow`. Zero-sized types must be aligned to at least 2 bytes to create owned values, since the
+    /// address of the `Rc` must be kept.
+    RcCow, Rc
+}
+
+#[cfg(target_has_atomic = "ptr")]
+rc_cow! {
+    /// A pointer-sized object that holds either a borrow (`&'a T`) or an atomically reference-counted value
+    /// (`Arc<T>`).
+    ///
+    /// Unlike `Cow`, cloning an owned `ArcCow` only increments the reference count, and the owned values can be
+    /// shared between threads. Zero-sized types must be aligned to at least 2 bytes to create owned values, since
+    /// the address of the `Arc` must be kept.
+    ArcCow, Arc
+}
+
+// SAFETY: an `ArcCow` is either a `&'a T` or an `Arc<T>`, which are both `Send` and `Sync` if `T: Send + Sync`.
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl<'a, T> Send for ArcCow<'a, T>
+where
+    T: ?Sized + CowPointee + Send + Sync,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+}
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl<'a, T> Sync for ArcCow<'a, T>
+where
+    T: ?Sized + CowPointee + Send + Sync,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_has_atomic = "ptr")]
+    use crate::ArcCow;
+    use crate::RcCow;
+    use std::{format, mem, ptr, rc::Rc};
+    #[cfg(target_has_atomic = "ptr")]
+    use std::{sync::Arc, thread};
+
+    #[test]
+    fn rc_cow() {
+        assert_eq!(mem::size_of::<*const u32>(), mem::size_of::<RcCow<u32>>());
+
+        let v = [1u32, 2];
+        let cow = RcCow::from(&v[..]);
+        assert_eq!(format!("{:?}", cow.clone()), "Borrowed([1, 2])");
+        assert!(cow.borrowed_ref().is_some());
+
+        let rc: Rc<[u32]> = Rc::from(&v[..]);
+        let cow = RcCow::from(rc.clone());
+        let cow2 = cow.clone();
+        assert_eq!(Rc::strong_count(&rc), 3);
+        assert_eq!(format!("{:?}", cow2), "Owned([1, 2])");
+        drop(cow2);
+        assert!(Rc::ptr_eq(&cow.into_owned(), &rc));
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[cfg(target_has_atomic = "ptr")]
+    #[test]
+    fn arc_cow() {
+        assert_eq!(mem::size_of::<*const u32>(), mem::size_of::<ArcCow<u32>>());
+
+        let x = 1u32;
+        let cow = ArcCow::borrowed(&x);
+        assert_eq!(format!("{:?}", cow.clone()), "Borrowed(1)");
+        let owned = cow.into_owned_cow();
+        assert!(owned.is_owned());
+
+        let arc: Arc<str> = Arc::from("hello");
+        let cow = ArcCow::owned(arc.clone());
+        let cow2 = cow.clone();
+        assert_eq!(Arc::strong_count(&arc), 3);
+        thread::spawn(move || assert_eq!(&*cow2, "hello")).join().unwrap();
+        assert_eq!(Arc::strong_count(&arc), 2);
+        assert!(Arc::ptr_eq(&cow.into_owned(), &arc));
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn zero_sized() {
+        #[repr(align(2))]
+        #[derive(Clone, Debug)]
+        struct Unit;
+
+        let cow = RcCow::borrowed(&());
+        assert!(cow.is_borrowed() && ptr::eq(cow.borrowed_ref().unwrap(), &()));
+
+        let rc = Rc::new(Unit);
+        let cow = RcCow::owned(rc.clone());
+        assert_eq!(Rc::strong_count(&rc), 2);
+        // the address is kept, so that the `Rc` can be rebuilt
+        assert!(Rc::ptr_eq(&cow.clone().into_owned(), &rc));
+        drop(cow);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
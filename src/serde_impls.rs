@@ -0,0 +1,183 @@
+#[cfg(target_has_atomic = "ptr")]
+use crate::TaggedArc;
+use crate::{Cow, PointeeAlign, PointerValuePair, PointerValuePairAccess, TaggedBox, TaggedRc};
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use alloc::{boxed::Box, rc::Rc, string::String};
+use core::{fmt, ops::Deref};
+use serde::{
+    de::{self, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+impl<'a, T> Serialize for Cow<'a, T>
+where
+    T: ?Sized + Serialize,
+    PointerValuePair<T>: PointerValuePairAccess,
+    Self: Deref<Target = T>,
+{
+    /// Serializes the value, regardless of whether it is borrowed or owned.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Cow<'_, T> {
+    /// Deserializes an owned `Cow`.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Box::deserialize(deserializer).map(Cow::owned)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Cow<'_, [T]> {
+    /// Deserializes an owned `Cow<[T]>`.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Box::deserialize(deserializer).map(Cow::owned_slice)
+    }
+}
+
+struct StrVisitor;
+
+impl<'de> Visitor<'de> for StrVisitor {
+    type Value = Cow<'de, str>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(Cow::borrowed_str(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Cow::owned_str(v.into()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Cow::from(v))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Cow<'a, str> {
+    /// Deserializes a `Cow<str>` borrowing from the input if the deserializer allows it (e.g. strings without escape
+    /// sequences in JSON), or an owned `Cow<str>` otherwise.
+    ///
+    /// There is no equivalent for `Cow<[u8]>`, since owned byte slices (with an alignment of 1) have no spare bit to
+    /// store the discriminant.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(StrVisitor)
+    }
+}
+
+/// Checks that a deserialized value fits in the alignment bits of `*const T`.
+fn check_value<T: ?Sized + PointeeAlign, E: de::Error>(value: usize) -> Result<usize, E> {
+    if value > PointerValuePair::<T>::max_value() {
+        return Err(E::invalid_value(
+            Unexpected::Unsigned(value as u64),
+            &"a value that fits in the alignment bits",
+        ));
+    }
+    Ok(value)
+}
+
+impl<T: ?Sized + PointeeAlign + Serialize> Serialize for TaggedBox<T> {
+    /// Serializes the boxed value and the value stored alongside the pointer, as a `(value, tag)` pair.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&**self, self.value()).serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for TaggedBox<T>
+where
+    T: ?Sized + PointeeAlign,
+    Box<T>: Deserialize<'de>,
+{
+    /// Deserializes a `(value, tag)` pair into a new allocation. It is an error if the tag doesn't fit in the
+    /// alignment bits.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (b, value) = <(Box<T>, usize)>::deserialize(deserializer)?;
+        Ok(TaggedBox::new(b, check_value::<T, _>(value)?))
+    }
+}
+
+macro_rules! tagged_rc_serde {
+    ($name:ident, $rc:ident) => {
+        impl<T: ?Sized + PointeeAlign + Serialize> Serialize for $name<T> {
+            /// Serializes the pointee and the value stored alongside the pointer, as a `(value, tag)` pair.
+            ///
+            #[doc = concat!("Like `", stringify!($rc), "`, values shared by several handles are serialized once per handle.")]
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                (&**self, $name::value(self)).serialize(serializer)
+            }
+        }
+
+        impl<'de, T> Deserialize<'de> for $name<T>
+        where
+            T: ?Sized + PointeeAlign,
+            Box<T>: Deserialize<'de>,
+        {
+            /// Deserializes a `(value, tag)` pair into a new, unshared allocation. It is an error if the tag doesn't
+            /// fit in the alignment bits.
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let (b, value) = <(Box<T>, usize)>::deserialize(deserializer)?;
+                Ok($name::new($rc::from(b), check_value::<T, _>(value)?))
+            }
+        }
+    };
+}
+
+tagged_rc_serde!(TaggedRc, Rc);
+#[cfg(target_has_atomic = "ptr")]
+tagged_rc_serde!(TaggedArc, Arc);
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cow, TaggedArc, TaggedBox, TaggedRc};
+    use std::{boxed::Box, format, ptr, string::String, sync::Arc, vec};
+
+    #[test]
+    fn round_trip() {
+        let x = 42u32;
+        assert_eq!(serde_json::to_string(&Cow::borrowed(&x)).unwrap(), "42");
+        let cow: Cow<u32> = serde_json::from_str("42").unwrap();
+        assert!(cow.is_owned());
+        assert_eq!(*cow, 42);
+
+        let cow: Cow<[u16]> = serde_json::from_str("[1,2,3]").unwrap();
+        assert_eq!(&*cow, &[1, 2, 3]);
+        assert_eq!(serde_json::to_string(&cow).unwrap(), "[1,2,3]");
+
+        let cow: Cow<str> = serde_json::from_str("\"hello\"").unwrap();
+        assert_eq!(serde_json::to_string(&cow).unwrap(), "\"hello\"");
+    }
+
+    #[test]
+    fn borrowed() {
+        let input = String::from("\"hello\"");
+        let cow: Cow<str> = serde_json::from_str(&input).unwrap();
+        assert!(cow.is_borrowed());
+        assert!(ptr::eq(cow.as_ptr(), &input.as_bytes()[1]));
+        // escape sequences can't be borrowed
+        let cow: Cow<str> = serde_json::from_str("\"a\\nb\"").unwrap();
+        assert!(cow.is_owned());
+        assert_eq!(&*cow, "a\nb");
+    }
+
+    #[test]
+    fn tagged() {
+        let b = TaggedBox::new(Box::new(42u32), 3);
+        assert_eq!(serde_json::to_string(&b).unwrap(), "[42,3]");
+        let b: TaggedBox<u32> = serde_json::from_str("[7,2]").unwrap();
+        assert_eq!((*b, b.value()), (7, 2));
+        let e = serde_json::from_str::<TaggedBox<u32>>("[7,4]").unwrap_err();
+        assert!(format!("{}", e).contains("fits in the alignment bits"));
+
+        let a = TaggedArc::<[u64]>::new(Arc::from(vec![1, 2]), 5);
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(json, "[[1,2],5]");
+        let a: TaggedArc<[u64]> = serde_json::from_str(&json).unwrap();
+        assert_eq!((&*a, TaggedArc::value(&a)), (&[1, 2][..], 5));
+        let r: TaggedRc<u16> = serde_json::from_str("[1,1]").unwrap();
+        assert_eq!((*r, TaggedRc::value(&r)), (1, 1));
+    }
+}
@@ -0,0 +1,343 @@
+use crate::{CowPointee, PointerValuePair, PointerValuePairAccess, ToBoxed};
+use alloc::{boxed::Box, sync::Arc};
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    ops::Deref,
+};
+
+const BORROWED: usize = 0usize;
+const OWNED: usize = 1usize;
+const SHARED: usize = 2usize;
+
+/// A pointer-sized object that holds either a borrow (`&'a T`), a uniquely owned value (`Box<T>`), or a shared value
+/// (`Arc<T>`).
+///
+/// This allows values to be promoted for sharing (see `into_shared`) without changing their type, while uniquely
+/// owned values are still cloned and mutated without reference counting.
+///
+/// # Notes
+///
+/// Two bits are needed to store the state, so `T` (or the element type, for slices) must be aligned to at least
+/// 4 bytes, which is checked at compile time when creating owned or shared `SharedCow`s. Unlike `Cow`, this also
+/// applies to zero-sized types, since the address of shared pointers must be kept for `Arc::from_raw`.
+#[repr(transparent)]
+pub struct SharedCow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    inner: PointerValuePair<T>,
+    _phantom: PhantomData<(&'a T, Box<T>, Arc<T>)>,
+}
+
+// SAFETY: a `SharedCow` is either a `&'a T`, a `Box<T>` or an `Arc<T>`, which are all `Send` and `Sync` if
+// `T: Send + Sync`.
+unsafe impl<'a, T> Send for SharedCow<'a, T>
+where
+    T: ?Sized + CowPointee + Send + Sync,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+}
+unsafe impl<'a, T> Sync for SharedCow<'a, T>
+where
+    T: ?Sized + CowPointee + Send + Sync,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+}
+
+impl<'a, T> SharedCow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Creates a new `SharedCow` representing a borrowed value.
+    pub fn borrowed(v: &'a T) -> SharedCow<'a, T> {
+        SharedCow {
+            inner: T::pack(v, BORROWED),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new `SharedCow` holding a uniquely owned value.
+    pub fn owned(v: Box<T>) -> SharedCow<'a, T> {
+        const { assert!(T::MAX_VALUE >= SHARED, "not enough alignment bits to store the state") };
+        SharedCow {
+            inner: T::pack(Box::into_raw(v), OWNED),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new `SharedCow` holding a shared value.
+    pub fn shared(v: Arc<T>) -> SharedCow<'a, T> {
+        const { assert!(T::MAX_VALUE >= SHARED, "not enough alignment bits to store the state") };
+        SharedCow {
+            inner: T::pack(Arc::into_raw(v), SHARED),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns whether this `SharedCow` holds a borrow.
+    pub fn is_borrowed(&self) -> bool {
+        self.inner.value() == BORROWED
+    }
+
+    /// Returns whether this `SharedCow` holds a `Box<T>`.
+    pub fn is_owned(&self) -> bool {
+        self.inner.value() == OWNED
+    }
+
+    /// Returns whether this `SharedCow` holds an `Arc<T>`.
+    pub fn is_shared(&self) -> bool {
+        self.inner.value() == SHARED
+    }
+
+    /// Returns the original reference if this `SharedCow` is borrowed.
+    pub fn borrowed_ref(&self) -> Option<&'a T> {
+        // SAFETY: the pointer comes from a `&'a T` passed to `borrowed`.
+        self.is_borrowed()
+            .then(|| unsafe { &*PointerValuePairAccess::ptr(self.inner) })
+    }
+
+    /// Converts this `SharedCow` into an `Arc<T>`.
+    ///
+    /// Boxed values are moved into the `Arc` without being cloned. Borrowed values are cloned.
+    pub fn into_shared(self) -> Arc<T>
+    where
+        T: ToBoxed,
+    {
+        let ptr = self.inner.mut_ptr();
+        let state = self.inner.value();
+        // ownership of the box or the reference count (if any) is transferred to the result
+        mem::forget(self);
+        unsafe {
+            match state {
+                // SAFETY: the pointer comes from a `&'a T` passed to `borrowed`.
+                BORROWED => Arc::from(T::to_boxed(&*ptr)),
+                // SAFETY: the pointer has been created with `Box::into_raw` by `owned`.
+                OWNED => Arc::from(Box::from_raw(ptr)),
+                // SAFETY: the pointer has been created with `Arc::into_raw` by `shared`.
+                _ => Arc::from_raw(ptr),
+            }
+        }
+    }
+
+    /// Converts this `SharedCow` into a `Box<T>`, cloning the value if it is borrowed or shared.
+    pub fn into_owned(self) -> Box<T>
+    where
+        T: ToBoxed,
+    {
+        if self.is_owned() {
+            let ptr = self.inner.mut_ptr();
+            // ownership is transferred to the returned box
+            mem::forget(self);
+            unsafe {
+                // SAFETY: the pointer has been created with `Box::into_raw` by `owned`.
+                Box::from_raw(ptr)
+            }
+        } else {
+            T::to_boxed(&self)
+        }
+    }
+
+    /// Returns a mutable reference to the value.
+    ///
+    /// Boxed values and shared values that are not shared with any other `Arc` (or `Weak`) are modified in place.
+    /// Otherwise, the value is cloned into a box first.
+    pub fn to_mut(&mut self) -> &mut T
+    where
+        T: ToBoxed,
+    {
+        if self.is_shared() {
+            unsafe {
+                // SAFETY: the pointer has been created with `Arc::into_raw` by `shared`, and `self` keeps the
+                // reference count.
+                let mut arc = ManuallyDrop::new(Arc::from_raw(PointerValuePairAccess::ptr(self.inner)));
+                if let Some(v) = Arc::get_mut(&mut arc) {
+                    // SAFETY: the value is uniquely owned by `self`, which we borrow mutably.
+                    return &mut *(v as *mut T);
+                }
+            }
+        }
+        if !self.is_owned() {
+            *self = SharedCow::owned(T::to_boxed(self));
+        }
+        // SAFETY: we own the boxed value, and borrow `self` mutably.
+        unsafe { &mut *self.inner.mut_ptr() }
+    }
+}
+
+impl<'a, T> Drop for SharedCow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    fn drop(&mut self) {
+        validate!(self.inner.value() <= SHARED, "corrupted SharedCow state");
+        unsafe {
+            match self.inner.value() {
+                // SAFETY: the pointer has been created with `Box::into_raw` by `owned`.
+                OWNED => drop(Box::from_raw(self.inner.mut_ptr())),
+                // SAFETY: the pointer has been created with `Arc::into_raw` by `shared`.
+                SHARED => drop(Arc::from_raw(PointerValuePairAccess::ptr(self.inner))),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'a, T> Deref for SharedCow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the pointer is either borrowed for `'a`, or points to an allocation that we (co-)own.
+        unsafe { &*PointerValuePairAccess::ptr(self.inner) }
+    }
+}
+
+impl<'a, T> Clone for SharedCow<'a, T>
+where
+    T: ?Sized + CowPointee + ToBoxed,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Copies the borrow, clones the boxed value into a new box, or increments the reference count.
+    fn clone(&self) -> Self {
+        match self.inner.value() {
+            OWNED => SharedCow::owned(T::to_boxed(self)),
+            SHARED => unsafe {
+                // SAFETY: the pointer has been created with `Arc::into_raw` by `shared`, and `self` holds a
+                // reference.
+                Arc::increment_strong_count(PointerValuePairAccess::ptr(self.inner));
+                SharedCow {
+                    inner: self.inner,
+                    _phantom: PhantomData,
+                }
+            },
+            _ => SharedCow {
+                inner: self.inner,
+                _phantom: PhantomData,
+            },
+        }
+    }
+}
+
+impl<'a, T> fmt::Debug for SharedCow<'a, T>
+where
+    T: ?Sized + CowPointee + fmt::Debug,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Formats the value as `Borrowed(..)`, `Owned(..)` or `Shared(..)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.inner.value() {
+            BORROWED => "Borrowed",
+            OWNED => "Owned",
+            _ => "Shared",
+        };
+        f.debug_tuple(state).field(&&**self).finish()
+    }
+}
+
+impl<'a, T> From<&'a T> for SharedCow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    fn from(v: &'a T) -> Self {
+        SharedCow::borrowed(v)
+    }
+}
+
+impl<T> From<Box<T>> for SharedCow<'_, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    fn from(v: Box<T>) -> Self {
+        SharedCow::owned(v)
+    }
+}
+
+impl<T> From<Arc<T>> for SharedCow<'_, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    fn from(v: Arc<T>) -> Self {
+        SharedCow::shared(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SharedCow;
+    use std::{boxed::Box, format, mem, ptr, sync::Arc, vec};
+
+    #[test]
+    fn states() {
+        assert_eq!(mem::size_of::<*const u32>(), mem::size_of::<SharedCow<u32>>());
+
+        let v = [1u32, 2];
+        let cow = SharedCow::from(&v[..]);
+        assert!(cow.is_borrowed() && cow.borrowed_ref().is_some());
+        assert_eq!(format!("{:?}", cow.clone()), "Borrowed([1, 2])");
+
+        let cow = SharedCow::owned(Box::new(3u32));
+        let cloned = cow.clone();
+        assert!(cloned.is_owned() && !ptr::eq(&*cow, &*cloned));
+        assert_eq!(format!("{:?}", cloned), "Owned(3)");
+
+        let arc = Arc::new(4u32);
+        let cow = SharedCow::shared(arc.clone());
+        let cloned = cow.clone();
+        assert_eq!(Arc::strong_count(&arc), 3);
+        assert!(ptr::eq(&*cloned, &*arc));
+        assert_eq!(format!("{:?}", cloned), "Shared(4)");
+        drop((cow, cloned));
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn conversions() {
+        let b: Box<[u32]> = vec![1, 2, 3].into_boxed_slice();
+        let arc = SharedCow::owned(b).into_shared();
+        assert_eq!(&*arc, &[1, 2, 3]);
+
+        let mut cow = SharedCow::shared(arc.clone());
+        cow.to_mut()[0] = 4;
+        // the value was shared with `arc`: cloned into a box
+        assert!(cow.is_owned());
+        assert_eq!((&*cow, &*arc), (&[4, 2, 3][..], &[1, 2, 3][..]));
+
+        let addr = arc.as_ptr();
+        let mut cow = SharedCow::shared(arc);
+        cow.to_mut()[1] = 5;
+        // unique: modified in place
+        assert!(cow.is_shared());
+        assert_eq!(cow.as_ptr(), addr);
+        assert_eq!(&*cow.into_shared(), &[1, 5, 3]);
+    }
+
+    #[test]
+    fn zero_sized() {
+        #[repr(align(4))]
+        #[derive(Clone, Debug)]
+        struct Unit;
+
+        let cow = SharedCow::borrowed(&());
+        assert!(cow.is_borrowed() && ptr::eq(cow.borrowed_ref().unwrap(), &()));
+
+        let arc = Arc::new(Unit);
+        let cow = SharedCow::shared(arc.clone());
+        // the address is kept, so that the `Arc` can be rebuilt
+        assert!(ptr::eq(&*cow, &*arc));
+        assert!(Arc::ptr_eq(&cow.clone().into_shared(), &arc));
+        drop(cow);
+        assert_eq!(Arc::strong_count(&arc), 1);
+        assert!(SharedCow::owned(Box::new(Unit)).is_owned());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::PointerValuePair;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    ptr,
+};
+
+/// A pair of a raw slice pointer (`*const [T]`) and an integer value, where the value is stored in the high `BITS`
+/// bits of the length of the slice, in addition to the alignment bits of the pointer.
+///
+/// Slices of types with a small alignment (e.g. `[u8]`) have no spare alignment bits, but realistic slice lengths
+/// never use the high bits of the length. The number of bits available to store the value is
+/// `BITS + PointerValuePair::<[T]>::available_bits()`, and the maximum length of the slices is
+/// `usize::MAX >> BITS` (see `max_len`).
+///
+/// `BITS` must be between 1 and `usize::BITS - 1`, which is checked at compile time.
+#[repr(transparent)]
+pub struct SliceLenPointerValuePair<T, const BITS: u32> {
+    inner: PointerValuePair<[T]>,
+}
+
+impl<T, const BITS: u32> Copy for SliceLenPointerValuePair<T, BITS> {}
+
+impl<T, const BITS: u32> Clone for SliceLenPointerValuePair<T, BITS> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, const BITS: u32> PartialEq for SliceLenPointerValuePair<T, BITS> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T, const BITS: u32> Eq for SliceLenPointerValuePair<T, BITS> {}
+
+impl<T, const BITS: u32> Hash for SliceLenPointerValuePair<T, BITS> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
+    }
+}
+
+impl<T, const BITS: u32> fmt::Debug for SliceLenPointerValuePair<T, BITS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SliceLenPointerValuePair")
+            .field("ptr", &(self.ptr() as *const T))
+            .field("len", &self.len())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<T, const BITS: u32> SliceLenPointerValuePair<T, BITS> {
+    /// Fails to compile if `BITS` is out of range.
+    const CHECK_BITS: () = assert!(
+        BITS >= 1 && BITS < usize::BITS,
+        "the number of length bits must be between 1 and usize::BITS - 1"
+    );
+
+    /// Shift of the high part of the value in the length.
+    const LEN_SHIFT: u32 = usize::BITS - BITS;
+
+    /// Creates a new `SliceLenPointerValuePair` from the given slice pointer and value, or returns `None` if the
+    /// slice is too long or the value doesn't fit in the available bits.
+    pub fn try_new(ptr: *const [T], value: usize) -> Option<SliceLenPointerValuePair<T, BITS>> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CHECK_BITS;
+        let len = ptr.len();
+        if len > Self::max_len() || value > Self::max_value() {
+            return None;
+        }
+        let low_bits = PointerValuePair::<[T]>::available_bits();
+        let low = value & PointerValuePair::<[T]>::max_value();
+        let high = value >> low_bits;
+        let packed_len = len | (high << Self::LEN_SHIFT);
+        Some(SliceLenPointerValuePair {
+            inner: PointerValuePair::new(ptr::slice_from_raw_parts(ptr as *const T, packed_len), low),
+        })
+    }
+
+    /// Creates a new `SliceLenPointerValuePair` from the given slice pointer and value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the slice is greater than `max_len()`, or if the value is greater than
+    /// `max_value()`.
+    pub fn new(ptr: *const [T], value: usize) -> SliceLenPointerValuePair<T, BITS> {
+        Self::try_new(ptr, value).unwrap_or_else(|| {
+            panic!(
+                "slice length ({}) or value ({}) out of range (maximum length {}, maximum value {})",
+                ptr.len(),
+                value,
+                Self::max_len(),
+                Self::max_value()
+            )
+        })
+    }
+
+    /// Returns the slice pointer.
+    pub fn ptr(self) -> *const [T] {
+        ptr::slice_from_raw_parts(self.inner.ptr() as *const T, self.len())
+    }
+
+    /// Returns the length of the slice.
+    pub fn len(self) -> usize {
+        self.inner.len() & Self::max_len()
+    }
+
+    /// Returns whether the slice is empty.
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the value stored alongside the pointer.
+    pub fn value(self) -> usize {
+        let high = self.inner.len() >> Self::LEN_SHIFT;
+        (high << PointerValuePair::<[T]>::available_bits()) | self.inner.value()
+    }
+
+    /// Returns the number of bits available to store the value.
+    pub const fn available_bits() -> u32 {
+        BITS + PointerValuePair::<[T]>::available_bits()
+    }
+
+    /// Returns the maximum (inclusive) integer value that can be stored.
+    pub const fn max_value() -> usize {
+        usize::MAX >> (usize::BITS - Self::available_bits())
+    }
+
+    /// Returns the maximum (inclusive) length of the slices that can be represented.
+    pub const fn max_len() -> usize {
+        usize::MAX >> BITS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SliceLenPointerValuePair;
+    use std::{format, mem};
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(
+            mem::size_of::<*const [u8]>(),
+            mem::size_of::<SliceLenPointerValuePair<u8, 8>>()
+        );
+    }
+
+    #[test]
+    fn bytes() {
+        type Pair = SliceLenPointerValuePair<u8, 8>;
+        assert_eq!(Pair::available_bits(), 8);
+        assert_eq!(Pair::max_value(), 255);
+        assert_eq!(Pair::max_len(), usize::MAX >> 8);
+
+        let s = b"hello";
+        let pv = Pair::new(&s[1..], 0xAB);
+        assert_eq!(pv.len(), 4);
+        assert_eq!(pv.value(), 0xAB);
+        assert_eq!(unsafe { &*pv.ptr() }, b"ello");
+        assert_eq!(
+            format!("{:?}", pv),
+            format!(
+                "SliceLenPointerValuePair {{ ptr: {:?}, len: 4, value: 171 }}",
+                &s[1] as *const u8
+            )
+        );
+        assert!(Pair::try_new(&s[..], 0x100).is_none());
+    }
+
+    #[test]
+    fn combined_with_alignment_bits() {
+        type Pair = SliceLenPointerValuePair<u32, 4>;
+        assert_eq!(Pair::available_bits(), 6);
+
+        let s = [1u32, 2, 3];
+        let pv = Pair::new(&s, 0b10_1101);
+        assert_eq!(pv.value(), 0b10_1101);
+        assert_eq!(unsafe { &*pv.ptr() }, &[1, 2, 3]);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{PointeeAlign, PointerValuePair};
+use alloc::boxed::Box;
+use core::{fmt, marker::PhantomData, mem, mem::MaybeUninit, ops::Deref};
+
+const BORROWED: usize = 0usize;
+const OWNED: usize = 1usize;
+
+/// A pointer-sized object that holds either a borrow (`&'a T`) or an owned value, for `Copy` types.
+///
+/// Values that fit in a pointer (e.g. `u8`, `u32`, or `(u16, u16)`) are always stored inline: since `T` is `Copy`,
+/// copying them is not more expensive than borrowing them, and no allocation is needed to own them. Larger values
+/// are borrowed, or boxed when owned, like `Cow`.
+///
+/// # Notes
+///
+/// `T` must either fit inline, or be aligned to at least 2 bytes, which is checked at compile time when creating
+/// owned `SmallCow`s.
+pub struct SmallCow<'a, T: Copy> {
+    /// Either the inline value, or the bits of a `PointerValuePair<T>`.
+    repr: MaybeUninit<*const T>,
+    _phantom: PhantomData<(&'a T, Box<T>)>,
+}
+
+// SAFETY: a `SmallCow` is either a `T`, a `&'a T` or a `Box<T>`.
+unsafe impl<'a, T: Copy + Send + Sync> Send for SmallCow<'a, T> {}
+// SAFETY: a `&SmallCow` only gives access to a `&T`.
+unsafe impl<'a, T: Copy + Sync> Sync for SmallCow<'a, T> {}
+
+impl<'a, T: Copy> SmallCow<'a, T> {
+    /// Whether the values of type `T` are stored inline.
+    pub const INLINE: bool =
+        mem::size_of::<T>() <= mem::size_of::<*const T>() && mem::align_of::<T>() <= mem::align_of::<*const T>();
+
+    fn from_pair(pv: PointerValuePair<T>) -> SmallCow<'a, T> {
+        SmallCow {
+            repr: MaybeUninit::new(pv.into_raw()),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn inline(v: T) -> SmallCow<'a, T> {
+        let mut repr = MaybeUninit::<*const T>::uninit();
+        unsafe {
+            // SAFETY: `T` fits in a pointer, with a compatible alignment (`INLINE` is true)
+            repr.as_mut_ptr().cast::<T>().write(v);
+        }
+        SmallCow {
+            repr,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the pair holding the pointer, if `T` is not stored inline.
+    fn pair(&self) -> Option<PointerValuePair<T>> {
+        // SAFETY: if `T` is not stored inline, `repr` is initialized with a pair
+        (!Self::INLINE).then(|| PointerValuePair::from_raw(unsafe { self.repr.assume_init() }))
+    }
+
+    /// Creates a new `SmallCow` representing a borrowed value. If `T` is stored inline, the value is copied instead.
+    pub fn borrowed(v: &'a T) -> SmallCow<'a, T> {
+        if Self::INLINE {
+            Self::inline(*v)
+        } else {
+            Self::from_pair(PointerValuePair::new(v, BORROWED))
+        }
+    }
+
+    /// Creates a new `SmallCow` holding the given value, either inline or in a box.
+    pub fn owned(v: T) -> SmallCow<'a, T> {
+        const {
+            assert!(
+                Self::INLINE || T::ALIGN > OWNED,
+                "not enough alignment bits to store the discriminant"
+            )
+        };
+        if Self::INLINE {
+            Self::inline(v)
+        } else {
+            Self::from_pair(PointerValuePair::new(Box::into_raw(Box::new(v)), OWNED))
+        }
+    }
+
+    /// Returns whether this `SmallCow` holds a borrow. This is always false if `T` is stored inline.
+    pub fn is_borrowed(&self) -> bool {
+        self.pair().is_some_and(|pv| pv.value() == BORROWED)
+    }
+
+    /// Returns whether this `SmallCow` holds a boxed value.
+    pub fn is_boxed(&self) -> bool {
+        self.pair().is_some_and(|pv| pv.value() == OWNED)
+    }
+
+    /// Returns a copy of the value.
+    pub fn get(&self) -> T {
+        **self
+    }
+}
+
+impl<'a, T: Copy> Drop for SmallCow<'a, T> {
+    fn drop(&mut self) {
+        if let Some(pv) = self.pair() {
+            validate!(pv.value() <= OWNED, "corrupted SmallCow discriminant");
+            if pv.value() == OWNED {
+                unsafe {
+                    // SAFETY: the pointer has been created with `Box::into_raw` by `SmallCow::owned`.
+                    drop(Box::from_raw(pv.ptr() as *mut T))
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T: Copy> Deref for SmallCow<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self.pair() {
+            // SAFETY: the pointer is either borrowed for `'a`, or points to a box that we own.
+            Some(pv) => unsafe { &*pv.ptr() },
+            // SAFETY: `repr` holds an inline value.
+            None => unsafe { &*self.repr.as_ptr().cast::<T>() },
+        }
+    }
+}
+
+impl<'a, T: Copy> Clone for SmallCow<'a, T> {
+    /// Copies the value if it is inline, copies the borrow, or clones the boxed value into a new box.
+    fn clone(&self) -> Self {
+        if self.is_boxed() {
+            SmallCow::owned(**self)
+        } else {
+            SmallCow {
+                repr: self.repr,
+                _phantom: PhantomData,
+            }
+        }
+    }
+}
+
+impl<'a, T: Copy + fmt::Debug> fmt::Debug for SmallCow<'a, T> {
+    /// Formats the value as `Inline(..)`, `Borrowed(..)` or `Owned(..)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if Self::INLINE {
+            "Inline"
+        } else if self.is_borrowed() {
+            "Borrowed"
+        } else {
+            "Owned"
+        };
+        f.debug_tuple(state).field(&**self).finish()
+    }
+}
+
+impl<'a, T: Copy> From<&'a T> for SmallCow<'a, T> {
+    fn from(v: &'a T) -> Self {
+        SmallCow::borrowed(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SmallCow;
+    use std::{format, mem, ptr};
+
+    #[test]
+    fn inline() {
+        assert_eq!(mem::size_of::<*const u8>(), mem::size_of::<SmallCow<u8>>());
+        const { assert!(SmallCow::<u8>::INLINE && SmallCow::<(u16, u16)>::INLINE && SmallCow::<()>::INLINE) };
+        const { assert!(!SmallCow::<[u64; 2]>::INLINE) };
+
+        let x = 7u8;
+        let cow = SmallCow::borrowed(&x);
+        assert!(!cow.is_borrowed() && !cow.is_boxed());
+        assert_eq!(cow.get(), 7);
+        assert_eq!(format!("{:?}", cow.clone()), "Inline(7)");
+        assert_eq!(*SmallCow::owned(usize::MAX), usize::MAX);
+    }
+
+    #[test]
+    fn large() {
+        let v = [1u64, 2, 3];
+        let cow = SmallCow::from(&v);
+        assert!(cow.is_borrowed());
+        assert!(ptr::eq(&*cow, &v));
+        assert_eq!(format!("{:?}", cow), "Borrowed([1, 2, 3])");
+
+        let cow = SmallCow::owned([4u64; 3]);
+        let cloned = cow.clone();
+        assert!(cow.is_boxed() && cloned.is_boxed());
+        assert!(!ptr::eq(&*cow, &*cloned));
+        assert_eq!(format!("{:?}", cloned), "Owned([4, 4, 4])");
+    }
+}
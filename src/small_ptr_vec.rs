@@ -0,0 +1,276 @@
+use crate::{AlignedPointerValuePair, SumPointer};
+use alloc::{boxed::Box, vec::Vec};
+use core::{fmt, iter::FusedIterator, marker::PhantomData, mem, ptr, slice};
+
+const INLINE: usize = 0usize;
+const HEAP: usize = 1usize;
+
+/// A vector of pointers (`Box<T>`, `Rc<T>`, `Arc<T>` or `&'static T`, see `SumPointer`) that is a single pointer
+/// wide, optimized for zero or one element.
+///
+/// A single element is stored in the word itself, and an empty vector is a null pointer. When a second element is
+/// pushed, the elements are moved to a boxed `Vec`, and the word points to it instead, with a tag bit to distinguish
+/// both states. This is similar to `TinyPtrVector` in LLVM.
+///
+/// # Notes
+///
+/// The pointees must be aligned to at least 2 bytes, which is checked at compile time.
+pub struct SmallPtrVec<P: SumPointer> {
+    /// Either the single element (null if empty), or a pointer to a `Vec<*const P::Target>`.
+    repr: AlignedPointerValuePair<u8, 2>,
+    _phantom: PhantomData<P>,
+}
+
+// SAFETY: `SmallPtrVec<P>` owns its elements, like `Vec<P>`.
+unsafe impl<P: SumPointer + Send> Send for SmallPtrVec<P> {}
+unsafe impl<P: SumPointer + Sync> Sync for SmallPtrVec<P> {}
+
+impl<P: SumPointer> SmallPtrVec<P> {
+    /// Creates a new empty `SmallPtrVec`, which doesn't allocate.
+    pub fn new() -> SmallPtrVec<P> {
+        const {
+            assert!(
+                mem::align_of::<P::Target>() >= 2,
+                "not enough alignment bits to store the tag"
+            )
+        };
+        SmallPtrVec {
+            repr: AlignedPointerValuePair::new(ptr::null(), INLINE),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the boxed vector of elements, if there is one.
+    fn heap(&self) -> Option<&Vec<*const P::Target>> {
+        // SAFETY: the pointer has been created with `Box::into_raw` by `push`
+        (self.repr.value() == HEAP).then(|| unsafe { &*self.repr.ptr().cast::<Vec<*const P::Target>>() })
+    }
+
+    fn heap_mut(&mut self) -> Option<&mut Vec<*const P::Target>> {
+        // SAFETY: the pointer has been created with `Box::into_raw` by `push`, and we borrow `self` mutably
+        (self.repr.value() == HEAP).then(|| unsafe { &mut *(self.repr.ptr() as *mut Vec<*const P::Target>) })
+    }
+
+    /// Returns the single element stored in the word, or null.
+    fn inline(&self) -> *const P::Target {
+        debug_assert_eq!(self.repr.value(), INLINE);
+        self.repr.ptr().cast()
+    }
+
+    /// Returns whether the elements are stored in a heap buffer.
+    pub fn spilled(&self) -> bool {
+        self.repr.value() == HEAP
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        match self.heap() {
+            Some(v) => v.len(),
+            None => usize::from(!self.inline().is_null()),
+        }
+    }
+
+    /// Returns whether the vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends an element, moving the elements to a heap buffer if the vector already holds one element.
+    pub fn push(&mut self, p: P) {
+        let raw = P::into_raw(p);
+        if let Some(v) = self.heap_mut() {
+            v.push(raw);
+            return;
+        }
+        let first = self.inline();
+        self.repr = if first.is_null() {
+            AlignedPointerValuePair::new(raw.cast(), INLINE)
+        } else {
+            let v: Box<Vec<*const P::Target>> = Box::new([first, raw].into());
+            AlignedPointerValuePair::new(Box::into_raw(v).cast(), HEAP)
+        };
+    }
+
+    /// Removes and returns the last element, or returns `None` if the vector is empty.
+    ///
+    /// The heap buffer (if any) is kept, even if it holds a single element afterwards.
+    pub fn pop(&mut self) -> Option<P> {
+        let raw = match self.heap_mut() {
+            Some(v) => v.pop()?,
+            None => {
+                let raw = self.inline();
+                if raw.is_null() {
+                    return None;
+                }
+                self.repr = AlignedPointerValuePair::new(ptr::null(), INLINE);
+                raw
+            }
+        };
+        // SAFETY: the pointer was returned by `into_raw`, and is no longer part of the vector
+        Some(unsafe { P::from_raw(raw) })
+    }
+
+    /// Returns a reference to the pointee of the element at the given index, or `None` if out of bounds.
+    pub fn get(&self, index: usize) -> Option<&P::Target> {
+        self.iter().nth(index)
+    }
+
+    /// Returns an iterator over the pointees of the elements.
+    pub fn iter(&self) -> SmallPtrVecIter<'_, P::Target> {
+        let inner = match self.heap() {
+            Some(v) => IterInner::Heap(v.iter()),
+            // SAFETY: the pointer was returned by `into_raw`, and is valid until dropped with `self`
+            None => IterInner::Inline(unsafe { self.inline().as_ref() }),
+        };
+        SmallPtrVecIter { inner }
+    }
+
+    /// Removes all the elements, and frees the heap buffer (if any).
+    pub fn clear(&mut self) {
+        *self = SmallPtrVec::new();
+    }
+}
+
+impl<P: SumPointer> Drop for SmallPtrVec<P> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+        if self.spilled() {
+            unsafe {
+                // SAFETY: the pointer has been created with `Box::into_raw` by `push`
+                drop(Box::from_raw(self.repr.ptr() as *mut Vec<*const P::Target>));
+            }
+        }
+    }
+}
+
+impl<P: SumPointer> Default for SmallPtrVec<P> {
+    fn default() -> Self {
+        SmallPtrVec::new()
+    }
+}
+
+impl<P: SumPointer> fmt::Debug for SmallPtrVec<P>
+where
+    P::Target: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<P: SumPointer> From<P> for SmallPtrVec<P> {
+    fn from(p: P) -> Self {
+        let mut v = SmallPtrVec::new();
+        v.push(p);
+        v
+    }
+}
+
+impl<P: SumPointer> Extend<P> for SmallPtrVec<P> {
+    fn extend<I: IntoIterator<Item = P>>(&mut self, iter: I) {
+        for p in iter {
+            self.push(p);
+        }
+    }
+}
+
+impl<P: SumPointer> FromIterator<P> for SmallPtrVec<P> {
+    fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
+        let mut v = SmallPtrVec::new();
+        v.extend(iter);
+        v
+    }
+}
+
+impl<'a, P: SumPointer> IntoIterator for &'a SmallPtrVec<P> {
+    type Item = &'a P::Target;
+    type IntoIter = SmallPtrVecIter<'a, P::Target>;
+
+    fn into_iter(self) -> SmallPtrVecIter<'a, P::Target> {
+        self.iter()
+    }
+}
+
+enum IterInner<'a, T> {
+    Inline(Option<&'a T>),
+    Heap(slice::Iter<'a, *const T>),
+}
+
+/// An iterator over the pointees of the elements of a `SmallPtrVec`.
+pub struct SmallPtrVecIter<'a, T> {
+    inner: IterInner<'a, T>,
+}
+
+impl<'a, T> Iterator for SmallPtrVecIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        match &mut self.inner {
+            IterInner::Inline(v) => v.take(),
+            // SAFETY: the pointers were returned by `into_raw`, and are valid while the vector is borrowed
+            IterInner::Heap(it) => it.next().map(|&p| unsafe { &*p }),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> ExactSizeIterator for SmallPtrVecIter<'_, T> {
+    fn len(&self) -> usize {
+        match &self.inner {
+            IterInner::Inline(v) => usize::from(v.is_some()),
+            IterInner::Heap(it) => it.len(),
+        }
+    }
+}
+
+impl<T> FusedIterator for SmallPtrVecIter<'_, T> {}
+
+impl<T> fmt::Debug for SmallPtrVecIter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmallPtrVecIter").field("len", &self.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SmallPtrVec;
+    use std::{boxed::Box, format, mem, rc::Rc, vec::Vec};
+
+    #[test]
+    fn spill() {
+        assert_eq!(mem::size_of::<*const u8>(), mem::size_of::<SmallPtrVec<Box<u32>>>());
+
+        let mut v = SmallPtrVec::new();
+        assert!(v.is_empty() && v.pop().is_none());
+        v.push(Box::new(1u32));
+        assert!(!v.spilled());
+        assert_eq!((v.len(), v.get(0), v.get(1)), (1, Some(&1), None));
+        assert_eq!(format!("{:?}", v), "[1]");
+
+        v.extend([Box::new(2), Box::new(3)]);
+        assert!(v.spilled());
+        assert_eq!(v.iter().copied().collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(v.pop().as_deref(), Some(&3));
+        v.clear();
+        assert!(v.is_empty() && !v.spilled());
+    }
+
+    #[test]
+    fn drops() {
+        let rc = Rc::new(7u64);
+        let v: SmallPtrVec<Rc<u64>> = SmallPtrVec::from(rc.clone());
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(v);
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        let v: SmallPtrVec<_> = (0..3).map(|_| rc.clone()).collect();
+        assert_eq!(Rc::strong_count(&rc), 4);
+        assert_eq!(v.iter().len(), 3);
+        drop(v);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
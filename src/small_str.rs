@@ -0,0 +1,214 @@
+use crate::{inline_str::InlineStr, ThinBox};
+use alloc::string::String;
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    mem::ManuallyDrop,
+    ops::Deref,
+    str,
+};
+
+union Repr {
+    heap: ManuallyDrop<ThinBox<str>>,
+    inline: InlineStr,
+}
+
+/// An immutable string that is a single pointer wide.
+///
+/// Short strings (up to `SmallStr::INLINE_CAPACITY` bytes, i.e. 7 bytes on 64-bit targets) are stored inline, in the
+/// word itself. Longer strings are stored in a `ThinBox<str>`, whose alignment bit distinguishes them from inline
+/// strings.
+///
+/// Comparisons and hashing behave like `str`.
+pub struct SmallStr {
+    repr: Repr,
+}
+
+impl SmallStr {
+    /// The maximum length (in bytes) of the strings stored inline.
+    pub const INLINE_CAPACITY: usize = InlineStr::CAPACITY;
+
+    /// Creates a new `SmallStr`, copying the string to the heap if it's longer than `INLINE_CAPACITY`.
+    pub fn new(s: &str) -> SmallStr {
+        match InlineStr::new(s) {
+            Some(inline) => SmallStr { repr: Repr { inline } },
+            None => SmallStr {
+                repr: Repr {
+                    heap: ManuallyDrop::new(ThinBox::new_str(s, 0)),
+                },
+            },
+        }
+    }
+
+    /// Returns whether the string is stored inline.
+    pub fn is_inline(&self) -> bool {
+        // SAFETY: all bit patterns are valid bytes, and the tag byte holds the low bits of the pointer of heap
+        // strings
+        unsafe { self.repr.inline.is_inline() }
+    }
+
+    /// Returns the string.
+    pub fn as_str(&self) -> &str {
+        unsafe {
+            if self.is_inline() {
+                // SAFETY: the tag is set, so the union holds an inline string
+                self.repr.inline.as_str()
+            } else {
+                // SAFETY: the tag is clear, so the union holds a `ThinBox`
+                &self.repr.heap
+            }
+        }
+    }
+}
+
+impl Drop for SmallStr {
+    fn drop(&mut self) {
+        if !self.is_inline() {
+            // SAFETY: the tag is clear, so the union holds a `ThinBox`
+            unsafe { ManuallyDrop::drop(&mut self.repr.heap) }
+        }
+    }
+}
+
+impl Clone for SmallStr {
+    /// Copies inline strings, and copies heap strings into a new allocation.
+    fn clone(&self) -> Self {
+        SmallStr::new(self)
+    }
+}
+
+impl Deref for SmallStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for SmallStr {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl Borrow<str> for SmallStr {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl Default for SmallStr {
+    fn default() -> Self {
+        SmallStr::new("")
+    }
+}
+
+impl PartialEq for SmallStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SmallStr {}
+
+impl PartialEq<str> for SmallStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SmallStr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialOrd for SmallStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SmallStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for SmallStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Debug for SmallStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for SmallStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl From<&str> for SmallStr {
+    fn from(s: &str) -> Self {
+        SmallStr::new(s)
+    }
+}
+
+impl From<String> for SmallStr {
+    /// Creates a `SmallStr` from a `String`. Long strings are copied into a new allocation.
+    fn from(s: String) -> Self {
+        SmallStr::new(&s)
+    }
+}
+
+impl From<SmallStr> for String {
+    fn from(s: SmallStr) -> Self {
+        String::from(s.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SmallStr;
+    use std::{collections::HashSet, format, mem, string::String, vec::Vec};
+
+    #[test]
+    fn inline_and_heap() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<SmallStr>());
+        let short = "a".repeat(SmallStr::INLINE_CAPACITY);
+        let long = "b".repeat(SmallStr::INLINE_CAPACITY + 1);
+
+        let s = SmallStr::new(&short);
+        assert!(s.is_inline());
+        assert_eq!(s, short.as_str());
+        assert!(SmallStr::default().is_inline() && SmallStr::default().is_empty());
+
+        let s = SmallStr::from(long.clone());
+        assert!(!s.is_inline());
+        assert_eq!(s.clone(), *long);
+        assert_eq!(String::from(s), long);
+
+        let s = SmallStr::new("héé");
+        assert_eq!((s.len(), s.chars().count()), (5, 3));
+        assert_eq!(format!("{:?} {}", s, s), "\"héé\" héé");
+    }
+
+    #[test]
+    fn compare() {
+        let words = ["pear", "apple", "watermelon", "apple", "blueberries"];
+        let mut strs: Vec<_> = words.iter().map(|&w| SmallStr::new(w)).collect();
+        strs.sort();
+        assert_eq!(strs, ["apple", "apple", "blueberries", "pear", "watermelon"]);
+
+        let set: HashSet<SmallStr> = strs.into_iter().collect();
+        assert_eq!(set.len(), 4);
+        assert!(set.contains("watermelon") && set.contains("pear"));
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{AtomicPointerValuePair, PointerValuePair};
+use alloc::boxed::Box;
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    mem::MaybeUninit,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// A node of a `TreiberStack`. The alignment leaves 6 bits for the version counter of the heads.
+#[repr(align(64))]
+struct Node<T> {
+    /// Read concurrently by `pop` even after the node has been popped, hence atomic.
+    next: AtomicPtr<Node<T>>,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// The head of a stack of nodes, with a version counter in the alignment bits, incremented by each update.
+struct Head<T> {
+    head: AtomicPointerValuePair<Node<T>>,
+}
+
+impl<T> Head<T> {
+    fn new() -> Head<T> {
+        Head {
+            head: AtomicPointerValuePair::new(PointerValuePair::new(ptr::null(), 0)),
+        }
+    }
+
+    /// Returns `node` with the version following the one of `head`.
+    fn next_version(head: PointerValuePair<Node<T>>, node: *const Node<T>) -> PointerValuePair<Node<T>> {
+        PointerValuePair::new(node, (head.value() + 1) & PointerValuePair::<Node<T>>::max_value())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Relaxed).ptr().is_null()
+    }
+
+    fn push(&self, node: *mut Node<T>) {
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            // SAFETY: the node is owned by the caller until the exchange succeeds
+            unsafe { (*node).next.store(head.ptr() as *mut Node<T>, Ordering::Relaxed) };
+            match self.head.compare_exchange_weak(
+                head,
+                Self::next_version(head, node),
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    fn pop(&self) -> Option<*mut Node<T>> {
+        let mut head = self.head.load(Ordering::Acquire);
+        loop {
+            let node = head.ptr();
+            if node.is_null() {
+                return None;
+            }
+            // SAFETY: nodes are only freed when the stack is dropped, so `node` is valid even if another thread has
+            // popped it in the meantime. In that case, `next` may be stale, but the version has changed and the
+            // exchange fails.
+            let next = unsafe { (*node).next.load(Ordering::Relaxed) };
+            match self.head.compare_exchange_weak(
+                head,
+                Self::next_version(head, next),
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(node as *mut Node<T>),
+                Err(current) => head = current,
+            }
+        }
+    }
+}
+
+/// A lock-free stack (Treiber stack), whose head pointer holds a version counter in its alignment bits to prevent
+/// the ABA problem.
+///
+/// `push` and `pop` only need a shared reference, and never block: they retry a compare-and-swap on the head until
+/// it succeeds. Each successful update increments the version, so a thread that read the head before a concurrent
+/// pop and push of the same node sees the exchange fail instead of corrupting the stack.
+///
+/// # Notes
+///
+/// - The nodes are aligned to 64 bytes, so the version is 6 bits wide and wraps around: the ABA problem can still
+///   occur if a thread is suspended between reading the head and updating it while the other threads perform
+///   exactly a multiple of 64 updates that restore the same head node.
+/// - Popped nodes are not freed, but kept in a free list and reused by `push`, so that threads that have read a
+///   stale head can still safely read it. The memory of the nodes is only released when the stack is dropped, so
+///   it is bounded by the maximum number of elements in the stack at any time.
+pub struct TreiberStack<T> {
+    items: Head<T>,
+    free: Head<T>,
+}
+
+// SAFETY: values are moved in and out of the stack, but never shared between threads.
+unsafe impl<T: Send> Send for TreiberStack<T> {}
+unsafe impl<T: Send> Sync for TreiberStack<T> {}
+
+impl<T> TreiberStack<T> {
+    /// Creates a new empty stack.
+    pub fn new() -> TreiberStack<T> {
+        TreiberStack {
+            items: Head::new(),
+            free: Head::new(),
+        }
+    }
+
+    /// Returns whether the stack is empty. The result may be outdated by the time it is returned, if other threads
+    /// access the stack.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Pushes a value on top of the stack.
+    pub fn push(&self, value: T) {
+        let node = self.free.pop().unwrap_or_else(|| {
+            Box::into_raw(Box::new(Node {
+                next: AtomicPtr::new(ptr::null_mut()),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }))
+        });
+        // SAFETY: the node is not in any stack, and other threads only read its `next` field
+        unsafe { (*node).value.get().write(MaybeUninit::new(value)) };
+        self.items.push(node);
+    }
+
+    /// Removes the value on top of the stack and returns it, or returns `None` if the stack is empty.
+    pub fn pop(&self) -> Option<T> {
+        let node = self.items.pop()?;
+        // SAFETY: the value has been initialized by `push`, and this thread has popped the node
+        let value = unsafe { (*node).value.get().read().assume_init() };
+        self.free.push(node);
+        Some(value)
+    }
+}
+
+impl<T> Drop for TreiberStack<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+        while let Some(node) = self.free.pop() {
+            // SAFETY: the nodes have been allocated by `push`, and their value has been moved out by `pop`
+            unsafe { drop(Box::from_raw(node)) }
+        }
+    }
+}
+
+impl<T> Default for TreiberStack<T> {
+    fn default() -> Self {
+        TreiberStack::new()
+    }
+}
+
+impl<T> fmt::Debug for TreiberStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TreiberStack").field("empty", &self.is_empty()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TreiberStack;
+    use std::{rc::Rc, sync::Mutex, thread, vec::Vec};
+
+    #[test]
+    fn lifo() {
+        let stack = TreiberStack::new();
+        assert!(stack.is_empty() && stack.pop().is_none());
+        for i in 0..3 {
+            stack.push(i);
+        }
+        assert_eq!([stack.pop(), stack.pop()], [Some(2), Some(1)]);
+        stack.push(3);
+        assert_eq!([stack.pop(), stack.pop(), stack.pop()], [Some(3), Some(0), None]);
+
+        let rc = Rc::new(());
+        let stack = TreiberStack::new();
+        stack.push(rc.clone());
+        stack.push(rc.clone());
+        drop(stack.pop());
+        drop(stack);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn concurrent() {
+        const THREADS: usize = 4;
+        const COUNT: usize = 1000;
+        let stack = TreiberStack::new();
+        let popped = Mutex::new(Vec::new());
+        thread::scope(|s| {
+            for t in 0..THREADS {
+                let (stack, popped) = (&stack, &popped);
+                s.spawn(move || {
+                    let mut local = Vec::new();
+                    for i in 0..COUNT {
+                        stack.push(t * COUNT + i);
+                        if i % 2 == 0 {
+                            local.extend(stack.pop());
+                        }
+                    }
+                    popped.lock().unwrap().extend(local);
+                });
+            }
+        });
+        let mut all = popped.into_inner().unwrap();
+        all.extend(core::iter::from_fn(|| stack.pop()));
+        all.sort();
+        assert_eq!(all, (0..THREADS * COUNT).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{Cow, CowPointee, PointerValuePair, PointerValuePairAccess, ToBoxed};
+use alloc::boxed::Box;
+use core::{fmt, marker::PhantomData, mem, ops::Deref};
+
+const BORROWED: usize = 0usize;
+const OWNED: usize = 1usize;
+const STATIC: usize = 2usize;
+
+/// A pointer-sized object that holds either a borrow (`&'a T`), a boxed value (`Box<T>`), or a static reference
+/// (`&'static T`).
+///
+/// Unlike `Cow`, it remembers whether a borrow is static, so that it can be converted into a
+/// `StaticCow<'static, T>` without cloning static data (see `into_static`).
+///
+/// # Notes
+///
+/// Two bits are needed to store the state, so `T` (or the element type, for slices) must be aligned to at least
+/// 4 bytes, which is checked at compile time when creating owned or static `StaticCow`s. In particular, string
+/// slices are not supported, since there is only one spare bit in a `*const str`.
+#[repr(transparent)]
+pub struct StaticCow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    inner: PointerValuePair<T>,
+    _phantom: PhantomData<(&'a T, Box<T>)>,
+}
+
+// SAFETY: a `StaticCow` is either a `&'a T` or a `Box<T>`.
+unsafe impl<'a, T> Send for StaticCow<'a, T>
+where
+    T: ?Sized + CowPointee + Send + Sync,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+}
+
+// SAFETY: a `&StaticCow` only gives access to a `&T`.
+unsafe impl<'a, T> Sync for StaticCow<'a, T>
+where
+    T: ?Sized + CowPointee + Sync,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+}
+
+impl<'a, T> StaticCow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Creates a new `StaticCow` representing a borrowed value.
+    pub fn borrowed(v: &'a T) -> StaticCow<'a, T> {
+        StaticCow {
+            inner: T::pack(v, BORROWED),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new `StaticCow` holding a boxed value.
+    pub fn owned(v: Box<T>) -> StaticCow<'a, T> {
+        const { assert!(T::MAX_VALUE >= STATIC, "not enough alignment bits to store the state") };
+        StaticCow {
+            inner: T::pack(Box::into_raw(v), OWNED),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new `StaticCow` holding a static reference.
+    pub fn from_static(v: &'static T) -> StaticCow<'a, T> {
+        const { assert!(T::MAX_VALUE >= STATIC, "not enough alignment bits to store the state") };
+        StaticCow {
+            inner: T::pack(v, STATIC),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns whether this `StaticCow` holds a non-static borrow.
+    pub fn is_borrowed(&self) -> bool {
+        self.inner.value() == BORROWED
+    }
+
+    /// Returns whether this `StaticCow` holds a boxed value.
+    pub fn is_owned(&self) -> bool {
+        self.inner.value() == OWNED
+    }
+
+    /// Returns whether this `StaticCow` holds a static reference.
+    pub fn is_static(&self) -> bool {
+        self.inner.value() == STATIC
+    }
+
+    /// Returns the static reference if this `StaticCow` holds one.
+    pub fn static_ref(&self) -> Option<&'static T> {
+        // SAFETY: the pointer comes from a `&'static T` passed to `from_static`.
+        self.is_static()
+            .then(|| unsafe { &*PointerValuePairAccess::ptr(self.inner) })
+    }
+
+    /// Returns the borrowed or static reference, or `None` if this `StaticCow` is owned.
+    pub fn borrowed_ref(&self) -> Option<&'a T> {
+        // SAFETY: the pointer comes from a `&'a T` or a `&'static T`.
+        (!self.is_owned()).then(|| unsafe { &*PointerValuePairAccess::ptr(self.inner) })
+    }
+
+    /// Converts this `StaticCow` into a `Box<T>`. If this `StaticCow` is not owned, clones the value and boxes it.
+    pub fn into_owned(self) -> Box<T>
+    where
+        T: ToBoxed,
+    {
+        if self.is_owned() {
+            let ptr = self.inner.mut_ptr();
+            // ownership is transferred to the returned box
+            mem::forget(self);
+            unsafe {
+                // SAFETY: the pointer has been created with `Box::into_raw` by `StaticCow::owned`.
+                Box::from_raw(ptr)
+            }
+        } else {
+            T::to_boxed(&self)
+        }
+    }
+
+    /// Converts this into a `StaticCow` that doesn't borrow non-static data, by cloning the value into a box only if
+    /// it is borrowed. Static references and boxed values are kept as is.
+    pub fn into_static(self) -> StaticCow<'static, T>
+    where
+        T: ToBoxed,
+    {
+        match self.static_ref() {
+            Some(v) => StaticCow::from_static(v),
+            None => StaticCow::owned(self.into_owned()),
+        }
+    }
+}
+
+impl<'a, T> Drop for StaticCow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    fn drop(&mut self) {
+        validate!(self.inner.value() <= STATIC, "corrupted StaticCow state");
+        if self.is_owned() {
+            unsafe {
+                // SAFETY: the pointer has been created with `Box::into_raw` by `StaticCow::owned`.
+                drop(Box::from_raw(self.inner.mut_ptr()))
+            }
+        }
+    }
+}
+
+impl<'a, T> Deref for StaticCow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the pointer is either borrowed for `'a` or `'static`, or points to a box that we own.
+        unsafe { &*PointerValuePairAccess::ptr(self.inner) }
+    }
+}
+
+impl<'a, T> Clone for StaticCow<'a, T>
+where
+    T: ?Sized + CowPointee + ToBoxed,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Copies the reference if this is a borrow or a static reference, or clones the boxed value into a new box.
+    fn clone(&self) -> Self {
+        if self.is_owned() {
+            StaticCow::owned(T::to_boxed(self))
+        } else {
+            StaticCow {
+                inner: self.inner,
+                _phantom: PhantomData,
+            }
+        }
+    }
+}
+
+impl<'a, T> fmt::Debug for StaticCow<'a, T>
+where
+    T: ?Sized + CowPointee + fmt::Debug,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Formats the value as `Borrowed(..)`, `Owned(..)` or `Static(..)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.inner.value() {
+            BORROWED => "Borrowed",
+            OWNED => "Owned",
+            _ => "Static",
+        };
+        f.debug_tuple(state).field(&&**self).finish()
+    }
+}
+
+impl<'a, T> From<&'a T> for StaticCow<'a, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Creates a borrowed `StaticCow`. Use `StaticCow::from_static` for static references.
+    fn from(v: &'a T) -> Self {
+        StaticCow::borrowed(v)
+    }
+}
+
+impl<T> From<Box<T>> for StaticCow<'_, T>
+where
+    T: ?Sized + CowPointee,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    fn from(v: Box<T>) -> Self {
+        StaticCow::owned(v)
+    }
+}
+
+impl<'a, T> From<Cow<'a, T>> for StaticCow<'a, T>
+where
+    T: ?Sized + CowPointee + ToBoxed,
+    PointerValuePair<T>: PointerValuePairAccess<Target = T>,
+{
+    /// Converts a `Cow` into a borrowed or owned `StaticCow`, without cloning.
+    fn from(v: Cow<'a, T>) -> Self {
+        match v.borrowed_ref() {
+            Some(v) => StaticCow::borrowed(v),
+            None => StaticCow::owned(v.into_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cow, StaticCow};
+    use std::{boxed::Box, format, mem, ptr};
+
+    static DEFAULTS: [u32; 3] = [1, 2, 3];
+
+    #[test]
+    fn states() {
+        assert_eq!(mem::size_of::<*const u32>(), mem::size_of::<StaticCow<u32>>());
+
+        let s = StaticCow::<[u32]>::from_static(&DEFAULTS);
+        assert!(s.is_static() && s.static_ref().is_some() && s.borrowed_ref().is_some());
+        assert_eq!(format!("{:?}", s.clone()), "Static([1, 2, 3])");
+
+        let v = [4u32, 5];
+        let b = StaticCow::borrowed(&v[..]);
+        assert!(b.is_borrowed() && b.static_ref().is_none());
+        assert_eq!(format!("{:?}", b), "Borrowed([4, 5])");
+
+        let o = StaticCow::owned(Box::new(6u64));
+        assert!(o.is_owned() && o.borrowed_ref().is_none());
+        assert_eq!(format!("{:?}", o.clone()), "Owned(6)");
+    }
+
+    #[test]
+    fn into_static() {
+        fn keep(v: StaticCow<'_, [u32]>) -> StaticCow<'static, [u32]> {
+            v.into_static()
+        }
+
+        let s = keep(StaticCow::from_static(&DEFAULTS));
+        assert!(s.is_static());
+        assert!(ptr::eq(&*s, &DEFAULTS[..]));
+
+        let v = [4u32, 5];
+        let b = keep(StaticCow::borrowed(&v[..]));
+        assert!(b.is_owned());
+        assert_eq!(&*b, &[4, 5]);
+
+        let o = StaticCow::<[u32]>::from(Cow::<[u32]>::from(&[7u32][..]).into_owned_cow());
+        let addr = o.as_ptr();
+        let o = keep(o);
+        assert!(o.is_owned());
+        assert_eq!(o.as_ptr(), addr);
+    }
+}
@@ -0,0 +1,276 @@
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+use alloc::sync::Arc;
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, rc::Rc};
+
+/// Pointer types that can be stored in the variants of a type generated by `pointer_sum_type!`.
+///
+/// # Safety
+///
+/// `into_raw` must return a non-null pointer, aligned to `mem::align_of::<Self::Target>()`, that is valid for shared
+/// accesses until it is passed to `drop_raw`.
+pub unsafe trait SumPointer {
+    /// The pointee type.
+    type Target;
+
+    /// Converts the pointer into a raw pointer, transferring its ownership (if any) to the raw pointer.
+    fn into_raw(self) -> *const Self::Target;
+
+    /// Converts a raw pointer returned by `into_raw` back into the pointer, transferring the ownership back.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `into_raw`, and must not be used afterwards.
+    unsafe fn from_raw(ptr: *const Self::Target) -> Self;
+
+    /// Releases the ownership of a raw pointer returned by `into_raw`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `into_raw`, and must not be used afterwards.
+    unsafe fn drop_raw(ptr: *const Self::Target);
+}
+
+unsafe impl<T> SumPointer for &'static T {
+    type Target = T;
+
+    fn into_raw(self) -> *const T {
+        self
+    }
+
+    unsafe fn from_raw(ptr: *const T) -> Self {
+        &*ptr
+    }
+
+    unsafe fn drop_raw(_ptr: *const T) {}
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<T> SumPointer for Box<T> {
+    type Target = T;
+
+    fn into_raw(self) -> *const T {
+        Box::into_raw(self)
+    }
+
+    unsafe fn from_raw(ptr: *const T) -> Self {
+        Box::from_raw(ptr as *mut T)
+    }
+
+    unsafe fn drop_raw(ptr: *const T) {
+        drop(Box::from_raw(ptr as *mut T))
+    }
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<T> SumPointer for Rc<T> {
+    type Target = T;
+
+    fn into_raw(self) -> *const T {
+        Rc::into_raw(self)
+    }
+
+    unsafe fn from_raw(ptr: *const T) -> Self {
+        Rc::from_raw(ptr)
+    }
+
+    unsafe fn drop_raw(ptr: *const T) {
+        drop(Rc::from_raw(ptr))
+    }
+}
+
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+unsafe impl<T> SumPointer for Arc<T> {
+    type Target = T;
+
+    fn into_raw(self) -> *const T {
+        Arc::into_raw(self)
+    }
+
+    unsafe fn from_raw(ptr: *const T) -> Self {
+        Arc::from_raw(ptr)
+    }
+
+    unsafe fn drop_raw(ptr: *const T) {
+        drop(Arc::from_raw(ptr))
+    }
+}
+
+/// Returns the smallest of the given alignments.
+#[doc(hidden)]
+pub const fn min_align(aligns: &[usize]) -> usize {
+    let mut min = usize::MAX;
+    let mut i = 0;
+    while i < aligns.len() {
+        if aligns[i] < min {
+            min = aligns[i];
+        }
+        i += 1;
+    }
+    min
+}
+
+/// Declares a one-word sum type whose variants each hold a pointer (`Box<T>`, `Rc<T>`, `Arc<T>` or `&'static T`,
+/// see `SumPointer`), with the discriminant stored in the alignment bits.
+///
+/// For each variant, this generates a constructor function with the name of the variant, and an accessor with the
+/// given name that returns a reference to the pointee if the variant is active. It also generates a fieldless enum
+/// (named after the `:`) returned by `kind()`, to `match` on the active variant. Owning pointers are dropped with
+/// the sum type.
+///
+/// It is checked at compile time that the least aligned pointee type has enough spare bits for all the
+/// discriminants.
+///
+/// ```ignore
+/// pointer_sum_type! {
+///     pub enum Expr: ExprKind {
+///         Lit(Box<Lit>) => as_lit,
+///         Binary(Box<Binary>) => as_binary,
+///         Builtin(&'static Builtin) => as_builtin,
+///     }
+/// }
+///
+/// let e = Expr::Lit(Box::new(Lit(42)));
+/// match e.kind() {
+///     ExprKind::Lit => println!("{}", e.as_lit().unwrap().0),
+///     _ => {}
+/// }
+/// ```
+#[macro_export]
+macro_rules! pointer_sum_type {
+    (@align $($ptr:ty),+) => {
+        $crate::__private::min_align(&[$(::core::mem::align_of::<<$ptr as $crate::SumPointer>::Target>()),+])
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident : $kind:ident {
+            $($(#[$vattr:meta])* $variant:ident($ptr:ty) => $as_variant:ident),+ $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            repr: $crate::AlignedPointerValuePair<u8, { $crate::pointer_sum_type!(@align $($ptr),+) }>,
+        }
+
+        #[doc = concat!("The variants of `", stringify!($name), "`.")]
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        $vis enum $kind {
+            $($variant),+
+        }
+
+        const _: () = assert!(
+            [$($kind::$variant),+].len() <= $crate::pointer_sum_type!(@align $($ptr),+),
+            "not enough alignment bits to store the discriminant"
+        );
+
+        impl $name {
+            $(
+                $(#[$vattr])*
+                #[allow(non_snake_case)]
+                $vis fn $variant(ptr: $ptr) -> $name {
+                    $name {
+                        repr: $crate::AlignedPointerValuePair::new(
+                            <$ptr as $crate::SumPointer>::into_raw(ptr).cast(),
+                            $kind::$variant as usize,
+                        ),
+                    }
+                }
+
+                #[doc = concat!("Returns the pointee if this is a `", stringify!($variant), "`.")]
+                $vis fn $as_variant(&self) -> ::core::option::Option<&<$ptr as $crate::SumPointer>::Target> {
+                    if self.kind() == $kind::$variant {
+                        // SAFETY: the pointer was returned by `into_raw`, and is valid until dropped with `self`
+                        ::core::option::Option::Some(unsafe { &*self.repr.ptr().cast() })
+                    } else {
+                        ::core::option::Option::None
+                    }
+                }
+            )+
+
+            /// Returns the active variant.
+            $vis fn kind(&self) -> $kind {
+                let tag = self.repr.value();
+                $(
+                    if tag == $kind::$variant as usize {
+                        return $kind::$variant;
+                    }
+                )+
+                ::core::unreachable!()
+            }
+        }
+
+        impl ::core::ops::Drop for $name {
+            fn drop(&mut self) {
+                let ptr = self.repr.ptr();
+                match self.kind() {
+                    $(
+                        // SAFETY: the pointer was returned by `into_raw` for this variant
+                        $kind::$variant => unsafe { <$ptr as $crate::SumPointer>::drop_raw(ptr.cast()) },
+                    )+
+                }
+            }
+        }
+
+        impl ::core::fmt::Debug for $name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.debug_struct(::core::stringify!($name))
+                    .field("kind", &self.kind())
+                    .field("ptr", &self.repr.ptr().cast::<()>())
+                    .finish()
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{boxed::Box, format, mem, rc::Rc};
+
+    struct Lit(u64);
+    struct Builtin(&'static str);
+
+    static PRINT: Builtin = Builtin("print");
+
+    pointer_sum_type! {
+        enum Expr: ExprKind {
+            Lit(Box<Lit>) => as_lit,
+            Shared(Rc<Lit>) => as_shared,
+            Builtin(&'static Builtin) => as_builtin,
+        }
+    }
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(mem::size_of::<*const u8>(), mem::size_of::<Expr>());
+    }
+
+    #[test]
+    fn variants() {
+        let e = Expr::Lit(Box::new(Lit(42)));
+        assert_eq!(e.kind(), ExprKind::Lit);
+        assert_eq!(e.as_lit().unwrap().0, 42);
+        assert!(e.as_builtin().is_none());
+
+        let e = Expr::Builtin(&PRINT);
+        assert_eq!(e.kind(), ExprKind::Builtin);
+        assert_eq!(e.as_builtin().unwrap().0, "print");
+        assert!(e.as_lit().is_none() && e.as_shared().is_none());
+        assert_eq!(
+            format!("{:?}", e),
+            format!(
+                "Expr {{ kind: Builtin, ptr: {:?} }}",
+                &PRINT as *const Builtin as *const ()
+            )
+        );
+    }
+
+    #[test]
+    fn drops() {
+        let rc = Rc::new(Lit(1));
+        let e = Expr::Shared(rc.clone());
+        assert_eq!(Rc::strong_count(&rc), 2);
+        assert_eq!(e.as_shared().unwrap().0, 1);
+        drop(e);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+}
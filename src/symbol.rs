@@ -0,0 +1,179 @@
+use crate::inline_str::InlineStr;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+#[derive(Copy, Clone)]
+union Repr {
+    interned: &'static &'static str,
+    inline: InlineStr,
+}
+
+/// Interns the strings of the `Symbol`s that are too long to be stored inline.
+///
+/// `intern` must return the same reference for equal strings, since symbols are compared by address. The strings
+/// are returned through a `&'static &'static str` so that the symbols only hold a thin pointer.
+pub trait Interner {
+    /// Returns the interned copy of the string.
+    fn intern(&self, s: &str) -> &'static &'static str;
+}
+
+/// The interner used by `Symbol::new`, a global set of leaked strings.
+///
+/// Only available with the `std` feature.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct GlobalInterner;
+
+#[cfg(feature = "std")]
+impl Interner for GlobalInterner {
+    fn intern(&self, s: &str) -> &'static &'static str {
+        use std::{
+            boxed::Box,
+            collections::HashMap,
+            sync::{Mutex, OnceLock},
+        };
+
+        static STRINGS: OnceLock<Mutex<HashMap<&'static str, &'static &'static str>>> = OnceLock::new();
+        let mut strings = STRINGS.get_or_init(Default::default).lock().unwrap();
+        if let Some(&interned) = strings.get(s) {
+            return interned;
+        }
+        let string: &'static str = Box::leak(Box::from(s));
+        let interned: &'static &'static str = Box::leak(Box::new(string));
+        strings.insert(interned, interned);
+        interned
+    }
+}
+
+/// An interned string that is a single pointer wide, with O(1) equality and hashing.
+///
+/// Short strings (up to `Symbol::INLINE_CAPACITY` bytes, i.e. 7 bytes on 64-bit targets) are stored inline, in the
+/// word itself, like `SmallStr`. Longer strings are stored in an `Interner`, and the symbol holds a pointer to the
+/// interned string, whose alignment bit distinguishes it from inline symbols. Either way, equal strings give equal
+/// words, so comparing and hashing symbols doesn't look at the strings. Symbols created with different interners
+/// must not be compared.
+#[derive(Copy, Clone)]
+pub struct Symbol {
+    repr: Repr,
+}
+
+impl Symbol {
+    /// The maximum length (in bytes) of the strings stored inline.
+    pub const INLINE_CAPACITY: usize = InlineStr::CAPACITY;
+
+    /// Creates a new `Symbol`, interning the string in the `GlobalInterner` if it's longer than `INLINE_CAPACITY`.
+    #[cfg(feature = "std")]
+    pub fn new(s: &str) -> Symbol {
+        Symbol::with_interner(s, &GlobalInterner)
+    }
+
+    /// Creates a new `Symbol`, interning the string in the given interner if it's longer than `INLINE_CAPACITY`.
+    pub fn with_interner(s: &str, interner: &impl Interner) -> Symbol {
+        match InlineStr::new(s) {
+            Some(inline) => Symbol { repr: Repr { inline } },
+            None => Symbol {
+                repr: Repr {
+                    interned: interner.intern(s),
+                },
+            },
+        }
+    }
+
+    /// Returns whether the string is stored inline.
+    pub fn is_inline(&self) -> bool {
+        self.word().is_inline()
+    }
+
+    /// Returns the string.
+    pub fn as_str(&self) -> &str {
+        unsafe {
+            if self.is_inline() {
+                // SAFETY: the tag is set, so the union holds an inline string
+                self.repr.inline.as_str()
+            } else {
+                // SAFETY: the tag is clear, so the union holds a reference
+                self.repr.interned
+            }
+        }
+    }
+
+    /// Returns the word, as an inline string or the bytes of the pointer to the interned string.
+    fn word(&self) -> InlineStr {
+        // SAFETY: all bit patterns are valid bytes
+        unsafe { self.repr.inline }
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        self.word() == other.word()
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.word().hash(state)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Interner, Symbol};
+    use std::{boxed::Box, cell::RefCell, format, mem, vec::Vec};
+
+    /// Interns the strings in a list, with a linear search.
+    #[derive(Default)]
+    struct ListInterner(RefCell<Vec<&'static &'static str>>);
+
+    impl Interner for ListInterner {
+        fn intern(&self, s: &str) -> &'static &'static str {
+            let mut strings = self.0.borrow_mut();
+            if let Some(&interned) = strings.iter().find(|&&i| *i == s) {
+                return interned;
+            }
+            let string: &'static str = Box::leak(Box::from(s));
+            let interned: &'static &'static str = Box::leak(Box::new(string));
+            strings.push(interned);
+            interned
+        }
+    }
+
+    #[test]
+    fn custom_interner() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<Symbol>());
+        let interner = ListInterner::default();
+        let short = Symbol::with_interner("x", &interner);
+        let long = Symbol::with_interner("a_long_identifier", &interner);
+        assert!(short.is_inline() && !long.is_inline());
+        assert_eq!((short.as_str(), long.as_str()), ("x", "a_long_identifier"));
+        assert_eq!(long, Symbol::with_interner("a_long_identifier", &interner));
+        assert_ne!(long, Symbol::with_interner("another_identifier", &interner));
+        assert_eq!(interner.0.borrow().len(), 2);
+        assert_eq!(format!("{:?} {}", short, long), "\"x\" a_long_identifier");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn global_interner() {
+        let a = Symbol::new("global_symbol");
+        assert_eq!(a, Symbol::new(&format!("global_{}", "symbol")));
+        assert_eq!(Symbol::new(""), Symbol::new(""));
+        assert_ne!(Symbol::new("a"), Symbol::new("b"));
+    }
+}
@@ -0,0 +1,408 @@
+use crate::{PointeeAlign, PointerValuePair};
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+/// Types that can be stored in the spare bits of a pointer, in a `TaggedPtr`.
+///
+/// Typically implemented by fieldless enums.
+pub trait TagValue: Copy {
+    /// The number of bits needed to represent all values of this type.
+    const BITS: u32;
+
+    /// Converts the value into bits. The result must fit in `BITS` bits.
+    fn into_bits(self) -> usize;
+
+    /// Converts bits back into a value.
+    ///
+    /// This is only called with bits returned by `into_bits`, and may panic otherwise.
+    fn from_bits(bits: usize) -> Self;
+}
+
+impl TagValue for bool {
+    const BITS: u32 = 1;
+
+    fn into_bits(self) -> usize {
+        self as usize
+    }
+
+    fn from_bits(bits: usize) -> Self {
+        bits != 0
+    }
+}
+
+/// Implements `TagValue` for types generated by the `bitflags!` macro, so that they can be used as the tag of a
+/// `TaggedPtr`.
+///
+/// The number of bits of the tag is computed from the highest defined flag, so it is checked at compile time that
+/// all flags fit in the spare bits of the pointer. `TaggedPtr::new` panics if the tag contains unknown bits beyond
+/// that.
+///
+/// Only available with the `bitflags` feature.
+///
+/// ```ignore
+/// bitflags::bitflags! {
+///     #[derive(Copy, Clone)]
+///     struct NodeFlags: u8 {
+///         const DIRTY = 1;
+///         const VISIBLE = 2;
+///     }
+/// }
+/// pointer_value_pair::impl_tag_value_for_bitflags!(NodeFlags);
+/// ```
+#[cfg(feature = "bitflags")]
+#[macro_export]
+macro_rules! impl_tag_value_for_bitflags {
+    ($($t:ty),+ $(,)?) => {$(
+        impl $crate::TagValue for $t {
+            const BITS: u32 = usize::BITS - (<$t>::all().bits() as usize).leading_zeros();
+
+            fn into_bits(self) -> usize {
+                $crate::__private::bitflags::Flags::bits(&self) as usize
+            }
+
+            fn from_bits(bits: usize) -> Self {
+                <$t as $crate::__private::bitflags::Flags>::from_bits_retain(bits as _)
+            }
+        }
+    )+};
+}
+
+/// A pair of a raw pointer and a typed tag value, packed so that it takes the size of a pointer.
+///
+/// This is the same as `PointerValuePair<T>`, but the value is of type `V` instead of `usize`.
+/// It is checked at compile time that `*const T` has enough spare bits to store `V::BITS` bits.
+#[repr(transparent)]
+pub struct TaggedPtr<T: ?Sized, V> {
+    inner: PointerValuePair<T>,
+    _phantom: PhantomData<V>,
+}
+
+impl<T: ?Sized, V> Copy for TaggedPtr<T, V> {}
+
+impl<T: ?Sized, V> Clone for TaggedPtr<T, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized, V> PartialEq for TaggedPtr<T, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T: ?Sized, V> Eq for TaggedPtr<T, V> {}
+
+impl<T: ?Sized, V> Hash for TaggedPtr<T, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
+    }
+}
+
+impl<T: ?Sized + PointeeAlign, V: TagValue + fmt::Debug> fmt::Debug for TaggedPtr<T, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaggedPtr")
+            .field("ptr", &self.ptr().cast::<()>())
+            .field("tag", &self.tag())
+            .finish()
+    }
+}
+
+impl<T: ?Sized + PointeeAlign, V: TagValue> TaggedPtr<T, V> {
+    /// Fails to compile if `*const T` doesn't have enough spare bits to store `V`.
+    const CHECK_BITS: () = assert!(
+        V::BITS <= PointerValuePair::<T>::available_bits(),
+        "not enough alignment bits to store the tag type"
+    );
+
+    /// Creates a new `TaggedPtr` from the given raw pointer and tag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tag.into_bits()` doesn't fit in `V::BITS` bits.
+    pub fn new(ptr: *const T, tag: V) -> TaggedPtr<T, V> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CHECK_BITS;
+        let bits = tag.into_bits();
+        assert!(
+            bits >> V::BITS == 0,
+            "tag bits ({:#x}) do not fit in {} bits",
+            bits,
+            V::BITS
+        );
+        TaggedPtr {
+            inner: PointerValuePair::new(ptr, bits),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the pointer.
+    pub fn ptr(self) -> *const T {
+        self.inner.ptr()
+    }
+
+    /// Returns the tag stored alongside the pointer.
+    pub fn tag(self) -> V {
+        validate!(
+            self.inner.value() >> V::BITS == 0,
+            "corrupted tag bits ({:#x})",
+            self.inner.value()
+        );
+        V::from_bits(self.inner.value())
+    }
+
+    /// Returns the underlying `PointerValuePair`, with the tag converted to bits.
+    pub fn into_pair(self) -> PointerValuePair<T> {
+        self.inner
+    }
+}
+
+/// A raw pointer with a boolean flag packed in its lowest alignment bit.
+///
+/// `*const T` must have at least one spare bit, which is checked at compile time.
+pub type FlaggedPtr<T> = TaggedPtr<T, bool>;
+
+impl<T: ?Sized + PointeeAlign> TaggedPtr<T, bool> {
+    /// Returns the flag stored alongside the pointer.
+    pub fn flag(self) -> bool {
+        self.tag()
+    }
+
+    /// Sets the flag stored alongside the pointer.
+    pub fn set_flag(&mut self, flag: bool) {
+        *self = TaggedPtr::new(self.ptr(), flag);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FlaggedPtr, TagValue, TaggedPtr};
+    use std::mem;
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    enum Color {
+        Red,
+        Green,
+        Blue,
+    }
+
+    impl TagValue for Color {
+        const BITS: u32 = 2;
+
+        fn into_bits(self) -> usize {
+            self as usize
+        }
+
+        fn from_bits(bits: usize) -> Self {
+            match bits {
+                0 => Color::Red,
+                1 => Color::Green,
+                2 => Color::Blue,
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(mem::size_of::<*const i32>(), mem::size_of::<TaggedPtr<i32, Color>>());
+    }
+
+    #[test]
+    fn typed_tags() {
+        let pointee = 42u32;
+        let tp = TaggedPtr::new(&pointee, Color::Blue);
+        assert_eq!(tp.ptr(), &pointee as *const u32);
+        assert_eq!(tp.tag(), Color::Blue);
+        assert_eq!(tp.into_pair().value(), 2);
+
+        let tp = TaggedPtr::new(&pointee, true);
+        assert!(tp.tag());
+    }
+
+    #[test]
+    fn flagged() {
+        let pointee = 42u16;
+        let mut fp: FlaggedPtr<u16> = FlaggedPtr::new(&pointee, false);
+        assert!(!fp.flag());
+        fp.set_flag(true);
+        assert!(fp.flag());
+        assert_eq!(fp.ptr(), &pointee as *const u16);
+    }
+
+    #[cfg(feature = "debug-validate")]
+    #[test]
+    #[should_panic = "corrupted tag bits"]
+    fn corrupted_tag() {
+        let pointee = 42u64;
+        let pv = crate::PointerValuePair::new(&pointee, 0b110);
+        // reinterpret a pair with a 3-bit value as a pair with a 2-bit tag
+        let tp: TaggedPtr<u64, Color> = unsafe { std::mem::transmute(pv) };
+        tp.tag();
+    }
+
+    #[cfg(feature = "bitflags")]
+    #[test]
+    fn bitflags() {
+        bitflags::bitflags! {
+            #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+            struct NodeFlags: u8 {
+                const DIRTY = 1;
+                const VISIBLE = 2;
+                const LEAF = 4;
+            }
+        }
+        crate::impl_tag_value_for_bitflags!(NodeFlags);
+
+        assert_eq!(NodeFlags::BITS, 3);
+        let pointee = 42u64;
+        let tp = TaggedPtr::new(&pointee, NodeFlags::DIRTY | NodeFlags::LEAF);
+        assert_eq!(tp.tag(), NodeFlags::DIRTY | NodeFlags::LEAF);
+        assert_eq!(tp.into_pair().value(), 5);
+    }
+
+    #[cfg(feature = "bitflags")]
+    #[test]
+    #[should_panic]
+    fn bitflags_unknown_bits() {
+        bitflags::bitflags! {
+            #[derive(Copy, Clone)]
+            struct NodeFlags: u8 {
+                const DIRTY = 1;
+            }
+        }
+        crate::impl_tag_value_for_bitflags!(NodeFlags);
+
+        let pointee = 42u64;
+        TaggedPtr::new(&pointee, NodeFlags::from_bits_retain(2));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive() {
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, TagValue)]
+        enum Kind {
+            Leaf,
+            Unary = 3,
+            Binary,
+        }
+
+        assert_eq!(Kind::BITS, 3);
+        assert_eq!(Kind::from_bits(Kind::Binary.into_bits()), Kind::Binary);
+
+        let pointee = 42u64;
+        let tp = TaggedPtr::new(&pointee, Kind::Unary);
+        assert_eq!(tp.tag(), Kind::Unary);
+        assert_eq!(TaggedPtr::new(&pointee, Kind::Leaf).tag(), Kind::Leaf);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_pack_pointer() {
+        use crate::PackPointer;
+
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, TagValue)]
+        enum Kind {
+            Leaf,
+            Unary,
+            Binary,
+        }
+
+        #[derive(Copy, Clone, PackPointer)]
+        struct Edge<'a> {
+            kind: Kind,
+            node: &'a u64,
+            visited: bool,
+        }
+
+        let (a, b) = (1u64, 2u64);
+        let mut e = PackedEdge::from(Edge {
+            kind: Kind::Binary,
+            node: &a,
+            visited: false,
+        });
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<PackedEdge>());
+        assert_eq!((e.kind(), *e.node(), e.visited()), (Kind::Binary, 1, false));
+
+        e.set_visited(true);
+        e.set_kind(Kind::Unary);
+        e.set_node(&b);
+        let e = Edge::from(e);
+        assert_eq!((e.kind, *e.node, e.visited), (Kind::Unary, 2, true));
+
+        #[derive(PackPointer)]
+        struct Raw {
+            ptr: *mut u32,
+            flag: bool,
+        }
+
+        let mut x = 3u32;
+        let r = PackedRaw::from(Raw {
+            ptr: &mut x,
+            flag: true,
+        });
+        assert_eq!((r.ptr(), r.flag()), (&mut x as *mut u32, true));
+    }
+
+    // a `TagValue` that breaks its contract if it holds more than `BITS` bits
+    #[cfg(feature = "derive")]
+    #[derive(Copy, Clone)]
+    struct Oversized(usize);
+
+    #[cfg(feature = "derive")]
+    impl TagValue for Oversized {
+        const BITS: u32 = 1;
+
+        fn into_bits(self) -> usize {
+            self.0
+        }
+
+        fn from_bits(bits: usize) -> Self {
+            Oversized(bits)
+        }
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    #[should_panic = "tag bits (0x2) do not fit in 1 bits"]
+    fn derive_pack_pointer_oversized_field() {
+        use crate::PackPointer;
+
+        #[derive(PackPointer)]
+        struct Node<'a> {
+            next: &'a u64,
+            flag: Oversized,
+        }
+
+        let next = 1u64;
+        let _ = PackedNode::from(Node {
+            next: &next,
+            flag: Oversized(0b10),
+        });
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    #[should_panic = "tag bits (0x2) do not fit in 1 bits"]
+    fn derive_pack_pointer_oversized_setter() {
+        use crate::PackPointer;
+
+        #[derive(Copy, Clone, PackPointer)]
+        struct Node<'a> {
+            next: &'a u64,
+            visited: bool,
+            flag: Oversized,
+        }
+
+        let next = 1u64;
+        let mut node = PackedNode::from(Node {
+            next: &next,
+            visited: false,
+            flag: Oversized(1),
+        });
+        node.set_flag(Oversized(0b10));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{PointeeAlign, PointerValuePair, TagValue};
+
+/// A typed sub-field of the value of a `PointerValuePair`: `Value::BITS` bits starting at bit `SHIFT` of the value.
+///
+/// This splits the available bits into independent fields (e.g. 2 bits of "kind" and 1 bit of "mark"), accessed with
+/// `PointerValuePair::field` and `PointerValuePair::set_field`. Fields are usually declared with `tag_fields!`,
+/// which lays them out one after the other so that they can't overlap.
+pub trait TagField {
+    /// The type of the field.
+    type Value: TagValue;
+
+    /// The offset of the field in the value.
+    const SHIFT: u32;
+
+    /// Returns the mask of the bits of the field in the value.
+    fn mask() -> usize {
+        ((1usize << Self::Value::BITS) - 1) << Self::SHIFT
+    }
+}
+
+/// Declares `TagField`s laid out one after the other, starting at bit 0 of the value.
+///
+/// Each field is declared as a unit struct with its type: `vis struct Name: Type;`. Whether all the fields fit in
+/// the available bits of a `PointerValuePair` is checked at compile time when they are accessed.
+///
+/// ```
+/// use pointer_value_pair::PointerValuePair;
+///
+/// pointer_value_pair::tag_fields! {
+///     /// Whether the node has been visited.
+///     pub struct Visited: bool;
+///     /// The mark bit of the garbage collector.
+///     pub struct Mark: bool;
+/// }
+///
+/// let node = 42u64;
+/// let mut pv = PointerValuePair::new(&node, 0);
+/// pv.set_field::<Mark>(true);
+/// assert!(pv.field::<Mark>() && !pv.field::<Visited>());
+/// ```
+#[macro_export]
+macro_rules! tag_fields {
+    (@at $shift:expr;) => {};
+    (@at $shift:expr; $(#[$attr:meta])* $vis:vis struct $name:ident : $ty:ty; $($rest:tt)*) => {
+        $(#[$attr])*
+        #[derive(Copy, Clone, Debug)]
+        $vis struct $name;
+
+        impl $crate::TagField for $name {
+            type Value = $ty;
+            const SHIFT: u32 = $shift;
+        }
+
+        $crate::tag_fields!(@at $shift + <$ty as $crate::TagValue>::BITS; $($rest)*);
+    };
+    ($($fields:tt)*) => {
+        $crate::tag_fields!(@at 0; $($fields)*);
+    };
+}
+
+impl<T: ?Sized + PointeeAlign> PointerValuePair<T> {
+    /// Returns the given field of the value. It is checked at compile time that the field fits in the available bits.
+    pub fn field<F: TagField>(self) -> F::Value {
+        const {
+            assert!(
+                F::SHIFT + <F::Value as TagValue>::BITS <= Self::available_bits(),
+                "not enough alignment bits to store the field"
+            )
+        };
+        F::Value::from_bits((self.value() & F::mask()) >> F::SHIFT)
+    }
+
+    /// Replaces the given field of the value, keeping the pointer and the other fields, and returns the previous one.
+    pub fn set_field<F: TagField>(&mut self, v: F::Value) -> F::Value {
+        let old = self.field::<F>();
+        let value = self.value() & !F::mask() | (v.into_bits() << F::SHIFT) & F::mask();
+        self.replace_value(value);
+        old
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PointerValuePair, TagField, TagValue};
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Kind {
+        Leaf,
+        Unary,
+        Binary,
+    }
+
+    impl TagValue for Kind {
+        const BITS: u32 = 2;
+
+        fn into_bits(self) -> usize {
+            self as usize
+        }
+
+        fn from_bits(bits: usize) -> Self {
+            [Kind::Leaf, Kind::Unary, Kind::Binary][bits]
+        }
+    }
+
+    crate::tag_fields! {
+        struct KindField: Kind;
+        struct Mark: bool;
+    }
+
+    #[test]
+    fn independent_fields() {
+        assert_eq!((KindField::SHIFT, Mark::SHIFT), (0, 2));
+        assert_eq!((KindField::mask(), Mark::mask()), (0b11, 0b100));
+
+        let pointee = 42u64;
+        let mut pv = PointerValuePair::new(&pointee, 0);
+        assert_eq!(pv.set_field::<KindField>(Kind::Binary), Kind::Leaf);
+        assert!(!pv.set_field::<Mark>(true));
+        assert_eq!((pv.field::<KindField>(), pv.field::<Mark>()), (Kind::Binary, true));
+        assert_eq!(pv.value(), 0b110);
+
+        pv.set_field::<KindField>(Kind::Unary);
+        assert_eq!((pv.field::<KindField>(), pv.field::<Mark>()), (Kind::Unary, true));
+        assert_eq!(pv.ptr(), &pointee as *const u64);
+    }
+}
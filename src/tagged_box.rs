@@ -0,0 +1,408 @@
+use crate::{
+    try_alloc::{try_box, try_box_slice},
+    AllocError, PointeeAlign, PointerValuePair,
+};
+use alloc::boxed::Box;
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ops::{Deref, DerefMut},
+    pin::Pin,
+};
+
+/// An owned heap allocation (`Box<T>`) with an integer value packed in the alignment bits of its pointer.
+///
+/// The allocation is freed when the `TaggedBox` is dropped. Like `Box<T>`, it is covariant in `T`: since it owns the
+/// value, no other code can observe the value through a longer-lived type.
+#[repr(transparent)]
+pub struct TaggedBox<T: ?Sized + PointeeAlign> {
+    inner: PointerValuePair<T>,
+    _phantom: PhantomData<T>,
+}
+
+// SAFETY: `TaggedBox` behaves like `Box<T>`.
+unsafe impl<T: ?Sized + PointeeAlign + Send> Send for TaggedBox<T> {}
+unsafe impl<T: ?Sized + PointeeAlign + Sync> Sync for TaggedBox<T> {}
+
+/// Allows coercing `TaggedBox<T>` into `TaggedBox<U>` when `T: Unsize<U>`, like `Box`.
+#[cfg(feature = "unsize")]
+impl<T, U> core::ops::CoerceUnsized<TaggedBox<U>> for TaggedBox<T>
+where
+    T: ?Sized + PointeeAlign + core::marker::Unsize<U>,
+    U: ?Sized + PointeeAlign,
+{
+}
+
+impl<T: ?Sized + PointeeAlign> TaggedBox<T> {
+    /// Creates a new `TaggedBox` from the given box and value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn new(b: Box<T>, value: usize) -> TaggedBox<T> {
+        // checked before `Box::into_raw` so that the box is not leaked on panic
+        Self::check_value(value);
+        TaggedBox {
+            inner: PointerValuePair::new(Box::into_raw(b), value),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Checks that the value fits in the available bits.
+    fn check_value(value: usize) {
+        assert!(
+            value <= PointerValuePair::<T>::max_value(),
+            "not enough alignment bits ({}) to store the value ({})",
+            PointerValuePair::<T>::available_bits(),
+            value
+        );
+    }
+
+    /// Converts this back into a `Box<T>`, discarding the value.
+    pub fn into_box(self) -> Box<T> {
+        let ptr = self.inner.ptr() as *mut T;
+        // ownership is transferred to the returned box
+        mem::forget(self);
+        unsafe {
+            // SAFETY: the pointer has been created with `Box::into_raw` by `TaggedBox::new`.
+            Box::from_raw(ptr)
+        }
+    }
+
+    /// Converts this back into a `Box<T>`, and returns it with the value.
+    pub fn into_parts(self) -> (Box<T>, usize) {
+        let value = self.value();
+        (self.into_box(), value)
+    }
+
+    /// Returns the value stored alongside the pointer.
+    pub fn value(&self) -> usize {
+        self.inner.value()
+    }
+
+    /// Sets the value stored alongside the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn set_value(&mut self, value: usize) {
+        self.inner.replace_value(value);
+    }
+
+    /// Returns the underlying `PointerValuePair`.
+    ///
+    /// The `TaggedBox` still owns the allocation: the returned pointer must not outlive it.
+    pub fn as_pair(&self) -> PointerValuePair<T> {
+        self.inner
+    }
+}
+
+// `Pin<TaggedBox<T>>` pins the pointee, like `Pin<Box<T>>`: the pointee is never moved by `TaggedBox`, and is
+// only accessible through `Pin::as_ref` and `Pin::as_mut`. The value stored alongside the pointer is not pinned.
+impl<T: ?Sized + PointeeAlign> TaggedBox<T> {
+    /// Creates a pinned `TaggedBox` from a pinned box and a value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn from_pin(b: Pin<Box<T>>, value: usize) -> Pin<TaggedBox<T>> {
+        unsafe {
+            // SAFETY: the pointee stays in the same allocation, which is never moved out of
+            Pin::new_unchecked(TaggedBox::new(Pin::into_inner_unchecked(b), value))
+        }
+    }
+
+    /// Converts a `TaggedBox` into a pinned `TaggedBox`, like `Box::into_pin`.
+    pub fn into_pin(this: TaggedBox<T>) -> Pin<TaggedBox<T>> {
+        // SAFETY: it's not possible to move out of a `Pin<TaggedBox<T>>` if `T: !Unpin`
+        unsafe { Pin::new_unchecked(this) }
+    }
+
+    /// Converts a pinned `TaggedBox` back into a pinned box, discarding the value.
+    pub fn into_pinned_box(this: Pin<TaggedBox<T>>) -> Pin<Box<T>> {
+        unsafe {
+            // SAFETY: the pointee stays in the same allocation
+            Box::into_pin(Pin::into_inner_unchecked(this).into_box())
+        }
+    }
+
+    /// Sets the value stored alongside the pointer of a pinned `TaggedBox`. The pointee is not moved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn set_pinned_value(this: &mut Pin<TaggedBox<T>>, value: usize) {
+        unsafe {
+            // SAFETY: `Pin` is `repr(transparent)`, and changing the value doesn't move the pointee
+            (*(this as *mut Pin<TaggedBox<T>> as *mut TaggedBox<T>)).set_value(value)
+        }
+    }
+
+    /// Returns a shared reference to the `TaggedBox` of a pinned `TaggedBox`, e.g. to read the value stored alongside
+    /// the pointer.
+    pub fn unpin_ref(this: &Pin<TaggedBox<T>>) -> &TaggedBox<T> {
+        // SAFETY: `Pin` is `repr(transparent)`, and `&TaggedBox<T>` doesn't allow moving the pointee
+        unsafe { &*(this as *const Pin<TaggedBox<T>> as *const TaggedBox<T>) }
+    }
+}
+
+impl<T> TaggedBox<T> {
+    /// Boxes the given object in a pinned `TaggedBox` with the given value, like `Box::pin`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn pin(v: T, value: usize) -> Pin<TaggedBox<T>> {
+        Self::from_pin(Box::pin(v), value)
+    }
+
+    /// Boxes the given object and creates a new `TaggedBox` with the given value, or returns an error if the
+    /// allocation fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn try_new(v: T, value: usize) -> Result<TaggedBox<T>, AllocError> {
+        Self::check_value(value);
+        try_box(v).map(|b| TaggedBox::new(b, value))
+    }
+
+    /// Allocates uninitialized memory for a `T`, like `Box::new_uninit`, and creates a new `TaggedBox` with the given
+    /// value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn new_uninit(value: usize) -> TaggedBox<MaybeUninit<T>> {
+        Self::check_value(value);
+        TaggedBox::new(Box::new_uninit(), value)
+    }
+}
+
+impl<T> TaggedBox<MaybeUninit<T>> {
+    /// Writes the object to the allocation, and returns the initialized `TaggedBox` with the same value.
+    pub fn write(mut self, v: T) -> TaggedBox<T> {
+        (*self).write(v);
+        // SAFETY: initialized above
+        unsafe { self.assume_init() }
+    }
+
+    /// Converts this into a `TaggedBox<T>` with the same value, without moving the pointee.
+    ///
+    /// # Safety
+    ///
+    /// The pointee must be initialized, see `MaybeUninit::assume_init`.
+    pub unsafe fn assume_init(self) -> TaggedBox<T> {
+        let (b, value) = self.into_parts();
+        // SAFETY: guaranteed by the caller
+        TaggedBox::new(unsafe { b.assume_init() }, value)
+    }
+}
+
+impl<T> TaggedBox<[MaybeUninit<T>]> {
+    /// Converts this into a `TaggedBox<[T]>` with the same value, without moving the elements.
+    ///
+    /// # Safety
+    ///
+    /// All the elements must be initialized, see `MaybeUninit::assume_init`.
+    pub unsafe fn assume_init(self) -> TaggedBox<[T]> {
+        let (b, value) = self.into_parts();
+        // SAFETY: guaranteed by the caller
+        TaggedBox::new(unsafe { b.assume_init() }, value)
+    }
+}
+
+impl<T> TaggedBox<[T]> {
+    /// Allocates uninitialized memory for `len` elements, like `Box::new_uninit_slice`, and creates a new `TaggedBox`
+    /// with the given value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn new_uninit_slice(len: usize, value: usize) -> TaggedBox<[MaybeUninit<T>]> {
+        Self::check_value(value);
+        TaggedBox::new(Box::new_uninit_slice(len), value)
+    }
+}
+
+impl<T: Clone> TaggedBox<[T]> {
+    /// Clones the given slice into a new boxed slice with the given value, or returns an error if the allocation
+    /// fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn try_from_slice(v: &[T], value: usize) -> Result<TaggedBox<[T]>, AllocError> {
+        Self::check_value(value);
+        try_box_slice(v).map(|b| TaggedBox::new(b, value))
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> From<(Box<T>, usize)> for TaggedBox<T> {
+    /// Creates a `TaggedBox` from a box and a value. See `TaggedBox::new`.
+    fn from((b, value): (Box<T>, usize)) -> Self {
+        TaggedBox::new(b, value)
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> From<TaggedBox<T>> for Pin<TaggedBox<T>> {
+    /// Pins the `TaggedBox`. See `TaggedBox::into_pin`.
+    fn from(b: TaggedBox<T>) -> Self {
+        TaggedBox::into_pin(b)
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> Drop for TaggedBox<T> {
+    fn drop(&mut self) {
+        unsafe {
+            // SAFETY: the pointer has been created with `Box::into_raw` by `TaggedBox::new`.
+            drop(Box::from_raw(self.inner.ptr() as *mut T));
+        }
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> Deref for TaggedBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        validate!(!self.inner.is_null(), "corrupted TaggedBox pointer");
+        unsafe {
+            // SAFETY: the pointer points to a live allocation owned by `self`.
+            &*self.inner.ptr()
+        }
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> DerefMut for TaggedBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        validate!(!self.inner.is_null(), "corrupted TaggedBox pointer");
+        unsafe {
+            // SAFETY: the pointer points to a live allocation owned by `self`, which we borrow mutably.
+            &mut *(self.inner.ptr() as *mut T)
+        }
+    }
+}
+
+impl<T: Clone> Clone for TaggedBox<T> {
+    /// Clones the boxed value, and keeps the value stored alongside the pointer.
+    fn clone(&self) -> Self {
+        TaggedBox::new(Box::new(T::clone(self)), self.value())
+    }
+}
+
+impl<T: ?Sized + PointeeAlign + fmt::Debug> fmt::Debug for TaggedBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaggedBox")
+            .field("box", &&**self)
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TaggedBox;
+    use std::{boxed::Box, format, marker::PhantomPinned, mem, ptr, rc::Rc, vec};
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(mem::size_of::<Box<u64>>(), mem::size_of::<TaggedBox<u64>>());
+    }
+
+    #[test]
+    fn basic() {
+        let mut b = TaggedBox::new(Box::new(42u64), 3);
+        assert_eq!(*b, 42);
+        assert_eq!(b.value(), 3);
+        *b += 1;
+        b.set_value(7);
+        assert_eq!(format!("{:?}", b), "TaggedBox { box: 43, value: 7 }");
+
+        let c = b.clone();
+        assert_eq!((*c, c.value()), (43, 7));
+        assert_eq!(*b.into_box(), 43);
+        let (c, value) = c.into_parts();
+        assert_eq!((*c, value), (43, 7));
+        let b: TaggedBox<u64> = (c, 1).into();
+        assert_eq!((*b, b.value()), (43, 1));
+
+        let s = TaggedBox::new(vec![1u32, 2, 3].into_boxed_slice(), 1);
+        assert_eq!(&*s, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn try_new() {
+        let b = TaggedBox::try_new(7u32, 2).unwrap();
+        assert_eq!((*b, b.value()), (7, 2));
+        let b = TaggedBox::try_from_slice(&[1u32, 2], 3).unwrap();
+        assert_eq!((&*b, b.value()), (&[1, 2][..], 3));
+    }
+
+    #[test]
+    fn drops() {
+        let rc = Rc::new(());
+        let b = TaggedBox::new(Box::new(rc.clone()), 1);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(b);
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        let b = TaggedBox::new(Box::new(rc.clone()), 1);
+        let inner = b.into_box();
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(inner);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn pinned() {
+        struct SelfRef {
+            data: u32,
+            ptr: *const u32,
+            _pinned: PhantomPinned,
+        }
+
+        let mut b = TaggedBox::pin(
+            SelfRef {
+                data: 5,
+                ptr: ptr::null(),
+                _pinned: PhantomPinned,
+            },
+            2,
+        );
+        unsafe {
+            let this = b.as_mut().get_unchecked_mut();
+            this.ptr = &this.data;
+        }
+        TaggedBox::set_pinned_value(&mut b, 3);
+        assert_eq!(TaggedBox::unpin_ref(&b).value(), 3);
+        assert_eq!(unsafe { *b.ptr }, 5);
+
+        let b = TaggedBox::into_pinned_box(b);
+        assert!(ptr::eq(b.ptr, &b.data));
+        let b = TaggedBox::from_pin(b, 1);
+        assert!(ptr::eq(b.ptr, &b.data));
+        assert_eq!(TaggedBox::unpin_ref(&b).value(), 1);
+    }
+
+    #[cfg(feature = "unsize")]
+    #[test]
+    fn unsize() {
+        let b: TaggedBox<[u64]> = TaggedBox::new(Box::new([1u64, 2, 3]), 5);
+        assert_eq!((&*b, b.value()), (&[1, 2, 3][..], 5));
+    }
+
+    #[test]
+    fn uninit() {
+        let b = TaggedBox::<u64>::new_uninit(3).write(42);
+        assert_eq!((*b, b.value()), (42, 3));
+
+        let mut b = TaggedBox::<[u64]>::new_uninit_slice(3, 2);
+        for (i, slot) in b.iter_mut().enumerate() {
+            slot.write(i as u64);
+        }
+        // SAFETY: all the elements are initialized
+        let b = unsafe { b.assume_init() };
+        assert_eq!((&*b, b.value()), (&[0, 1, 2][..], 2));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{PointeeAlign, PointerValuePair};
+use core::{cell::Cell, fmt};
+
+/// A `Cell<PointerValuePair<T>>`: a pointer-value pair whose value (and pointer) can be modified through a shared
+/// reference.
+///
+/// This lets single-threaded data structures flip tag bits (e.g. "visited" flags during a graph traversal) without
+/// threading `&mut` through the traversal or paying for atomics. Like `Cell`, it is not `Sync`.
+#[repr(transparent)]
+pub struct TaggedCell<T: ?Sized> {
+    pv: Cell<PointerValuePair<T>>,
+}
+
+impl<T: ?Sized + PointeeAlign> TaggedCell<T> {
+    /// Creates a new `TaggedCell` holding the given pair.
+    pub const fn new(pv: PointerValuePair<T>) -> TaggedCell<T> {
+        TaggedCell { pv: Cell::new(pv) }
+    }
+
+    /// Returns the pair.
+    pub fn get(&self) -> PointerValuePair<T> {
+        self.pv.get()
+    }
+
+    /// Replaces the pair.
+    pub fn set(&self, pv: PointerValuePair<T>) {
+        self.pv.set(pv)
+    }
+
+    /// Replaces the pair, and returns the previous one.
+    pub fn replace(&self, pv: PointerValuePair<T>) -> PointerValuePair<T> {
+        self.pv.replace(pv)
+    }
+
+    /// Returns the pointer.
+    pub fn ptr(&self) -> *const T {
+        self.get().ptr()
+    }
+
+    /// Returns the value stored alongside the pointer.
+    pub fn value(&self) -> usize {
+        self.get().value()
+    }
+
+    /// Replaces the value stored alongside the pointer, keeping the pointer, and returns the previous value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn set_value_only(&self, value: usize) -> usize {
+        let mut pv = self.get();
+        let old = pv.replace_value(value);
+        self.set(pv);
+        old
+    }
+
+    /// Replaces the value stored alongside the pointer with the result of `f` applied to the current value, and
+    /// returns the new value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn update_value(&self, f: impl FnOnce(usize) -> usize) -> usize {
+        let mut pv = self.get();
+        let value = pv.update_value(f);
+        self.set(pv);
+        value
+    }
+
+    /// Returns a mutable reference to the pair.
+    pub fn get_mut(&mut self) -> &mut PointerValuePair<T> {
+        self.pv.get_mut()
+    }
+
+    /// Returns the pair.
+    pub fn into_inner(self) -> PointerValuePair<T> {
+        self.pv.into_inner()
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> Clone for TaggedCell<T> {
+    fn clone(&self) -> Self {
+        TaggedCell::new(self.get())
+    }
+}
+
+impl<T> Default for TaggedCell<T> {
+    /// Returns a `TaggedCell` with a null pointer and a zero value.
+    fn default() -> Self {
+        TaggedCell::new(PointerValuePair::default())
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> From<PointerValuePair<T>> for TaggedCell<T> {
+    fn from(pv: PointerValuePair<T>) -> Self {
+        TaggedCell::new(pv)
+    }
+}
+
+impl<T: ?Sized + PointeeAlign> fmt::Debug for TaggedCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaggedCell")
+            .field("ptr", &self.ptr())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PointerValuePair, TaggedCell};
+    use std::{mem, ptr, vec::Vec};
+
+    struct Node {
+        edges: Vec<TaggedCell<Node>>,
+    }
+
+    #[test]
+    fn visited_flags() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<TaggedCell<u64>>());
+        let leaf = Node { edges: Vec::new() };
+        let root = Node {
+            edges: (0..3)
+                .map(|_| TaggedCell::new(PointerValuePair::new(&leaf, 0)))
+                .collect(),
+        };
+
+        // flag edges through shared references only
+        for (i, e) in root.edges.iter().enumerate() {
+            if i != 1 {
+                e.set_value_only(1);
+            }
+        }
+        let visited: Vec<_> = root.edges.iter().map(TaggedCell::value).collect();
+        assert_eq!(visited, [1, 0, 1]);
+        assert!(root.edges.iter().all(|e| ptr::eq(e.ptr(), &leaf)));
+        assert!(unsafe { (*root.edges[0].ptr()).edges.is_empty() });
+    }
+
+    #[test]
+    fn update() {
+        let pointee = 42u64;
+        let mut c = TaggedCell::new(PointerValuePair::new(&pointee, 1));
+        assert_eq!(c.update_value(|v| v + 2), 3);
+        assert_eq!(c.set_value_only(0), 3);
+        c.get_mut().replace_value(7);
+        let old = c.replace(PointerValuePair::null(2));
+        assert_eq!((old.ptr(), old.value()), (&pointee as *const u64, 7));
+        assert!(c.clone().into_inner().is_null());
+        assert_eq!(TaggedCell::<u64>::default().value(), 0);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{PackError, TagValue};
+use core::fmt;
+
+/// An index (e.g. into a `Vec`) with a `BITS`-bit value packed in the same `usize`.
+///
+/// The value is stored in the low `BITS` bits, and the index in the remaining high bits, so indices are limited to
+/// `max_index()` (e.g. 2^61 - 1 with 3 bits on 64-bit targets). The API mirrors `PointerIntPair` and `TaggedPtr`:
+/// construction is checked, and the value can be read as a `usize` or as a typed `TagValue`, so that index-based and
+/// pointer-based data structures can be written the same way.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct TaggedIndex<const BITS: u32> {
+    repr: usize,
+}
+
+impl<const BITS: u32> fmt::Debug for TaggedIndex<BITS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaggedIndex")
+            .field("index", &self.index())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<const BITS: u32> TaggedIndex<BITS> {
+    /// The number of bits of the value.
+    pub const BITS: u32 = BITS;
+
+    /// Returns the maximum (inclusive) value.
+    pub const fn max_value() -> usize {
+        const {
+            assert!(
+                BITS < usize::BITS,
+                "the value must leave at least one bit for the index"
+            )
+        };
+        (1 << BITS) - 1
+    }
+
+    /// Returns the maximum (inclusive) index.
+    pub const fn max_index() -> usize {
+        usize::MAX >> BITS
+    }
+
+    /// Creates a new `TaggedIndex` from an index and a value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is greater than `max_index()`, or if the value is greater than `max_value()`.
+    pub fn new(index: usize, value: usize) -> TaggedIndex<BITS> {
+        match Self::try_new(index, value) {
+            Ok(i) => i,
+            Err(PackError::ValueTooLarge) => panic!("value ({value}) does not fit in {BITS} bits"),
+            Err(_) => panic!("index ({index}) does not fit in {} bits", usize::BITS - BITS),
+        }
+    }
+
+    /// Creates a new `TaggedIndex` from an index and a value, checking that both fit in their bits.
+    pub fn try_new(index: usize, value: usize) -> Result<TaggedIndex<BITS>, PackError> {
+        if value > Self::max_value() {
+            return Err(PackError::ValueTooLarge);
+        }
+        if index > Self::max_index() {
+            return Err(PackError::IndexTooLarge);
+        }
+        Ok(TaggedIndex {
+            repr: (index << BITS) | value,
+        })
+    }
+
+    /// Creates a `TaggedIndex` from its packed representation, returned by `into_raw`.
+    pub const fn from_raw(repr: usize) -> TaggedIndex<BITS> {
+        TaggedIndex { repr }
+    }
+
+    /// Returns the packed representation.
+    pub const fn into_raw(self) -> usize {
+        self.repr
+    }
+
+    /// Returns the index.
+    pub fn index(self) -> usize {
+        self.repr >> BITS
+    }
+
+    /// Returns the value stored alongside the index.
+    pub fn value(self) -> usize {
+        self.repr & Self::max_value()
+    }
+
+    /// Replaces the value stored alongside the index, and returns the previous one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value is greater than `max_value()`.
+    pub fn replace_value(&mut self, value: usize) -> usize {
+        let old = self.value();
+        *self = TaggedIndex::new(self.index(), value);
+        old
+    }
+
+    /// Creates a new `TaggedIndex` from an index and a typed tag. It is checked at compile time that the tag fits in
+    /// `BITS` bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is greater than `max_index()`.
+    pub fn with_tag<V: TagValue>(index: usize, tag: V) -> TaggedIndex<BITS> {
+        const { assert!(V::BITS <= BITS, "not enough bits to store the tag") };
+        TaggedIndex::new(index, tag.into_bits())
+    }
+
+    /// Returns the value stored alongside the index, as a typed tag.
+    pub fn tag<V: TagValue>(self) -> V {
+        const { assert!(V::BITS <= BITS, "not enough bits to store the tag") };
+        V::from_bits(self.value())
+    }
+
+    /// Returns a reference to the element of the slice at the index, or `None` if out of bounds.
+    pub fn get<T>(self, slice: &[T]) -> Option<&T> {
+        slice.get(self.index())
+    }
+
+    /// Returns a mutable reference to the element of the slice at the index, or `None` if out of bounds.
+    pub fn get_mut<T>(self, slice: &mut [T]) -> Option<&mut T> {
+        slice.get_mut(self.index())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PackError, TaggedIndex};
+    use std::{format, mem};
+
+    #[test]
+    fn pack() {
+        assert_eq!(mem::size_of::<usize>(), mem::size_of::<TaggedIndex<3>>());
+        assert_eq!(TaggedIndex::<3>::max_value(), 7);
+        assert_eq!(TaggedIndex::<3>::max_index(), usize::MAX >> 3);
+
+        let mut i = TaggedIndex::<2>::new(5, 3);
+        assert_eq!((i.index(), i.value()), (5, 3));
+        assert_eq!(i.replace_value(1), 3);
+        assert_eq!(TaggedIndex::from_raw(i.into_raw()), i);
+        assert_eq!(format!("{:?}", i), "TaggedIndex { index: 5, value: 1 }");
+
+        let mut data = [10, 20, 30];
+        let i = TaggedIndex::<1>::with_tag(2, true);
+        assert!(i.tag::<bool>());
+        *i.get_mut(&mut data).unwrap() += 1;
+        assert_eq!(
+            (i.get(&data), TaggedIndex::<1>::new(3, 0).get(&data)),
+            (Some(&31), None)
+        );
+    }
+
+    #[test]
+    fn checked() {
+        assert_eq!(TaggedIndex::<2>::try_new(0, 4), Err(PackError::ValueTooLarge));
+        assert_eq!(
+            TaggedIndex::<2>::try_new(usize::MAX >> 1, 0),
+            Err(PackError::IndexTooLarge)
+        );
+        assert!(TaggedIndex::<2>::try_new(usize::MAX >> 2, 3).is_ok());
+    }
+
+    #[test]
+    #[should_panic = "does not fit"]
+    fn too_large() {
+        TaggedIndex::<4>::new(0, 16);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{PointeeAlign, PointerValuePair};
+use alloc::rc::Rc;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use core::{fmt, marker::PhantomData, mem, ops::Deref};
+
+macro_rules! tagged_rc {
+    ($(#[$attr:meta])* $name:ident, $rc:ident, $into:ident) => {
+        $(#[$attr])*
+        #[repr(transparent)]
+        pub struct $name<T: ?Sized + PointeeAlign> {
+            inner: PointerValuePair<T>,
+            _phantom: PhantomData<$rc<T>>,
+        }
+
+        impl<T: ?Sized + PointeeAlign> $name<T> {
+            #[doc = concat!("Creates a new `", stringify!($name), "` from the given `", stringify!($rc), "` and value.")]
+            ///
+            /// # Panics
+            ///
+            /// Panics if the value doesn't fit in the available bits.
+            pub fn new(rc: $rc<T>, value: usize) -> $name<T> {
+                // checked before `into_raw` so that the reference is not leaked on panic
+                assert!(
+                    value <= PointerValuePair::<T>::max_value(),
+                    "not enough alignment bits ({}) to store the value ({})",
+                    PointerValuePair::<T>::available_bits(),
+                    value
+                );
+                $name {
+                    inner: PointerValuePair::new($rc::into_raw(rc), value),
+                    _phantom: PhantomData,
+                }
+            }
+
+            #[doc = concat!("Converts this back into a `", stringify!($rc), "<T>`, discarding the value.")]
+            pub fn $into(this: Self) -> $rc<T> {
+                let ptr = this.inner.ptr();
+                // the reference count is transferred to the returned pointer
+                mem::forget(this);
+                unsafe {
+                    // SAFETY: the pointer has been created with `into_raw` by `new`.
+                    $rc::from_raw(ptr)
+                }
+            }
+
+            /// Returns the value stored alongside the pointer.
+            pub fn value(this: &Self) -> usize {
+                this.inner.value()
+            }
+
+            /// Sets the value stored alongside the pointer.
+            ///
+            /// This only affects this handle, not the other clones.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the new value doesn't fit in the available bits.
+            pub fn set_value(this: &mut Self, value: usize) {
+                this.inner.replace_value(value);
+            }
+
+            /// Returns whether the two handles point to the same allocation, regardless of their values.
+            pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+                this.inner.ptr_eq(other.inner)
+            }
+        }
+
+        impl<T: ?Sized + PointeeAlign> Clone for $name<T> {
+            /// Increments the reference count, and keeps the value stored alongside the pointer.
+            fn clone(&self) -> Self {
+                unsafe {
+                    // SAFETY: the pointer has been created with `into_raw` by `new`, and `self` holds a reference.
+                    $rc::increment_strong_count(self.inner.ptr());
+                }
+                $name {
+                    inner: self.inner,
+                    _phantom: PhantomData,
+                }
+            }
+        }
+
+        impl<T: ?Sized + PointeeAlign> Drop for $name<T> {
+            fn drop(&mut self) {
+                unsafe {
+                    // SAFETY: the pointer has been created with `into_raw` by `new`.
+                    drop($rc::from_raw(self.inner.ptr()));
+                }
+            }
+        }
+
+        impl<T: ?Sized + PointeeAlign> Deref for $name<T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                unsafe {
+                    // SAFETY: `self` holds a reference, so the allocation is live.
+                    &*self.inner.ptr()
+                }
+            }
+        }
+
+        impl<T: ?Sized + PointeeAlign + fmt::Debug> fmt::Debug for $name<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("rc", &&**self)
+                    .field("value", &Self::value(self))
+                    .finish()
+            }
+        }
+    };
+}
+
+tagged_rc! {
+    /// A reference-counted pointer (`Rc<T>`) with an integer value packed in the alignment bits of its pointer.
+    ///
+    /// Cloning a `TaggedRc` increments the reference count and keeps the value. Like `Rc`, the methods are
+    /// associated functions (`TaggedRc::value(&rc)`) to avoid conflicts with the methods of `T`.
+    TaggedRc, Rc, into_rc
+}
+
+#[cfg(target_has_atomic = "ptr")]
+tagged_rc! {
+    /// An atomically reference-counted pointer (`Arc<T>`) with an integer value packed in the alignment bits of its
+    /// pointer.
+    ///
+    /// Cloning a `TaggedArc` increments the reference count and keeps the value. Like `Arc`, the methods are
+    /// associated functions (`TaggedArc::value(&arc)`) to avoid conflicts with the methods of `T`.
+    TaggedArc, Arc, into_arc
+}
+
+// SAFETY: `TaggedArc` behaves like `Arc<T>`.
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl<T: ?Sized + PointeeAlign + Send + Sync> Send for TaggedArc<T> {}
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl<T: ?Sized + PointeeAlign + Send + Sync> Sync for TaggedArc<T> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{TaggedArc, TaggedRc};
+    use std::{format, mem, ptr, rc::Rc, sync::Arc, thread, vec::Vec};
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(mem::size_of::<Rc<u64>>(), mem::size_of::<TaggedRc<u64>>());
+        assert_eq!(mem::size_of::<Arc<u64>>(), mem::size_of::<TaggedArc<u64>>());
+    }
+
+    #[test]
+    fn rc_counts() {
+        let rc = Rc::new(42u64);
+        let a = TaggedRc::new(rc.clone(), 3);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        let mut b = a.clone();
+        assert_eq!(Rc::strong_count(&rc), 3);
+        TaggedRc::set_value(&mut b, 5);
+        assert_eq!((TaggedRc::value(&a), TaggedRc::value(&b)), (3, 5));
+        assert!(TaggedRc::ptr_eq(&a, &b));
+        assert_eq!(*b, 42);
+        assert_eq!(format!("{:?}", b), "TaggedRc { rc: 42, value: 5 }");
+
+        drop(a);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        let b = TaggedRc::into_rc(b);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(b);
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        let s: Rc<[u32]> = Rc::from(&[1, 2, 3][..]);
+        let t = TaggedRc::new(s, 1);
+        assert_eq!(&*t.clone(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn arc_threads() {
+        let arc = Arc::new(42u64);
+        let t = TaggedArc::new(arc.clone(), 7);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let t = t.clone();
+                thread::spawn(move || *t + TaggedArc::value(&t) as u64)
+            })
+            .collect();
+        for h in handles {
+            assert_eq!(h.join().unwrap(), 49);
+        }
+        assert!(Arc::ptr_eq(&TaggedArc::into_arc(t), &arc));
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn zero_sized() {
+        let rc = Rc::new(());
+        let t = TaggedRc::new(rc.clone(), 0);
+        assert!(ptr::eq(&*t, &*rc));
+        drop(t.clone());
+        assert!(Rc::ptr_eq(&TaggedRc::into_rc(t), &rc));
+        assert_eq!(Rc::strong_count(&rc), 1);
+
+        let arc = Arc::new(());
+        let t = TaggedArc::new(arc.clone(), 0);
+        drop(t.clone());
+        assert!(Arc::ptr_eq(&TaggedArc::into_arc(t), &arc));
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{InvariantPointerValuePair, PointeeAlign, PointerValuePair};
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+/// A shared reference (`&'a T`) with an integer value packed in its alignment bits.
+///
+/// This is a safe wrapper around `PointerValuePair<T>`: since it is always created from a reference, the pointer
+/// can be dereferenced without `unsafe`. Like `&'a T`, it is covariant in `'a` and `T`.
+#[repr(transparent)]
+pub struct TaggedRef<'a, T: ?Sized> {
+    inner: PointerValuePair<T>,
+    _phantom: PhantomData<&'a T>,
+}
+
+// SAFETY: `TaggedRef` behaves like `&'a T`.
+unsafe impl<T: ?Sized + Sync> Send for TaggedRef<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for TaggedRef<'_, T> {}
+
+impl<T: ?Sized> Copy for TaggedRef<'_, T> {}
+
+impl<T: ?Sized> Clone for TaggedRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> PartialEq for TaggedRef<'_, T> {
+    /// Compares the addresses and values of both references, not the referenced values.
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T: ?Sized> Eq for TaggedRef<'_, T> {}
+
+impl<T: ?Sized> Hash for TaggedRef<'_, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
+    }
+}
+
+impl<T: ?Sized + PointeeAlign + fmt::Debug> fmt::Debug for TaggedRef<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaggedRef")
+            .field("ref", &self.get())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<'a, T: ?Sized + PointeeAlign> TaggedRef<'a, T> {
+    /// Creates a new `TaggedRef` from the given reference and value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn new(r: &'a T, value: usize) -> TaggedRef<'a, T> {
+        TaggedRef {
+            inner: PointerValuePair::new(r, value),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the reference.
+    pub fn get(&self) -> &'a T {
+        unsafe {
+            // SAFETY: the pointer was created from a `&'a T`.
+            &*self.inner.ptr()
+        }
+    }
+
+    /// Returns the value stored alongside the reference.
+    pub fn value(&self) -> usize {
+        self.inner.value()
+    }
+
+    /// Replaces the value stored alongside the reference, and returns the previous one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn replace_value(&mut self, value: usize) -> usize {
+        self.inner.replace_value(value)
+    }
+
+    /// Returns whether the two `TaggedRef`s point to the same value, regardless of their values.
+    pub fn ptr_eq(self, other: Self) -> bool {
+        self.inner.ptr_eq(other.inner)
+    }
+
+    /// Returns the reference and the value.
+    pub fn into_parts(self) -> (&'a T, usize) {
+        (self.get(), self.value())
+    }
+
+    /// Returns the underlying `PointerValuePair`.
+    pub fn into_pair(self) -> PointerValuePair<T> {
+        self.inner
+    }
+}
+
+impl<'a, T: ?Sized + PointeeAlign> From<(&'a T, usize)> for TaggedRef<'a, T> {
+    /// Creates a `TaggedRef` from a reference and a value. See `TaggedRef::new`.
+    fn from((r, value): (&'a T, usize)) -> Self {
+        TaggedRef::new(r, value)
+    }
+}
+
+/// An exclusive reference (`&'a mut T`) with an integer value packed in its alignment bits.
+///
+/// This is the mutable counterpart of `TaggedRef`. Like `&'a mut T`, it is not `Copy`, the referenced value is
+/// borrowed exclusively for `'a`, and it is covariant in `'a` but invariant in `T`.
+#[repr(transparent)]
+pub struct TaggedMut<'a, T: ?Sized> {
+    inner: InvariantPointerValuePair<T>,
+    _phantom: PhantomData<&'a mut T>,
+}
+
+// SAFETY: `TaggedMut` behaves like `&'a mut T`.
+unsafe impl<T: ?Sized + Send> Send for TaggedMut<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for TaggedMut<'_, T> {}
+
+impl<T: ?Sized + PointeeAlign + fmt::Debug> fmt::Debug for TaggedMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaggedMut")
+            .field("ref", &self.get())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<'a, T: ?Sized + PointeeAlign> TaggedMut<'a, T> {
+    /// Creates a new `TaggedMut` from the given reference and value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn new(r: &'a mut T, value: usize) -> TaggedMut<'a, T> {
+        TaggedMut {
+            // go through `*mut T` so that the pointer keeps write permissions
+            inner: InvariantPointerValuePair::from_pair(PointerValuePair::new(r as *mut T, value)),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns a shared reference to the referenced value.
+    pub fn get(&self) -> &T {
+        unsafe {
+            // SAFETY: the pointer was created from a `&'a mut T`, which we borrow immutably.
+            &*self.inner.ptr()
+        }
+    }
+
+    /// Returns an exclusive reference to the referenced value.
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe {
+            // SAFETY: the pointer was created from a `&'a mut T`, which we borrow mutably.
+            &mut *(self.inner.ptr() as *mut T)
+        }
+    }
+
+    /// Converts this into the original exclusive reference.
+    pub fn into_mut(self) -> &'a mut T {
+        unsafe {
+            // SAFETY: the pointer was created from a `&'a mut T`, and `self` is consumed.
+            &mut *(self.inner.ptr() as *mut T)
+        }
+    }
+
+    /// Returns the value stored alongside the reference.
+    pub fn value(&self) -> usize {
+        self.inner.value()
+    }
+
+    /// Sets the value stored alongside the reference.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn set_value(&mut self, value: usize) {
+        self.inner.replace_value(value);
+    }
+
+    /// Returns the exclusive reference and the value.
+    pub fn into_parts(self) -> (&'a mut T, usize) {
+        let value = self.value();
+        (self.into_mut(), value)
+    }
+
+    /// Reborrows this reference for a shorter lifetime, keeping the value.
+    pub fn reborrow(&mut self) -> TaggedMut<'_, T> {
+        TaggedMut {
+            inner: self.inner,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: ?Sized + PointeeAlign> From<(&'a mut T, usize)> for TaggedMut<'a, T> {
+    /// Creates a `TaggedMut` from an exclusive reference and a value. See `TaggedMut::new`.
+    fn from((r, value): (&'a mut T, usize)) -> Self {
+        TaggedMut::new(r, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{TaggedMut, TaggedRef};
+    use std::{format, mem};
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(mem::size_of::<&u64>(), mem::size_of::<TaggedRef<u64>>());
+        assert_eq!(mem::size_of::<&[u64]>(), mem::size_of::<TaggedRef<[u64]>>());
+    }
+
+    #[test]
+    fn get_value() {
+        let pointee = 42u64;
+        let mut r = TaggedRef::new(&pointee, 5);
+        assert_eq!(*r.get(), 42);
+        assert_eq!(r.value(), 5);
+        assert_eq!(r.replace_value(7), 5);
+        assert_eq!(r.value(), 7);
+        assert_eq!(r.into_pair().ptr(), &pointee as *const u64);
+        assert_eq!(format!("{:?}", r), "TaggedRef { ref: 42, value: 7 }");
+        assert!(r.ptr_eq(TaggedRef::new(&pointee, 1)) && r != TaggedRef::new(&pointee, 1));
+
+        let s = [1u32, 2, 3];
+        let r = TaggedRef::new(&s[..], 3);
+        assert_eq!(r.get(), &[1, 2, 3]);
+        assert_eq!(r.value(), 3);
+    }
+
+    #[test]
+    fn outlives_wrapper() {
+        let pointee = 42u64;
+        fn get(r: TaggedRef<'_, u64>) -> &u64 {
+            r.get()
+        }
+        let r = get(TaggedRef::new(&pointee, 1));
+        assert_eq!(*r, 42);
+    }
+
+    #[test]
+    fn tagged_mut() {
+        let mut pointee = 42u64;
+        let mut r = TaggedMut::new(&mut pointee, 2);
+        assert_eq!(mem::size_of_val(&r), mem::size_of::<&mut u64>());
+        *r.get_mut() += 1;
+        r.set_value(5);
+        assert_eq!(*r.get(), 43);
+        assert_eq!(r.value(), 5);
+
+        let mut r2 = r.reborrow();
+        *r2.get_mut() += 1;
+        assert_eq!(r2.value(), 5);
+        assert_eq!(format!("{:?}", r), "TaggedMut { ref: 44, value: 5 }");
+
+        *r.into_mut() = 0;
+        assert_eq!(pointee, 0);
+    }
+
+    #[test]
+    fn tuples() {
+        let pointee = 42u64;
+        let r: TaggedRef<u64> = (&pointee, 3).into();
+        let (r, value) = r.into_parts();
+        assert_eq!((*r, value), (42, 3));
+
+        let mut pointee = 42u64;
+        let r: TaggedMut<u64> = (&mut pointee, 3).into();
+        let (r, value) = r.into_parts();
+        *r += value as u64;
+        assert_eq!(pointee, 45);
+    }
+}
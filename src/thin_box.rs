@@ -0,0 +1,359 @@
+use crate::PointerValuePair;
+#[cfg(feature = "ptr_metadata")]
+use alloc::boxed::Box;
+use alloc::{
+    alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    vec::Vec,
+};
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr,
+};
+
+/// An owned heap allocation of a possibly dynamically-sized value (e.g. a slice, a `str` or a trait object), with a
+/// pointer-sized handle that carries an integer value in its alignment bits.
+///
+/// `Box<T>` is two words wide when `T` is dynamically-sized. `ThinBox` stores the full pointer to the value (with
+/// its length or vtable) in a header at the start of the allocation instead, so the handle is a thin pointer to the
+/// header. The header is pointer-aligned, which leaves 2 bits (32-bit targets) or 3 bits (64-bit targets) to store
+/// the value.
+///
+/// # Notes
+///
+/// Trait objects are created from sized values with `ThinBox::new_unsize`. With the `ptr_metadata` feature (nightly
+/// only), they can also be converted from and to boxes (`ThinBox::from_box`, `ThinBox::into_box`).
+#[repr(transparent)]
+pub struct ThinBox<T: ?Sized> {
+    /// Pointer to the header, which holds the pointer to the value.
+    inner: PointerValuePair<*mut T>,
+    _phantom: PhantomData<T>,
+}
+
+// SAFETY: `ThinBox` behaves like `Box<T>`.
+unsafe impl<T: ?Sized + Send> Send for ThinBox<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for ThinBox<T> {}
+
+impl<T: ?Sized> ThinBox<T> {
+    /// Checks that the value fits in the available bits.
+    fn check_value(value: usize) {
+        assert!(
+            value <= Self::max_value(),
+            "not enough alignment bits ({}) to store the value ({})",
+            PointerValuePair::<*mut T>::available_bits(),
+            value
+        );
+    }
+
+    /// Returns the layout of the allocation holding the header and a value of the given layout, and the offset of
+    /// the value.
+    fn layout(value: Layout) -> (Layout, usize) {
+        Layout::new::<*mut T>().extend(value).expect("allocation too large")
+    }
+
+    /// Allocates memory with the given layout, which has a non-zero size since it contains the header.
+    fn allocate(layout: Layout) -> *mut u8 {
+        unsafe {
+            // SAFETY: the layout has a non-zero size
+            let base = alloc(layout);
+            if base.is_null() {
+                handle_alloc_error(layout);
+            }
+            base
+        }
+    }
+
+    /// Writes the pointer to the value in the header, and creates the `ThinBox`.
+    ///
+    /// # Safety
+    ///
+    /// `base` must have been allocated by `allocate` with the layout returned by `layout` for the value, and `ptr`
+    /// must point to the initialized value in the allocation.
+    unsafe fn from_header(base: *mut u8, ptr: *mut T, value: usize) -> ThinBox<T> {
+        base.cast::<*mut T>().write(ptr);
+        ThinBox {
+            inner: PointerValuePair::new(base.cast::<*mut T>(), value),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the pointer to the value, read from the header.
+    fn value_ptr(&self) -> *mut T {
+        validate!(!self.inner.is_null(), "corrupted ThinBox pointer");
+        // SAFETY: the header is initialized by `from_header`
+        unsafe { *self.inner.ptr() }
+    }
+
+    /// Moves a sized value into a new `ThinBox`, and unsizes it with `coerce`, which must return the reference it is
+    /// given (e.g. `|v| v as &mut dyn Trait`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits, or if `coerce` returns a reference to another object.
+    pub fn new_unsize<S>(v: S, value: usize, coerce: impl FnOnce(&mut S) -> &mut T) -> ThinBox<T> {
+        Self::check_value(value);
+        let (layout, offset) = Self::layout(Layout::new::<S>());
+        let base = Self::allocate(layout);
+        unsafe {
+            // SAFETY: the value is at `offset` in the allocation
+            let data = base.add(offset).cast::<S>();
+            data.write(v);
+            let ptr: *mut T = coerce(&mut *data);
+            assert!(
+                ptr.cast::<S>() == data && Layout::for_value(&*ptr) == Layout::new::<S>(),
+                "the unsizing function must return the reference it is given"
+            );
+            Self::from_header(base, ptr, value)
+        }
+    }
+
+    /// Returns the value stored alongside the pointer.
+    pub fn value(&self) -> usize {
+        self.inner.value()
+    }
+
+    /// Sets the value stored alongside the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn set_value(&mut self, value: usize) {
+        self.inner.replace_value(value);
+    }
+
+    /// Returns the maximum (inclusive) integer value that can be stored alongside the pointer.
+    pub const fn max_value() -> usize {
+        PointerValuePair::<*mut T>::max_value()
+    }
+}
+
+#[cfg(feature = "ptr_metadata")]
+impl<T: ?Sized> ThinBox<T> {
+    /// Moves the value of a box into a new `ThinBox`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn from_box(b: Box<T>, value: usize) -> ThinBox<T> {
+        Self::check_value(value);
+        let value_layout = Layout::for_value(&*b);
+        let (layout, offset) = Self::layout(value_layout);
+        let base = Self::allocate(layout);
+        unsafe {
+            // SAFETY: the value is moved to `offset` in the new allocation, and the box is deallocated without
+            // dropping it.
+            let src = Box::into_raw(b);
+            let data = base.add(offset);
+            ptr::copy_nonoverlapping(src.cast::<u8>(), data, value_layout.size());
+            if value_layout.size() != 0 {
+                dealloc(src.cast(), value_layout);
+            }
+            Self::from_header(base, ptr::from_raw_parts_mut(data, ptr::metadata(src)), value)
+        }
+    }
+
+    /// Moves the value into a new box, discarding the value stored alongside the pointer.
+    pub fn into_box(self) -> Box<T> {
+        let src = self.value_ptr();
+        let value_layout = Layout::for_value(unsafe { &*src });
+        let data = if value_layout.size() == 0 {
+            ptr::without_provenance_mut(value_layout.align())
+        } else {
+            Self::allocate(value_layout)
+        };
+        unsafe {
+            // SAFETY: the value is moved to the new allocation, which has the same layout, and the `ThinBox` is
+            // deallocated without dropping it.
+            ptr::copy_nonoverlapping(src.cast::<u8>(), data, value_layout.size());
+            dealloc(self.inner.ptr() as *mut u8, Self::layout(value_layout).0);
+            mem::forget(self);
+            Box::from_raw(ptr::from_raw_parts_mut(data, ptr::metadata(src)))
+        }
+    }
+}
+
+impl<T> ThinBox<T> {
+    /// Moves the value into a new `ThinBox`, with the given value stored alongside the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn new(v: T, value: usize) -> ThinBox<T> {
+        Self::new_unsize(v, value, |v| v)
+    }
+
+    /// Moves the value out of the `ThinBox`.
+    pub fn into_inner(self) -> T {
+        let ptr = self.value_ptr();
+        unsafe {
+            // SAFETY: the value is moved out, and the allocation is freed without dropping it
+            let v = ptr.read();
+            dealloc(self.inner.ptr() as *mut u8, Self::layout(Layout::new::<T>()).0);
+            mem::forget(self);
+            v
+        }
+    }
+}
+
+impl<T> ThinBox<[T]> {
+    /// Moves the elements of a vector into a new `ThinBox`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn from_vec(mut v: Vec<T>, value: usize) -> ThinBox<[T]> {
+        Self::check_value(value);
+        let len = v.len();
+        let (layout, offset) = Self::layout(Layout::for_value(&*v));
+        let base = Self::allocate(layout);
+        unsafe {
+            // SAFETY: the elements are moved to `offset` in the allocation, and no longer owned by the vector
+            let data = base.add(offset).cast::<T>();
+            ptr::copy_nonoverlapping(v.as_ptr(), data, len);
+            v.set_len(0);
+            Self::from_header(base, ptr::slice_from_raw_parts_mut(data, len), value)
+        }
+    }
+}
+
+impl ThinBox<str> {
+    /// Copies a string into a new `ThinBox`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn new_str(s: &str, value: usize) -> ThinBox<str> {
+        Self::check_value(value);
+        let (layout, offset) = Self::layout(Layout::for_value(s));
+        let base = Self::allocate(layout);
+        unsafe {
+            // SAFETY: the bytes are copied to `offset` in the allocation
+            let data = base.add(offset);
+            ptr::copy_nonoverlapping(s.as_ptr(), data, s.len());
+            Self::from_header(base, ptr::slice_from_raw_parts_mut(data, s.len()) as *mut str, value)
+        }
+    }
+}
+
+impl<T: ?Sized> Drop for ThinBox<T> {
+    fn drop(&mut self) {
+        let ptr = self.value_ptr();
+        unsafe {
+            // SAFETY: the value is initialized, and the allocation has been created with the layout returned by
+            // `layout` for the value.
+            let (layout, _) = Self::layout(Layout::for_value(&*ptr));
+            ptr::drop_in_place(ptr);
+            dealloc(self.inner.ptr() as *mut u8, layout);
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for ThinBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the pointer points to the value in a live allocation owned by `self`.
+        unsafe { &*self.value_ptr() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for ThinBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the pointer points to the value in a live allocation owned by `self`, which we borrow mutably.
+        unsafe { &mut *self.value_ptr() }
+    }
+}
+
+impl<T: Clone> Clone for ThinBox<T> {
+    /// Clones the boxed value, and keeps the value stored alongside the pointer.
+    fn clone(&self) -> Self {
+        ThinBox::new(T::clone(self), self.value())
+    }
+}
+
+impl<T: Clone> Clone for ThinBox<[T]> {
+    /// Clones the elements, and keeps the value stored alongside the pointer.
+    fn clone(&self) -> Self {
+        ThinBox::from_vec(self.to_vec(), self.value())
+    }
+}
+
+impl Clone for ThinBox<str> {
+    /// Copies the string, and keeps the value stored alongside the pointer.
+    fn clone(&self) -> Self {
+        ThinBox::new_str(self, self.value())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for ThinBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThinBox")
+            .field("box", &&**self)
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ThinBox;
+    use std::{boxed::Box, fmt::Debug, format, mem, rc::Rc, string::ToString, vec, vec::Vec};
+
+    #[test]
+    fn thin() {
+        assert_eq!(mem::size_of::<*const u8>(), mem::size_of::<ThinBox<[u64]>>());
+        assert_eq!(mem::size_of::<*const u8>(), mem::size_of::<ThinBox<dyn Debug>>());
+
+        let mut b = ThinBox::new(42u8, 7);
+        *b += 1;
+        assert_eq!((*b, b.value()), (43, 7));
+        assert_eq!(format!("{:?}", b.clone()), "ThinBox { box: 43, value: 7 }");
+        assert_eq!(b.into_inner(), 43);
+
+        let mut s = ThinBox::from_vec(vec![1u16, 2, 3], 1);
+        s[0] = 4;
+        s.set_value(2);
+        assert_eq!((&*s.clone(), s.value()), (&[4, 2, 3][..], 2));
+
+        let s = ThinBox::new_str("hello", 3);
+        assert_eq!(&*s.clone(), "hello");
+        assert!(ThinBox::new_str("", 0).is_empty());
+    }
+
+    #[test]
+    fn trait_objects() {
+        let items: [ThinBox<dyn Debug>; 3] = [
+            ThinBox::new_unsize(1u8, 0, |v| v as &mut dyn Debug),
+            ThinBox::new_unsize("two".to_string(), 1, |v| v as &mut dyn Debug),
+            ThinBox::new_unsize([3u64; 2], 2, |v| v as &mut dyn Debug),
+        ];
+        let formatted: Vec<_> = items.iter().map(|b| format!("{:?}/{}", &**b, b.value())).collect();
+        assert_eq!(formatted, ["1/0", "\"two\"/1", "[3, 3]/2"]);
+
+        let rc = Rc::new(());
+        let b = ThinBox::new_unsize(rc.clone(), 0, |v| v as &mut dyn Debug);
+        assert_eq!(Rc::strong_count(&rc), 2);
+        drop(b);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_coercion() {
+        let _ = ThinBox::<u32>::new_unsize(1u32, 0, |_| Box::leak(Box::new(0u32)));
+    }
+
+    #[cfg(feature = "ptr_metadata")]
+    #[test]
+    fn boxes() {
+        let b: Box<dyn Debug> = Box::new(vec![1u32, 2]);
+        let thin = ThinBox::from_box(b, 3);
+        assert_eq!(format!("{:?}", thin), "ThinBox { box: [1, 2], value: 3 }");
+        assert_eq!(format!("{:?}", thin.into_box()), "[1, 2]");
+
+        let b: Box<[()]> = Box::new([(), ()]);
+        assert_eq!(ThinBox::from_box(b, 0).into_box().len(), 2);
+    }
+}
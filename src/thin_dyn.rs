@@ -0,0 +1,200 @@
+use crate::PointerValuePair;
+use core::{fmt, marker::PhantomData, ptr};
+
+/// Returns the pointer to the unsized value of the `ThinDyn` pointed to by `this`.
+type Trampoline<T> = unsafe fn(this: *const ()) -> *const T;
+
+unsafe fn trampoline<T: ?Sized, S, F: Fn(&S) -> &T>(this: *const ()) -> *const T {
+    // SAFETY: the caller passes a pointer to a `ThinDyn<T, S, F>`
+    let this = &*(this as *const ThinDyn<T, S, F>);
+    (this.unsize)(&this.value)
+}
+
+/// A value of type `S` that can be referenced as an unsized `T` (typically a trait object) through a thin pointer
+/// (see `ThinDynRef`).
+///
+/// The function that converts a `&S` into a `&T` (e.g. `|v| v as &dyn Trait`) is stored with the value, behind a
+/// function pointer, which plays the role of the vtable pointer of the trait object. Non-capturing closures take no
+/// space.
+#[repr(C)]
+pub struct ThinDyn<T: ?Sized, S, F = fn(&S) -> &T> {
+    // first field, so that it can be read without knowing `S` and `F`
+    trampoline: Trampoline<T>,
+    unsize: F,
+    value: S,
+}
+
+impl<T: ?Sized, S, F: Fn(&S) -> &T> ThinDyn<T, S, F> {
+    /// Creates a new `ThinDyn` from a value and the function that references it as a `T`.
+    pub const fn new(value: S, unsize: F) -> ThinDyn<T, S, F> {
+        ThinDyn {
+            trampoline: trampoline::<T, S, F>,
+            unsize,
+            value,
+        }
+    }
+
+    /// Returns a reference to the value.
+    pub fn get(&self) -> &S {
+        &self.value
+    }
+
+    /// Returns a mutable reference to the value.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.value
+    }
+
+    /// Returns the value as a `&T`.
+    pub fn as_dyn(&self) -> &T {
+        (self.unsize)(&self.value)
+    }
+
+    /// Returns a thin reference to the value, with the given value stored alongside the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn as_thin(&self, value: usize) -> ThinDynRef<'_, T> {
+        ThinDynRef::new(self, value)
+    }
+
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> S {
+        self.value
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, S, F: Fn(&S) -> &T> fmt::Debug for ThinDyn<T, S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ThinDyn").field(&self.as_dyn()).finish()
+    }
+}
+
+/// A shared reference to an unsized value (typically a trait object) that is a single pointer wide, with an integer
+/// value packed in its alignment bits.
+///
+/// `&dyn Trait` is two words wide. `ThinDynRef` points to a `ThinDyn`, which stores the equivalent of the vtable
+/// pointer next to the value, so that the reference itself only needs the address. The pointer is aligned to a
+/// function pointer, which usually leaves 2 bits (32-bit targets) or 3 bits (64-bit targets) to store the value.
+///
+/// # Notes
+///
+/// Since the type of the referenced `ThinDyn` is erased, `ThinDynRef` is neither `Send` nor `Sync`.
+#[repr(transparent)]
+pub struct ThinDynRef<'a, T: ?Sized> {
+    inner: PointerValuePair<Trampoline<T>>,
+    _phantom: PhantomData<&'a T>,
+}
+
+impl<T: ?Sized> Copy for ThinDynRef<'_, T> {}
+
+impl<T: ?Sized> Clone for ThinDynRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> PartialEq for ThinDynRef<'_, T> {
+    /// Compares the addresses and values of both references, not the referenced values.
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T: ?Sized> Eq for ThinDynRef<'_, T> {}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for ThinDynRef<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThinDynRef")
+            .field("ref", &self.get())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl<'a, T: ?Sized> ThinDynRef<'a, T> {
+    /// Creates a new `ThinDynRef` to the given `ThinDyn`, with the given value stored alongside the pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value doesn't fit in the available bits.
+    pub fn new<S, F: Fn(&S) -> &T>(r: &'a ThinDyn<T, S, F>, value: usize) -> ThinDynRef<'a, T> {
+        ThinDynRef {
+            inner: PointerValuePair::new(ptr::from_ref(r).cast::<Trampoline<T>>(), value),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the referenced value.
+    pub fn get(&self) -> &'a T {
+        let this = self.inner.ptr();
+        unsafe {
+            // SAFETY: the pointer comes from a `&'a ThinDyn<T, S, F>`, which starts with the trampoline for `S` and
+            // `F`, and the trampoline returns a reference derived from the `&S` borrowed for `'a`.
+            let trampoline = *this;
+            &*trampoline(this.cast())
+        }
+    }
+
+    /// Returns the value stored alongside the pointer.
+    pub fn value(&self) -> usize {
+        self.inner.value()
+    }
+
+    /// Replaces the value stored alongside the pointer, and returns the previous value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new value doesn't fit in the available bits.
+    pub fn replace_value(&mut self, value: usize) -> usize {
+        self.inner.replace_value(value)
+    }
+
+    /// Returns whether both references point to the same `ThinDyn`, ignoring the values stored alongside the
+    /// pointers.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.inner.ptr() == other.inner.ptr()
+    }
+}
+
+impl<'a, T: ?Sized, S, F: Fn(&S) -> &T> From<&'a ThinDyn<T, S, F>> for ThinDynRef<'a, T> {
+    /// Creates a `ThinDynRef` with a value of 0.
+    fn from(r: &'a ThinDyn<T, S, F>) -> Self {
+        ThinDynRef::new(r, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ThinDyn, ThinDynRef};
+    use std::{cell::Cell, fmt::Debug, format, mem, vec::Vec};
+
+    static GREETING: ThinDyn<dyn Debug, &str> = ThinDyn::new("hello", |v| v);
+
+    #[test]
+    fn thin() {
+        assert_eq!(mem::size_of::<*const u8>(), mem::size_of::<ThinDynRef<dyn Debug>>());
+
+        let n = ThinDyn::new(42u32, |v| v as &dyn Debug);
+        let items = [ThinDynRef::new(&n, 1), GREETING.as_thin(2), ThinDynRef::from(&GREETING)];
+        let formatted: Vec<_> = items.iter().map(|r| format!("{:?}/{}", r.get(), r.value())).collect();
+        assert_eq!(formatted, ["42/1", "\"hello\"/2", "\"hello\"/0"]);
+        assert!(items[1].ptr_eq(&items[2]) && items[1] != items[2]);
+        assert_eq!(format!("{:?}", n), "ThinDyn(42)");
+        assert_eq!(format!("{:?}", items[0]), "ThinDynRef { ref: 42, value: 1 }");
+    }
+
+    #[test]
+    fn callbacks() {
+        let count = Cell::new(0);
+        let add = ThinDyn::new(|| count.set(count.get() + 1), |f| f as &dyn Fn());
+        let double = ThinDyn::new(|| count.set(count.get() * 2), |f| f as &dyn Fn());
+        let mut callbacks = [add.as_thin(0), double.as_thin(0), add.as_thin(0)];
+        callbacks[1].replace_value(1);
+        for cb in callbacks {
+            cb.get()();
+        }
+        assert_eq!(count.get(), 3);
+        assert_eq!(callbacks.map(|cb| cb.value()), [0, 1, 0]);
+    }
+}
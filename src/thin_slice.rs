@@ -0,0 +1,161 @@
+#[cfg(all(
+    feature = "high-bits",
+    target_pointer_width = "64",
+    any(target_arch = "x86_64", target_arch = "aarch64"),
+    not(target_os = "android")
+))]
+use crate::HighBitsPointerValuePair as Repr;
+#[cfg(not(all(
+    feature = "high-bits",
+    target_pointer_width = "64",
+    any(target_arch = "x86_64", target_arch = "aarch64"),
+    not(target_os = "android")
+)))]
+use crate::PointerValuePair as Repr;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    ptr,
+};
+
+/// A pointer to a slice of bounded length (`*const [T]`), with the pointer and the length packed in a single word.
+///
+/// Regular slice pointers are two words wide. For workloads with many short slices, packing the length in the
+/// spare bits of the pointer halves the memory footprint.
+///
+/// By default, the length is stored in the alignment bits of the pointer, so the maximum length depends on the
+/// alignment of `T` (e.g. 7 for `u64`). With the `high-bits` feature on 64-bit x86-64 and AArch64 (except Android),
+/// the length is stored in the unused high bits of the address instead, and can go up to 65535 regardless of `T`.
+/// See `ThinSlicePtr::max_len`.
+#[repr(transparent)]
+pub struct ThinSlicePtr<T> {
+    repr: Repr<T>,
+}
+
+impl<T> Copy for ThinSlicePtr<T> {}
+
+impl<T> Clone for ThinSlicePtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for ThinSlicePtr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.repr == other.repr
+    }
+}
+
+impl<T> Eq for ThinSlicePtr<T> {}
+
+impl<T> Hash for ThinSlicePtr<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.repr.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for ThinSlicePtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThinSlicePtr")
+            .field("ptr", &self.as_ptr())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<T> ThinSlicePtr<T> {
+    /// Creates a new `ThinSlicePtr` from a slice pointer, or returns `None` if the slice is too long.
+    pub fn try_new(ptr: *const [T]) -> Option<ThinSlicePtr<T>> {
+        Self::try_from_raw_parts(ptr as *const T, ptr.len())
+    }
+
+    /// Creates a new `ThinSlicePtr` from a slice pointer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the slice is greater than `ThinSlicePtr::<T>::max_len()`.
+    pub fn new(ptr: *const [T]) -> ThinSlicePtr<T> {
+        Self::try_new(ptr).unwrap_or_else(|| {
+            panic!(
+                "slice length ({}) exceeds the maximum length of a thin slice pointer ({})",
+                ptr.len(),
+                Self::max_len()
+            )
+        })
+    }
+
+    /// Creates a new `ThinSlicePtr` from a pointer to the first element and a length, or returns `None` if the
+    /// length is too large.
+    pub fn try_from_raw_parts(data: *const T, len: usize) -> Option<ThinSlicePtr<T>> {
+        if len <= Self::max_len() {
+            Some(ThinSlicePtr {
+                repr: Repr::new(data, len),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the slice pointer.
+    pub fn ptr(self) -> *const [T] {
+        ptr::slice_from_raw_parts(self.as_ptr(), self.len())
+    }
+
+    /// Returns the pointer to the first element of the slice.
+    pub fn as_ptr(self) -> *const T {
+        self.repr.ptr()
+    }
+
+    /// Returns the length of the slice.
+    pub fn len(self) -> usize {
+        self.repr.value()
+    }
+
+    /// Returns whether the slice is empty.
+    pub fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the maximum (inclusive) length of the slices that can be represented.
+    pub const fn max_len() -> usize {
+        Repr::<T>::max_value()
+    }
+}
+
+impl<T> From<ThinSlicePtr<T>> for *const [T] {
+    fn from(ptr: ThinSlicePtr<T>) -> Self {
+        ptr.ptr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ThinSlicePtr;
+    use std::{mem, vec};
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(mem::size_of::<*const u64>(), mem::size_of::<ThinSlicePtr<u64>>());
+    }
+
+    #[test]
+    fn round_trip() {
+        let s = [1u64, 2, 3, 4, 5];
+        let thin = ThinSlicePtr::new(&s[1..4]);
+        assert_eq!(thin.len(), 3);
+        assert!(!thin.is_empty());
+        assert_eq!(thin.as_ptr(), &s[1] as *const u64);
+        assert_eq!(unsafe { &*thin.ptr() }, &[2, 3, 4]);
+        let fat: *const [u64] = thin.into();
+        assert_eq!(fat, &s[1..4] as *const [u64]);
+
+        assert!(ThinSlicePtr::new(&s[..0]).is_empty());
+    }
+
+    #[test]
+    fn too_long() {
+        let v = vec![0u64; ThinSlicePtr::<u64>::max_len() + 1];
+        assert!(ThinSlicePtr::try_new(&v[..]).is_none());
+        assert!(ThinSlicePtr::try_new(&v[1..]).is_some());
+    }
+}
@@ -0,0 +1,125 @@
+use alloc::{
+    alloc::{alloc, dealloc, Layout},
+    boxed::Box,
+};
+use core::{fmt, mem, ptr};
+
+/// Error returned by the fallible constructors (`Cow::try_owned_value`, `TaggedBox::try_new`...) when the memory
+/// allocator fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AllocError;
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "memory allocation failed")
+    }
+}
+
+impl core::error::Error for AllocError {}
+
+/// Allocates a box for `v`, returning an error instead of aborting if the allocation fails.
+pub(crate) fn try_box<T>(v: T) -> Result<Box<T>, AllocError> {
+    let layout = Layout::new::<T>();
+    if layout.size() == 0 {
+        // doesn't allocate
+        return Ok(Box::new(v));
+    }
+    unsafe {
+        // SAFETY: the layout has a non-zero size
+        let ptr = alloc(layout) as *mut T;
+        if ptr.is_null() {
+            return Err(AllocError);
+        }
+        // SAFETY: the pointer has been allocated with the layout of `T`, which is the layout expected by `Box`
+        ptr.write(v);
+        Ok(Box::from_raw(ptr))
+    }
+}
+
+/// Deallocates a partially initialized slice if cloning an element panics.
+struct PartialSlice<T> {
+    ptr: *mut T,
+    initialized: usize,
+    layout: Layout,
+}
+
+impl<T> Drop for PartialSlice<T> {
+    fn drop(&mut self) {
+        unsafe {
+            // SAFETY: the first `initialized` elements have been written, and the pointer has been allocated with
+            // `layout`.
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.ptr, self.initialized));
+            dealloc(self.ptr as *mut u8, self.layout);
+        }
+    }
+}
+
+/// Clones a slice into a new boxed slice, returning an error instead of aborting if the allocation fails.
+pub(crate) fn try_box_slice<T: Clone>(src: &[T]) -> Result<Box<[T]>, AllocError> {
+    let layout = Layout::array::<T>(src.len()).map_err(|_| AllocError)?;
+    if layout.size() == 0 {
+        // doesn't allocate
+        return Ok(src.into());
+    }
+    unsafe {
+        // SAFETY: the layout has a non-zero size
+        let ptr = alloc(layout) as *mut T;
+        if ptr.is_null() {
+            return Err(AllocError);
+        }
+        let mut partial = PartialSlice {
+            ptr,
+            initialized: 0,
+            layout,
+        };
+        for v in src {
+            // SAFETY: the allocation has room for `src.len()` elements
+            ptr.add(partial.initialized).write(v.clone());
+            partial.initialized += 1;
+        }
+        // the slice is fully initialized, ownership is transferred to the box
+        mem::forget(partial);
+        // SAFETY: the pointer has been allocated with the layout of `[T]` of length `src.len()`
+        Ok(Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, src.len())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{try_box, try_box_slice};
+    use std::{cell::Cell, panic, rc::Rc};
+
+    #[test]
+    fn boxes() {
+        assert_eq!(*try_box(5u64).unwrap(), 5);
+        assert_eq!(*try_box(()).unwrap(), ());
+        assert_eq!(&*try_box_slice(&[1u16, 2, 3]).unwrap(), &[1, 2, 3]);
+        assert!(try_box_slice::<u32>(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn clone_panic() {
+        struct Bomb<'a>(&'a Cell<u32>, Rc<()>);
+        impl Clone for Bomb<'_> {
+            fn clone(&self) -> Self {
+                if self.0.get() == 2 {
+                    panic!("boom");
+                }
+                self.0.set(self.0.get() + 1);
+                Bomb(self.0, self.1.clone())
+            }
+        }
+
+        let count = Cell::new(0);
+        let rc = Rc::new(());
+        let src = [
+            Bomb(&count, rc.clone()),
+            Bomb(&count, rc.clone()),
+            Bomb(&count, rc.clone()),
+        ];
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| try_box_slice(&src)));
+        assert!(result.is_err());
+        // the two clones have been dropped
+        assert_eq!(Rc::strong_count(&rc), 4);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::AlignedPointerValuePair;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem,
+};
+
+/// A pointer to either an `A` or a `B`, with the discriminant stored in the lowest alignment bit.
+///
+/// This is the equivalent of LLVM's `PointerUnion`. Both `A` and `B` must be aligned to at least 2 bytes,
+/// which is checked at compile time.
+#[repr(transparent)]
+pub struct PointerUnion<A, B> {
+    // both pointee types are at least 2-aligned, so the lowest bit is always available
+    inner: AlignedPointerValuePair<u8, 2>,
+    _phantom: PhantomData<(*const A, *const B)>,
+}
+
+const IS_A: usize = 0;
+const IS_B: usize = 1;
+
+impl<A, B> Copy for PointerUnion<A, B> {}
+
+impl<A, B> Clone for PointerUnion<A, B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A, B> PartialEq for PointerUnion<A, B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<A, B> Eq for PointerUnion<A, B> {}
+
+impl<A, B> Hash for PointerUnion<A, B> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
+    }
+}
+
+impl<A, B> fmt::Debug for PointerUnion<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.as_a(), self.as_b()) {
+            (Some(a), _) => f.debug_tuple("A").field(&a).finish(),
+            (_, Some(b)) => f.debug_tuple("B").field(&b).finish(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<A, B> PointerUnion<A, B> {
+    /// Fails to compile if `A` or `B` is not aligned to at least 2 bytes.
+    const CHECK_ALIGN: () = assert!(
+        mem::align_of::<A>() >= 2 && mem::align_of::<B>() >= 2,
+        "the pointee types must be aligned to at least 2 bytes"
+    );
+
+    /// Creates a `PointerUnion` holding a pointer to an `A`.
+    pub fn from_a(ptr: *const A) -> PointerUnion<A, B> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CHECK_ALIGN;
+        PointerUnion {
+            inner: AlignedPointerValuePair::new(ptr.cast(), IS_A),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a `PointerUnion` holding a pointer to a `B`.
+    pub fn from_b(ptr: *const B) -> PointerUnion<A, B> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CHECK_ALIGN;
+        PointerUnion {
+            inner: AlignedPointerValuePair::new(ptr.cast(), IS_B),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a `PointerUnion` holding a pointer to an `A` from a reference.
+    pub fn from_ref_a(r: &A) -> PointerUnion<A, B> {
+        Self::from_a(r)
+    }
+
+    /// Creates a `PointerUnion` holding a pointer to a `B` from a reference.
+    pub fn from_ref_b(r: &B) -> PointerUnion<A, B> {
+        Self::from_b(r)
+    }
+
+    /// Returns whether this holds a pointer to `U`, which must be either `A` or `B`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `U` is neither `A` nor `B`, or if `A` and `B` are the same type.
+    pub fn is<U: 'static>(self) -> bool
+    where
+        A: 'static,
+        B: 'static,
+    {
+        use core::any::TypeId;
+        let u = TypeId::of::<U>();
+        let (a, b) = (TypeId::of::<A>(), TypeId::of::<B>());
+        assert!(a != b, "`is` is ambiguous when both pointee types are the same");
+        if u == a {
+            self.is_a()
+        } else if u == b {
+            self.is_b()
+        } else {
+            panic!("the type is not one of the pointee types of the union")
+        }
+    }
+
+    /// Returns whether this holds a pointer to an `A`.
+    pub fn is_a(self) -> bool {
+        self.inner.value() == IS_A
+    }
+
+    /// Returns whether this holds a pointer to a `B`.
+    pub fn is_b(self) -> bool {
+        self.inner.value() == IS_B
+    }
+
+    /// Returns the pointer to `A`, or `None` if this holds a pointer to a `B`.
+    pub fn as_a(self) -> Option<*const A> {
+        self.is_a().then(|| self.inner.ptr().cast())
+    }
+
+    /// Returns the pointer to `B`, or `None` if this holds a pointer to an `A`.
+    pub fn as_b(self) -> Option<*const B> {
+        self.is_b().then(|| self.inner.ptr().cast())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PointerUnion;
+    use std::{format, mem};
+
+    struct Decl(u16);
+    struct Expr(u64);
+
+    #[test]
+    fn pointer_sized() {
+        assert_eq!(mem::size_of::<*const u8>(), mem::size_of::<PointerUnion<Decl, Expr>>());
+    }
+
+    #[test]
+    fn discriminate() {
+        // `Decl` is less aligned than `Expr`
+        let d = [Decl(0), Decl(1)];
+        let d = &d[1];
+        let e = Expr(2);
+        let u = PointerUnion::<Decl, Expr>::from_ref_a(d);
+        assert!(u.is_a() && !u.is_b());
+        assert!(u.is::<Decl>() && !u.is::<Expr>());
+        assert_eq!(unsafe { &*u.as_a().unwrap() }.0, 1);
+        assert!(u.as_b().is_none());
+
+        let u = PointerUnion::<Decl, Expr>::from_ref_b(&e);
+        assert!(u.is::<Expr>());
+        assert_eq!(u.as_b(), Some(&e as *const Expr));
+        assert_eq!(unsafe { &*u.as_b().unwrap() }.0, 2);
+        assert!(u.as_a().is_none());
+        assert_eq!(format!("{:?}", u), format!("B({:?})", &e as *const Expr));
+    }
+
+    #[test]
+    #[should_panic]
+    fn is_other_type() {
+        let d = Decl(1);
+        PointerUnion::<Decl, Expr>::from_ref_a(&d).is::<u32>();
+    }
+}